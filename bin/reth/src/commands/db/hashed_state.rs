@@ -0,0 +1,79 @@
+use clap::{Parser, Subcommand};
+use reth_db::database::Database;
+use reth_primitives::B256;
+use reth_provider::ProviderFactory;
+use reth_trie::{export_hashed_state, import_hashed_state, import_hashed_state_verified};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+use tracing::info;
+
+/// The arguments for the `reth db hashed-state` command
+#[derive(Parser, Debug)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+/// `reth db hashed-state` subcommands
+#[derive(Subcommand, Debug)]
+pub enum Subcommands {
+    /// Writes all hashed accounts and storage slots to a file
+    Export {
+        /// The path of the file to write the hashed state to.
+        path: PathBuf,
+    },
+    /// Imports the hashed accounts and storage slots from a file written by `export`
+    Import {
+        /// The path of the file to read the hashed state from.
+        path: PathBuf,
+        /// Recomputes the state root of the imported state and fails without writing anything if
+        /// it does not match the given root.
+        #[arg(long, value_name = "ROOT")]
+        verify: Option<B256>,
+    },
+}
+
+impl Command {
+    /// Execute `db hashed-state` command
+    pub fn execute<DB: Database>(self, provider_factory: ProviderFactory<DB>) -> eyre::Result<()> {
+        match self.command {
+            Subcommands::Export { path } => {
+                let provider = provider_factory.provider()?;
+                let mut writer = BufWriter::new(File::create(&path)?);
+                let counts = export_hashed_state(provider.tx_ref(), &mut writer)?;
+                writer.flush()?;
+                info!(
+                    target: "reth::cli",
+                    accounts = counts.accounts,
+                    storage_slots = counts.storage_slots,
+                    path = %path.display(),
+                    "Exported hashed state"
+                );
+            }
+            Subcommands::Import { path, verify } => {
+                let provider = provider_factory.provider_rw()?;
+                let mut reader = BufReader::new(File::open(&path)?);
+                // The transaction is dropped without committing if the import fails.
+                let counts = match verify {
+                    Some(root) => {
+                        import_hashed_state_verified(provider.tx_ref(), &mut reader, root)?
+                    }
+                    None => import_hashed_state(provider.tx_ref(), &mut reader)?,
+                };
+                provider.commit()?;
+                info!(
+                    target: "reth::cli",
+                    accounts = counts.accounts,
+                    storage_slots = counts.storage_slots,
+                    verified = verify.is_some(),
+                    "Imported hashed state, rebuild the trie with `reth stage run merkle`"
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
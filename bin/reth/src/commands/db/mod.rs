@@ -24,8 +24,10 @@ mod checksum;
 mod clear;
 mod diff;
 mod get;
+mod hashed_state;
 mod list;
 mod stats;
+mod trie;
 /// DB List TUI
 mod tui;
 
@@ -68,6 +70,10 @@ pub enum Subcommands {
     Diff(diff::Command),
     /// Gets the content of a table for the given key
     Get(get::Command),
+    /// Inspects the state trie
+    Trie(trie::Command),
+    /// Exports and imports the hashed state
+    HashedState(hashed_state::Command),
     /// Deletes all database entries
     Drop {
         /// Bypasses the interactive confirmation and drops the database directly
@@ -130,6 +136,21 @@ impl Command {
                     command.execute(&tool)?;
                 });
             }
+            Subcommands::Trie(command) => {
+                db_ro_exec!(self.chain, &db_path, db_args, static_files_path, tool, {
+                    command.execute(&tool)?;
+                });
+            }
+            Subcommands::HashedState(command) => {
+                let db = open_db(&db_path, db_args)?;
+                let provider_factory = ProviderFactory::new(
+                    db,
+                    self.chain.clone(),
+                    StaticFileProvider::read_write(&static_files_path)?,
+                );
+
+                command.execute(provider_factory)?;
+            }
             Subcommands::Drop { force } => {
                 if !force {
                     // Ask for confirmation
@@ -5,7 +5,8 @@ use eyre::WrapErr;
 use human_bytes::human_bytes;
 use itertools::Itertools;
 use reth_db::{
-    database::Database, mdbx, static_file::iter_static_files, DatabaseEnv, TableViewer, Tables,
+    cursor::DbCursorRO, database::Database, mdbx, static_file::iter_static_files, table::Table,
+    tables, transaction::DbTx, DatabaseEnv, RawTable, TableViewer, Tables,
 };
 use reth_fs_util as fs;
 use reth_node_core::dirs::{ChainPath, DataDirPath};
@@ -32,6 +33,13 @@ pub struct Command {
     /// For individual table checksums, use the `reth db checksum` command.
     #[arg(long, default_value_t = false)]
     checksum: bool,
+
+    /// Show a histogram of the stored node sizes of the trie tables.
+    ///
+    /// WARNING: this option will take a long time to run, as it needs to traverse and decode the
+    /// entire trie tables.
+    #[arg(long, default_value_t = false)]
+    trie_node_sizes: bool,
 }
 
 impl Command {
@@ -47,6 +55,12 @@ impl Command {
             println!("\n");
         }
 
+        if self.trie_node_sizes {
+            let trie_node_sizes_table = self.trie_node_sizes_table(tool)?;
+            println!("{trie_node_sizes_table}");
+            println!("\n");
+        }
+
         let static_files_stats_table = self.static_files_stats_table(data_dir)?;
         println!("{static_files_stats_table}");
 
@@ -306,6 +320,47 @@ impl Command {
         Ok(table)
     }
 
+    fn trie_node_sizes_table(&self, tool: &DbTool<DatabaseEnv>) -> eyre::Result<ComfyTable> {
+        let mut table = ComfyTable::new();
+        table.load_preset(comfy_table::presets::ASCII_MARKDOWN);
+        table.set_header(["Table Name", "Node Size", "# Nodes", "Total Size"]);
+
+        let provider = tool.provider_factory.provider()?.disable_long_read_transaction_safety();
+        let tx = provider.tx_ref();
+
+        // The nodes are measured on their raw values, as they are stored with the encoding they
+        // were written with.
+        let mut account_node_sizes = TrieNodeSizes::default();
+        for entry in tx.cursor_read::<RawTable<tables::AccountsTrie>>()?.walk(None)? {
+            let (_, node) = entry?;
+            account_node_sizes.record(node.raw_value().len());
+        }
+
+        // The values of the dupsort table are prefixed with the subkey, the path of the node.
+        let mut storage_node_sizes = TrieNodeSizes::default();
+        for entry in tx.cursor_read::<RawTable<tables::StoragesTrie>>()?.walk(None)? {
+            let (_, entry) = entry?;
+            storage_node_sizes
+                .record(entry.raw_value().len().saturating_sub(STORAGE_TRIE_SUBKEY_LEN));
+        }
+
+        for (db_table, node_sizes) in [
+            (tables::AccountsTrie::NAME, account_node_sizes),
+            (tables::StoragesTrie::NAME, storage_node_sizes),
+        ] {
+            for (bucket, (count, size)) in node_sizes.buckets() {
+                let mut row = Row::new();
+                row.add_cell(Cell::new(db_table))
+                    .add_cell(Cell::new(bucket))
+                    .add_cell(Cell::new(count))
+                    .add_cell(Cell::new(human_bytes(size as f64)));
+                table.add_row(row);
+            }
+        }
+
+        Ok(table)
+    }
+
     fn checksum_report(&self, tool: &DbTool<DatabaseEnv>) -> eyre::Result<ComfyTable> {
         let mut table = ComfyTable::new();
         table.load_preset(comfy_table::presets::ASCII_MARKDOWN);
@@ -346,3 +401,41 @@ impl Command {
         Ok(table)
     }
 }
+
+/// The exclusive upper bounds of the trie node size buckets in bytes, the nodes of at least the
+/// last bound fall into an extra unbounded bucket.
+const TRIE_NODE_SIZE_BOUNDS: [usize; 5] = [32, 64, 128, 256, 512];
+
+/// The length of the subkey prefixing the values of the storage trie table, the path of the node
+/// padded to 64 nibbles followed by its length.
+const STORAGE_TRIE_SUBKEY_LEN: usize = 65;
+
+/// The histogram of the stored trie node sizes.
+#[derive(Default, Debug)]
+struct TrieNodeSizes {
+    /// The number of nodes per bucket.
+    counts: [u64; TRIE_NODE_SIZE_BOUNDS.len() + 1],
+    /// The total encoded size of the nodes per bucket.
+    sizes: [u64; TRIE_NODE_SIZE_BOUNDS.len() + 1],
+}
+
+impl TrieNodeSizes {
+    /// Records the size of the node as it is stored in the trie tables.
+    fn record(&mut self, size: usize) {
+        let bucket = TRIE_NODE_SIZE_BOUNDS
+            .iter()
+            .position(|bound| size < *bound)
+            .unwrap_or(TRIE_NODE_SIZE_BOUNDS.len());
+        self.counts[bucket] += 1;
+        self.sizes[bucket] += size as u64;
+    }
+
+    /// Returns the labels of the buckets with the number and the total size of their nodes.
+    fn buckets(&self) -> impl Iterator<Item = (String, (u64, u64))> + '_ {
+        let labels = TRIE_NODE_SIZE_BOUNDS
+            .iter()
+            .map(|bound| format!("< {bound} B"))
+            .chain([format!(">= {} B", TRIE_NODE_SIZE_BOUNDS[TRIE_NODE_SIZE_BOUNDS.len() - 1])]);
+        labels.zip(self.counts.iter().copied().zip(self.sizes.iter().copied()))
+    }
+}
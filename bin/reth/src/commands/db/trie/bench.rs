@@ -0,0 +1,146 @@
+use crate::utils::DbTool;
+use clap::{builder::RangedU64ValueParser, Parser};
+use comfy_table::Table as ComfyTable;
+use reth_db::{database::Database, transaction::DbTx};
+use reth_primitives::B256;
+use reth_provider::BlockNumReader;
+use reth_trie::{
+    metrics::{StateRootMetrics, TrieRootMetricsSnapshot},
+    prefix_set::{PrefixSetLoader, TriePrefixSets},
+    trie_cursor::{BranchNodeCache, CachedTrieCursorFactory},
+    StateRoot,
+};
+use std::time::{Duration, Instant};
+
+/// The arguments for the `reth db trie bench` command
+#[derive(Parser, Debug)]
+pub struct Command {
+    /// The number of times each root is computed.
+    #[arg(long, default_value_t = 3, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    iterations: usize,
+
+    /// Also compute the incremental root over the changes of the given number of latest blocks.
+    #[arg(long, value_name = "BLOCKS", value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    incremental: Option<u64>,
+
+    /// Read the trie nodes through a branch node cache shared by all iterations.
+    #[arg(long)]
+    branch_node_cache: bool,
+
+    /// Hint the operating system to prefetch the database pages before each calculation.
+    #[arg(long)]
+    sequential_hint: bool,
+}
+
+impl Command {
+    /// Execute `db trie bench` command
+    pub fn execute<DB: Database>(self, tool: &DbTool<DB>) -> eyre::Result<()> {
+        let provider = tool.provider_factory.provider()?;
+        let tx = provider.tx_ref();
+        let cache = self.branch_node_cache.then(BranchNodeCache::default);
+
+        let mut table = ComfyTable::new();
+        table.load_preset(comfy_table::presets::ASCII_MARKDOWN);
+        table.set_header([
+            "Calculation",
+            "Min",
+            "Median",
+            "Max",
+            "Leaves",
+            "Branches added",
+            "Descended subtrees",
+        ]);
+
+        // The root of an unchanged trie is read from the stored root node, so the full root is
+        // computed from scratch.
+        let (root, result) = self.bench(tx, TriePrefixSets::all_changed, cache.as_ref())?;
+        println!("Full root: {root}");
+        table.add_row(result.into_row("Full"));
+
+        if let Some(blocks) = self.incremental {
+            let tip = provider.best_block_number()?;
+            let range = tip.saturating_sub(blocks - 1).max(1)..=tip;
+            let prefix_sets = PrefixSetLoader::new(tx).load(range.clone())?;
+            let (root, result) = self.bench(tx, || prefix_sets.clone(), cache.as_ref())?;
+            println!("Incremental root over blocks {range:?}: {root}");
+            table.add_row(result.into_row("Incremental"));
+        }
+
+        println!("{table}");
+        println!("Each root was computed {} times", self.iterations);
+        if let Some(cache) = cache {
+            let (hits, misses) = cache.hits_and_misses();
+            println!("Branch node cache: {hits} hits, {misses} misses, {} cached", cache.len());
+        }
+
+        Ok(())
+    }
+
+    /// Computes the root with the given prefix sets the configured number of times, returning the
+    /// root along with the timings and the visited nodes of the last computation.
+    fn bench<TX: DbTx>(
+        &self,
+        tx: &TX,
+        prefix_sets: impl Fn() -> TriePrefixSets,
+        cache: Option<&BranchNodeCache>,
+    ) -> eyre::Result<(B256, BenchResult)> {
+        let mut durations = Vec::with_capacity(self.iterations);
+        let mut last = None;
+        for _ in 0..self.iterations {
+            let metrics = StateRootMetrics::default();
+            let started_at = Instant::now();
+            let mut state_root = StateRoot::from_tx(tx)
+                .with_prefix_sets(prefix_sets())
+                .with_metrics(metrics.clone());
+            if self.sequential_hint {
+                state_root = state_root.with_sequential_hint();
+            }
+
+            let root = match cache {
+                Some(cache) => state_root
+                    .with_trie_cursor_factory(CachedTrieCursorFactory::new(tx, cache.clone()))
+                    .root()?,
+                None => state_root.root()?,
+            };
+            durations.push(started_at.elapsed());
+            last = Some((root, metrics));
+        }
+        durations.sort_unstable();
+
+        let (root, metrics) = last.expect("at least one iteration");
+        Ok((
+            root,
+            BenchResult {
+                durations,
+                state: metrics.state_trie.snapshot(),
+                storage: metrics.storage_trie.snapshot(),
+            },
+        ))
+    }
+}
+
+/// The sorted timings of the computations of a root and the totals of the last one.
+#[derive(Debug)]
+struct BenchResult {
+    durations: Vec<Duration>,
+    state: TrieRootMetricsSnapshot,
+    storage: TrieRootMetricsSnapshot,
+}
+
+impl BenchResult {
+    /// Returns the table row with the min, median and max timings and the visited nodes.
+    fn into_row(self, name: &str) -> [String; 7] {
+        let total = |field: fn(&TrieRootMetricsSnapshot) -> u64| {
+            (field(&self.state) + field(&self.storage)).to_string()
+        };
+        [
+            name.to_string(),
+            format!("{:?}", self.durations[0]),
+            format!("{:?}", self.durations[self.durations.len() / 2]),
+            format!("{:?}", self.durations[self.durations.len() - 1]),
+            total(|snapshot| snapshot.leaves_added),
+            total(|snapshot| snapshot.branches_added),
+            total(|snapshot| snapshot.subtrees_descended),
+        ]
+    }
+}
@@ -0,0 +1,89 @@
+use crate::utils::DbTool;
+use clap::Parser;
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{
+    keccak256,
+    trie::{Nibbles, NibblesExt},
+    Address, B256,
+};
+use reth_trie::maintenance::find_leaf;
+
+/// The arguments for the `reth db trie find-leaf` command
+#[derive(Parser, Debug)]
+pub struct Command {
+    /// The hash of the leaf node.
+    hash: B256,
+
+    /// The address of the account whose storage trie is searched instead of the account trie.
+    #[arg(long)]
+    address: Option<Address>,
+
+    /// Search only the leaves whose hashed keys start with the given hex nibbles, e.g. `3a0`.
+    #[arg(long, value_parser = Nibbles::from_hex_str, default_value = "")]
+    prefix: Nibbles,
+
+    /// Look up the preimage of the hashed key of the found leaf in the plain state.
+    ///
+    /// The plain accounts are scanned in full, the plain storage only of the given account.
+    #[arg(long)]
+    preimage: bool,
+}
+
+impl Command {
+    /// Execute `db trie find-leaf` command
+    pub fn execute<DB: Database>(self, tool: &DbTool<DB>) -> eyre::Result<()> {
+        let provider = tool.provider_factory.provider()?;
+        let tx = provider.tx_ref();
+        let hashed_address = self.address.map(keccak256);
+
+        let Some(leaf) = find_leaf(tx, self.hash, hashed_address, &self.prefix)? else {
+            println!("No leaf with hash {} found", self.hash);
+            return Ok(())
+        };
+
+        println!("Leaf path: {:?}", leaf.path);
+        match self.address {
+            Some(address) => {
+                println!("Storage slot of {address}, hashed slot {}", leaf.key);
+                if self.preimage {
+                    let mut cursor = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+                    let slot = cursor
+                        .walk_dup(Some(address), None)?
+                        .map(|entry| entry.map(|(_, entry)| entry.key))
+                        .find(|slot| slot.as_ref().map_or(true, |slot| keccak256(slot) == leaf.key))
+                        .transpose()?;
+                    print_preimage(slot);
+                }
+            }
+            None => {
+                println!("Account, hashed address {}", leaf.key);
+                if self.preimage {
+                    let mut cursor = tx.cursor_read::<tables::PlainAccountState>()?;
+                    let address = cursor
+                        .walk(None)?
+                        .map(|entry| entry.map(|(address, _)| address))
+                        .find(|address| {
+                            address.as_ref().map_or(true, |address| keccak256(address) == leaf.key)
+                        })
+                        .transpose()?;
+                    print_preimage(address);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Prints the preimage of the hashed key, if found in the plain state.
+fn print_preimage(preimage: Option<impl std::fmt::Display>) {
+    match preimage {
+        Some(preimage) => println!("Preimage: {preimage}"),
+        None => println!("Preimage not found in the plain state"),
+    }
+}
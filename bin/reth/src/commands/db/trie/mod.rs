@@ -0,0 +1,44 @@
+use crate::utils::DbTool;
+use clap::{Parser, Subcommand};
+use reth_db::database::Database;
+
+mod bench;
+mod find_leaf;
+mod quick_check;
+mod stats;
+mod verify;
+
+/// The arguments for the `reth db trie` command
+#[derive(Parser, Debug)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+/// `reth db trie` subcommands
+#[derive(Subcommand, Debug)]
+pub enum Subcommands {
+    /// Reports statistics of the storage trie of an account
+    Stats(stats::Command),
+    /// Checks the stored state trie root against the state root of the latest header
+    QuickCheck(quick_check::Command),
+    /// Checks the integrity of the stored state trie
+    Verify(verify::Command),
+    /// Benchmarks the state root calculation on the database
+    Bench(bench::Command),
+    /// Locates the account or the storage slot of a leaf node by its hash
+    FindLeaf(find_leaf::Command),
+}
+
+impl Command {
+    /// Execute `db trie` command
+    pub fn execute<DB: Database>(self, tool: &DbTool<DB>) -> eyre::Result<()> {
+        match self.command {
+            Subcommands::Stats(command) => command.execute(tool),
+            Subcommands::QuickCheck(command) => command.execute(tool),
+            Subcommands::Verify(command) => command.execute(tool),
+            Subcommands::Bench(command) => command.execute(tool),
+            Subcommands::FindLeaf(command) => command.execute(tool),
+        }
+    }
+}
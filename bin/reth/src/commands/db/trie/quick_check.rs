@@ -0,0 +1,48 @@
+use crate::utils::DbTool;
+use clap::Parser;
+use reth_db::database::Database;
+use reth_primitives::B256;
+use reth_provider::{BlockNumReader, HeaderProvider, ProviderError};
+use reth_trie::maintenance::{quick_root_check, QuickRootCheckError};
+
+/// The arguments for the `reth db trie quick-check` command
+#[derive(Parser, Debug)]
+pub struct Command {
+    /// The expected state root, defaults to the state root of the latest header.
+    #[arg(long, value_name = "ROOT")]
+    expected: Option<B256>,
+}
+
+impl Command {
+    /// Execute `db trie quick-check` command
+    pub fn execute<DB: Database>(self, tool: &DbTool<DB>) -> eyre::Result<()> {
+        let provider = tool.provider_factory.provider()?;
+        let expected = match self.expected {
+            Some(expected) => expected,
+            None => {
+                let best_block = provider.best_block_number()?;
+                provider
+                    .sealed_header(best_block)?
+                    .ok_or(ProviderError::HeaderNotFound(best_block.into()))?
+                    .state_root
+            }
+        };
+
+        let matches = match quick_root_check(provider.tx_ref(), expected) {
+            Err(QuickRootCheckError::RootNodeMissing) => eyre::bail!(
+                "The root node of the stored state trie is missing, \
+                 store it with `reth recover trie-root-nodes`"
+            ),
+            result => result?,
+        };
+        if !matches {
+            eyre::bail!(
+                "The stored state trie does not match the state root {expected}, \
+                 rebuild it with `reth stage drop merkle`"
+            )
+        }
+        println!("The stored state trie matches the state root {expected}");
+
+        Ok(())
+    }
+}
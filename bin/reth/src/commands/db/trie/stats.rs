@@ -0,0 +1,49 @@
+use crate::utils::DbTool;
+use clap::Parser;
+use comfy_table::Table as ComfyTable;
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_primitives::{keccak256, Address};
+use reth_trie::{storage_root::depth_histogram, trie_cursor::DatabaseStorageTrieCursor};
+
+/// The arguments for the `reth db trie stats` command
+#[derive(Parser, Debug)]
+pub struct Command {
+    /// The address of the account whose storage trie is inspected.
+    #[arg(long)]
+    address: Address,
+}
+
+impl Command {
+    /// Execute `db trie stats` command
+    pub fn execute<DB: Database>(self, tool: &DbTool<DB>) -> eyre::Result<()> {
+        let provider = tool.provider_factory.provider()?;
+        let hashed_address = keccak256(self.address);
+        let histogram = depth_histogram(provider.tx_ref(), hashed_address)?;
+        let stored_nodes = DatabaseStorageTrieCursor::new(
+            provider.tx_ref().cursor_dup_read::<tables::StoragesTrie>()?,
+            hashed_address,
+        )
+        .count_nodes()?;
+
+        println!("Storage trie of {} (hashed {hashed_address})", self.address);
+        let Some(max_depth) = histogram.max_depth() else {
+            println!("The storage trie is empty");
+            println!("Stored nodes: {stored_nodes}");
+            return Ok(())
+        };
+
+        let mut table = ComfyTable::new();
+        table.load_preset(comfy_table::presets::ASCII_MARKDOWN);
+        table.set_header(["Depth", "Leaves"]);
+        for (depth, leaves) in histogram.iter() {
+            table.add_row([depth.to_string(), leaves.to_string()]);
+        }
+        println!("{table}");
+        println!(
+            "Leaves: {}, max depth: {max_depth}, stored nodes: {stored_nodes}",
+            histogram.leaves()
+        );
+
+        Ok(())
+    }
+}
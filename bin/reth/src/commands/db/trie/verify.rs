@@ -0,0 +1,108 @@
+use crate::utils::DbTool;
+use clap::Parser;
+use reth_db::database::Database;
+use reth_trie::maintenance::{
+    verify_trie_key_encodings, verify_trie_key_lengths, verify_trie_references,
+};
+
+/// The arguments for the `reth db trie verify` command
+#[derive(Parser, Debug)]
+pub struct Command {
+    /// Checks that every child referenced by a stored branch node is backed by a stored trie node
+    /// or by hashed entries.
+    #[arg(long)]
+    references: bool,
+    /// Checks that the paths of the stored trie nodes are canonically encoded, one nibble per
+    /// byte.
+    #[arg(long)]
+    keys: bool,
+    /// Checks that the paths of the stored trie nodes are not longer than the path of a branch
+    /// node can be.
+    #[arg(long)]
+    lengths: bool,
+}
+
+impl Command {
+    /// Execute `db trie verify` command
+    pub fn execute<DB: Database>(self, tool: &DbTool<DB>) -> eyre::Result<()> {
+        if !self.references && !self.keys && !self.lengths {
+            eyre::bail!(
+                "No checks selected, pass `--references` to scan the trie node references, \
+                 `--keys` to scan the trie key encodings or `--lengths` to scan the trie key \
+                 lengths"
+            )
+        }
+
+        let provider = tool.provider_factory.provider()?;
+        if self.keys {
+            let non_canonical = verify_trie_key_encodings(provider.tx_ref())?;
+            for key in &non_canonical {
+                match key.hashed_address {
+                    Some(hashed_address) => {
+                        println!("{} of {hashed_address}: non-canonical key {}", key.table, key.key)
+                    }
+                    None => println!("{}: non-canonical key {}", key.table, key.key),
+                }
+            }
+
+            if !non_canonical.is_empty() {
+                eyre::bail!(
+                    "Found {} non-canonical trie keys, rebuild the trie with \
+                     `reth stage drop merkle`",
+                    non_canonical.len()
+                )
+            }
+            println!("All trie keys are canonically encoded");
+        }
+
+        if self.lengths {
+            let overlong = verify_trie_key_lengths(provider.tx_ref())?;
+            for key in &overlong {
+                match key.hashed_address {
+                    Some(hashed_address) => println!(
+                        "{} of {hashed_address}: key {} of {} nibbles",
+                        key.table, key.key, key.len
+                    ),
+                    None => println!("{}: key {} of {} nibbles", key.table, key.key, key.len),
+                }
+            }
+
+            if !overlong.is_empty() {
+                eyre::bail!(
+                    "Found {} overlong trie keys, rebuild the trie with `reth stage drop merkle`",
+                    overlong.len()
+                )
+            }
+            println!("All trie keys are within the maximum path length");
+        }
+
+        if !self.references {
+            return Ok(())
+        }
+
+        let dangling = verify_trie_references(provider.tx_ref())?;
+        for reference in &dangling {
+            match reference.hashed_address {
+                Some(hashed_address) => println!(
+                    "Storage trie of {hashed_address}: child {:x} of node {:?} has no {:?}",
+                    reference.nibble, reference.parent, reference.missing
+                ),
+                None => println!(
+                    "Account trie: child {:x} of node {:?} has no {:?}",
+                    reference.nibble, reference.parent, reference.missing
+                ),
+            }
+        }
+
+        if !dangling.is_empty() {
+            eyre::bail!(
+                "Found {} dangling trie node references, rebuild the trie with \
+                 `reth stage drop merkle`",
+                dangling.len()
+            )
+        }
+        println!("All trie node references are backed by the database");
+
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@ use clap::{Parser, Subcommand};
 use reth_cli_runner::CliContext;
 
 mod storage_tries;
+mod trie_root_nodes;
 
 /// `reth recover` command
 #[derive(Debug, Parser)]
@@ -17,6 +18,8 @@ pub struct Command {
 pub enum Subcommands {
     /// Recover the node by deleting dangling storage tries.
     StorageTries(storage_tries::Command),
+    /// Recover the node by storing the missing root nodes of the state tries.
+    TrieRootNodes(trie_root_nodes::Command),
 }
 
 impl Command {
@@ -24,6 +27,7 @@ impl Command {
     pub async fn execute(self, ctx: CliContext) -> eyre::Result<()> {
         match self.command {
             Subcommands::StorageTries(command) => command.execute(ctx).await,
+            Subcommands::TrieRootNodes(command) => command.execute(ctx).await,
         }
     }
 }
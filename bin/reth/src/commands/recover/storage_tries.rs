@@ -2,18 +2,25 @@ use crate::args::utils::{chain_help, genesis_value_parser, SUPPORTED_CHAINS};
 use clap::Parser;
 use reth_cli_runner::CliContext;
 use reth_db::{
-    cursor::{DbCursorRO, DbDupCursorRW},
+    cursor::{DbCursorRO, DbDupCursorRO, DbDupCursorRW},
     init_db, tables,
     transaction::DbTx,
 };
 use reth_db_common::init::init_genesis;
 use reth_node_core::args::{DatabaseArgs, DatadirArgs};
-use reth_primitives::ChainSpec;
+use reth_primitives::{trie::Nibbles, ChainSpec, B256};
 use reth_provider::{
     providers::StaticFileProvider, BlockNumReader, HeaderProvider, ProviderError, ProviderFactory,
 };
-use reth_trie::StateRoot;
-use std::{fs, sync::Arc};
+use reth_trie::{
+    maintenance::{
+        check_storage_tries, rebuild_all_storage_tries, StorageTrieStatus,
+        DEFAULT_REBUILD_COMMIT_THRESHOLD,
+    },
+    prefix_set::{PrefixSetMut, TriePrefixSets},
+    StateRoot,
+};
+use std::{fs, path::PathBuf, str::FromStr, sync::Arc};
 use tracing::*;
 
 /// `reth recover storage-tries` command
@@ -37,6 +44,31 @@ pub struct Command {
     /// All database related arguments
     #[command(flatten)]
     pub db: DatabaseArgs,
+
+    /// Rebuild the storage tries of all accounts from the hashed storage after pruning the
+    /// dangling ones.
+    ///
+    /// Use this option to recover from corrupted storage tries. With the suspect accounts given,
+    /// only their corrupted storage tries are rebuilt.
+    #[arg(long, default_value_t = false)]
+    rebuild: bool,
+
+    /// The hashed addresses of the accounts whose storage tries are suspected of corruption.
+    ///
+    /// Only the storage tries of these accounts are checked, and rebuilt with `--rebuild`, instead
+    /// of scanning all storage tries.
+    #[arg(long = "suspect", value_name = "HASHED_ADDRESS", value_delimiter = ',')]
+    suspects: Vec<B256>,
+
+    /// The path to a file with the hashed addresses of the suspect accounts, one per line.
+    ///
+    /// The addresses are checked along with the ones given by `--suspect`.
+    #[arg(long, value_name = "PATH")]
+    suspects_file: Option<PathBuf>,
+
+    /// The number of accounts after which the rebuilt storage tries are committed.
+    #[arg(long, default_value_t = DEFAULT_REBUILD_COMMIT_THRESHOLD, requires = "rebuild")]
+    commit_threshold: u64,
 }
 
 impl Command {
@@ -62,6 +94,48 @@ impl Command {
             .sealed_header(best_block)?
             .ok_or(ProviderError::HeaderNotFound(best_block.into()))?;
 
+        let verify_state_root = |state_root: B256| {
+            if state_root != best_header.state_root {
+                eyre::bail!(
+                    "Recovery failed. Incorrect state root. Expected: {:?}. Received: {:?}",
+                    best_header.state_root,
+                    state_root
+                );
+            }
+            Ok(())
+        };
+
+        let suspects = self.suspects()?;
+        if !suspects.is_empty() {
+            info!(target: "reth::cli", suspects = suspects.len(), "Checking suspect storage tries");
+            let tx_mut = provider.tx_mut();
+            let statuses = check_storage_tries(tx_mut, &suspects, self.rebuild)?;
+            let mut corrupted = 0;
+            for (hashed_address, status) in statuses {
+                info!(target: "reth::cli", %hashed_address, ?status, "Checked storage trie");
+                corrupted += (status == StorageTrieStatus::Corrupted) as usize;
+            }
+            if corrupted > 0 {
+                eyre::bail!(
+                    "Found {corrupted} corrupted storage tries, rebuild them with `--rebuild`"
+                );
+            }
+
+            // The storage roots of the suspects are recomputed from their checked storage tries.
+            let mut account_prefix_set = PrefixSetMut::default();
+            for hashed_address in &suspects {
+                account_prefix_set.insert(Nibbles::unpack(hashed_address));
+            }
+            let prefix_sets = TriePrefixSets {
+                account_prefix_set: account_prefix_set.freeze(),
+                ..Default::default()
+            };
+            verify_state_root(StateRoot::from_tx(tx_mut).with_prefix_sets(prefix_sets).root()?)?;
+            provider.commit()?;
+            info!(target: "reth::cli", "Finished recovery of suspect storage tries");
+            return Ok(())
+        }
+
         let mut deleted_tries = 0;
         let tx_mut = provider.tx_mut();
         let mut hashed_account_cursor = tx_mut.cursor_read::<tables::HashedAccounts>()?;
@@ -73,23 +147,44 @@ impl Command {
             if hashed_account_cursor.seek_exact(hashed_address)?.is_none() {
                 deleted_tries += 1;
                 storage_trie_cursor.delete_current_duplicates()?;
+                // The deleted trie is gone, so the seek lands on the next storage trie.
+                entry = storage_trie_cursor.seek(hashed_address)?;
+            } else {
+                // Skip the remaining nodes of the storage trie.
+                entry = storage_trie_cursor.next_no_dup()?;
             }
-
-            entry = storage_trie_cursor.next()?;
         }
 
-        let state_root = StateRoot::from_tx(tx_mut).root()?;
-        if state_root != best_header.state_root {
-            eyre::bail!(
-                "Recovery failed. Incorrect state root. Expected: {:?}. Received: {:?}",
-                best_header.state_root,
-                state_root
-            );
-        }
+        if self.rebuild {
+            provider.commit()?;
+            info!(target: "reth::cli", deleted = deleted_tries, "Finished pruning of storage tries");
 
-        provider.commit()?;
+            verify_state_root(rebuild_all_storage_tries(factory.db_ref(), self.commit_threshold)?)?;
+        } else {
+            verify_state_root(StateRoot::from_tx(tx_mut).root()?)?;
+            provider.commit()?;
+        }
         info!(target: "reth::cli", deleted = deleted_tries, "Finished recovery");
 
         Ok(())
     }
+
+    /// Returns the hashed addresses of the suspect accounts given on the command line and in the
+    /// suspects file.
+    fn suspects(&self) -> eyre::Result<Vec<B256>> {
+        let mut suspects = self.suspects.clone();
+        if let Some(path) = &self.suspects_file {
+            for line in fs::read_to_string(path)?.lines().map(str::trim) {
+                if line.is_empty() {
+                    continue
+                }
+                let hashed_address = B256::from_str(line)
+                    .map_err(|err| eyre::eyre!("Invalid hashed address {line:?}: {err}"))?;
+                suspects.push(hashed_address);
+            }
+        }
+        suspects.sort_unstable();
+        suspects.dedup();
+        Ok(suspects)
+    }
 }
@@ -0,0 +1,57 @@
+use crate::args::utils::{chain_help, genesis_value_parser, SUPPORTED_CHAINS};
+use clap::Parser;
+use reth_cli_runner::CliContext;
+use reth_db::init_db;
+use reth_node_core::args::{DatabaseArgs, DatadirArgs};
+use reth_primitives::ChainSpec;
+use reth_provider::{providers::StaticFileProvider, ProviderFactory};
+use reth_trie::maintenance::backfill_root_nodes;
+use std::{fs, sync::Arc};
+use tracing::*;
+
+/// `reth recover trie-root-nodes` command
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The chain this node is running.
+    ///
+    /// Possible values are either a built-in chain or the path to a chain specification file.
+    #[arg(
+        long,
+        value_name = "CHAIN_OR_PATH",
+        long_help = chain_help(),
+        default_value = SUPPORTED_CHAINS[0],
+        value_parser = genesis_value_parser
+    )]
+    chain: Arc<ChainSpec>,
+
+    #[command(flatten)]
+    datadir: DatadirArgs,
+
+    /// All database related arguments
+    #[command(flatten)]
+    pub db: DatabaseArgs,
+}
+
+impl Command {
+    /// Execute `trie-root-nodes` recovery command
+    pub async fn execute(self, _ctx: CliContext) -> eyre::Result<()> {
+        let data_dir = self.datadir.resolve_datadir(self.chain.chain);
+        let db_path = data_dir.db();
+        fs::create_dir_all(&db_path)?;
+        let db = Arc::new(init_db(db_path, self.db.database_args())?);
+
+        let factory = ProviderFactory::new(
+            &db,
+            self.chain.clone(),
+            StaticFileProvider::read_write(data_dir.static_files())?,
+        );
+
+        let provider = factory.provider_rw()?;
+        info!(target: "reth::cli", "Backfilling trie root nodes");
+        let backfilled = backfill_root_nodes(provider.tx_ref())?;
+        provider.commit()?;
+        info!(target: "reth::cli", backfilled, "Finished recovery");
+
+        Ok(())
+    }
+}
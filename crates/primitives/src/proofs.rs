@@ -10,6 +10,7 @@ use crate::{
 use alloy_eips::eip7685::Encodable7685;
 use alloy_rlp::Encodable;
 use itertools::Itertools;
+use std::cmp::Ordering;
 
 /// Adjust the index of an item for rlp encoding.
 pub const fn adjust_index_for_rlp(i: usize, len: usize) -> usize {
@@ -241,6 +242,79 @@ pub fn storage_root(storage: impl IntoIterator<Item = (B256, U256)>) -> B256 {
     hb.root()
 }
 
+/// Calculates the root hash of the trie with the given leaf paths and values.
+///
+/// The leaves are fed one by one into the [`HashBuilder`], which can be used directly to mix in
+/// hashes of subtries as well.
+///
+/// # Panics
+///
+/// If the paths are not in strictly ascending order.
+pub fn root_from_sorted_leaves<V: AsRef<[u8]>>(
+    leaves: impl IntoIterator<Item = (Nibbles, V)>,
+) -> B256 {
+    let mut hb = HashBuilder::default();
+    for (path, value) in leaves {
+        hb.add_leaf(path, value.as_ref());
+    }
+    hb.root()
+}
+
+/// Sorts the leaves by their paths and calculates the root hash of the trie.
+/// See [`root_from_sorted_leaves`] for more info.
+///
+/// The leaves are collected and sorted in memory before they are fed into the [`HashBuilder`], so
+/// the memory used is proportional to the number of leaves. Prefer [`root_from_sorted_leaves`] for
+/// large inputs that can be produced in order, e.g. from a database cursor.
+///
+/// # Panics
+///
+/// If a path is repeated.
+pub fn root_from_unsorted<V: AsRef<[u8]>>(leaves: impl IntoIterator<Item = (Nibbles, V)>) -> B256 {
+    root_from_sorted_leaves(leaves.into_iter().sorted_unstable_by(|(a, _), (b, _)| a.cmp(b)))
+}
+
+/// Calculates the root hash of the trie with the base leaves updated by the overlay changes.
+///
+/// Both inputs are merged in a single pass without random access, so the leaves of the resulting
+/// trie are fed into the [`HashBuilder`] as they are produced. An overlay change with a value
+/// inserts the leaf or replaces the base leaf at the same path, and a change without a value
+/// deletes the base leaf, if any. This is the streaming counterpart of the overlay of the hashed
+/// state over the database for the inputs that are already sorted, e.g. read from a database
+/// cursor.
+///
+/// # Panics
+///
+/// If the paths of either input are not in strictly ascending order.
+pub fn root_from_merged_sorted_leaves<V: AsRef<[u8]>>(
+    base: impl IntoIterator<Item = (Nibbles, V)>,
+    overlay: impl IntoIterator<Item = (Nibbles, Option<V>)>,
+) -> B256 {
+    let mut base = base.into_iter().peekable();
+    let mut overlay = overlay.into_iter().peekable();
+    let mut hb = HashBuilder::default();
+    loop {
+        let ordering = match (base.peek(), overlay.peek()) {
+            (Some((base_path, _)), Some((overlay_path, _))) => base_path.cmp(overlay_path),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        let leaf = match ordering {
+            Ordering::Less => base.next().map(|(path, value)| (path, Some(value))),
+            Ordering::Equal => {
+                base.next();
+                overlay.next()
+            }
+            Ordering::Greater => overlay.next(),
+        };
+        if let Some((path, Some(value))) = leaf {
+            hb.add_leaf(path, value.as_ref());
+        }
+    }
+    hb.root()
+}
+
 /// Implementation of hasher using our keccak256 hashing function
 /// for compatibility with `triehash` crate.
 #[cfg(any(test, feature = "test-utils"))]
@@ -271,12 +345,12 @@ pub mod triehash {
 mod tests {
     use super::*;
     use crate::{
-        bloom, constants::EMPTY_ROOT_HASH, hex_literal::hex, Block, GenesisAccount, Log, TxType,
-        GOERLI, HOLESKY, MAINNET, SEPOLIA,
+        bloom, constants::EMPTY_ROOT_HASH, hex_literal::hex, trie::TrieMask, Block, GenesisAccount,
+        Log, TxType, GOERLI, HOLESKY, MAINNET, SEPOLIA,
     };
     use alloy_primitives::{b256, LogData};
     use alloy_rlp::Decodable;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     #[test]
     fn check_transaction_root() {
@@ -286,6 +360,14 @@ mod tests {
 
         let tx_root = calculate_transaction_root(&block.body);
         assert_eq!(block.transactions_root, tx_root, "Must be the same");
+
+        // The generic trie yields the same root over the encoded transactions.
+        let entries = block.body.iter().enumerate().map(|(index, tx)| {
+            let mut encoded = Vec::new();
+            tx.encode_inner(&mut encoded, false);
+            (alloy_rlp::encode(index).into(), encoded.into())
+        });
+        assert_eq!(crate::trie::compute_root(entries), Ok(block.transactions_root));
     }
 
     /// Tests that the receipt root is computed correctly for the regolith block.
@@ -624,6 +706,102 @@ mod tests {
         assert_eq!(block.withdrawals_root, Some(withdrawals_root));
     }
 
+    #[test]
+    fn check_root_from_merged_sorted_leaves() {
+        let leaf = |i: u64| (Nibbles::unpack(keccak256(i.to_be_bytes())), alloy_rlp::encode(i));
+        let base = (0..1000).map(leaf).sorted_by(|(a, _), (b, _)| a.cmp(b)).collect::<Vec<_>>();
+
+        // Every third leaf is updated, every fifth is deleted and new leaves are inserted.
+        let mut expected = base.iter().cloned().collect::<BTreeMap<_, _>>();
+        let mut overlay = BTreeMap::new();
+        for i in (0..1000).step_by(3).chain(1000..1100) {
+            let (path, _) = leaf(i);
+            let value = alloy_rlp::encode(i + 1);
+            expected.insert(path.clone(), value.clone());
+            overlay.insert(path, Some(value));
+        }
+        for i in (0..1000).step_by(5) {
+            let (path, _) = leaf(i);
+            expected.remove(&path);
+            overlay.insert(path, None);
+        }
+        // Deleting a missing leaf is a no-op.
+        overlay.insert(leaf(2000).0, None);
+
+        let root = root_from_merged_sorted_leaves(base.clone(), overlay.clone());
+        assert_eq!(root, root_from_sorted_leaves(expected));
+
+        assert_eq!(
+            root_from_merged_sorted_leaves(base.clone(), std::iter::empty()),
+            root_from_sorted_leaves(base.clone())
+        );
+        let inserted = overlay
+            .into_iter()
+            .filter_map(|(path, value)| Some((path, value?)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            root_from_merged_sorted_leaves(
+                std::iter::empty(),
+                inserted.iter().map(|(path, value)| (path.clone(), Some(value)))
+            ),
+            root_from_sorted_leaves(inserted)
+        );
+        let deleted = base.iter().map(|(path, _)| (path.clone(), None));
+        assert_eq!(root_from_merged_sorted_leaves(base.clone(), deleted), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn check_root_from_sorted_leaves() {
+        let leaves = [
+            (
+                b256!("0100000000000000000000000000000000000000000000000000000000000000"),
+                &b"first"[..],
+            ),
+            (
+                b256!("0200000000000000000000000000000000000000000000000000000000000000"),
+                &b"second"[..],
+            ),
+            (
+                b256!("1000000000000000000000000000000000000000000000000000000000000000"),
+                &b"third"[..],
+            ),
+        ];
+        let expected = ::triehash::trie_root::<super::triehash::KeccakHasher, _, _, _>(leaves);
+        let root = root_from_sorted_leaves(
+            leaves.iter().map(|(key, value)| (Nibbles::unpack(key), value)),
+        );
+        assert_eq!(root, expected);
+
+        // The order of the leaves does not matter when they are sorted first.
+        let shuffled = [leaves[2], leaves[0], leaves[1]];
+        let root =
+            root_from_unsorted(shuffled.iter().map(|(key, value)| (Nibbles::unpack(key), value)));
+        assert_eq!(root, expected);
+
+        // The hashed keys come in no particular order.
+        let hashed = (0..100u64)
+            .map(|i| (Nibbles::unpack(keccak256(i.to_be_bytes())), alloy_rlp::encode(i)))
+            .collect::<Vec<_>>();
+        let sorted = hashed.iter().cloned().sorted_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(root_from_unsorted(hashed), root_from_sorted_leaves(sorted));
+
+        // Build the same trie leaf by leaf and inspect the emitted branch nodes.
+        let mut hb = HashBuilder::default().with_updates(true);
+        for (key, value) in leaves {
+            hb.add_leaf(Nibbles::unpack(key), value);
+        }
+        assert_eq!(hb.root(), expected);
+
+        // The branch at `0x0` only holds leaves, so the root branch is the only one emitted.
+        let (_, updates) = hb.split();
+        assert_eq!(updates.keys().collect::<Vec<_>>(), vec![&Nibbles::default()]);
+        let root_node = &updates[&Nibbles::default()];
+        assert_eq!(root_node.state_mask, TrieMask::new(0b11));
+        assert_eq!(root_node.hash_mask, TrieMask::new(0b01));
+        assert_eq!(root_node.hashes.len(), 1);
+        assert_eq!(root_node.root_hash, Some(expected));
+    }
+
     #[test]
     fn check_empty_state_root() {
         let genesis_alloc = HashMap::<Address, GenesisAccount>::new();
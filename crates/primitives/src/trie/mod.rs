@@ -10,14 +10,17 @@ mod mask;
 pub(crate) use mask::StoredTrieMask;
 
 mod nibbles;
-pub use nibbles::{Nibbles, StoredNibbles, StoredNibblesSubKey};
+pub use nibbles::{Nibbles, NibblesExt, ParseNibblesError, StoredNibbles, StoredNibblesSubKey};
 
 pub mod nodes;
-pub use nodes::StoredBranchNode;
+pub use nodes::{BranchNodeCompactExt, StoredBranchNode};
 
 mod proofs;
 pub use proofs::{AccountProof, StorageProof};
 
+mod root;
+pub use root::{compute_root, PrefixKeyError, SecureTrie, Trie};
+
 mod storage;
 pub use storage::StorageTrieEntry;
 
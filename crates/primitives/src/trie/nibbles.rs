@@ -5,6 +5,59 @@ use serde::{Deserialize, Serialize};
 
 pub use nybbles::Nibbles;
 
+/// Conversions of [`Nibbles`] to and from their hex string and packed byte representations.
+///
+/// The paths of the trie nodes are stored in the database unpacked, one nibble per byte, see
+/// [`StoredNibbles`] and [`StoredNibblesSubKey`], while the keys of the hashed state tables are
+/// the packed 32-byte hashes that are unpacked into the leaf paths with [`Nibbles::unpack`].
+pub trait NibblesExt: Sized {
+    /// Parses the nibbles from the hex string with one nibble per character, optionally prefixed
+    /// with `0x`. Unlike the hex encoding of bytes, the string may have an odd length.
+    fn from_hex_str(s: &str) -> Result<Self, ParseNibblesError>;
+
+    /// Returns the hex string with one character per nibble, without the `0x` prefix.
+    fn to_hex_string(&self) -> String;
+
+    /// Packs the nibbles into bytes, two nibbles per byte. The low nibble of the last byte is
+    /// zero if the number of nibbles is odd, so the length is not preserved.
+    fn pack_to_vec(&self) -> Vec<u8>;
+}
+
+impl NibblesExt for Nibbles {
+    fn from_hex_str(s: &str) -> Result<Self, ParseNibblesError> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        let nibbles = digits
+            .chars()
+            .enumerate()
+            .map(|(index, character)| {
+                character
+                    .to_digit(16)
+                    .map(|nibble| nibble as u8)
+                    .ok_or(ParseNibblesError { index, character })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_nibbles_unchecked(nibbles))
+    }
+
+    fn to_hex_string(&self) -> String {
+        self.iter().map(|nibble| char::from_digit(*nibble as u32, 16).unwrap_or('?')).collect()
+    }
+
+    fn pack_to_vec(&self) -> Vec<u8> {
+        self.pack().to_vec()
+    }
+}
+
+/// The error of parsing [`Nibbles`] from a hex string.
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone, Copy)]
+#[error("invalid nibble {character:?} at position {index}")]
+pub struct ParseNibblesError {
+    /// The position of the invalid character, not counting the `0x` prefix.
+    pub index: usize,
+    /// The invalid character.
+    pub character: char,
+}
+
 /// The representation of nibbles of the merkle trie stored in the database.
 #[derive(
     Clone,
@@ -56,6 +109,17 @@ impl core::borrow::Borrow<[u8]> for StoredNibbles {
     }
 }
 
+impl StoredNibbles {
+    /// Returns `true` if the bytes are the canonical database encoding of the nibbles, i.e. at
+    /// most 64 bytes with one nibble per byte.
+    ///
+    /// Decoding never fails, so a key that is not canonically encoded is read back as nibbles
+    /// greater than `0xf` and breaks the ordering assumed by the trie walk.
+    pub fn is_canonical_encoding(buf: &[u8]) -> bool {
+        buf.len() <= 64 && buf.iter().all(|nibble| *nibble <= 0xf)
+    }
+}
+
 impl Compact for StoredNibbles {
     fn to_compact<B>(self, buf: &mut B) -> usize
     where
@@ -97,6 +161,24 @@ impl From<StoredNibblesSubKey> for Nibbles {
     }
 }
 
+impl StoredNibblesSubKey {
+    /// Returns `true` if the bytes start with the canonical database encoding of the nibbles, i.e.
+    /// 64 bytes with one nibble per byte right-padded with zeros, followed by the number of
+    /// nibbles.
+    ///
+    /// Decoding a subkey with a length greater than 64 panics, so this has to be checked on the
+    /// raw bytes before decoding them.
+    pub fn is_canonical_encoding(buf: &[u8]) -> bool {
+        match buf.get(..65) {
+            Some([nibbles @ .., len]) if *len <= 64 => {
+                let (nibbles, padding) = nibbles.split_at(*len as usize);
+                nibbles.iter().all(|nibble| *nibble <= 0xf) && padding.iter().all(|byte| *byte == 0)
+            }
+            _ => false,
+        }
+    }
+}
+
 impl Compact for StoredNibblesSubKey {
     fn to_compact<B>(self, buf: &mut B) -> usize
     where
@@ -118,3 +200,75 @@ impl Compact for StoredNibblesSubKey {
         (Self(Nibbles::from_nibbles_unchecked(&buf[..len])), &buf[65..])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nibbles_hex_str() {
+        for (s, nibbles) in [
+            ("", vec![]),
+            ("0x", vec![]),
+            ("a", vec![0xa]),
+            ("0x0a1", vec![0x0, 0xa, 0x1]),
+            ("Ab0f", vec![0xa, 0xb, 0x0, 0xf]),
+        ] {
+            let parsed = Nibbles::from_hex_str(s).unwrap();
+            assert_eq!(parsed.as_slice(), &nibbles[..]);
+            assert_eq!(Nibbles::from_hex_str(&parsed.to_hex_string()).unwrap(), parsed);
+        }
+        assert_eq!(Nibbles::from_nibbles([0xa, 0xb, 0x0]).to_hex_string(), "ab0");
+
+        assert_eq!(
+            Nibbles::from_hex_str("0x12g4"),
+            Err(ParseNibblesError { index: 2, character: 'g' })
+        );
+    }
+
+    #[test]
+    fn nibbles_pack_unpack() {
+        // Even number of nibbles round trips.
+        let nibbles = Nibbles::from_nibbles([0x1, 0x2, 0xa, 0xb]);
+        assert_eq!(nibbles.pack_to_vec(), vec![0x12, 0xab]);
+        assert_eq!(Nibbles::unpack(nibbles.pack_to_vec()), nibbles);
+
+        // Odd number of nibbles is padded with a zero nibble.
+        let nibbles = Nibbles::from_nibbles([0x1, 0x2, 0xa]);
+        assert_eq!(nibbles.pack_to_vec(), vec![0x12, 0xa0]);
+        assert_eq!(Nibbles::unpack(nibbles.pack_to_vec()).as_slice(), &[0x1, 0x2, 0xa, 0x0]);
+
+        // The empty path.
+        assert!(Nibbles::default().pack_to_vec().is_empty());
+        assert!(Nibbles::unpack([0u8; 0]).is_empty());
+
+        // The stored path is unpacked, one nibble per byte.
+        let mut buf = Vec::new();
+        StoredNibbles(nibbles).to_compact(&mut buf);
+        assert_eq!(buf, vec![0x1, 0x2, 0xa]);
+    }
+
+    #[test]
+    fn stored_nibbles_canonical_encoding() {
+        let nibbles = Nibbles::from_nibbles([0x1, 0x2, 0xa]);
+
+        let mut buf = Vec::new();
+        StoredNibbles(nibbles.clone()).to_compact(&mut buf);
+        assert!(StoredNibbles::is_canonical_encoding(&buf));
+        assert!(StoredNibbles::is_canonical_encoding(&[]));
+        assert!(!StoredNibbles::is_canonical_encoding(&[0x1, 0x12]));
+        assert!(!StoredNibbles::is_canonical_encoding(&[0; 65]));
+
+        let mut buf = Vec::new();
+        StoredNibblesSubKey(nibbles).to_compact(&mut buf);
+        assert!(StoredNibblesSubKey::is_canonical_encoding(&buf));
+        assert!(!StoredNibblesSubKey::is_canonical_encoding(&buf[..64]));
+
+        // A nibble out of range, a non-zero padding byte and a length out of range.
+        for (index, byte) in [(1, 0x12), (3, 0x1), (64, 65)] {
+            let mut malformed = buf.clone();
+            malformed[index] = byte;
+            assert!(!StoredNibblesSubKey::is_canonical_encoding(&malformed));
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use crate::trie::StoredTrieMask;
+use crate::{keccak256, trie::StoredTrieMask, Bytes};
 use alloy_primitives::B256;
+use alloy_rlp::{Encodable, Header, EMPTY_STRING_CODE};
 use alloy_trie::BranchNodeCompact;
-use bytes::Buf;
+use bytes::{Buf, BufMut};
 use reth_codecs::Compact;
 use serde::{Deserialize, Serialize};
 
@@ -68,9 +69,54 @@ impl Compact for StoredBranchNode {
     }
 }
 
+/// The canonical RLP encoding of [`BranchNodeCompact`], as produced by the reference
+/// implementations of the Merkle Patricia Trie, independent of the stored format.
+pub trait BranchNodeCompactExt {
+    /// Returns the RLP encoding of the branch node, i.e. the list of the 16 child references
+    /// followed by the empty value.
+    ///
+    /// Returns `None` unless all children are in the hash mask. The compact node only retains the
+    /// hashes of the branch children, the references of the leaf children are not stored, whether
+    /// they are hashed or embedded into the node.
+    fn rlp_encode(&self) -> Option<Bytes>;
+
+    /// Returns the hash of the RLP encoding of the branch node, see [`Self::rlp_encode`].
+    fn rlp_hash(&self) -> Option<B256> {
+        self.rlp_encode().map(keccak256)
+    }
+}
+
+impl BranchNodeCompactExt for BranchNodeCompact {
+    fn rlp_encode(&self) -> Option<Bytes> {
+        if self.hash_mask != self.state_mask {
+            return None
+        }
+
+        let payload_length = (0..16u8)
+            .map(
+                |nibble| if self.state_mask.is_bit_set(nibble) { B256::len_bytes() + 1 } else { 1 },
+            )
+            .sum::<usize>() +
+            1;
+        let mut out = Vec::with_capacity(payload_length + 3);
+        Header { list: true, payload_length }.encode(&mut out);
+        for nibble in 0..16u8 {
+            if self.state_mask.is_bit_set(nibble) {
+                self.hash_for_nibble(nibble).encode(&mut out);
+            } else {
+                out.put_u8(EMPTY_STRING_CODE);
+            }
+        }
+        // The value of the branch node is always empty in the state and storage tries.
+        out.put_u8(EMPTY_STRING_CODE);
+        Some(out.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::trie::{HashBuilder, Nibbles, TrieMask};
     use alloy_primitives::hex;
 
     #[test]
@@ -90,4 +136,57 @@ mod tests {
         let compact_len = StoredBranchNode(n.clone()).to_compact(&mut out);
         assert_eq!(StoredBranchNode::from_compact(&out, compact_len).0 .0, n);
     }
+
+    #[test]
+    fn node_rlp_encoding() {
+        // The storage trie node of `0x4242424242424242424242424242424242424242` at block zero of
+        // the Holesky testnet, see the storage proof of slot `0x22`.
+        let node = BranchNodeCompact::new(
+            0b1000_0000_0000_0010,
+            0,
+            0b1000_0000_0000_0010,
+            vec![
+                hex!("776aa456ba9c5008e03b82b841a9cf2fc1e8578cfacd5c9015804eae315f17fb").into(),
+                hex!("72e3e284d47badbb0a5ca1421e1179d3ea90cc10785b26b74fb8a81f0f9e8418").into(),
+            ],
+            None,
+        );
+        let expected = hex!("f85180a0776aa456ba9c5008e03b82b841a9cf2fc1e8578cfacd5c9015804eae315f17fb80808080808080808080808080a072e3e284d47badbb0a5ca1421e1179d3ea90cc10785b26b74fb8a81f0f9e841880");
+        assert_eq!(node.rlp_encode().as_deref(), Some(&expected[..]));
+        assert_eq!(node.rlp_hash(), Some(keccak256(expected)));
+
+        // The parent node references the node by its hash.
+        let parent = hex!("f9019180a0aafd5b14a6edacd149e110ba6776a654f2dbffca340902be933d011113f2750380a0a502c93b1918c4c6534d4593ae03a5a23fa10ebc30ffb7080b297bff2446e42da02eb2bf45fd443bd1df8b6f9c09726a4c6252a0f7896a131a081e39a7f644b38980a0a9cf7f673a0bce76fd40332afe8601542910b48dea44e93933a3e5e930da5d19a0ddf79db0a36d0c8134ba143bcb541cd4795a9a2bae8aca0ba24b8d8963c2a77da0b973ec0f48f710bf79f63688485755cbe87f9d4c68326bb83c26af620802a80ea0f0855349af6bf84afc8bca2eda31c8ef8c5139be1929eeb3da4ba6b68a818cb0a0c271e189aeeb1db5d59d7fe87d7d6327bbe7cfa389619016459196497de3ccdea0e7503ba5799e77aa31bbe1310c312ca17b2c5bcc8fa38f266675e8f154c2516ba09278b846696d37213ab9d20a5eb42b03db3173ce490a2ef3b2f3b3600579fc63a0e9041059114f9c910adeca12dbba1fef79b2e2c8899f2d7213cd22dfe4310561a047c59da56bb2bf348c9dd2a2e8f5538a92b904b661cfe54a4298b85868bbe4858080");
+        let hash = node.rlp_hash().unwrap();
+        assert!(parent.windows(B256::len_bytes()).any(|window| window == hash.as_slice()));
+
+        // The reference of a child outside of the hash mask is not retained.
+        let embedded = BranchNodeCompact::new(0b11, 0, 0b01, vec![B256::ZERO], None);
+        assert_eq!(embedded.rlp_encode(), None);
+    }
+
+    #[test]
+    fn node_rlp_encoding_with_leaf_children() {
+        let root_node = |keys: &[u8]| {
+            let mut hash_builder = HashBuilder::default().with_updates(true);
+            for key in keys {
+                // The values are long enough for the leaves to be referenced by their hashes.
+                hash_builder.add_leaf(Nibbles::from_nibbles([key >> 4, key & 0xf]), &[0xff; 32]);
+            }
+            let root = hash_builder.root();
+            let (_, updates) = hash_builder.split();
+            (root, updates[&Nibbles::default()].clone())
+        };
+
+        // Both children of the root are branches, so the root node is fully hashed.
+        let (root, node) = root_node(&[0x00, 0x01, 0x10, 0x11]);
+        assert_eq!(node.hash_mask, node.state_mask);
+        assert_eq!(node.rlp_hash(), Some(root));
+
+        // The leaf child at nibble 1 is hashed in the trie but left out of the hash mask.
+        let (_, node) = root_node(&[0x00, 0x01, 0x10]);
+        assert_eq!(node.state_mask, TrieMask::new(0b11));
+        assert_eq!(node.hash_mask, TrieMask::new(0b01));
+        assert_eq!(node.rlp_encode(), None);
+    }
 }
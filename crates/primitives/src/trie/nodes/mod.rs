@@ -1,6 +1,6 @@
 //! Various branch nodes produced by the hash builder.
 
 mod branch;
-pub use branch::StoredBranchNode;
+pub use branch::{BranchNodeCompactExt, StoredBranchNode};
 
 pub use alloy_trie::nodes::*;
@@ -5,11 +5,11 @@ use super::{
     Nibbles, TrieAccount,
 };
 use crate::{keccak256, Account, Address, Bytes, B256, U256};
-use alloy_rlp::encode_fixed_size;
+use alloy_rlp::{encode_fixed_size, Decodable, Header};
 use alloy_trie::EMPTY_ROOT_HASH;
 
 /// The merkle proof with the relevant account info.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct AccountProof {
     /// The address associated with the account.
     pub address: Address,
@@ -22,6 +22,8 @@ pub struct AccountProof {
     pub storage_root: B256,
     /// Array of storage proofs as requested.
     pub storage_proofs: Vec<StorageProof>,
+    /// The account bytecode, if requested and the account has non-empty code.
+    pub code: Option<Bytes>,
 }
 
 impl AccountProof {
@@ -33,6 +35,7 @@ impl AccountProof {
             proof: Vec::new(),
             storage_root: EMPTY_ROOT_HASH,
             storage_proofs: Vec::new(),
+            code: None,
         }
     }
 
@@ -53,6 +56,25 @@ impl AccountProof {
         self.proof = proof;
     }
 
+    /// Set account bytecode.
+    pub fn set_code(&mut self, code: Bytes) {
+        self.code = Some(code);
+    }
+
+    /// Brings the proof into its canonical form, so that the proofs with the same nodes compare
+    /// equal regardless of the order the nodes were collected in.
+    ///
+    /// The nodes of the account proof and of every storage proof are ordered along the path, see
+    /// [`StorageProof::canonicalize`], and the storage proofs are sorted by their slots. A valid
+    /// proof stays valid.
+    pub fn canonicalize(&mut self) {
+        canonicalize_proof_nodes(&mut self.proof);
+        for storage_proof in &mut self.storage_proofs {
+            storage_proof.canonicalize();
+        }
+        self.storage_proofs.sort_by_key(|storage_proof| storage_proof.key);
+    }
+
     /// Verify the storage proofs and account proof against the provided state root.
     pub fn verify(&self, root: B256) -> Result<(), ProofVerificationError> {
         // Verify storage proofs.
@@ -72,10 +94,85 @@ impl AccountProof {
         let nibbles = Nibbles::unpack(keccak256(self.address));
         verify_proof(root, nibbles, expected, &self.proof)
     }
+
+    /// Returns the account proven by the proof, decoded from the value of the leaf node at the
+    /// end of the proof, or `None` if the proof proves the absence of the account.
+    ///
+    /// The proof is expected to be verified with [`Self::verify`], the nodes are not checked
+    /// against each other. The encoded account alone is longer than a hash, so the account leaf is
+    /// never embedded into its parent and is always the last node of the proof.
+    pub fn decoded_account(&self) -> Result<Option<TrieAccount>, alloy_rlp::Error> {
+        let Some(node) = self.proof.last() else { return Ok(None) };
+        let Some((key, mut value)) = decode_leaf(node)? else { return Ok(None) };
+        if !Nibbles::unpack(keccak256(self.address)).ends_with(&key) {
+            return Ok(None)
+        }
+
+        let account = TrieAccount::decode(&mut value)?;
+        if !value.is_empty() {
+            return Err(alloy_rlp::Error::Custom("trailing bytes after the account"))
+        }
+        Ok(Some(account))
+    }
+}
+
+/// Decodes the remainder of the key and the value of the leaf node, or returns `None` if the node
+/// is a branch or an extension node.
+fn decode_leaf(mut node: &[u8]) -> Result<Option<(Vec<u8>, &[u8])>, alloy_rlp::Error> {
+    let header = Header::decode(&mut node)?;
+    if !header.list {
+        return Err(alloy_rlp::Error::UnexpectedString)
+    }
+    if node.len() != header.payload_length {
+        return Err(alloy_rlp::Error::UnexpectedLength)
+    }
+
+    // The first item of a branch node may be an embedded child node.
+    if Header::decode(&mut &node[..])?.list {
+        return Ok(None)
+    }
+    let path = next_string(&mut node)?;
+    let value = next_string(&mut node)?;
+    // The branch nodes have 17 items, the leaf and extension nodes have 2.
+    if !node.is_empty() {
+        return Ok(None)
+    }
+
+    let Some((&first, rest)) = path.split_first() else {
+        return Err(alloy_rlp::Error::Custom("empty node path"))
+    };
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(alloy_rlp::Error::Custom("invalid hex-prefix flag"))
+    }
+    if flag < 2 {
+        return Ok(None)
+    }
+
+    let mut key = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 1 == 1 {
+        key.push(first & 0x0f);
+    }
+    key.extend(rest.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]));
+    Ok(Some((key, value)))
+}
+
+/// Decodes the next RLP string item of the list and returns its payload.
+fn next_string<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], alloy_rlp::Error> {
+    let header = Header::decode(buf)?;
+    if header.list {
+        return Err(alloy_rlp::Error::UnexpectedList)
+    }
+    if buf.len() < header.payload_length {
+        return Err(alloy_rlp::Error::InputTooShort)
+    }
+    let (payload, rest) = buf.split_at(header.payload_length);
+    *buf = rest;
+    Ok(payload)
 }
 
 /// The merkle proof of the storage entry.
-#[derive(PartialEq, Eq, Default, Debug)]
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
 pub struct StorageProof {
     /// The raw storage key.
     pub key: B256,
@@ -115,6 +212,16 @@ impl StorageProof {
         self.proof = proof;
     }
 
+    /// Brings the proof into its canonical form, so that the proofs with the same nodes compare
+    /// equal regardless of the order the nodes were collected in.
+    ///
+    /// The duplicate nodes are removed and the rest are ordered from the root along the path, the
+    /// only order accepted by [`Self::verify`], so a valid proof stays valid. The nodes off the
+    /// path, which fail the verification anyway, are moved to the end in the order of their hashes.
+    pub fn canonicalize(&mut self) {
+        canonicalize_proof_nodes(&mut self.proof);
+    }
+
     /// Verify the proof against the provided storage root.
     pub fn verify(&self, root: B256) -> Result<(), ProofVerificationError> {
         let expected =
@@ -122,3 +229,206 @@ impl StorageProof {
         verify_proof(root, self.nibbles.clone(), expected, &self.proof)
     }
 }
+
+/// Orders the proof nodes from the root along the path and removes the duplicates.
+///
+/// Every node on the path but the root is referenced by its parent, either by its hash or inline
+/// if its RLP is shorter than 32 bytes, so the order is recovered from the child references of the
+/// decoded nodes. The root is the node not referenced by any other node.
+fn canonicalize_proof_nodes(proof: &mut Vec<Bytes>) {
+    let references = |parent: &Bytes, child: &Bytes| {
+        let hash;
+        let reference = if child.len() < 32 {
+            child.as_ref()
+        } else {
+            hash = keccak256(child);
+            hash.as_slice()
+        };
+        parent != child && child_references(parent).contains(&reference)
+    };
+
+    let mut remaining = std::mem::take(proof);
+    remaining.sort_unstable_by_key(keccak256);
+    remaining.dedup();
+
+    let mut next =
+        remaining.iter().position(|node| !remaining.iter().any(|other| references(other, node)));
+    while let Some(index) = next {
+        let node = remaining.remove(index);
+        next = remaining.iter().position(|child| references(&node, child));
+        proof.push(node);
+    }
+    proof.extend(remaining);
+}
+
+/// Returns the references to the children of the node, i.e. the hashes of the hashed children and
+/// the RLP encodings of the children embedded into the node. The leaf nodes and the nodes that fail
+/// to decode have no children.
+fn child_references(mut node: &[u8]) -> Vec<&[u8]> {
+    let Ok(header) = Header::decode(&mut node) else { return Vec::new() };
+    if !header.list || node.len() != header.payload_length {
+        return Vec::new()
+    }
+
+    let mut items = Vec::with_capacity(17);
+    while !node.is_empty() {
+        let item = node;
+        let Ok(header) = Header::decode(&mut node) else { return Vec::new() };
+        if node.len() < header.payload_length {
+            return Vec::new()
+        }
+        let (payload, rest) = node.split_at(header.payload_length);
+        node = rest;
+        // The embedded child nodes are referenced by their whole encoding.
+        items.push(if header.list { &item[..item.len() - rest.len()] } else { payload });
+    }
+
+    match items.len() {
+        // The branch node references its children by the first 16 items, the last is the value.
+        17 => items.into_iter().take(16).filter(|item| !item.is_empty()).collect(),
+        // The extension node references its child, the leaf node holds the value.
+        2 if items[0].first().is_some_and(|first| first >> 4 < 2) => vec![items[1]],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trie::{proof::ProofRetainer, HashBuilder};
+    use alloy_rlp::{Encodable, EMPTY_STRING_CODE};
+
+    #[test]
+    fn canonical_account_proof() {
+        let accounts = (0..100u64)
+            .map(|i| {
+                let account = Account { nonce: i, ..Default::default() };
+                (keccak256(Address::with_last_byte(i as u8)), account)
+            })
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let address = Address::with_last_byte(42);
+        let target = Nibbles::unpack(keccak256(address));
+
+        let mut hb = HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target]));
+        for (hashed_address, account) in &accounts {
+            let value = alloy_rlp::encode(TrieAccount::from((*account, EMPTY_ROOT_HASH)));
+            hb.add_leaf(Nibbles::unpack(hashed_address), &value);
+        }
+        let root = hb.root();
+
+        let mut proof = AccountProof::new(address);
+        proof.set_account(accounts[&keccak256(address)], EMPTY_ROOT_HASH, Vec::new());
+        proof.set_proof(hb.take_proofs().into_values().collect());
+        assert!(proof.proof.len() > 1);
+        assert_eq!(proof.verify(root), Ok(()));
+
+        // The proof collected in the order of the path is already canonical.
+        let mut canonical = proof.clone();
+        canonical.canonicalize();
+        assert_eq!(canonical, proof);
+
+        // The reordered and duplicated nodes no longer verify until canonicalized.
+        let mut shuffled = proof.clone();
+        shuffled.proof.reverse();
+        shuffled.proof.push(proof.proof[0].clone());
+        assert_ne!(shuffled, proof);
+        assert!(shuffled.verify(root).is_err());
+        shuffled.canonicalize();
+        assert_eq!(shuffled, proof);
+        assert_eq!(shuffled.verify(root), Ok(()));
+
+        // The storage proofs are ordered by their slots.
+        let storage_proofs =
+            [B256::with_last_byte(2), B256::with_last_byte(1)].map(StorageProof::new);
+        let mut with_storage = proof.clone();
+        with_storage.storage_proofs = storage_proofs.to_vec();
+        let mut reordered = proof;
+        reordered.storage_proofs = storage_proofs.into_iter().rev().collect();
+        with_storage.canonicalize();
+        reordered.canonicalize();
+        assert_eq!(with_storage, reordered);
+    }
+
+    #[test]
+    fn proof_node_child_references() {
+        let hash = B256::repeat_byte(0xab);
+        let embedded =
+            alloy_rlp::encode(vec![Bytes::from_static(&[0x20]), Bytes::from_static(&[0x01])]);
+
+        // The branch node with a hashed child and an embedded one.
+        let mut payload = Vec::new();
+        hash.encode(&mut payload);
+        payload.extend_from_slice(&embedded);
+        payload.extend([EMPTY_STRING_CODE; 15]);
+        let mut branch = Vec::new();
+        Header { list: true, payload_length: payload.len() }.encode(&mut branch);
+        branch.extend(payload);
+        assert_eq!(child_references(&branch), vec![hash.as_slice(), &embedded[..]]);
+
+        let extension = alloy_rlp::encode(vec![
+            Bytes::from_static(&[0x00, 0x12]),
+            Bytes::copy_from_slice(hash.as_slice()),
+        ]);
+        assert_eq!(child_references(&extension), vec![hash.as_slice()]);
+
+        // The hash in the value of the leaf node is not a child reference.
+        let leaf = alloy_rlp::encode(vec![
+            Bytes::from_static(&[0x20]),
+            Bytes::copy_from_slice(hash.as_slice()),
+        ]);
+        assert!(leaf.windows(B256::len_bytes()).any(|window| window == hash.as_slice()));
+        assert!(child_references(&leaf).is_empty());
+    }
+
+    #[test]
+    fn decoded_account_from_proof() {
+        let storage_root = B256::with_last_byte(0xaa);
+        let accounts = (0..100u64)
+            .map(|i| {
+                let account = Account { nonce: i, balance: U256::from(i), ..Default::default() };
+                (keccak256(Address::with_last_byte(i as u8)), account)
+            })
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let account_proof = |address: Address| {
+            let target = Nibbles::unpack(keccak256(address));
+            let mut hb =
+                HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target]));
+            for (hashed_address, account) in &accounts {
+                let value = alloy_rlp::encode(TrieAccount::from((*account, storage_root)));
+                hb.add_leaf(Nibbles::unpack(hashed_address), &value);
+            }
+            let root = hb.root();
+
+            let mut proof = AccountProof::new(address);
+            if let Some(account) = accounts.get(&keccak256(address)) {
+                proof.set_account(*account, storage_root, Vec::new());
+            }
+            proof.set_proof(hb.take_proofs().into_values().collect());
+            assert_eq!(proof.verify(root), Ok(()));
+            proof
+        };
+
+        let address = Address::with_last_byte(42);
+        assert_eq!(
+            account_proof(address).decoded_account(),
+            Ok(Some(TrieAccount::from((accounts[&keccak256(address)], storage_root))))
+        );
+
+        // The exclusion proof and the proof of the empty trie prove no account.
+        assert_eq!(account_proof(Address::with_last_byte(200)).decoded_account(), Ok(None));
+        assert_eq!(AccountProof::new(address).decoded_account(), Ok(None));
+
+        // The leaf of the account with a value that is not an account is rejected.
+        let mut proof = AccountProof::new(address);
+        let path = [&[0x20][..], keccak256(address).as_slice()].concat();
+        let leaf = alloy_rlp::encode(vec![Bytes::from(path), Bytes::from_static(&[0x01])]);
+        proof.set_proof(vec![leaf.into()]);
+        assert!(proof.decoded_account().is_err());
+
+        // The truncated node is rejected.
+        let mut truncated = account_proof(address);
+        let last = truncated.proof.pop().unwrap();
+        truncated.proof.push(Bytes::copy_from_slice(&last[..last.len() - 1]));
+        assert!(truncated.decoded_account().is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use super::{HashBuilder, Nibbles};
+use crate::{keccak256, Bytes, B256};
+use alloy_rlp::Encodable;
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// The builder of a Merkle Patricia Trie over arbitrary byte keys and RLP encodable values.
+///
+/// The entries can be inserted in any order, they are sorted by their keys before being fed into
+/// the [`HashBuilder`], so the root does not depend on the insertion order. Inserting an existing
+/// key replaces its value. The keys are used as the paths of the leaves as is, see
+/// [`crate::proofs::state_root`] and [`crate::proofs::storage_root`] for the special cases keyed
+/// by the hashes of the addresses and the storage slots.
+///
+/// The [`HashBuilder`] has no support for values stored in branch nodes, so no key may be a
+/// prefix of another key, see [`PrefixKeyError`]. The RLP encoded indices of the transaction and
+/// receipt tries satisfy this by construction.
+#[derive(Clone, Debug)]
+pub struct Trie<K, V> {
+    /// The values by the paths of their keys.
+    entries: BTreeMap<Nibbles, V>,
+    _key: PhantomData<fn(K)>,
+}
+
+impl<K, V> Default for Trie<K, V> {
+    fn default() -> Self {
+        Self { entries: BTreeMap::new(), _key: PhantomData }
+    }
+}
+
+impl<K: AsRef<[u8]>, V: Encodable> Trie<K, V> {
+    /// Creates an empty trie.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the value at the given key, returning the previous value of the key if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.entries.insert(Nibbles::unpack(key), value)
+    }
+
+    /// Returns the number of entries in the trie.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the trie has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Computes the root hash of the trie with the RLP encoded values in the leaves.
+    ///
+    /// Returns an error if a key is a prefix of another key.
+    pub fn root(&self) -> Result<B256, PrefixKeyError> {
+        root_from_sorted(
+            self.entries.iter().map(|(path, value)| (path.clone(), alloy_rlp::encode(value))),
+        )
+    }
+}
+
+impl<K: AsRef<[u8]>, V: Encodable> FromIterator<(K, V)> for Trie<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut trie = Self::new();
+        for (key, value) in iter {
+            trie.insert(key, value);
+        }
+        trie
+    }
+}
+
+/// Computes the root hash of the Merkle Patricia Trie with the given keys and values.
+///
+/// Unlike [`Trie`], the values are put into the leaves as they are, e.g. the already encoded
+/// transactions or receipts keyed by their RLP encoded indices. The entries can be given in any
+/// order, the last value of a repeated key is used and the entries with empty values are left out,
+/// as an empty value is equivalent to an absent key.
+///
+/// Returns an error if a key is a prefix of another key.
+pub fn compute_root(
+    entries: impl IntoIterator<Item = (Bytes, Bytes)>,
+) -> Result<B256, PrefixKeyError> {
+    let entries = entries
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| (Nibbles::unpack(key), value))
+        .collect::<BTreeMap<_, _>>();
+    root_from_sorted(entries.into_iter())
+}
+
+/// The secure variant of the Merkle Patricia Trie, keyed by the keccak256 hashes of the raw keys.
+///
+/// This is how the account and storage tries are keyed, so the root of the raw addresses and the
+/// RLP encoded [`TrieAccount`](super::TrieAccount)s is the state root. The hashed keys all have
+/// the same length, so unlike with [`compute_root`] no key can be a prefix of another.
+#[derive(Clone, Copy, Debug)]
+pub struct SecureTrie;
+
+impl SecureTrie {
+    /// Computes the root hash of the secure trie with the given raw keys and values.
+    ///
+    /// The keys are hashed before insertion, the values are put into the leaves as they are, see
+    /// [`compute_root`].
+    pub fn root(entries: impl IntoIterator<Item = (Bytes, Bytes)>) -> B256 {
+        compute_root(entries.into_iter().map(|(key, value)| (Bytes::from(keccak256(key).0), value)))
+            .expect("hashed keys of the same length are not prefixes of each other")
+    }
+}
+
+/// The error of the root of a trie with a key that is a prefix of another key, see [`Trie`].
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone)]
+#[error("key {prefix} is a prefix of key {key}")]
+pub struct PrefixKeyError {
+    /// The shorter key.
+    pub prefix: Bytes,
+    /// The key starting with the shorter key.
+    pub key: Bytes,
+}
+
+/// Feeds the leaves sorted by their paths into the [`HashBuilder`] and returns the root hash.
+fn root_from_sorted<V: AsRef<[u8]>>(
+    leaves: impl Iterator<Item = (Nibbles, V)>,
+) -> Result<B256, PrefixKeyError> {
+    let mut hb = HashBuilder::default();
+    let mut last: Option<Nibbles> = None;
+    for (path, value) in leaves {
+        if let Some(last) = last.as_ref().filter(|last| path.starts_with(last)) {
+            return Err(PrefixKeyError {
+                prefix: Bytes::copy_from_slice(&last.pack()),
+                key: Bytes::copy_from_slice(&path.pack()),
+            })
+        }
+        hb.add_leaf(path.clone(), value.as_ref());
+        last = Some(path);
+    }
+    Ok(hb.root())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::EMPTY_ROOT_HASH,
+        proofs::{state_root_unhashed, triehash::KeccakHasher},
+        trie::TrieAccount,
+        Account, Address, U256,
+    };
+
+    #[test]
+    fn generic_root_matches_triehash() {
+        assert_eq!(Trie::<Bytes, u64>::new().root(), Ok(EMPTY_ROOT_HASH));
+        assert_eq!(compute_root([]), Ok(EMPTY_ROOT_HASH));
+
+        // The RLP encoded indices have different lengths, but none is a prefix of another.
+        let entries = (0..1_000u64)
+            .map(|i| (alloy_rlp::encode(i), alloy_rlp::encode(i * i)))
+            .collect::<Vec<_>>();
+        let expected = triehash::trie_root::<KeccakHasher, _, _, _>(entries.clone());
+
+        // The insertion order does not matter.
+        let trie =
+            (0..1_000u64).rev().map(|i| (alloy_rlp::encode(i), i * i)).collect::<Trie<_, _>>();
+        assert_eq!(trie.len(), 1_000);
+        assert_eq!(trie.root(), Ok(expected));
+
+        let raw = entries.into_iter().map(|(key, value)| (key.into(), value.into()));
+        assert_eq!(
+            compute_root(raw.chain([(Bytes::from_static(&[0xff]), Bytes::new())])),
+            Ok(expected)
+        );
+    }
+
+    #[test]
+    fn secure_root_matches_state_root() {
+        let accounts = (0..100u64)
+            .map(|i| {
+                let account =
+                    Account { nonce: i, balance: U256::from(i) << 64, ..Default::default() };
+                (Address::from_word(B256::from(U256::from(i))), account)
+            })
+            .collect::<Vec<_>>();
+        let expected = state_root_unhashed(
+            accounts.iter().map(|(address, account)| (*address, (*account, EMPTY_ROOT_HASH))),
+        );
+
+        let entries = accounts.into_iter().map(|(address, account)| {
+            let value = alloy_rlp::encode(TrieAccount::from((account, EMPTY_ROOT_HASH)));
+            (Bytes::copy_from_slice(address.as_slice()), value.into())
+        });
+        assert_eq!(SecureTrie::root(entries), expected);
+    }
+
+    #[test]
+    fn generic_root_rejects_prefix_keys() {
+        let err =
+            PrefixKeyError { prefix: Bytes::from_static(b"dog"), key: Bytes::from_static(b"doge") };
+        assert_eq!(
+            compute_root([
+                (Bytes::from_static(b"doge"), Bytes::from_static(b"coin")),
+                (Bytes::from_static(b"dog"), Bytes::from_static(b"puppy")),
+            ]),
+            Err(err.clone())
+        );
+        let trie = Trie::from_iter([(b"dog".to_vec(), 1u64), (b"doge".to_vec(), 2)]);
+        assert_eq!(trie.root(), Err(err));
+    }
+}
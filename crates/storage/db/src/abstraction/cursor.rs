@@ -74,6 +74,11 @@ pub trait DbDupCursorRO<T: DupSort> {
 
     /// Positions the cursor at the entry greater than or equal to the provided key/subkey pair.
     ///
+    /// The returned value is always one of the duplicates of the given key: the first one, in the
+    /// sort order of the encoded values, whose subkey is greater than or equal to the given subkey.
+    /// If the key has no such duplicate, `None` is returned rather than a duplicate of the next
+    /// key.
+    ///
     /// # Note
     ///
     /// The position of the cursor might not correspond to the key/subkey pair if the entry does not
@@ -26,6 +26,15 @@ pub trait DbTx: Send + Sync {
     fn entries<T: Table>(&self) -> Result<usize, DatabaseError>;
     /// Disables long-lived read transaction safety guarantees.
     fn disable_long_read_transaction_safety(&mut self);
+    /// Hints the operating system that large parts of the database are about to be read
+    /// sequentially, e.g. by a full walk of the hashed state and the trie tables with a cold page
+    /// cache, so that it prefetches the pages ahead of the reads.
+    ///
+    /// The hint does not change the data read through the transaction. It is a no-op by default
+    /// and on the platforms that do not support it.
+    fn advise_sequential_reads(&self) -> Result<(), DatabaseError> {
+        Ok(())
+    }
 }
 
 /// Read write transaction that allows writing to database
@@ -321,6 +321,13 @@ impl<K: TransactionKind> DbTx for Tx<K> {
             .entries())
     }
 
+    /// Asks MDBX to prefetch the pages of the database file, see
+    /// [`reth_libmdbx::Transaction::advise_prefetch`].
+    fn advise_sequential_reads(&self) -> Result<(), DatabaseError> {
+        self.inner.advise_prefetch().map_err(|e| DatabaseError::Read(e.into()))?;
+        Ok(())
+    }
+
     /// Disables long-lived read transaction safety guarantees, such as backtrace recording and
     /// timeout.
     fn disable_long_read_transaction_safety(&mut self) {
@@ -249,6 +249,22 @@ where
         }
     }
 
+    /// Hints the operating system to asynchronously prefetch the pages of the database, e.g.
+    /// before large sequential reads with a cold page cache.
+    ///
+    /// The hint covers the allocated portion of the database file and does not change the data
+    /// read through the transaction. Returns `false` if the platform does not support the hint, in
+    /// which case it is a no-op.
+    pub fn advise_prefetch(&self) -> Result<bool> {
+        let code = self.txn_execute(|txn| unsafe {
+            ffi::mdbx_env_warmup(ptr::null(), txn, ffi::MDBX_warmup_default, 0)
+        })?;
+        match code {
+            ffi::MDBX_ENOSYS => Ok(false),
+            code => mdbx_result(code).map(|_| true),
+        }
+    }
+
     /// Open a new cursor on the given database.
     pub fn cursor(&self, db: &Database) -> Result<Cursor<K>> {
         Cursor::new(self.clone(), db.dbi())
@@ -596,16 +596,18 @@ mod tests {
         mdbx::DatabaseArguments,
         tables,
         test_utils::{create_test_static_files_dir, ERROR_TEMPDIR},
+        transaction::DbTxMut,
     };
     use reth_primitives::{
-        hex_literal::hex, ChainSpecBuilder, PruneMode, PruneModes, SealedBlock, StaticFileSegment,
-        TxNumber, B256, U256,
+        hex_literal::hex, Account, ChainSpecBuilder, GotExpected, Header, PruneMode, PruneModes,
+        SealedBlock, StaticFileSegment, TxNumber, B256, U256,
     };
     use reth_storage_errors::provider::ProviderError;
     use reth_testing_utils::{
         generators,
         generators::{random_block, random_header},
     };
+    use reth_trie::StateRoot;
     use std::{ops::RangeInclusive, sync::Arc};
     use tokio::sync::watch;
 
@@ -766,4 +768,49 @@ mod tests {
         assert_eq!(gap.local_head, head);
         assert_eq!(gap.target.tip(), consensus_tip.into());
     }
+
+    #[test]
+    fn verify_latest_state_root_with_static_files() {
+        let hashed_address = B256::with_last_byte(1);
+        let account = Account { nonce: 1, ..Default::default() };
+        let state_root = {
+            let factory = create_test_provider_factory();
+            let provider_rw = factory.provider_rw().unwrap();
+            provider_rw.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            StateRoot::from_tx(provider_rw.tx_ref()).root().unwrap()
+        };
+
+        // The header of the best block is either in the database or in the static files.
+        let verify = |root: B256, in_static_files: bool| {
+            let factory = create_test_provider_factory();
+            let provider_rw = factory.provider_rw().unwrap();
+            provider_rw.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            let head = Header { state_root: root, ..Default::default() }.seal_slow();
+            if in_static_files {
+                let mut static_file_writer = provider_rw
+                    .static_file_provider()
+                    .latest_writer(StaticFileSegment::Headers)
+                    .unwrap();
+                static_file_writer
+                    .append_header(head.header().clone(), U256::ZERO, head.hash())
+                    .unwrap();
+                static_file_writer.commit().unwrap();
+            } else {
+                provider_rw.tx_ref().put::<tables::Headers>(0, head.header().clone()).unwrap();
+                provider_rw.tx_ref().put::<tables::CanonicalHeaders>(0, head.hash()).unwrap();
+            }
+            provider_rw.commit().unwrap();
+            factory.provider().unwrap().verify_latest_state_root()
+        };
+
+        assert_eq!(verify(state_root, false), Ok(state_root));
+        assert_eq!(verify(state_root, true), Ok(state_root));
+        for in_static_files in [false, true] {
+            assert_matches!(
+                verify(B256::ZERO, in_static_files),
+                Err(ProviderError::StateRootMismatch(mismatch))
+                    if mismatch.root == GotExpected { got: state_root, expected: B256::ZERO }
+            );
+        }
+    }
 }
@@ -358,6 +358,29 @@ impl<TX: DbTx> DatabaseProvider<TX> {
     pub fn chain_spec(&self) -> &ChainSpec {
         &self.chain_spec
     }
+
+    /// Computes the state root of the latest state and checks it against the state root of the
+    /// best block, returning the root.
+    ///
+    /// The hashed state and the trie tables are always read from the database, while the header
+    /// of the best block is read from the static files if the headers have been moved there, so
+    /// the root is verified the same way on any static file layout.
+    pub fn verify_latest_state_root(&self) -> ProviderResult<B256> {
+        let best_block = self.best_block_number()?;
+        let best_header = self
+            .sealed_header(best_block)?
+            .ok_or_else(|| ProviderError::HeaderNotFound(best_block.into()))?;
+        let state_root =
+            StateRoot::from_tx(&self.tx).root().map_err(Into::<reth_db::DatabaseError>::into)?;
+        if state_root != best_header.state_root {
+            return Err(ProviderError::StateRootMismatch(Box::new(RootMismatch {
+                root: GotExpected { got: state_root, expected: best_header.state_root },
+                block_number: best_block,
+                block_hash: best_header.hash(),
+            })))
+        }
+        Ok(state_root)
+    }
 }
 
 impl<TX: DbTxMut + DbTx> DatabaseProvider<TX> {
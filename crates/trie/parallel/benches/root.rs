@@ -2,7 +2,7 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use proptest::{prelude::*, strategy::ValueTree, test_runner::TestRunner};
 use rayon::ThreadPoolBuilder;
-use reth_primitives::{Account, B256, U256};
+use reth_primitives::{keccak256, Account, B256, U256};
 use reth_provider::{
     bundle_state::HashedStateChanges, providers::ConsistentDbView,
     test_utils::create_test_provider_factory,
@@ -56,6 +56,29 @@ pub fn calculate_state_root(c: &mut Criterion) {
             )
         });
 
+        // state root with storage trie prefetching
+        group.bench_function(BenchmarkId::new("sync root with prefetch", size), |b| {
+            b.to_async(&runtime).iter_with_setup(
+                || {
+                    let sorted_state = updated_state.clone().into_sorted();
+                    let prefix_sets = updated_state.construct_prefix_sets();
+                    let provider = provider_factory.provider().unwrap();
+                    let db = provider_factory.db_ref().clone();
+                    (provider, db, sorted_state, prefix_sets)
+                },
+                |(provider, db, sorted_state, prefix_sets)| async move {
+                    StateRoot::from_tx(provider.tx_ref())
+                        .with_hashed_cursor_factory(HashedPostStateCursorFactory::new(
+                            provider.tx_ref(),
+                            &sorted_state,
+                        ))
+                        .with_prefix_sets(prefix_sets)
+                        .with_prefetch(64, db)
+                        .root()
+                },
+            )
+        });
+
         // parallel root
         group.bench_function(BenchmarkId::new("parallel root", size), |b| {
             b.to_async(&runtime).iter_with_setup(
@@ -74,6 +97,76 @@ pub fn calculate_state_root(c: &mut Criterion) {
     }
 }
 
+pub fn parallel_storage_threshold(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Parallel Storage Threshold");
+    group.sample_size(20);
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    for size in [1_000, 5_000] {
+        let (db_state, updated_state) = generate_block_test_data(size);
+        let provider_factory = create_test_provider_factory();
+        {
+            let provider_rw = provider_factory.provider_rw().unwrap();
+            HashedStateChanges(db_state).write_to_db(provider_rw.tx_ref()).unwrap();
+            let (_, updates) =
+                StateRoot::from_tx(provider_rw.tx_ref()).root_with_updates().unwrap();
+            updates.flush(provider_rw.tx_ref()).unwrap();
+            provider_rw.commit().unwrap();
+        }
+
+        let view = ConsistentDbView::new(provider_factory.clone(), None);
+
+        for threshold in [0, 1, 4, 16, 64] {
+            group.bench_function(
+                BenchmarkId::new(format!("parallel root with storage threshold {threshold}"), size),
+                |b| {
+                    b.to_async(&runtime).iter_with_setup(
+                        || {
+                            ParallelStateRoot::new(view.clone(), updated_state.clone())
+                                .with_parallel_storage_threshold(threshold)
+                        },
+                        |calculator| async { calculator.incremental_root() },
+                    );
+                },
+            );
+        }
+    }
+}
+
+/// Generates the state of accounts with 100 slots each and the changes of a typical block, where
+/// most of the changed accounts have no storage changes, some change a few slots and a handful
+/// change many.
+fn generate_block_test_data(size: usize) -> (HashedPostState, HashedPostState) {
+    let hashed_key = |i: usize| keccak256(B256::from(U256::from(i)));
+    let storage = |slots: usize, value: usize| {
+        HashedStorage::from_iter(
+            false,
+            (0..slots).map(|slot| (hashed_key(slot), U256::from(value + 1))),
+        )
+    };
+    let account = |nonce: u64| Some(Account { nonce, ..Default::default() });
+
+    let db_state = HashedPostState::default()
+        .with_accounts((0..size).map(|i| (hashed_key(i), account(1))))
+        .with_storages((0..size).map(|i| (hashed_key(i), storage(100, i))));
+
+    // Every other account changes, one in five of them changes 4 slots and one in fifty 64 slots.
+    let changed = (0..size).step_by(2);
+    let updated_state = HashedPostState::default()
+        .with_accounts(changed.clone().map(|i| (hashed_key(i), account(2))))
+        .with_storages(changed.filter_map(|i| {
+            let slots = match i % 100 {
+                0 => 64,
+                n if n % 10 == 0 => 4,
+                _ => return None,
+            };
+            Some((hashed_key(i), storage(slots, size + i)))
+        }));
+
+    (db_state, updated_state)
+}
+
 fn generate_test_data(size: usize) -> (HashedPostState, HashedPostState) {
     let storage_size = 1_000;
     let mut runner = TestRunner::new(ProptestConfig::default());
@@ -131,5 +224,5 @@ fn generate_test_data(size: usize) -> (HashedPostState, HashedPostState) {
     )
 }
 
-criterion_group!(state_root, calculate_state_root);
+criterion_group!(state_root, calculate_state_root, parallel_storage_threshold);
 criterion_main!(state_root);
@@ -1,4 +1,7 @@
-use crate::{stats::ParallelTrieTracker, storage_root_targets::StorageRootTargets};
+use crate::{
+    stats::{ParallelTrieTracker, StorageRootTimings},
+    storage_root_targets::StorageRootTargets,
+};
 use alloy_rlp::{BufMut, Encodable};
 use rayon::prelude::*;
 use reth_db::database::Database;
@@ -16,18 +19,25 @@ use reth_trie::{
     walker::TrieWalker,
     HashedPostState, StorageRoot,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 use thiserror::Error;
 use tracing::*;
 
 #[cfg(feature = "metrics")]
 use crate::metrics::ParallelStateRootMetrics;
 
+/// The default minimum number of changed storage keys of an account for its storage root to be
+/// pre-computed in parallel, the accounts without storage changes are calculated inline.
+pub const DEFAULT_PARALLEL_STORAGE_THRESHOLD: usize = 1;
+
 /// Parallel incremental state root calculator.
 ///
 /// The calculator starts off by pre-computing storage roots of changed
 /// accounts in parallel. Once that's done, it proceeds to walking the state
-/// trie retrieving the pre-computed storage roots when needed.
+/// trie retrieving the pre-computed storage roots when needed. The storage
+/// roots of the accounts with fewer changed storage keys than the
+/// [parallel storage threshold](Self::with_parallel_storage_threshold)
+/// are calculated inline during the walk instead.
 ///
 /// Internally, the calculator uses [`ConsistentDbView`] since
 /// it needs to rely on database state saying the same until
@@ -41,6 +51,11 @@ pub struct ParallelStateRoot<DB, Provider> {
     view: ConsistentDbView<DB, Provider>,
     /// Changed hashed state.
     hashed_state: HashedPostState,
+    /// The minimum number of changed storage keys for the storage root to be pre-computed in
+    /// parallel.
+    parallel_storage_threshold: usize,
+    /// The timings of the parallel storage root calculations, if recorded.
+    storage_root_timings: Option<StorageRootTimings>,
     /// Parallel state root metrics.
     #[cfg(feature = "metrics")]
     metrics: ParallelStateRootMetrics,
@@ -52,10 +67,33 @@ impl<DB, Provider> ParallelStateRoot<DB, Provider> {
         Self {
             view,
             hashed_state,
+            parallel_storage_threshold: DEFAULT_PARALLEL_STORAGE_THRESHOLD,
+            storage_root_timings: None,
             #[cfg(feature = "metrics")]
             metrics: ParallelStateRootMetrics::default(),
         }
     }
+
+    /// Set the minimum number of changed storage keys of an account for its storage root to be
+    /// pre-computed in parallel, defaults to [`DEFAULT_PARALLEL_STORAGE_THRESHOLD`].
+    ///
+    /// The storage roots of the accounts below the threshold are calculated inline during the walk
+    /// of the account trie. The wiped storages are always pre-computed in parallel, `0`
+    /// pre-computes the storage roots of all changed accounts.
+    pub const fn with_parallel_storage_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_storage_threshold = threshold;
+        self
+    }
+
+    /// Record the duration of each parallel storage root calculation into the given timings, to
+    /// find the accounts whose storage roots dominate the cost of the state root.
+    ///
+    /// Only the storage roots pre-computed in parallel are timed, see
+    /// [`Self::with_parallel_storage_threshold`].
+    pub fn with_storage_root_timings(mut self, timings: StorageRootTimings) -> Self {
+        self.storage_root_timings = Some(timings);
+        self
+    }
 }
 
 impl<DB, Provider> ParallelStateRoot<DB, Provider>
@@ -81,18 +119,26 @@ where
     ) -> Result<(B256, TrieUpdates), ParallelStateRootError> {
         let mut tracker = ParallelTrieTracker::default();
         let prefix_sets = self.hashed_state.construct_prefix_sets();
-        let storage_root_targets = StorageRootTargets::new(
+        let mut storage_root_targets = StorageRootTargets::new(
             self.hashed_state.accounts.keys().copied(),
             prefix_sets.storage_prefix_sets,
         );
+        let mut inline_targets =
+            storage_root_targets.split_off_below(self.parallel_storage_threshold);
         let hashed_state_sorted = self.hashed_state.into_sorted();
 
         // Pre-calculate storage roots in parallel for accounts which were changed.
         tracker.set_precomputed_storage_roots(storage_root_targets.len() as u64);
-        debug!(target: "trie::parallel_state_root", len = storage_root_targets.len(), "pre-calculating storage roots");
+        debug!(
+            target: "trie::parallel_state_root",
+            len = storage_root_targets.len(),
+            inline = inline_targets.len(),
+            "pre-calculating storage roots"
+        );
         let mut storage_roots = storage_root_targets
             .into_par_iter()
             .map(|(hashed_address, prefix_set)| {
+                let started_at = Instant::now();
                 let provider_ro = self.view.provider_ro()?;
                 let storage_root_result = StorageRoot::new_hashed(
                     provider_ro.tx_ref(),
@@ -103,6 +149,9 @@ where
                 )
                 .with_prefix_set(prefix_set)
                 .calculate(retain_updates);
+                if let Some(timings) = &self.storage_root_timings {
+                    timings.record(hashed_address, started_at.elapsed());
+                }
                 Ok((hashed_address, storage_root_result?))
             })
             .collect::<Result<HashMap<_, _>, ParallelStateRootError>>()?;
@@ -135,10 +184,14 @@ where
                 TrieElement::Leaf(hashed_address, account) => {
                     let (storage_root, _, updates) = match storage_roots.remove(&hashed_address) {
                         Some(result) => result,
-                        // Since we do not store all intermediate nodes in the database, there might
-                        // be a possibility of re-adding a non-modified leaf to the hash builder.
                         None => {
-                            tracker.inc_missed_leaves();
+                            // Since we do not store all intermediate nodes in the database, there
+                            // might be a possibility of re-adding a non-modified leaf to the hash
+                            // builder.
+                            let prefix_set = inline_targets.remove(&hashed_address);
+                            if prefix_set.is_none() {
+                                tracker.inc_missed_leaves();
+                            }
                             StorageRoot::new_hashed(
                                 trie_cursor_factory,
                                 hashed_cursor_factory.clone(),
@@ -146,6 +199,7 @@ where
                                 #[cfg(feature = "metrics")]
                                 self.metrics.storage_trie.clone(),
                             )
+                            .with_prefix_set(prefix_set.unwrap_or_default())
                             .calculate(retain_updates)?
                         }
                     };
@@ -219,6 +273,7 @@ mod tests {
     use reth_primitives::{keccak256, Account, Address, StorageEntry, U256};
     use reth_provider::{test_utils::create_test_provider_factory, HashingWriter};
     use reth_trie::{test_utils, HashedStorage};
+    use std::collections::HashSet;
 
     #[tokio::test]
     async fn random_parallel_root() {
@@ -297,9 +352,82 @@ mod tests {
             }
         }
 
+        let expected = test_utils::state_root(state);
+        for threshold in [0, DEFAULT_PARALLEL_STORAGE_THRESHOLD, 50, usize::MAX] {
+            assert_eq!(
+                ParallelStateRoot::new(consistent_view.clone(), hashed_state.clone())
+                    .with_parallel_storage_threshold(threshold)
+                    .incremental_root()
+                    .unwrap(),
+                expected,
+                "threshold {threshold}"
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_storage_root_timings() {
+        let factory = create_test_provider_factory();
+        let consistent_view = ConsistentDbView::new(factory.clone(), None);
+        let slot = |i: u64| B256::from(U256::from(i));
+
+        {
+            let provider_rw = factory.provider_rw().unwrap();
+            provider_rw
+                .insert_account_for_hashing(
+                    (0..20u8).map(|i| (Address::with_last_byte(i), Some(Account::default()))),
+                )
+                .unwrap();
+            provider_rw
+                .insert_storage_for_hashing((0..10u8).map(|i| {
+                    (
+                        Address::with_last_byte(i),
+                        (1..20u64).map(|j| StorageEntry { key: slot(j), value: U256::from(j) }),
+                    )
+                }))
+                .unwrap();
+            provider_rw.commit().unwrap();
+        }
+
+        // The storage of the first accounts changes, the other accounts change their nonce only.
+        let mut hashed_state = HashedPostState::default();
+        for i in 0..5u8 {
+            hashed_state.storages.insert(
+                keccak256(Address::with_last_byte(i)),
+                HashedStorage::from_iter(false, [(keccak256(slot(1)), U256::from(100))]),
+            );
+        }
+        for i in 10..15u8 {
+            hashed_state.accounts.insert(
+                keccak256(Address::with_last_byte(i)),
+                Some(Account { nonce: 1, ..Default::default() }),
+            );
+        }
+
+        // Every pre-computed storage root is timed.
+        let timings = StorageRootTimings::default();
+        let root = ParallelStateRoot::new(consistent_view.clone(), hashed_state.clone())
+            .with_parallel_storage_threshold(0)
+            .with_storage_root_timings(timings.clone())
+            .incremental_root()
+            .unwrap();
+        let recorded = timings.take_slowest_first();
+        assert_eq!(recorded.len(), 10);
         assert_eq!(
-            ParallelStateRoot::new(consistent_view, hashed_state).incremental_root().unwrap(),
-            test_utils::state_root(state)
+            recorded.iter().map(|(hashed_address, _)| *hashed_address).collect::<HashSet<_>>(),
+            (0..5u8).chain(10..15).map(|i| keccak256(Address::with_last_byte(i))).collect()
         );
+        assert!(recorded.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert!(timings.take().is_empty());
+
+        // The storage roots calculated inline, here of the accounts without storage changes, are
+        // not timed.
+        let inline_root = ParallelStateRoot::new(consistent_view, hashed_state)
+            .with_parallel_storage_threshold(1)
+            .with_storage_root_timings(timings.clone())
+            .incremental_root()
+            .unwrap();
+        assert_eq!(inline_root, root);
+        assert_eq!(timings.take().len(), 5);
     }
 }
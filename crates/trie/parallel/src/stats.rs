@@ -1,5 +1,10 @@
 use derive_more::Deref;
+use reth_primitives::B256;
 use reth_trie::stats::{TrieStats, TrieTracker};
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
 
 /// Trie stats.
 #[derive(Deref, Clone, Copy, Debug)]
@@ -66,3 +71,40 @@ impl ParallelTrieTracker {
         }
     }
 }
+
+/// The durations of the storage root calculations of the parallel tasks, by the hashed address of
+/// the account, for finding the contracts that dominate the cost of the state root.
+///
+/// Each task records a single duration once its storage root is calculated. The storage roots
+/// calculated inline during the walk of the account trie are not recorded.
+///
+/// The timings are cheap to clone, the clones share the recorded durations.
+#[derive(Clone, Default, Debug)]
+pub struct StorageRootTimings {
+    /// The recorded durations shared by the clones, in the order the tasks finished.
+    inner: Arc<Mutex<Vec<(B256, Duration)>>>,
+}
+
+impl StorageRootTimings {
+    /// Record the duration of the storage root calculation of the account.
+    pub fn record(&self, hashed_address: B256, duration: Duration) {
+        self.lock().push((hashed_address, duration));
+    }
+
+    /// Take the recorded durations in the order the tasks finished, leaving the timings empty.
+    pub fn take(&self) -> Vec<(B256, Duration)> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Take the recorded durations sorted from the slowest to the fastest, leaving the timings
+    /// empty.
+    pub fn take_slowest_first(&self) -> Vec<(B256, Duration)> {
+        let mut timings = self.take();
+        timings.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+        timings
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<(B256, Duration)>> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -25,6 +25,23 @@ impl StorageRootTargets {
                 .collect(),
         )
     }
+
+    /// Removes and returns the targets with fewer than `threshold` changed storage keys.
+    ///
+    /// The targets of the wiped storages, i.e. with prefix sets marking all keys as changed, are
+    /// always kept.
+    pub fn split_off_below(&mut self, threshold: usize) -> HashMap<B256, PrefixSet> {
+        let below = self
+            .0
+            .iter()
+            .filter(|(_, prefix_set)| !prefix_set.all() && prefix_set.len() < threshold)
+            .map(|(hashed_address, _)| *hashed_address)
+            .collect::<Vec<_>>();
+        below
+            .into_iter()
+            .filter_map(|hashed_address| self.0.remove_entry(&hashed_address))
+            .collect()
+    }
 }
 
 impl IntoIterator for StorageRootTargets {
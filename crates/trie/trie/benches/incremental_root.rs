@@ -0,0 +1,90 @@
+#![allow(missing_docs, unreachable_pub)]
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use reth_db::{
+    models::{AccountBeforeTx, BlockNumberAddress},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{keccak256, Account, Address, StorageEntry, B256, U256};
+use reth_provider::test_utils::create_test_provider_factory;
+use reth_trie::{trie_cursor::BranchNodeCache, IncrementalRootSession, StateRoot};
+
+pub fn incremental_root(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Incremental Root");
+    group.sample_size(10);
+
+    let accounts = 10_000u64;
+    for blocks in [10, 100] {
+        let provider_factory = create_test_provider_factory();
+        let provider_rw = provider_factory.provider_rw().unwrap();
+        let tx = provider_rw.tx_ref();
+
+        for i in 0..accounts {
+            let address = Address::from_word(B256::from(U256::from(i)));
+            let account = Account { nonce: 1, ..Default::default() };
+            tx.put::<tables::PlainAccountState>(address, account).unwrap();
+            tx.put::<tables::HashedAccounts>(keccak256(address), account).unwrap();
+        }
+
+        // Every block changes a few accounts and one storage slot of each of them.
+        for block in 1..=blocks {
+            for i in (block..accounts).step_by(accounts as usize / 10) {
+                let address = Address::from_word(B256::from(U256::from(i)));
+                let before = tx.get::<tables::PlainAccountState>(address).unwrap();
+                let changeset = AccountBeforeTx { address, info: before };
+                tx.put::<tables::AccountChangeSets>(block, changeset).unwrap();
+                let account = Account { nonce: block + 1, ..Default::default() };
+                tx.put::<tables::PlainAccountState>(address, account).unwrap();
+                tx.put::<tables::HashedAccounts>(keccak256(address), account).unwrap();
+
+                let key = B256::from(U256::from(block));
+                let changeset = StorageEntry { key, value: U256::ZERO };
+                tx.put::<tables::StorageChangeSets>(
+                    BlockNumberAddress((block, address)),
+                    changeset,
+                )
+                .unwrap();
+                let entry = StorageEntry { key: keccak256(key), value: U256::from(block) };
+                tx.put::<tables::HashedStorages>(keccak256(address), entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+        provider_rw.commit().unwrap();
+
+        group.bench_function(BenchmarkId::new("repeated incremental_root", blocks), |b| {
+            b.iter(|| {
+                let provider = provider_factory.provider().unwrap();
+                for block in 1..=blocks {
+                    StateRoot::incremental_root_with_updates(provider.tx_ref(), block..=block)
+                        .unwrap();
+                }
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("session", blocks), |b| {
+            b.iter(|| {
+                let provider = provider_factory.provider().unwrap();
+                let mut session = IncrementalRootSession::new(provider.tx_ref()).unwrap();
+                for block in 1..=blocks {
+                    session.advance(block..=block).unwrap();
+                }
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("session with branch node cache", blocks), |b| {
+            b.iter(|| {
+                let provider = provider_factory.provider().unwrap();
+                let mut session = IncrementalRootSession::new(provider.tx_ref())
+                    .unwrap()
+                    .with_branch_node_cache(BranchNodeCache::default());
+                for block in 1..=blocks {
+                    session.advance(block..=block).unwrap();
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(incremental, incremental_root);
+criterion_main!(incremental);
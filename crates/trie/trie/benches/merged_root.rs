@@ -0,0 +1,77 @@
+#![allow(missing_docs, unreachable_pub)]
+use alloy_rlp::Encodable;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use reth_db::{
+    cursor::DbCursorRO,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{
+    constants::EMPTY_ROOT_HASH,
+    keccak256,
+    proofs::root_from_merged_sorted_leaves,
+    trie::{Nibbles, TrieAccount},
+    Account, B256, U256,
+};
+use reth_provider::test_utils::create_test_provider_factory;
+use reth_trie::HashedPostState;
+use std::collections::BTreeMap;
+
+pub fn merged_root(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Merged Root");
+    group.sample_size(10);
+
+    let accounts = 100_000u64;
+    let provider_factory = create_test_provider_factory();
+    let provider_rw = provider_factory.provider_rw().unwrap();
+    let tx = provider_rw.tx_ref();
+    for i in 0..accounts {
+        let account = Account { nonce: i, ..Default::default() };
+        tx.put::<tables::HashedAccounts>(keccak256(B256::from(U256::from(i))), account).unwrap();
+    }
+    provider_rw.commit().unwrap();
+
+    for changes in [100, 10_000] {
+        // Every change either updates an existing account, creates a new one or destroys one.
+        let mut post_state = HashedPostState::default();
+        for i in (0..accounts + changes).step_by(((accounts + changes) / changes) as usize) {
+            let account = (i % 3 != 0).then(|| Account { nonce: i + 1, ..Default::default() });
+            post_state.accounts.insert(keccak256(B256::from(U256::from(i))), account);
+        }
+
+        let provider = provider_factory.provider().unwrap();
+        let expected = post_state.state_root(provider.tx_ref()).unwrap();
+        assert_eq!(merged_stream_root(provider.tx_ref(), &post_state), expected);
+
+        group.bench_function(BenchmarkId::new("cursor overlay", changes), |b| {
+            b.iter(|| post_state.state_root(provider.tx_ref()).unwrap())
+        });
+
+        group.bench_function(BenchmarkId::new("merged streams", changes), |b| {
+            b.iter(|| merged_stream_root(provider.tx_ref(), &post_state))
+        });
+    }
+}
+
+/// Merges the hashed accounts without storage with the changed accounts in a single pass.
+fn merged_stream_root(tx: &impl DbTx, post_state: &HashedPostState) -> B256 {
+    let encode = |account: Account| {
+        let mut buf = Vec::new();
+        TrieAccount::from((account, EMPTY_ROOT_HASH)).encode(&mut buf);
+        buf
+    };
+    let mut cursor = tx.cursor_read::<tables::HashedAccounts>().unwrap();
+    let base = cursor.walk(None).unwrap().map(|entry| {
+        let (hashed_address, account) = entry.unwrap();
+        (Nibbles::unpack(hashed_address), encode(account))
+    });
+    let overlay = post_state
+        .accounts
+        .iter()
+        .map(|(hashed_address, account)| (Nibbles::unpack(hashed_address), account.map(encode)))
+        .collect::<BTreeMap<_, _>>();
+    root_from_merged_sorted_leaves(base, overlay)
+}
+
+criterion_group!(merged, merged_root);
+criterion_main!(merged);
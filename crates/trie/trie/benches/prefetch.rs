@@ -0,0 +1,57 @@
+#![allow(missing_docs, unreachable_pub)]
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use reth_db::{tables, transaction::DbTxMut};
+use reth_primitives::{keccak256, Account, StorageEntry, B256, U256};
+use reth_provider::test_utils::create_test_provider_factory;
+use reth_trie::StateRoot;
+
+pub fn prefetch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Storage Trie Prefetch");
+    group.sample_size(10);
+
+    let accounts = 10_000u64;
+    let provider_factory = create_test_provider_factory();
+    let provider_rw = provider_factory.provider_rw().unwrap();
+    let tx = provider_rw.tx_ref();
+    for i in 0..accounts {
+        let hashed_address = keccak256(B256::from(U256::from(i)));
+        tx.put::<tables::HashedAccounts>(
+            hashed_address,
+            Account { nonce: i, ..Default::default() },
+        )
+        .unwrap();
+        for slot in 0..i % 20 {
+            let entry =
+                StorageEntry { key: keccak256(B256::from(U256::from(slot))), value: U256::from(i) };
+            tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+        }
+    }
+    provider_rw.commit().unwrap();
+
+    // No trie nodes are stored, so every root computes all storage roots as a full rebuild does.
+    let expected =
+        StateRoot::from_tx(provider_factory.provider().unwrap().tx_ref()).root().unwrap();
+
+    group.bench_function("without prefetch", |b| {
+        b.iter(|| {
+            let provider = provider_factory.provider_rw().unwrap();
+            assert_eq!(StateRoot::from_tx(provider.tx_ref()).root().unwrap(), expected);
+        })
+    });
+
+    for depth in [16, 256] {
+        group.bench_function(BenchmarkId::new("with prefetch", depth), |b| {
+            b.iter(|| {
+                let provider = provider_factory.provider_rw().unwrap();
+                let root = StateRoot::from_tx(provider.tx_ref())
+                    .with_prefetch(depth, provider_factory.db_ref().clone())
+                    .root()
+                    .unwrap();
+                assert_eq!(root, expected);
+            })
+        });
+    }
+}
+
+criterion_group!(state_root, prefetch);
+criterion_main!(state_root);
@@ -0,0 +1,137 @@
+use reth_primitives::{
+    trie::{HashBuilder, Nibbles},
+    Bytes, B256,
+};
+use serde::{Deserialize, Serialize};
+
+/// The sequence of the branch hashes and the leaves combined into the root of the account trie,
+/// see [`StateRoot::root_with_audit_trail`](crate::StateRoot::root_with_audit_trail).
+///
+/// The steps are the inputs of the hash builder in the order they were fed to it, so replaying
+/// them re-derives the root deterministically without the database. The branch steps stand for
+/// the unchanged subtries reused by their stored hashes and the leaf steps carry the encoded
+/// accounts, including their storage roots, which are taken as given.
+#[derive(PartialEq, Eq, Clone, Default, Debug, Serialize, Deserialize)]
+pub struct AuditTrail {
+    /// The steps in the order they were combined.
+    pub steps: Vec<AuditStep>,
+}
+
+/// A single input of the hash builder recorded in the [`AuditTrail`].
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum AuditStep {
+    /// The hash of the subtrie at the given path.
+    Branch {
+        /// The path of the subtrie.
+        key: Nibbles,
+        /// The hash of the subtrie.
+        hash: B256,
+        /// Whether the children of the subtrie are stored in the trie.
+        children_are_in_trie: bool,
+    },
+    /// The leaf at the given path.
+    Leaf {
+        /// The path of the leaf, the unpacked hashed address.
+        key: Nibbles,
+        /// The encoded account.
+        value: Bytes,
+    },
+}
+
+impl AuditTrail {
+    /// Records the hash of a subtrie.
+    pub fn record_branch(&mut self, key: Nibbles, hash: B256, children_are_in_trie: bool) {
+        self.steps.push(AuditStep::Branch { key, hash, children_are_in_trie });
+    }
+
+    /// Records a leaf.
+    pub fn record_leaf(&mut self, key: Nibbles, value: &[u8]) {
+        self.steps.push(AuditStep::Leaf { key, value: Bytes::copy_from_slice(value) });
+    }
+
+    /// Returns the number of recorded steps.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns `true` if no steps were recorded, which is the trail of the empty trie.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Replays the steps into a fresh hash builder and returns the resulting root.
+    ///
+    /// # Panics
+    ///
+    /// If the paths of the steps are not in ascending order, e.g. in a tampered trail.
+    pub fn replay(&self) -> B256 {
+        let mut hash_builder = HashBuilder::default();
+        for step in &self.steps {
+            match step {
+                AuditStep::Branch { key, hash, children_are_in_trie } => {
+                    hash_builder.add_branch(key.clone(), *hash, *children_are_in_trie)
+                }
+                AuditStep::Leaf { key, value } => hash_builder.add_leaf(key.clone(), value),
+            }
+        }
+        hash_builder.root()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StateRoot;
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{constants::EMPTY_ROOT_HASH, keccak256, Account, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn replay_audit_trail() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let (root, trail) = StateRoot::from_tx(tx).root_with_audit_trail().unwrap();
+        assert_eq!(root, EMPTY_ROOT_HASH);
+        assert!(trail.is_empty());
+        assert_eq!(trail.replay(), root);
+
+        for i in 0..500u64 {
+            let hashed_address = keccak256(B256::from(U256::from(i)));
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            let entry = StorageEntry { key: B256::with_last_byte(1), value: U256::from(i + 1) };
+            tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+        }
+
+        // The trail of the trie computed from scratch consists of the leaves.
+        let (root, trail) = StateRoot::from_tx(tx).root_with_audit_trail().unwrap();
+        assert_eq!(root, StateRoot::from_tx(tx).root().unwrap());
+        assert_eq!(trail.len(), 500);
+        assert!(trail.steps.iter().all(|step| matches!(step, AuditStep::Leaf { .. })));
+        assert_eq!(trail.replay(), root);
+
+        // The unchanged subtries of the stored trie are reused by their hashes.
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+        let (stored_root, trail) = StateRoot::from_tx(tx).root_with_audit_trail().unwrap();
+        assert_eq!(stored_root, root);
+        assert!(trail.steps.iter().all(|step| matches!(step, AuditStep::Branch { .. })));
+
+        // The trail survives the serialization and replays without the database.
+        let serialized = serde_json::to_string(&trail).unwrap();
+        drop(provider);
+        drop(factory);
+        let deserialized = serde_json::from_str::<AuditTrail>(&serialized).unwrap();
+        assert_eq!(deserialized, trail);
+        assert_eq!(deserialized.replay(), root);
+
+        // A tampered step changes the replayed root.
+        let mut tampered = deserialized;
+        if let Some(AuditStep::Branch { hash, .. }) = tampered.steps.first_mut() {
+            *hash = B256::ZERO;
+        }
+        assert_ne!(tampered.replay(), root);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    trie_cursor::TrieCursorFactory,
+    StateRoot,
+};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{keccak256, B256};
+use std::collections::HashSet;
+
+/// The state root and the account leaves of the trie at a point in time, chained to the previous
+/// checkpoint into an append-only log, see [`checkpoint_root`] and [`verify_extends`].
+///
+/// The [`hash`](Self::hash) of the checkpoint commits to the hash of the previous checkpoint, the
+/// state root and the accounts, so the log of the checkpoint hashes can be persisted or signed
+/// and each checkpoint later checked against it.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct TrieCheckpoint {
+    /// The hash of the previous checkpoint, zero for the first checkpoint of the log.
+    pub parent: B256,
+    /// The state root.
+    pub root: B256,
+    /// The hashed addresses of the accounts, sorted.
+    pub accounts: Vec<B256>,
+}
+
+impl TrieCheckpoint {
+    /// Returns the hash of the checkpoint, the keccak256 hash of the hash of the previous
+    /// checkpoint, the state root and the hashed addresses of the accounts concatenated in order.
+    pub fn hash(&self) -> B256 {
+        let mut buf = Vec::with_capacity((self.accounts.len() + 2) * 32);
+        buf.extend_from_slice(self.parent.as_slice());
+        buf.extend_from_slice(self.root.as_slice());
+        for hashed_address in &self.accounts {
+            buf.extend_from_slice(hashed_address.as_slice());
+        }
+        keccak256(buf)
+    }
+}
+
+/// The error returned when the trie does not extend the previous checkpoint, see
+/// [`verify_extends`].
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum CheckpointError {
+    /// The account of the previous checkpoint is missing and was not pruned.
+    #[error("account {0} of the previous checkpoint is missing")]
+    MissingAccount(B256),
+    /// The state root calculation failed.
+    #[error(transparent)]
+    StateRoot(#[from] StateRootError),
+}
+
+/// Returns the first checkpoint of a log, with the current state root and accounts.
+///
+/// The state root is computed with the stored trie, reusing the stored root node, so the trie
+/// has to be up to date with the hashed state, e.g. right after the merkle stage.
+pub fn checkpoint_root<F>(factory: F) -> Result<TrieCheckpoint, StateRootError>
+where
+    F: TrieCursorFactory + HashedCursorFactory + Clone + Send,
+{
+    checkpoint(factory, B256::ZERO, &[], &HashSet::new()).map_err(|error| match error {
+        CheckpointError::StateRoot(error) => error,
+        CheckpointError::MissingAccount(_) => unreachable!("no previous accounts"),
+    })
+}
+
+/// Verifies that the current trie extends the previous checkpoint and returns the next checkpoint
+/// of the log, see [`checkpoint_root`].
+///
+/// The trie extends the checkpoint if the accounts of the current state are a superset of the
+/// accounts of the checkpoint, except for the given hashed addresses of the pruned accounts, e.g.
+/// the destroyed accounts of the blocks since the checkpoint. The values of the accounts and the
+/// storage tries are free to change, they are committed to by the state root only. The check is a
+/// single pass over the hashed accounts, merged with the sorted accounts of the checkpoint.
+pub fn verify_extends<F>(
+    prev: &TrieCheckpoint,
+    factory: F,
+    pruned: &HashSet<B256>,
+) -> Result<TrieCheckpoint, CheckpointError>
+where
+    F: TrieCursorFactory + HashedCursorFactory + Clone + Send,
+{
+    checkpoint(factory, prev.hash(), &prev.accounts, pruned)
+}
+
+/// Reads the current accounts, checking that the previous ones are present unless pruned, and
+/// computes the checkpoint.
+fn checkpoint<F>(
+    factory: F,
+    parent: B256,
+    prev_accounts: &[B256],
+    pruned: &HashSet<B256>,
+) -> Result<TrieCheckpoint, CheckpointError>
+where
+    F: TrieCursorFactory + HashedCursorFactory + Clone + Send,
+{
+    let mut accounts = Vec::new();
+    let mut prev_accounts = prev_accounts.iter().filter(|key| !pruned.contains(*key)).peekable();
+    let mut cursor = factory.hashed_account_cursor().map_err(StateRootError::from)?;
+    let mut entry = cursor.seek(B256::ZERO).map_err(StateRootError::from)?;
+    while let Some((hashed_address, _)) = entry {
+        if let Some(prev) = prev_accounts.next_if(|prev| **prev <= hashed_address) {
+            if *prev != hashed_address {
+                return Err(CheckpointError::MissingAccount(*prev))
+            }
+        }
+        accounts.push(hashed_address);
+        entry = cursor.next().map_err(StateRootError::from)?;
+    }
+    if let Some(prev) = prev_accounts.next() {
+        return Err(CheckpointError::MissingAccount(*prev))
+    }
+
+    let root = StateRoot::new(factory.clone(), factory).root()?;
+    Ok(TrieCheckpoint { parent, root, accounts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prefix_set::TriePrefixSets;
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{Account, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn checkpoints_across_states() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed = |i: u64| keccak256(B256::from(U256::from(i)));
+        let write_state = |accounts: &[u64], nonce: u64| {
+            for i in accounts {
+                let account = Account { nonce: nonce + i, ..Default::default() };
+                tx.put::<tables::HashedAccounts>(hashed(*i), account).unwrap();
+            }
+            let (_, updates) = StateRoot::from_tx(tx)
+                .with_prefix_sets(TriePrefixSets::all_changed())
+                .root_with_updates()
+                .unwrap();
+            updates.flush(tx).unwrap();
+        };
+
+        // The first state.
+        write_state(&(0..50).collect::<Vec<_>>(), 0);
+        let first = checkpoint_root(tx).unwrap();
+        assert_eq!(first.parent, B256::ZERO);
+        assert_eq!(first.root, StateRoot::from_tx(tx).root().unwrap());
+        assert_eq!(first.accounts.len(), 50);
+        assert!(first.accounts.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // The second state updates the accounts and adds new ones.
+        write_state(&(0..80).collect::<Vec<_>>(), 1);
+        let second = verify_extends(&first, tx, &HashSet::new()).unwrap();
+        assert_eq!(second.parent, first.hash());
+        assert_ne!(second.root, first.root);
+        assert_eq!(second.root, StateRoot::from_tx(tx).root().unwrap());
+        assert_eq!(second.accounts.len(), 80);
+        assert!(first.accounts.iter().all(|account| second.accounts.contains(account)));
+        assert_ne!(second.hash(), first.hash());
+
+        // The removed account breaks the extension unless it is pruned.
+        tx.delete::<tables::HashedAccounts>(hashed(10), None).unwrap();
+        write_state(&[], 0);
+        assert_eq!(
+            verify_extends(&second, tx, &HashSet::new()),
+            Err(CheckpointError::MissingAccount(hashed(10)))
+        );
+        let third = verify_extends(&second, tx, &HashSet::from([hashed(10)])).unwrap();
+        assert_eq!(third.parent, second.hash());
+        assert_eq!(third.accounts.len(), 79);
+
+        // The tampered checkpoint is not the parent of the next one.
+        let mut tampered = second.clone();
+        tampered.root = B256::ZERO;
+        let next = verify_extends(&tampered, tx, &HashSet::from([hashed(10)])).unwrap();
+        assert_ne!(next.parent, third.parent);
+    }
+}
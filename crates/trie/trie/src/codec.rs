@@ -0,0 +1,37 @@
+use alloy_rlp::Encodable;
+use reth_primitives::{trie::TrieAccount, Account, B256, U256};
+
+/// The encoding of the leaf values of the account and storage tries.
+///
+/// The keys of the tries are always the hashed addresses and slots, only the bytes stored in the
+/// leaves are determined by the codec.
+pub trait ValueCodec {
+    /// Whether the encoding is the Ethereum one, the encoding of the hashes of the nodes stored in
+    /// the trie tables, see [`EthereumValueCodec`].
+    const IS_ETHEREUM: bool = false;
+
+    /// Encodes the account leaf value of the account with the given storage root into the buffer.
+    fn encode_account(&self, account: Account, storage_root: B256, buf: &mut Vec<u8>);
+
+    /// Encodes the storage leaf value of the slot value into the buffer.
+    fn encode_storage(&self, value: U256, buf: &mut Vec<u8>);
+}
+
+/// The Ethereum leaf value encoding, the RLP encoding of [`TrieAccount`] for accounts and the RLP
+/// encoding of the slot value for storage.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct EthereumValueCodec;
+
+impl ValueCodec for EthereumValueCodec {
+    const IS_ETHEREUM: bool = true;
+
+    #[inline]
+    fn encode_account(&self, account: Account, storage_root: B256, buf: &mut Vec<u8>) {
+        TrieAccount::from((account, storage_root)).encode(buf);
+    }
+
+    #[inline]
+    fn encode_storage(&self, value: U256, buf: &mut Vec<u8>) {
+        value.encode(buf);
+    }
+}
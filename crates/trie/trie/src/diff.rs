@@ -0,0 +1,272 @@
+use crate::{
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    trie_cursor::TrieCursorFactory,
+    StorageRoot,
+};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{Account, B256};
+
+#[cfg(feature = "metrics")]
+use crate::metrics::{TrieRootMetrics, TrieType};
+
+/// The contents of an account leaf in the state trie.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct AccountLeaf {
+    /// The account info.
+    pub account: Account,
+    /// The storage root of the account.
+    pub storage_root: B256,
+}
+
+/// An account whose state trie leaf differs between two hashed states.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct AccountLeafDiff {
+    /// The hashed address of the account.
+    pub hashed_address: B256,
+    /// The account leaf in the old state, `None` if the account is absent.
+    pub old: Option<AccountLeaf>,
+    /// The account leaf in the new state, `None` if the account is absent.
+    pub new: Option<AccountLeaf>,
+}
+
+impl AccountLeafDiff {
+    /// Returns `true` if the account info differs between the two states.
+    pub fn account_changed(&self) -> bool {
+        self.old.map(|leaf| leaf.account) != self.new.map(|leaf| leaf.account)
+    }
+
+    /// Returns `true` if the storage root differs between the two states.
+    pub fn storage_root_changed(&self) -> bool {
+        self.old.map(|leaf| leaf.storage_root) != self.new.map(|leaf| leaf.storage_root)
+    }
+}
+
+/// One side of the account leaf comparison.
+#[derive(Debug)]
+struct DiffSide<T, H: HashedCursorFactory> {
+    trie_cursor_factory: T,
+    hashed_cursor_factory: H,
+    account_cursor: H::AccountCursor,
+    next: Option<(B256, Account)>,
+}
+
+impl<T, H> DiffSide<T, H>
+where
+    T: TrieCursorFactory + Clone,
+    H: HashedCursorFactory + Clone,
+{
+    fn new(trie_cursor_factory: T, hashed_cursor_factory: H) -> Result<Self, StateRootError> {
+        let mut account_cursor = hashed_cursor_factory.hashed_account_cursor()?;
+        let next = account_cursor.seek(B256::ZERO)?;
+        Ok(Self { trie_cursor_factory, hashed_cursor_factory, account_cursor, next })
+    }
+
+    /// Takes the current account and advances the cursor.
+    fn advance(&mut self) -> Result<Option<(B256, Account)>, StateRootError> {
+        let current = self.next.take();
+        if current.is_some() {
+            self.next = self.account_cursor.next()?;
+        }
+        Ok(current)
+    }
+
+    fn leaf(
+        &self,
+        hashed_address: B256,
+        account: Account,
+        #[cfg(feature = "metrics")] metrics: &TrieRootMetrics,
+    ) -> Result<AccountLeaf, StateRootError> {
+        let storage_root = StorageRoot::new_hashed(
+            self.trie_cursor_factory.clone(),
+            self.hashed_cursor_factory.clone(),
+            hashed_address,
+            #[cfg(feature = "metrics")]
+            metrics.clone(),
+        )
+        .root()?;
+        Ok(AccountLeaf { account, storage_root })
+    }
+}
+
+/// Streaming iterator over the accounts whose state trie leaves differ between two hashed states.
+///
+/// The hashed accounts of both states are walked in ascending order of hashed addresses and
+/// compared. Accounts present in both states additionally have their storage roots compared.
+/// Only the accounts that differ are yielded, so the iterator can be bounded with
+/// [`Iterator::take`] when only the first few differences are of interest.
+///
+/// This is the most granular tool for answering why the state root of one state differs from
+/// another.
+#[derive(Debug)]
+pub struct AccountLeafDiffIter<T1, H1: HashedCursorFactory, T2, H2: HashedCursorFactory> {
+    old: DiffSide<T1, H1>,
+    new: DiffSide<T2, H2>,
+    #[cfg(feature = "metrics")]
+    metrics: TrieRootMetrics,
+}
+
+impl<T1, H1, T2, H2> AccountLeafDiffIter<T1, H1, T2, H2>
+where
+    T1: TrieCursorFactory + Clone,
+    H1: HashedCursorFactory + Clone,
+    T2: TrieCursorFactory + Clone,
+    H2: HashedCursorFactory + Clone,
+{
+    /// Create new iterator comparing the old state given by the first pair of trie and hashed
+    /// cursor factories against the new state given by the second pair.
+    pub fn new(old: (T1, H1), new: (T2, H2)) -> Result<Self, StateRootError> {
+        Ok(Self {
+            old: DiffSide::new(old.0, old.1)?,
+            new: DiffSide::new(new.0, new.1)?,
+            #[cfg(feature = "metrics")]
+            metrics: TrieRootMetrics::new(TrieType::Storage),
+        })
+    }
+
+    fn try_next(&mut self) -> Result<Option<AccountLeafDiff>, StateRootError> {
+        loop {
+            let old_key = self.old.next.as_ref().map(|(key, _)| *key);
+            let new_key = self.new.next.as_ref().map(|(key, _)| *key);
+            let (old, new) = match (old_key, new_key) {
+                (None, None) => return Ok(None),
+                (Some(old_key), Some(new_key)) if old_key == new_key => {
+                    (self.old.advance()?, self.new.advance()?)
+                }
+                (Some(old_key), Some(new_key)) if old_key < new_key => (self.old.advance()?, None),
+                (Some(_), None) => (self.old.advance()?, None),
+                _ => (None, self.new.advance()?),
+            };
+
+            let hashed_address = old.or(new).map(|(key, _)| key).expect("entry is present");
+            let old = old
+                .map(|(_, account)| {
+                    self.old.leaf(
+                        hashed_address,
+                        account,
+                        #[cfg(feature = "metrics")]
+                        &self.metrics,
+                    )
+                })
+                .transpose()?;
+            let new = new
+                .map(|(_, account)| {
+                    self.new.leaf(
+                        hashed_address,
+                        account,
+                        #[cfg(feature = "metrics")]
+                        &self.metrics,
+                    )
+                })
+                .transpose()?;
+
+            if old != new {
+                return Ok(Some(AccountLeafDiff { hashed_address, old, new }))
+            }
+        }
+    }
+}
+
+impl<T1, H1, T2, H2> Iterator for AccountLeafDiffIter<T1, H1, T2, H2>
+where
+    T1: TrieCursorFactory + Clone,
+    H1: HashedCursorFactory + Clone,
+    T2: TrieCursorFactory + Clone,
+    H2: HashedCursorFactory + Clone,
+{
+    type Item = Result<AccountLeafDiff, StateRootError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::storage_root_prehashed;
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{constants::EMPTY_ROOT_HASH, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn account_leaf_diffs() {
+        let unchanged = B256::with_last_byte(1);
+        let balance_changed = B256::with_last_byte(2);
+        let storage_changed = B256::with_last_byte(3);
+        let removed = B256::with_last_byte(4);
+        let added = B256::with_last_byte(5);
+        let slot = B256::with_last_byte(0xa);
+
+        let old_factory = create_test_provider_factory();
+        let old_provider = old_factory.provider_rw().unwrap();
+        let old_tx = old_provider.tx_ref();
+        for hashed_address in [unchanged, balance_changed, storage_changed, removed] {
+            old_tx
+                .put::<tables::HashedAccounts>(
+                    hashed_address,
+                    Account { nonce: 1, ..Default::default() },
+                )
+                .unwrap();
+        }
+        old_tx
+            .put::<tables::HashedStorages>(
+                storage_changed,
+                StorageEntry { key: slot, value: U256::from(1) },
+            )
+            .unwrap();
+
+        let new_factory = create_test_provider_factory();
+        let new_provider = new_factory.provider_rw().unwrap();
+        let new_tx = new_provider.tx_ref();
+        for hashed_address in [unchanged, storage_changed, added] {
+            new_tx
+                .put::<tables::HashedAccounts>(
+                    hashed_address,
+                    Account { nonce: 1, ..Default::default() },
+                )
+                .unwrap();
+        }
+        new_tx
+            .put::<tables::HashedAccounts>(
+                balance_changed,
+                Account { nonce: 1, balance: U256::from(1), ..Default::default() },
+            )
+            .unwrap();
+        new_tx
+            .put::<tables::HashedStorages>(
+                storage_changed,
+                StorageEntry { key: slot, value: U256::from(2) },
+            )
+            .unwrap();
+
+        let diffs = AccountLeafDiffIter::new((old_tx, old_tx), (new_tx, new_tx))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            diffs.iter().map(|diff| diff.hashed_address).collect::<Vec<_>>(),
+            vec![balance_changed, storage_changed, removed, added]
+        );
+
+        assert!(diffs[0].account_changed());
+        assert!(!diffs[0].storage_root_changed());
+
+        assert!(!diffs[1].account_changed());
+        assert_eq!(
+            diffs[1].old.map(|leaf| leaf.storage_root),
+            Some(storage_root_prehashed([(slot, U256::from(1))]))
+        );
+        assert_eq!(
+            diffs[1].new.map(|leaf| leaf.storage_root),
+            Some(storage_root_prehashed([(slot, U256::from(2))]))
+        );
+
+        assert_eq!(diffs[2].new, None);
+        assert_eq!(diffs[2].old.map(|leaf| leaf.storage_root), Some(EMPTY_ROOT_HASH));
+        assert_eq!(diffs[3].old, None);
+
+        // Comparing the state against itself yields no differences.
+        let mut diffs = AccountLeafDiffIter::new((old_tx, old_tx), (old_tx, old_tx)).unwrap();
+        assert!(diffs.next().is_none());
+    }
+}
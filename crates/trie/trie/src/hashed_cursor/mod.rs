@@ -8,6 +8,10 @@ pub use default::DatabaseHashedStorageCursor;
 mod post_state;
 pub use post_state::*;
 
+/// Implementation of hashed state cursor traits for in-memory sorted accounts.
+mod sorted_accounts;
+pub use sorted_accounts::{SortedAccountsCursor, SortedAccountsCursorFactory};
+
 /// The factory trait for creating cursors over the hashed state.
 pub trait HashedCursorFactory {
     /// The hashed account cursor type.
@@ -0,0 +1,101 @@
+use super::{HashedCursor, HashedCursorFactory};
+use reth_primitives::{Account, B256};
+use std::sync::Arc;
+
+/// The hashed cursor factory serving the accounts from an in-memory list and the storage from the
+/// underlying factory.
+///
+/// The accounts of the underlying factory are never read, the in-memory list is the complete set
+/// of accounts.
+#[derive(Debug, Clone)]
+pub struct SortedAccountsCursorFactory<CF> {
+    cursor_factory: CF,
+    accounts: Arc<[(B256, Account)]>,
+}
+
+impl<CF> SortedAccountsCursorFactory<CF> {
+    /// Create a new factory from the accounts sorted by their hashed address.
+    ///
+    /// # Panics
+    ///
+    /// If the hashed addresses of the accounts are not strictly ascending.
+    pub fn new(cursor_factory: CF, accounts: impl IntoIterator<Item = (B256, Account)>) -> Self {
+        let accounts: Arc<[(B256, Account)]> = accounts.into_iter().collect();
+        assert!(
+            accounts.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "accounts must be sorted by hashed address without duplicates"
+        );
+        Self { cursor_factory, accounts }
+    }
+}
+
+impl<CF: HashedCursorFactory> HashedCursorFactory for SortedAccountsCursorFactory<CF> {
+    type AccountCursor = SortedAccountsCursor;
+    type StorageCursor = CF::StorageCursor;
+
+    fn hashed_account_cursor(&self) -> Result<Self::AccountCursor, reth_db::DatabaseError> {
+        Ok(SortedAccountsCursor::new(self.accounts.clone()))
+    }
+
+    fn hashed_storage_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageCursor, reth_db::DatabaseError> {
+        self.cursor_factory.hashed_storage_cursor(hashed_address)
+    }
+}
+
+/// The cursor over the in-memory accounts sorted by their hashed address.
+#[derive(Debug, Clone)]
+pub struct SortedAccountsCursor {
+    accounts: Arc<[(B256, Account)]>,
+    /// The index of the entry returned by the next call to [`HashedCursor::next`].
+    next_index: usize,
+}
+
+impl SortedAccountsCursor {
+    /// Create a new cursor over the accounts sorted by their hashed address.
+    pub const fn new(accounts: Arc<[(B256, Account)]>) -> Self {
+        Self { accounts, next_index: 0 }
+    }
+}
+
+impl HashedCursor for SortedAccountsCursor {
+    type Value = Account;
+
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, Self::Value)>, reth_db::DatabaseError> {
+        self.next_index =
+            self.accounts.partition_point(|(hashed_address, _)| *hashed_address < key);
+        self.next()
+    }
+
+    fn next(&mut self) -> Result<Option<(B256, Self::Value)>, reth_db::DatabaseError> {
+        let entry = self.accounts.get(self.next_index).copied();
+        if entry.is_some() {
+            self.next_index += 1;
+        }
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seek_and_next() {
+        let account = |nonce| Account { nonce, ..Default::default() };
+        let accounts: Arc<[(B256, Account)]> =
+            [(B256::with_last_byte(2), account(1)), (B256::with_last_byte(4), account(2))].into();
+        let mut cursor = SortedAccountsCursor::new(accounts.clone());
+
+        assert_eq!(cursor.seek(B256::ZERO).unwrap(), Some(accounts[0]));
+        assert_eq!(cursor.next().unwrap(), Some(accounts[1]));
+        assert_eq!(cursor.next().unwrap(), None);
+
+        assert_eq!(cursor.seek(B256::with_last_byte(3)).unwrap(), Some(accounts[1]));
+        assert_eq!(cursor.seek(B256::with_last_byte(2)).unwrap(), Some(accounts[0]));
+        assert_eq!(cursor.seek(B256::with_last_byte(5)).unwrap(), None);
+        assert_eq!(cursor.next().unwrap(), None);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    prefix_set::load_prefix_sets,
+    trie_cursor::{BranchNodeCache, CachedTrieCursorFactory},
+    updates::TrieUpdates,
+    StateRoot,
+};
+use reth_db::{tables, transaction::DbTx, DatabaseError};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{keccak256, Address, BlockNumber, B256};
+use std::{collections::HashMap, ops::RangeInclusive};
+use tracing::debug;
+
+/// The maximum number of hashes cached by the session, the cache is cleared once it is exceeded.
+const MAX_CACHED_HASHES: usize = 1 << 20;
+
+/// The session computing the incremental state roots of successive block ranges on a single
+/// database transaction.
+///
+/// Unlike repeated calls to [`StateRoot::incremental_root_with_updates`], the session opens the
+/// changeset cursors once and caches the hashes of the changed addresses and storage keys across
+/// the ranges, amortizing the setup over many small ranges such as single blocks.
+///
+/// The trie tables are read through the transaction on every call, so the trie updates returned by
+/// [`Self::advance`] have to be written to the same transaction before the next call for the next
+/// root to be computed on top of them. The cached hashes do not depend on the database and stay
+/// valid regardless of the changes written between the calls.
+///
+/// With a [`BranchNodeCache`], the branch nodes read by a call are carried over to the next one,
+/// see [`Self::with_branch_node_cache`].
+pub struct IncrementalRootSession<'a, TX: DbTx> {
+    /// The database transaction.
+    tx: &'a TX,
+    /// The cursor over the account changesets.
+    account_changeset_cursor: TX::Cursor<tables::AccountChangeSets>,
+    /// The cursor over the plain account state, used to detect destroyed accounts.
+    account_plain_state_cursor: TX::Cursor<tables::PlainAccountState>,
+    /// The cursor over the storage changesets.
+    storage_changeset_cursor: TX::DupCursor<tables::StorageChangeSets>,
+    /// The cached hashes of the changed addresses.
+    hashed_addresses: HashMap<Address, B256>,
+    /// The cached hashes of the changed storage keys.
+    hashed_storage_keys: HashMap<B256, B256>,
+    /// The cache of the branch nodes read by the previous calls.
+    branch_node_cache: Option<BranchNodeCache>,
+}
+
+impl<TX: DbTx> std::fmt::Debug for IncrementalRootSession<'_, TX> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IncrementalRootSession")
+            .field("hashed_addresses", &self.hashed_addresses.len())
+            .field("hashed_storage_keys", &self.hashed_storage_keys.len())
+            .field("branch_node_cache", &self.branch_node_cache)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, TX: DbTx> IncrementalRootSession<'a, TX> {
+    /// Create a new session, opening the changeset cursors of the transaction.
+    pub fn new(tx: &'a TX) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            tx,
+            account_changeset_cursor: tx.cursor_read()?,
+            account_plain_state_cursor: tx.cursor_read()?,
+            storage_changeset_cursor: tx.cursor_dup_read()?,
+            hashed_addresses: HashMap::new(),
+            hashed_storage_keys: HashMap::new(),
+            branch_node_cache: None,
+        })
+    }
+
+    /// Set the cache of the branch nodes to be read through and kept warm across the calls.
+    ///
+    /// The cache is invalidated with the prefix sets of every call, so the trie updates of a call
+    /// have to be written before the next one as usual. The cache can be shared with the session
+    /// of the next transaction as long as the transaction sees the updates of this one.
+    pub fn with_branch_node_cache(mut self, cache: BranchNodeCache) -> Self {
+        self.branch_node_cache = Some(cache);
+        self
+    }
+
+    /// Computes the state root after the changes of the given block range on top of the trie
+    /// stored in the transaction, collecting the trie updates in the process.
+    ///
+    /// # Returns
+    ///
+    /// The updated state root and the trie updates.
+    pub fn advance(
+        &mut self,
+        range: RangeInclusive<BlockNumber>,
+    ) -> Result<(B256, TrieUpdates), StateRootError> {
+        debug!(target: "trie::loader", ?range, "incremental state root session");
+        if self.hashed_addresses.len() + self.hashed_storage_keys.len() > MAX_CACHED_HASHES {
+            self.hashed_addresses.clear();
+            self.hashed_storage_keys.clear();
+        }
+
+        let hashed_addresses = &mut self.hashed_addresses;
+        let hashed_storage_keys = &mut self.hashed_storage_keys;
+        let prefix_sets = load_prefix_sets(
+            &mut self.account_changeset_cursor,
+            &mut self.account_plain_state_cursor,
+            &mut self.storage_changeset_cursor,
+            range,
+            |address| *hashed_addresses.entry(address).or_insert_with(|| keccak256(address)),
+            |key| *hashed_storage_keys.entry(key).or_insert_with(|| keccak256(key)),
+        )?;
+        let Some(cache) = &self.branch_node_cache else {
+            return StateRoot::from_tx(self.tx).with_prefix_sets(prefix_sets).root_with_updates()
+        };
+        let result = StateRoot::from_tx(self.tx)
+            .with_trie_cursor_factory(CachedTrieCursorFactory::new(self.tx, cache.clone()))
+            .with_prefix_sets(prefix_sets.clone())
+            .root_with_updates();
+        cache.invalidate(&prefix_sets);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prefix_set::TriePrefixSets;
+    use reth_db::{
+        models::{AccountBeforeTx, BlockNumberAddress},
+        transaction::DbTxMut,
+    };
+    use reth_primitives::{Account, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn session_matches_full_root() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let addresses = (0..10u8).map(Address::with_last_byte).collect::<Vec<_>>();
+        for address in &addresses {
+            let account = Account { nonce: 1, ..Default::default() };
+            tx.put::<tables::PlainAccountState>(*address, account).unwrap();
+            tx.put::<tables::HashedAccounts>(keccak256(address), account).unwrap();
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        let mut session = IncrementalRootSession::new(tx).unwrap();
+        let cache = BranchNodeCache::default();
+        let mut cached_session =
+            IncrementalRootSession::new(tx).unwrap().with_branch_node_cache(cache.clone());
+        for block in 1..=3u64 {
+            for address in addresses.iter().skip(block as usize).step_by(3) {
+                let before = tx.get::<tables::PlainAccountState>(*address).unwrap();
+                let changeset = AccountBeforeTx { address: *address, info: before };
+                tx.put::<tables::AccountChangeSets>(block, changeset).unwrap();
+                let account = Account { nonce: block + 1, ..Default::default() };
+                tx.put::<tables::PlainAccountState>(*address, account).unwrap();
+                tx.put::<tables::HashedAccounts>(keccak256(address), account).unwrap();
+
+                let key = B256::with_last_byte(block as u8);
+                let changeset = StorageEntry { key, value: U256::ZERO };
+                tx.put::<tables::StorageChangeSets>(
+                    BlockNumberAddress((block, *address)),
+                    changeset,
+                )
+                .unwrap();
+                let entry = StorageEntry { key: keccak256(key), value: U256::from(block) };
+                tx.put::<tables::HashedStorages>(keccak256(address), entry).unwrap();
+            }
+
+            // The destroyed account.
+            if block == 3 {
+                let before = tx.get::<tables::PlainAccountState>(addresses[0]).unwrap();
+                let changeset = AccountBeforeTx { address: addresses[0], info: before };
+                tx.put::<tables::AccountChangeSets>(block, changeset).unwrap();
+                tx.delete::<tables::PlainAccountState>(addresses[0], None).unwrap();
+                tx.delete::<tables::HashedAccounts>(keccak256(addresses[0]), None).unwrap();
+            }
+
+            let (root, updates) = session.advance(block..=block).unwrap();
+            let expected = StateRoot::from_tx(tx)
+                .with_prefix_sets(TriePrefixSets::all_changed())
+                .root()
+                .unwrap();
+            assert_eq!(root, expected);
+            assert_eq!(cached_session.advance(block..=block).unwrap(), (root, updates.clone()));
+            updates.flush(tx).unwrap();
+        }
+        assert!(!cache.is_empty());
+    }
+}
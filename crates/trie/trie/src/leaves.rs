@@ -0,0 +1,252 @@
+use crate::{
+    diff::AccountLeaf,
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    trie_cursor::{TrieCursor, TrieCursorFactory},
+    walker::pack_key,
+    StorageRoot,
+};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{
+    trie::{BranchNodeCompact, Nibbles},
+    B256,
+};
+
+#[cfg(feature = "metrics")]
+use crate::metrics::{TrieRootMetrics, TrieType};
+
+/// Returns the iterator over the account leaves of the stored account trie in trie order, see
+/// [`AccountLeafIter`].
+pub fn iter_account_leaves<F>(factory: &F) -> Result<AccountLeafIter<'_, F>, StateRootError>
+where
+    F: TrieCursorFactory + HashedCursorFactory + Clone,
+{
+    AccountLeafIter::new(factory)
+}
+
+/// Iterator over the account leaves reconstructed from the structure of the stored account trie.
+///
+/// The account trie stores only the branch nodes, so the iterator walks the stored branch nodes
+/// depth-first and reads the accounts under every child that the trie marks as present but not
+/// stored from the hashed state. The storage root of every leaf is computed with the stored
+/// storage trie, reusing its stored nodes. The leaves are yielded in ascending order of hashed
+/// addresses.
+///
+/// Unlike the hashed account cursor, the iterator does not yield the hashed accounts at the paths
+/// missing from the stored trie, e.g. under an unset bit of the state mask or under a missing
+/// stored child, and yields the stale storage roots of the storage tries not updated along with
+/// the hashed storage. Comparing the leaves with the hashed state thus reveals the divergence of
+/// the stored tries from the hashed state. If no branch nodes are stored, all hashed accounts are
+/// yielded.
+pub struct AccountLeafIter<'a, F: HashedCursorFactory> {
+    factory: &'a F,
+    trie_cursor: Box<dyn TrieCursor + 'a>,
+    account_cursor: F::AccountCursor,
+    /// The stored branch nodes being walked, along with the next child nibble to visit.
+    stack: Vec<(Nibbles, BranchNodeCompact, u8)>,
+    /// The path of the child whose accounts are being read, and whether the account cursor is
+    /// already positioned within it.
+    leaf_prefix: Option<(Nibbles, bool)>,
+    #[cfg(feature = "metrics")]
+    metrics: TrieRootMetrics,
+}
+
+impl<F: HashedCursorFactory> std::fmt::Debug for AccountLeafIter<'_, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccountLeafIter")
+            .field("stack", &self.stack)
+            .field("leaf_prefix", &self.leaf_prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, F> AccountLeafIter<'a, F>
+where
+    F: TrieCursorFactory + HashedCursorFactory + Clone,
+{
+    /// Create new iterator over the account leaves of the given factory.
+    pub fn new(factory: &'a F) -> Result<Self, StateRootError> {
+        let mut trie_cursor = factory.account_trie_cursor()?;
+        let mut stack = Vec::new();
+        let mut leaf_prefix = None;
+        match trie_cursor.seek(Nibbles::default())? {
+            Some((key, node)) => stack.push((key, node, 0)),
+            None => leaf_prefix = Some((Nibbles::default(), false)),
+        }
+
+        Ok(Self {
+            factory,
+            trie_cursor,
+            account_cursor: factory.hashed_account_cursor()?,
+            stack,
+            leaf_prefix,
+            #[cfg(feature = "metrics")]
+            metrics: TrieRootMetrics::new(TrieType::Storage),
+        })
+    }
+
+    fn try_next(&mut self) -> Result<Option<(B256, AccountLeaf)>, StateRootError> {
+        loop {
+            if let Some((prefix, positioned)) = &mut self.leaf_prefix {
+                let entry = if *positioned {
+                    self.account_cursor.next()?
+                } else {
+                    *positioned = true;
+                    self.account_cursor.seek(pack_key(prefix))?
+                };
+                match entry {
+                    Some((hashed_address, account))
+                        if Nibbles::unpack(hashed_address).starts_with(prefix) =>
+                    {
+                        let storage_root = StorageRoot::new_hashed(
+                            self.factory.clone(),
+                            self.factory.clone(),
+                            hashed_address,
+                            #[cfg(feature = "metrics")]
+                            self.metrics.clone(),
+                        )
+                        .root()?;
+                        return Ok(Some((hashed_address, AccountLeaf { account, storage_root })))
+                    }
+                    _ => self.leaf_prefix = None,
+                }
+            }
+
+            let Some((key, node, nibble)) = self.stack.last_mut() else { return Ok(None) };
+            if *nibble == 16 {
+                self.stack.pop();
+                continue
+            }
+            let child_nibble = *nibble;
+            *nibble += 1;
+            if !node.state_mask.is_bit_set(child_nibble) {
+                continue
+            }
+
+            let mut child = key.clone();
+            child.push(child_nibble);
+            if node.tree_mask.is_bit_set(child_nibble) {
+                // The stored child is deeper than the child path if the child is an extension.
+                if let Some((child_key, child_node)) = self.trie_cursor.seek(child.clone())? {
+                    if child_key.starts_with(&child) {
+                        self.stack.push((child_key, child_node, 0));
+                    }
+                }
+            } else {
+                self.leaf_prefix = Some((child, false));
+            }
+        }
+    }
+}
+
+impl<'a, F> Iterator for AccountLeafIter<'a, F>
+where
+    F: TrieCursorFactory + HashedCursorFactory + Clone,
+{
+    type Item = Result<(B256, AccountLeaf), StateRootError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::storage_root_prehashed, StateRoot};
+    use reth_db::{
+        cursor::DbCursorRO,
+        tables,
+        transaction::{DbTx, DbTxMut},
+    };
+    use reth_primitives::{keccak256, Account, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+    use std::collections::BTreeMap;
+
+    /// Returns the leaves expected from the hashed state.
+    fn hashed_leaves(tx: &impl DbTx) -> Vec<(B256, AccountLeaf)> {
+        let mut storages = BTreeMap::<B256, BTreeMap<B256, U256>>::new();
+        for entry in tx.cursor_read::<tables::HashedStorages>().unwrap().walk(None).unwrap() {
+            let (hashed_address, StorageEntry { key, value }) = entry.unwrap();
+            storages.entry(hashed_address).or_default().insert(key, value);
+        }
+        tx.cursor_read::<tables::HashedAccounts>()
+            .unwrap()
+            .walk(None)
+            .unwrap()
+            .map(|entry| {
+                let (hashed_address, account) = entry.unwrap();
+                let storage = storages.remove(&hashed_address).unwrap_or_default();
+                let storage_root = storage_root_prehashed(storage);
+                (hashed_address, AccountLeaf { account, storage_root })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn account_leaves_follow_trie_structure() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // The first byte of the hashed addresses is the index, so the root branch node has no
+        // children under the first nibbles from 0xd to 0xf.
+        let hashed_address = |i: u64| B256::from(U256::from(i) << 248);
+        let with_storage = hashed_address(28);
+        for i in 0..200u64 {
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(hashed_address(i), account).unwrap();
+        }
+        for slot in 0..100u64 {
+            let entry = StorageEntry {
+                key: keccak256(B256::from(U256::from(slot))),
+                value: U256::from(slot + 1),
+            };
+            tx.put::<tables::HashedStorages>(with_storage, entry).unwrap();
+        }
+        // No branch nodes are stored yet, so all hashed accounts are yielded.
+        let leaves = || iter_account_leaves(&tx).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(leaves(), hashed_leaves(tx));
+
+        // The leaves match the hashed state on a consistent database.
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+        assert!(tx.entries::<tables::AccountsTrie>().unwrap() > 0);
+        let expected = hashed_leaves(tx);
+        assert_eq!(expected.len(), 200);
+        assert_eq!(leaves(), expected);
+
+        // An account at a path missing from the root branch node is not yielded.
+        let (root_key, root_node) = tx
+            .cursor_read::<tables::AccountsTrie>()
+            .unwrap()
+            .first()
+            .unwrap()
+            .map(|(key, node)| (key.0, node.0))
+            .unwrap();
+        assert!(root_key.is_empty());
+        let missing_nibble =
+            (0..16).find(|nibble| !root_node.state_mask.is_bit_set(*nibble)).unwrap();
+        let mut missing = B256::ZERO;
+        missing[0] = missing_nibble << 4;
+        tx.put::<tables::HashedAccounts>(missing, Account::default()).unwrap();
+
+        // The storage root of the storage trie not updated along with the hashed storage is stale.
+        let slot = keccak256(B256::ZERO);
+        let entry = StorageEntry { key: slot, value: U256::from(1) };
+        tx.delete::<tables::HashedStorages>(with_storage, Some(entry)).unwrap();
+        let entry = StorageEntry { key: slot, value: U256::from(1_000) };
+        tx.put::<tables::HashedStorages>(with_storage, entry).unwrap();
+
+        let leaves = leaves();
+        let expected = hashed_leaves(tx);
+        assert_ne!(leaves, expected);
+        assert_eq!(leaves.len(), 200);
+        assert!(expected.iter().any(|(hashed_address, _)| *hashed_address == missing));
+        assert!(leaves.iter().all(|(hashed_address, _)| *hashed_address != missing));
+        let stale = |leaves: &[(B256, AccountLeaf)]| {
+            leaves.iter().find(|(hashed_address, _)| *hashed_address == with_storage).unwrap().1
+        };
+        assert_eq!(stale(&leaves).account, stale(&expected).account);
+        assert_ne!(stale(&leaves).storage_root, stale(&expected).storage_root);
+    }
+}
@@ -29,27 +29,85 @@ pub mod walker;
 /// The iterators for traversing existing intermediate hashes and updated trie leaves.
 pub mod node_iter;
 
-/// In-memory hashed state.
+/// In-memory hashed state and the export of the stored one.
 mod state;
 pub use state::*;
 
 /// Merkle proof generation.
 pub mod proof;
 
+/// The incremental builder of trie roots from leaves and subtrie hashes, see also
+/// [`root_from_sorted_leaves`](reth_primitives::proofs::root_from_sorted_leaves).
+///
+/// The leaves and branch hashes have to be added in strictly ascending order of their paths,
+/// see [`root_from_unsorted`](reth_primitives::proofs::root_from_unsorted) for the leaves that
+/// are not sorted.
+pub use reth_primitives::trie::HashBuilder;
+
 /// The implementation of the Merkle Patricia Trie.
 mod trie;
-pub use trie::{StateRoot, StorageRoot};
+pub use trie::{
+    account_exists, referenced_hashes, StateRoot, StorageKey, StorageRoot, StorageRootMismatch,
+    StorageRootVerification,
+};
+
+/// The state root along with the witness of the changed keys.
+mod witness;
+pub use witness::{
+    root_updates_and_witness, StateWitness, WitnessDecodeError, WitnessVerificationError,
+    WITNESS_ENCODING_VERSION,
+};
+
+/// The checkpoints of the state root chained into an append-only log.
+mod checkpoint;
+pub use checkpoint::{checkpoint_root, verify_extends, CheckpointError, TrieCheckpoint};
+
+/// The audit trail of the inputs combined into the state root.
+mod audit;
+pub use audit::{AuditStep, AuditTrail};
+
+/// The account leaves reconstructed from the structure of the stored account trie.
+mod leaves;
+pub use leaves::{iter_account_leaves, AccountLeafIter};
+
+/// The stable read view of the database for the computation of roots and proofs.
+mod snapshot;
+pub use snapshot::SnapshotFactory;
+
+/// Incremental state roots of successive block ranges.
+mod incremental;
+pub use incremental::IncrementalRootSession;
+
+/// Historical state roots computed from the changesets.
+pub mod state_root;
+
+/// Storage trie diagnostics.
+pub mod storage_root;
+
+/// Read-ahead of the storage tries during the account walk.
+mod prefetch;
+
+/// Leaf value encodings of the account and storage tries.
+pub mod codec;
 
 /// Buffer for trie updates.
 pub mod updates;
 
 /// Utilities for state root checkpoint progress.
 mod progress;
-pub use progress::{IntermediateStateRootState, StateRootProgress};
+pub use progress::{
+    IntermediateStateRootState, ProgressReport, ProgressReporter, StateRootProgress,
+};
 
 /// Trie calculation stats.
 pub mod stats;
 
+/// Maintenance routines for repairing the stored trie.
+pub mod maintenance;
+
+/// Diagnostics for comparing the account leaves of two hashed states.
+pub mod diff;
+
 /// Trie calculation metrics.
 #[cfg(feature = "metrics")]
 pub mod metrics;
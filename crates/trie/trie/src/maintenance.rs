@@ -0,0 +1,1474 @@
+use crate::{
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    trie_cursor::noop::NoopTrieCursorFactory,
+    updates::{TrieKey, TrieOp, TrieUpdates},
+    walker::pack_key,
+    StateRoot, StorageRoot,
+};
+use alloy_rlp::{BufMut, Encodable, Header};
+use rayon::{
+    prelude::{IntoParallelIterator, ParallelIterator},
+    ThreadPool,
+};
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO, DbDupCursorRW},
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+    DatabaseError, RawTable, Tables,
+};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{
+    keccak256,
+    trie::{
+        BranchNodeCompact, HashBuilder, Nibbles, StoredNibbles, StoredNibblesSubKey, TrieAccount,
+    },
+    Account, Bytes, B256,
+};
+use std::collections::BTreeMap;
+use tracing::{debug, info, warn};
+
+/// The default number of accounts after which the rebuilt storage tries are committed.
+pub const DEFAULT_REBUILD_COMMIT_THRESHOLD: u64 = 10_000;
+
+/// The number of partitions of the hashed keyspace rebuilt by [`rebuild_storage_roots_parallel`],
+/// one per value of the first two bytes of the hashed address.
+const REBUILD_PARTITIONS: usize = 1 << 16;
+
+/// The maximum length of the path of a stored trie node. The children of a branch node are one
+/// nibble deeper than the node, so no branch node is stored at the full 64 nibbles of a hashed key.
+const MAX_TRIE_NODE_PATH_LEN: usize = 63;
+
+/// The number of scanned trie nodes after which the progress of the reference scan is logged.
+const REFERENCE_SCAN_LOG_INTERVAL: u64 = 100_000;
+
+/// Rebuilds the storage tries of all accounts in [`tables::HashedAccounts`].
+///
+/// Unlike pruning dangling storage tries, this recomputes the storage root and the intermediate
+/// nodes of every account from the hashed storage entries, ignoring the nodes that are currently
+/// stored, and overwrites the [`tables::StoragesTrie`] entries of the account. This is the
+/// recovery path for storage tries that are corrupted rather than merely dangling.
+///
+/// The rebuilt tries are committed every `commit_threshold` accounts, so the progress is not lost
+/// if the process is interrupted.
+///
+/// # Returns
+///
+/// The state root computed from the hashed accounts and the rebuilt storage roots. The account
+/// trie is neither read nor modified, so the caller is expected to verify the returned root
+/// against the state root of the latest header.
+pub fn rebuild_all_storage_tries<DB: Database>(
+    db: &DB,
+    commit_threshold: u64,
+) -> Result<B256, StateRootError> {
+    let commit_threshold = commit_threshold.max(1);
+    let mut hash_builder = HashBuilder::default();
+    let mut account_rlp = Vec::with_capacity(128);
+    let mut last_hashed_address = None;
+    let mut rebuilt = 0u64;
+
+    info!(target: "trie::maintenance", "Starting rebuild of storage tries");
+    loop {
+        let tx = db.tx_mut()?;
+        let mut hashed_account_cursor = tx.cursor_read::<tables::HashedAccounts>()?;
+        let mut storage_trie_cursor = tx.cursor_dup_write::<tables::StoragesTrie>()?;
+
+        // Resume from the account following the last processed one.
+        let mut entry = match last_hashed_address {
+            Some(last) => match hashed_account_cursor.seek(last)? {
+                Some((hashed_address, _)) if hashed_address == last => {
+                    hashed_account_cursor.next()?
+                }
+                entry => entry,
+            },
+            None => hashed_account_cursor.first()?,
+        };
+
+        let mut processed_in_batch = 0;
+        while let Some((hashed_address, account)) = entry {
+            let (storage_root, _, updates) = StorageRoot::from_tx_hashed(&tx, hashed_address)
+                .with_trie_cursor_factory(NoopTrieCursorFactory)
+                .calculate(true)?;
+
+            // Remove all existing nodes of the storage trie before writing the rebuilt ones.
+            if storage_trie_cursor.seek_exact(hashed_address)?.is_some() {
+                storage_trie_cursor.delete_current_duplicates()?;
+            }
+            updates.flush(&tx)?;
+
+            account_rlp.clear();
+            TrieAccount::from((account, storage_root)).encode(&mut account_rlp as &mut dyn BufMut);
+            hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp);
+
+            last_hashed_address = Some(hashed_address);
+            processed_in_batch += 1;
+            if processed_in_batch >= commit_threshold {
+                break
+            }
+            entry = hashed_account_cursor.next()?;
+        }
+
+        let done = processed_in_batch < commit_threshold;
+        drop(hashed_account_cursor);
+        drop(storage_trie_cursor);
+        tx.commit()?;
+        rebuilt += processed_in_batch;
+        debug!(target: "trie::maintenance", rebuilt, last = ?last_hashed_address, "Committed rebuilt storage tries");
+
+        if done {
+            break
+        }
+        info!(target: "trie::maintenance", rebuilt, "Rebuilding storage tries");
+    }
+
+    let root = hash_builder.root();
+    info!(target: "trie::maintenance", rebuilt, %root, "Finished rebuild of storage tries");
+    Ok(root)
+}
+
+/// Rebuilds the storage tries of all accounts in [`tables::HashedAccounts`] concurrently on the
+/// given thread pool, see [`rebuild_all_storage_tries`].
+///
+/// The hashed keyspace is split into partitions by the first two bytes of the hashed address.
+/// The partitions are rebuilt in batches: the storage tries of the partitions of a batch are
+/// computed concurrently, each through a read-only transaction opened on the worker thread, and
+/// are then written serially in the order of the hashed addresses, so the written tries and the
+/// returned root do not depend on the scheduling of the workers. Only the rebuilt tries of a
+/// single batch are held in memory. The writes are committed every
+/// [`DEFAULT_REBUILD_COMMIT_THRESHOLD`] accounts and once the rebuild completes.
+///
+/// The read transactions are opened independently, so the hashed state must not be written to
+/// concurrently for the rebuild to be consistent.
+///
+/// # Returns
+///
+/// The state root computed from the hashed accounts and the rebuilt storage roots, the same as
+/// the one of [`rebuild_all_storage_tries`], to be verified against the state root of the latest
+/// header.
+pub fn rebuild_storage_roots_parallel<DB: Database>(
+    db: &DB,
+    pool: &ThreadPool,
+) -> Result<B256, StateRootError> {
+    // A few partitions per thread even out the partitions of different sizes.
+    let batch_size = pool.current_num_threads().max(1) * 4;
+    let mut hash_builder = HashBuilder::default();
+    let mut account_rlp = Vec::with_capacity(128);
+    let mut rebuilt = 0u64;
+    let mut uncommitted = 0u64;
+
+    info!(
+        target: "trie::maintenance",
+        threads = pool.current_num_threads(),
+        "Starting parallel rebuild of storage tries"
+    );
+    let mut tx = db.tx_mut()?;
+    for batch_start in (0..REBUILD_PARTITIONS).step_by(batch_size) {
+        let partitions = batch_start..(batch_start + batch_size).min(REBUILD_PARTITIONS);
+        let rebuilt_partitions = pool.install(|| {
+            partitions
+                .into_par_iter()
+                .map(|partition| rebuild_partition(db, partition as u16))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let mut storage_trie_cursor = tx.cursor_dup_write::<tables::StoragesTrie>()?;
+        for (hashed_address, account, storage_root, updates) in
+            rebuilt_partitions.into_iter().flatten()
+        {
+            // Remove all existing nodes of the storage trie before writing the rebuilt ones.
+            if storage_trie_cursor.seek_exact(hashed_address)?.is_some() {
+                storage_trie_cursor.delete_current_duplicates()?;
+            }
+            updates.flush(&tx)?;
+
+            account_rlp.clear();
+            TrieAccount::from((account, storage_root)).encode(&mut account_rlp as &mut dyn BufMut);
+            hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp);
+            rebuilt += 1;
+            uncommitted += 1;
+        }
+        drop(storage_trie_cursor);
+
+        if uncommitted >= DEFAULT_REBUILD_COMMIT_THRESHOLD {
+            tx.commit()?;
+            tx = db.tx_mut()?;
+            uncommitted = 0;
+            info!(target: "trie::maintenance", rebuilt, "Rebuilding storage tries");
+        }
+    }
+    tx.commit()?;
+
+    let root = hash_builder.root();
+    info!(target: "trie::maintenance", rebuilt, %root, "Finished parallel rebuild of storage tries");
+    Ok(root)
+}
+
+/// The storage trie rebuilt by [`rebuild_partition`]: the hashed address, the account, the
+/// storage root and the updates writing the nodes of the storage trie.
+type RebuiltStorageTrie = (B256, Account, B256, TrieUpdates);
+
+/// Computes the storage tries of the accounts whose hashed addresses start with the given two
+/// bytes from scratch, in the order of the hashed addresses.
+fn rebuild_partition<DB: Database>(
+    db: &DB,
+    partition: u16,
+) -> Result<Vec<RebuiltStorageTrie>, StateRootError> {
+    let prefix = partition.to_be_bytes();
+    let mut start = B256::ZERO;
+    start[..2].copy_from_slice(&prefix);
+
+    let tx = db.tx()?;
+    let mut hashed_account_cursor = tx.cursor_read::<tables::HashedAccounts>()?;
+    let mut rebuilt = Vec::new();
+    let mut entry = hashed_account_cursor.seek(start)?;
+    while let Some((hashed_address, account)) = entry {
+        if !hashed_address.starts_with(&prefix) {
+            break
+        }
+        let (storage_root, _, updates) = StorageRoot::from_tx_hashed(&tx, hashed_address)
+            .with_trie_cursor_factory(NoopTrieCursorFactory)
+            .calculate(true)?;
+        rebuilt.push((hashed_address, account, storage_root, updates));
+        entry = hashed_account_cursor.next()?;
+    }
+    Ok(rebuilt)
+}
+
+/// The state of a storage trie checked by [`check_storage_tries`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum StorageTrieStatus {
+    /// The stored nodes match the ones computed from the hashed storage.
+    Valid,
+    /// The stored nodes differ from the ones computed from the hashed storage.
+    Corrupted,
+    /// The storage trie was corrupted and has been rebuilt from the hashed storage.
+    Rebuilt,
+}
+
+/// Checks the storage tries of the given accounts, e.g. the contracts suspected of corruption,
+/// against the ones computed from their hashed storage, optionally rebuilding the corrupted ones.
+///
+/// The nodes of every listed storage trie are recomputed from scratch with [`StorageRoot`],
+/// ignoring the stored nodes, and compared with the stored ones. Unlike
+/// [`rebuild_all_storage_tries`], only the listed accounts are touched, so the known-problem
+/// contracts can be recovered without a full scan. The storage tries of the absent accounts are
+/// expected to be empty.
+///
+/// The account trie is not modified, so the caller is expected to verify the state root with the
+/// listed accounts in the account prefix set once the rebuilt tries are committed.
+///
+/// # Returns
+///
+/// The status of the storage trie of every listed account, in the given order.
+pub fn check_storage_tries<TX: DbTx + DbTxMut>(
+    tx: &TX,
+    hashed_addresses: &[B256],
+    rebuild: bool,
+) -> Result<Vec<(B256, StorageTrieStatus)>, StateRootError> {
+    let mut statuses = Vec::with_capacity(hashed_addresses.len());
+    for hashed_address in hashed_addresses.iter().copied() {
+        let (storage_root, _, updates) = StorageRoot::from_tx_hashed(tx, hashed_address)
+            .with_trie_cursor_factory(NoopTrieCursorFactory)
+            .calculate(true)?;
+        let expected = updates
+            .iter()
+            .filter_map(|(key, op)| match (key, op) {
+                (TrieKey::StorageNode(_, nibbles), TrieOp::Update(node)) => {
+                    Some((nibbles.0.clone(), node.clone()))
+                }
+                _ => None,
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut storage_trie_cursor = tx.cursor_dup_write::<tables::StoragesTrie>()?;
+        let stored = storage_trie_cursor
+            .walk_dup(Some(hashed_address), None)?
+            .map(|entry| entry.map(|(_, entry)| (entry.nibbles.0, entry.node)))
+            .collect::<Result<BTreeMap<_, _>, _>>()?;
+        if stored == expected {
+            debug!(
+                target: "trie::maintenance",
+                %hashed_address,
+                %storage_root,
+                "Storage trie is valid"
+            );
+            statuses.push((hashed_address, StorageTrieStatus::Valid));
+            continue
+        }
+
+        if !rebuild {
+            warn!(
+                target: "trie::maintenance",
+                %hashed_address,
+                %storage_root,
+                "Storage trie is corrupted"
+            );
+            statuses.push((hashed_address, StorageTrieStatus::Corrupted));
+            continue
+        }
+
+        if storage_trie_cursor.seek_exact(hashed_address)?.is_some() {
+            storage_trie_cursor.delete_current_duplicates()?;
+        }
+        updates.flush(tx)?;
+        info!(
+            target: "trie::maintenance",
+            %hashed_address,
+            %storage_root,
+            "Rebuilt corrupted storage trie"
+        );
+        statuses.push((hashed_address, StorageTrieStatus::Rebuilt));
+    }
+    Ok(statuses)
+}
+
+/// Stores the missing root nodes of the account trie and the storage tries.
+///
+/// The root node of a trie is stored at the empty path along with the other nodes, but the tries
+/// written before the root nodes were stored have none. Such a trie gets its root node on its
+/// first change, the backfill writes the root nodes of all tries at once instead, e.g. to migrate
+/// an older database. The root of a trie without the root node is computed from the nodes right
+/// below it and the leaves hanging off the root, the small tries whose root has no stored or
+/// hashed children have no root node to store.
+///
+/// # Returns
+///
+/// The number of tries whose root node was written.
+pub fn backfill_root_nodes<TX: DbTx + DbTxMut>(tx: &TX) -> Result<usize, StateRootError> {
+    let mut backfilled = 0;
+
+    let mut hashed_storage_cursor = tx.cursor_dup_read::<tables::HashedStorages>()?;
+    let mut storage_trie_cursor = tx.cursor_dup_read::<tables::StoragesTrie>()?;
+    let mut entry = hashed_storage_cursor.first()?;
+    while let Some((hashed_address, _)) = entry {
+        let root_key = StoredNibblesSubKey(Nibbles::default());
+        if storage_trie_cursor
+            .seek_by_key_subkey(hashed_address, root_key.clone())?
+            .filter(|node| node.nibbles == root_key)
+            .is_none()
+        {
+            let (_, _, updates) =
+                StorageRoot::from_tx_hashed(tx, hashed_address).root_with_updates()?;
+            if updates.contains_key(&TrieKey::StorageNode(hashed_address, root_key)) {
+                updates.flush(tx)?;
+                backfilled += 1;
+            }
+        }
+        entry = hashed_storage_cursor.next_no_dup()?;
+    }
+
+    let root_key = StoredNibbles(Nibbles::default());
+    if tx.get::<tables::AccountsTrie>(root_key.clone())?.is_none() &&
+        tx.cursor_read::<tables::HashedAccounts>()?.first()?.is_some()
+    {
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates()?;
+        if updates.contains_key(&TrieKey::AccountNode(root_key)) {
+            updates.flush(tx)?;
+            backfilled += 1;
+        }
+    }
+
+    info!(target: "trie::maintenance", backfilled, "Backfilled trie root nodes");
+    Ok(backfilled)
+}
+
+/// Checks the root hash of the stored account trie against the expected state root, e.g. the
+/// state root of the latest header, without walking the trie.
+///
+/// The root hash is read from the root node of the account trie, which is stored along with the
+/// hash of the whole trie. Only the trie tables are checked this way, not their consistency with
+/// the hashed state. A mismatch means that the stored trie is stale or corrupted and has to be
+/// recovered.
+///
+/// The root node is only stored if it is a branch node with hashed children, which is always the
+/// case for states larger than a handful of accounts. If no account trie node is stored at all,
+/// e.g. for an empty or a small state, the root is computed with [`StateRoot`] instead.
+///
+/// The databases written before the root nodes were persisted store the other nodes of the
+/// account trie without the root node, in which case [`QuickRootCheckError::RootNodeMissing`] is
+/// returned until the root nodes are stored with [`backfill_root_nodes`].
+pub fn quick_root_check<TX: DbTx>(tx: &TX, expected: B256) -> Result<bool, QuickRootCheckError> {
+    let mut cursor = tx.cursor_read::<tables::AccountsTrie>().map_err(StateRootError::from)?;
+    match cursor.first().map_err(StateRootError::from)? {
+        Some((StoredNibbles(path), node)) if path.is_empty() => {
+            let root_hash = node.0.root_hash.ok_or(QuickRootCheckError::RootNodeMissing)?;
+            debug!(target: "trie::maintenance", %root_hash, %expected, "Checking stored trie root");
+            Ok(root_hash == expected)
+        }
+        Some(_) => Err(QuickRootCheckError::RootNodeMissing),
+        None => {
+            debug!(target: "trie::maintenance", %expected, "No trie nodes, computing the root");
+            Ok(StateRoot::from_tx(tx).root()? == expected)
+        }
+    }
+}
+
+/// The error returned by [`quick_root_check`].
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum QuickRootCheckError {
+    /// The account trie is stored without its root node, e.g. by a database written before the
+    /// root nodes were persisted.
+    #[error("root node of the account trie not stored, backfill the root nodes of the trie")]
+    RootNodeMissing,
+    /// The state root calculation failed.
+    #[error(transparent)]
+    StateRoot(#[from] StateRootError),
+}
+
+/// The child of a stored branch node that is missing from the database.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DanglingRef {
+    /// The hashed address of the account owning the storage trie, `None` for the account trie.
+    pub hashed_address: Option<B256>,
+    /// The path of the branch node referencing the child.
+    pub parent: Nibbles,
+    /// The nibble of the child.
+    pub nibble: u8,
+    /// What is missing below the child.
+    pub missing: MissingChild,
+}
+
+/// The part of the database missing below a child of a branch node, see [`DanglingRef`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MissingChild {
+    /// The child is flagged as stored in the trie, but no trie node is stored below it.
+    Node,
+    /// The child is not stored in the trie, but no hashed entries exist below it.
+    Leaves,
+}
+
+/// Scans the stored account and storage tries for the branch node children that are missing from
+/// the database.
+///
+/// Every child of a stored branch node has to be backed either by a trie node stored below it, if
+/// the child is flagged in the tree mask, or by the hashed entries below it otherwise. The hashes
+/// of the children are not recomputed, so a child that exists but has a different hash is not
+/// detected, see [`quick_root_check`] and [`StateRoot`] for the root comparison.
+///
+/// The trie tables are streamed through the cursors and the progress is logged periodically.
+pub fn verify_trie_references<TX: DbTx>(tx: &TX) -> Result<Vec<DanglingRef>, DatabaseError> {
+    let mut dangling = Vec::new();
+    let mut scanned = 0u64;
+    info!(target: "trie::maintenance", "Starting scan of trie node references");
+
+    let mut account_trie_cursor = tx.cursor_read::<tables::AccountsTrie>()?;
+    let mut hashed_account_cursor = tx.cursor_read::<tables::HashedAccounts>()?;
+    let mut account_nodes = tx.cursor_read::<tables::AccountsTrie>()?;
+    let mut entry = account_nodes.first()?;
+    while let Some((StoredNibbles(parent), node)) = entry {
+        let missing = missing_children(
+            &parent,
+            &node.0,
+            |path| Ok(account_trie_cursor.seek(StoredNibbles(path))?.map(|(key, _)| key.0)),
+            |key| Ok(hashed_account_cursor.seek(key)?.map(|(key, _)| key)),
+        )?;
+        dangling.extend(missing.into_iter().map(|(nibble, missing)| DanglingRef {
+            hashed_address: None,
+            parent: parent.clone(),
+            nibble,
+            missing,
+        }));
+
+        scanned += 1;
+        if scanned % REFERENCE_SCAN_LOG_INTERVAL == 0 {
+            info!(
+                target: "trie::maintenance",
+                scanned,
+                dangling = dangling.len(),
+                "Scanning account trie node references"
+            );
+        }
+        entry = account_nodes.next()?;
+    }
+
+    let mut storage_trie_cursor = tx.cursor_dup_read::<tables::StoragesTrie>()?;
+    let mut hashed_storage_cursor = tx.cursor_dup_read::<tables::HashedStorages>()?;
+    let mut storage_nodes = tx.cursor_dup_read::<tables::StoragesTrie>()?;
+    let mut entry = storage_nodes.first()?;
+    while let Some((hashed_address, node)) = entry {
+        let missing = missing_children(
+            &node.nibbles.0,
+            &node.node,
+            |path| {
+                Ok(storage_trie_cursor
+                    .seek_by_key_subkey(hashed_address, StoredNibblesSubKey(path))?
+                    .map(|entry| entry.nibbles.0))
+            },
+            |key| {
+                Ok(hashed_storage_cursor
+                    .seek_by_key_subkey(hashed_address, key)?
+                    .map(|entry| entry.key))
+            },
+        )?;
+        dangling.extend(missing.into_iter().map(|(nibble, missing)| DanglingRef {
+            hashed_address: Some(hashed_address),
+            parent: node.nibbles.0.clone(),
+            nibble,
+            missing,
+        }));
+
+        scanned += 1;
+        if scanned % REFERENCE_SCAN_LOG_INTERVAL == 0 {
+            info!(
+                target: "trie::maintenance",
+                scanned,
+                dangling = dangling.len(),
+                "Scanning storage trie node references"
+            );
+        }
+        entry = storage_nodes.next()?;
+    }
+
+    info!(
+        target: "trie::maintenance",
+        scanned,
+        dangling = dangling.len(),
+        "Finished scan of trie node references"
+    );
+    Ok(dangling)
+}
+
+/// A trie table key whose nibbles are not canonically encoded, see
+/// [`StoredNibbles::is_canonical_encoding`] and [`StoredNibblesSubKey::is_canonical_encoding`].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct NonCanonicalKey {
+    /// The table the key is stored in.
+    pub table: Tables,
+    /// The hashed address of the account owning the storage trie, `None` for the account trie.
+    pub hashed_address: Option<B256>,
+    /// The raw encoded nibbles, for the storage trie the subkey along with the padding and the
+    /// length byte.
+    pub key: Bytes,
+}
+
+/// Scans the keys of the stored account and storage tries for nibbles that are not canonically
+/// encoded.
+///
+/// The paths are stored unpacked, one nibble per byte, and the decoding does not validate them, so
+/// a corrupted key is silently read back as a path the walker never expects. The keys are checked
+/// on their raw bytes without decoding them, so a malformed key is reported and logged rather than
+/// causing a panic.
+pub fn verify_trie_key_encodings<TX: DbTx>(tx: &TX) -> Result<Vec<NonCanonicalKey>, DatabaseError> {
+    let mut non_canonical = Vec::new();
+    info!(target: "trie::maintenance", "Starting scan of trie key encodings");
+
+    let mut account_nodes = tx.cursor_read::<RawTable<tables::AccountsTrie>>()?;
+    for entry in account_nodes.walk(None)? {
+        let (key, _) = entry?;
+        if !StoredNibbles::is_canonical_encoding(key.raw_key()) {
+            let key = Bytes::from(key.into_key());
+            warn!(target: "trie::maintenance", %key, "Non-canonical account trie key");
+            non_canonical.push(NonCanonicalKey {
+                table: Tables::AccountsTrie,
+                hashed_address: None,
+                key,
+            });
+        }
+    }
+
+    // The subkeys are the prefixes of the values of the dupsort table.
+    let mut storage_nodes = tx.cursor_read::<RawTable<tables::StoragesTrie>>()?;
+    for entry in storage_nodes.walk(None)? {
+        let (hashed_address, value) = entry?;
+        let subkey = value.raw_value();
+        if !StoredNibblesSubKey::is_canonical_encoding(subkey) {
+            let hashed_address = hashed_address.key()?;
+            let key = Bytes::copy_from_slice(&subkey[..subkey.len().min(65)]);
+            warn!(
+                target: "trie::maintenance",
+                %hashed_address,
+                %key,
+                "Non-canonical storage trie key"
+            );
+            non_canonical.push(NonCanonicalKey {
+                table: Tables::StoragesTrie,
+                hashed_address: Some(hashed_address),
+                key,
+            });
+        }
+    }
+
+    info!(
+        target: "trie::maintenance",
+        non_canonical = non_canonical.len(),
+        "Finished scan of trie key encodings"
+    );
+    Ok(non_canonical)
+}
+
+/// A trie table key whose path is longer than the path of any stored trie node can be, see
+/// [`verify_trie_key_lengths`].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct OverlongKey {
+    /// The table the key is stored in.
+    pub table: Tables,
+    /// The hashed address of the account owning the storage trie, `None` for the account trie.
+    pub hashed_address: Option<B256>,
+    /// The number of nibbles of the path.
+    pub len: usize,
+    /// The raw encoded nibbles, for the storage trie without the padding and the length byte.
+    pub key: Bytes,
+}
+
+/// Scans the keys of the stored account and storage tries for the paths longer than the path of
+/// a branch node can be.
+///
+/// The hashed keys are 64 nibbles long and the children of a branch node are one nibble deeper
+/// than the node, so the path of a stored node never exceeds 63 nibbles. A longer path indicates
+/// a corrupted key or a bug in the trie updates. The lengths are read from the raw keys in a
+/// single pass without decoding them, see [`verify_trie_key_encodings`] for the encoding of the
+/// nibbles themselves.
+pub fn verify_trie_key_lengths<TX: DbTx>(tx: &TX) -> Result<Vec<OverlongKey>, DatabaseError> {
+    let mut overlong = Vec::new();
+    info!(target: "trie::maintenance", "Starting scan of trie key lengths");
+
+    let mut account_nodes = tx.cursor_read::<RawTable<tables::AccountsTrie>>()?;
+    for entry in account_nodes.walk(None)? {
+        let (key, _) = entry?;
+        let len = key.raw_key().len();
+        if len > MAX_TRIE_NODE_PATH_LEN {
+            let key = Bytes::from(key.into_key());
+            warn!(target: "trie::maintenance", len, %key, "Overlong account trie key");
+            overlong.push(OverlongKey {
+                table: Tables::AccountsTrie,
+                hashed_address: None,
+                len,
+                key,
+            });
+        }
+    }
+
+    // The subkeys are the prefixes of the values of the dupsort table, padded to 64 nibbles and
+    // followed by their length.
+    let mut storage_nodes = tx.cursor_read::<RawTable<tables::StoragesTrie>>()?;
+    for entry in storage_nodes.walk(None)? {
+        let (hashed_address, value) = entry?;
+        let subkey = value.raw_value();
+        let Some(&len) = subkey.get(64) else { continue };
+        let len = len as usize;
+        if len > MAX_TRIE_NODE_PATH_LEN {
+            let hashed_address = hashed_address.key()?;
+            let key = Bytes::copy_from_slice(&subkey[..len.min(64)]);
+            warn!(
+                target: "trie::maintenance",
+                %hashed_address,
+                len,
+                %key,
+                "Overlong storage trie key"
+            );
+            overlong.push(OverlongKey {
+                table: Tables::StoragesTrie,
+                hashed_address: Some(hashed_address),
+                len,
+                key,
+            });
+        }
+    }
+
+    info!(
+        target: "trie::maintenance",
+        overlong = overlong.len(),
+        "Finished scan of trie key lengths"
+    );
+    Ok(overlong)
+}
+
+/// The leaf node located by [`find_leaf`].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct FoundLeaf {
+    /// The hashed key of the leaf, the hashed address or the hashed slot.
+    pub key: B256,
+    /// The path of the leaf node in the trie, the prefix of the unpacked key up to the node.
+    pub path: Nibbles,
+}
+
+/// Locates the leaf node with the given hash in the account trie or, if the hashed address is
+/// given, in the storage trie of the account.
+///
+/// The leaf nodes are not stored, so the hashed entries are walked in order and the leaf node of
+/// every entry is encoded and hashed. The path of a leaf node is one nibble longer than the common
+/// prefix of its key with the neighbouring keys, or empty for the only leaf of the trie. The value
+/// of an account leaf includes the storage root of the account, computed with the stored storage
+/// trie. The leaf nodes shorter than 32 bytes are inlined into their parent rather than referenced
+/// by their hash, the hash of their encoding is matched nonetheless.
+///
+/// The walk is restricted to the keys starting with the given prefix, pass an empty prefix to walk
+/// the whole trie. The keys right outside of the prefix are read as the neighbours of the first
+/// and the last key under it.
+///
+/// Returns `None` if no leaf under the prefix has the given hash.
+pub fn find_leaf<TX: DbTx>(
+    tx: &TX,
+    hash: B256,
+    hashed_address: Option<B256>,
+    prefix: &Nibbles,
+) -> Result<Option<FoundLeaf>, StateRootError> {
+    match hashed_address {
+        Some(hashed_address) => {
+            let mut cursor = tx.hashed_storage_cursor(hashed_address)?;
+            find_leaf_with(&mut cursor, hash, prefix, |_, value| {
+                Ok(alloy_rlp::encode_fixed_size(&value).to_vec())
+            })
+        }
+        None => {
+            let mut cursor = tx.hashed_account_cursor()?;
+            find_leaf_with(&mut cursor, hash, prefix, |hashed_address, account| {
+                let storage_root = StorageRoot::from_tx_hashed(tx, hashed_address).root()?;
+                let mut value = Vec::with_capacity(128);
+                TrieAccount::from((account, storage_root)).encode(&mut value as &mut dyn BufMut);
+                Ok(value)
+            })
+        }
+    }
+}
+
+/// Walks the hashed entries of the cursor under the prefix for the leaf node with the given hash,
+/// encoding the leaf values with the given closure, see [`find_leaf`].
+fn find_leaf_with<C: HashedCursor>(
+    cursor: &mut C,
+    hash: B256,
+    prefix: &Nibbles,
+    mut encode_value: impl FnMut(B256, C::Value) -> Result<Vec<u8>, StateRootError>,
+) -> Result<Option<FoundLeaf>, StateRootError> {
+    // Every leaf under a scope with at least two keys has a neighbour under the scope, which
+    // shares a longer prefix with the leaf than any key outside of the scope. The scope is thus
+    // widened until it contains two keys, so that the neighbours under it determine the paths.
+    let mut scope = prefix.clone();
+    while !scope.is_empty() {
+        let mut keys = 0;
+        let mut entry = cursor.seek(pack_key(&scope))?;
+        while keys < 2 &&
+            entry.as_ref().is_some_and(|(key, _)| Nibbles::unpack(key).has_prefix(&scope))
+        {
+            keys += 1;
+            entry = cursor.next()?;
+        }
+        if keys == 2 {
+            break
+        }
+        scope = Nibbles::from_nibbles_unchecked(&scope[..scope.len() - 1]);
+    }
+
+    let mut previous: Option<Nibbles> = None;
+    let mut entry = cursor.seek(pack_key(&scope))?;
+    while let Some((key, value)) = entry.filter(|(key, _)| Nibbles::unpack(key).has_prefix(&scope))
+    {
+        let path = Nibbles::unpack(key);
+        if !path.has_prefix(prefix) && path > *prefix {
+            break
+        }
+
+        let next = cursor.next()?;
+        if path.has_prefix(prefix) {
+            let next_path = next
+                .as_ref()
+                .map(|(key, _)| Nibbles::unpack(key))
+                .filter(|next| next.has_prefix(&scope));
+            let depth = previous
+                .iter()
+                .chain(next_path.iter())
+                .map(|neighbour| common_prefix_len(&path, neighbour) + 1)
+                .max()
+                .unwrap_or(0);
+            let value = encode_value(key, value)?;
+            if keccak256(encode_leaf_node(&path[depth..], &value)) == hash {
+                return Ok(Some(FoundLeaf {
+                    key,
+                    path: Nibbles::from_nibbles_unchecked(&path[..depth]),
+                }))
+            }
+        }
+        previous = Some(path);
+        entry = next;
+    }
+
+    Ok(None)
+}
+
+/// Returns the number of the leading nibbles shared by both paths.
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Returns the RLP encoding of the leaf node with the given remaining nibbles of the key and the
+/// encoded value.
+fn encode_leaf_node(key: &[u8], value: &[u8]) -> Vec<u8> {
+    // The compact encoding of the key, with the leaf flag and the odd nibble in the first byte.
+    let mut path = Vec::with_capacity(33);
+    let mut nibbles = key.iter();
+    if key.len() % 2 == 1 {
+        path.push(0x30 | nibbles.next().expect("odd length"));
+    } else {
+        path.push(0x20);
+    }
+    while let (Some(high), Some(low)) = (nibbles.next(), nibbles.next()) {
+        path.push(high << 4 | low);
+    }
+
+    let mut out = Vec::with_capacity(path.len() + value.len() + 8);
+    Header { list: true, payload_length: path[..].length() + value.length() }.encode(&mut out);
+    path[..].encode(&mut out);
+    value.encode(&mut out);
+    out
+}
+
+/// Returns the children of the branch node at the given path that are missing from the database.
+///
+/// The `seek_node` and `seek_leaf` closures return the path of the first stored trie node and the
+/// first hashed key that are not less than the given path and key respectively.
+fn missing_children(
+    parent: &Nibbles,
+    node: &BranchNodeCompact,
+    mut seek_node: impl FnMut(Nibbles) -> Result<Option<Nibbles>, DatabaseError>,
+    mut seek_leaf: impl FnMut(B256) -> Result<Option<B256>, DatabaseError>,
+) -> Result<Vec<(u8, MissingChild)>, DatabaseError> {
+    let mut missing = Vec::new();
+    for nibble in (0..16).filter(|nibble| node.state_mask.is_bit_set(*nibble)) {
+        let mut child = parent.clone();
+        child.push(nibble);
+        if node.tree_mask.is_bit_set(nibble) {
+            if !seek_node(child.clone())?.is_some_and(|path| path.has_prefix(&child)) {
+                missing.push((nibble, MissingChild::Node));
+            }
+        } else if !seek_leaf(pack_key(&child))?
+            .is_some_and(|key| Nibbles::unpack(key).has_prefix(&child))
+        {
+            missing.push((nibble, MissingChild::Leaves));
+        }
+    }
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prefix_set::{PrefixSetMut, TriePrefixSets},
+        proof::Proof,
+        test_utils::state_root,
+    };
+    use rayon::ThreadPoolBuilder;
+    use reth_db::{
+        cursor::{DbCursorRW, DbDupCursorRO},
+        transaction::DbTxMut,
+        RawKey, RawValue,
+    };
+    use reth_primitives::{
+        constants::EMPTY_ROOT_HASH,
+        keccak256,
+        trie::{BranchNodeCompact, StorageTrieEntry, StoredBranchNode},
+        Account, Address, StorageEntry, U256,
+    };
+    use reth_provider::{test_utils::create_test_provider_factory, ProviderFactory};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn rebuild_corrupted_storage_tries() {
+        let factory = create_test_provider_factory();
+        let state = (1..=10u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage = (0..(i as u64 * 3))
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + 1)))
+                    .collect::<BTreeMap<_, _>>();
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let tx = factory.provider_rw().unwrap();
+        for (address, (account, storage)) in &state {
+            let hashed_address = keccak256(address);
+            tx.tx_ref().put::<tables::HashedAccounts>(hashed_address, *account).unwrap();
+            for (slot, value) in storage {
+                tx.tx_ref()
+                    .put::<tables::HashedStorages>(
+                        hashed_address,
+                        StorageEntry { key: keccak256(slot), value: *value },
+                    )
+                    .unwrap();
+            }
+
+            // Insert bogus storage trie nodes.
+            tx.tx_ref()
+                .put::<tables::StoragesTrie>(
+                    hashed_address,
+                    StorageTrieEntry {
+                        nibbles: vec![0x1].into(),
+                        node: BranchNodeCompact::new(
+                            0b11,
+                            0,
+                            0b11,
+                            vec![B256::random(), B256::random()],
+                            None,
+                        ),
+                    },
+                )
+                .unwrap();
+        }
+        tx.commit().unwrap();
+
+        let root = rebuild_all_storage_tries(factory.db_ref(), 3).unwrap();
+        assert_eq!(root, state_root(state.clone()));
+
+        // The stored storage tries must match the ones computed from scratch.
+        let tx = factory.provider().unwrap();
+        for address in state.keys() {
+            let hashed_address = keccak256(address);
+            let (_, _, expected) = StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address)
+                .with_trie_cursor_factory(NoopTrieCursorFactory)
+                .calculate(true)
+                .unwrap();
+            let expected = expected
+                .into_iter()
+                .filter_map(|(key, op)| match (key, op) {
+                    (TrieKey::StorageNode(_, nibbles), TrieOp::Update(node))
+                        if !nibbles.is_empty() =>
+                    {
+                        Some((nibbles, node))
+                    }
+                    _ => None,
+                })
+                .collect::<BTreeMap<_, _>>();
+
+            // The root nodes are committed to by the state root checked above.
+            let mut cursor = tx.tx_ref().cursor_dup_read::<tables::StoragesTrie>().unwrap();
+            let stored = cursor
+                .walk_dup(Some(hashed_address), None)
+                .unwrap()
+                .map(|entry| entry.map(|(_, entry)| (entry.nibbles, entry.node)))
+                .filter(|entry| entry.as_ref().map_or(true, |(nibbles, _)| !nibbles.is_empty()))
+                .collect::<Result<BTreeMap<_, _>, _>>()
+                .unwrap();
+            assert_eq!(stored, expected);
+        }
+    }
+
+    #[test]
+    fn rebuild_storage_tries_in_parallel() {
+        let state = (0..50u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage = (0..(i as u64 % 7 * 20))
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + 1)))
+                    .collect::<BTreeMap<_, _>>();
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let populate = || {
+            let factory = create_test_provider_factory();
+            let tx = factory.provider_rw().unwrap();
+            for (address, (account, storage)) in &state {
+                let hashed_address = keccak256(address);
+                tx.tx_ref().put::<tables::HashedAccounts>(hashed_address, *account).unwrap();
+                for (slot, value) in storage {
+                    let entry = StorageEntry { key: keccak256(slot), value: *value };
+                    tx.tx_ref().put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+                }
+
+                // Insert bogus storage trie nodes.
+                let entry = StorageTrieEntry {
+                    nibbles: vec![0x1].into(),
+                    node: BranchNodeCompact::new(0b11, 0, 0b11, vec![B256::random(); 2], None),
+                };
+                tx.tx_ref().put::<tables::StoragesTrie>(hashed_address, entry).unwrap();
+            }
+            tx.commit().unwrap();
+            factory
+        };
+        let storage_tries = |factory: &ProviderFactory<_>| {
+            let tx = factory.provider().unwrap();
+            let mut cursor = tx.tx_ref().cursor_dup_read::<tables::StoragesTrie>().unwrap();
+            cursor
+                .walk(None)
+                .unwrap()
+                .map(|entry| entry.map(|(key, entry)| (key, entry.nibbles, entry.node)))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        let serial = populate();
+        let serial_root = rebuild_all_storage_tries(serial.db_ref(), 7).unwrap();
+        assert_eq!(serial_root, state_root(state.clone()));
+
+        // The parallel rebuild agrees with the serial one regardless of the number of threads.
+        for threads in [1, 4] {
+            let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            let parallel = populate();
+            let root = rebuild_storage_roots_parallel(parallel.db_ref(), &pool).unwrap();
+            assert_eq!(root, serial_root);
+            assert_eq!(quick_root_check(parallel.provider().unwrap().tx_ref(), root), Ok(true));
+            assert_eq!(storage_tries(&parallel), storage_tries(&serial));
+        }
+    }
+
+    #[test]
+    fn check_suspect_storage_tries() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+        let state = (1..=4u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage = (0..50u64)
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + i as u64)))
+                    .collect::<BTreeMap<_, _>>();
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (address, (account, storage)) in &state {
+            let hashed_address = keccak256(address);
+            tx.tx_ref().put::<tables::HashedAccounts>(hashed_address, *account).unwrap();
+            for (slot, value) in storage {
+                tx.tx_ref()
+                    .put::<tables::HashedStorages>(
+                        hashed_address,
+                        StorageEntry { key: keccak256(slot), value: *value },
+                    )
+                    .unwrap();
+            }
+        }
+        let (root, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        assert_eq!(root, state_root(state.clone()));
+        updates.flush(tx.tx_ref()).unwrap();
+
+        // Corrupt a stored node of the second account and add a node to the trie of an absent one.
+        let [good, corrupted] = [1, 2].map(|i| keccak256(Address::with_last_byte(i)));
+        let absent = keccak256(Address::with_last_byte(100));
+        let bogus = StorageTrieEntry {
+            nibbles: vec![0x1].into(),
+            node: BranchNodeCompact::new(0b11, 0, 0b11, vec![B256::random(), B256::random()], None),
+        };
+        let mut cursor = tx.tx_ref().cursor_dup_write::<tables::StoragesTrie>().unwrap();
+        let mut entry = cursor
+            .seek_by_key_subkey(corrupted, StoredNibblesSubKey(Nibbles::default()))
+            .unwrap()
+            .unwrap();
+        cursor.delete_current().unwrap();
+        entry.node.hashes[0] = B256::random();
+        cursor.upsert(corrupted, entry).unwrap();
+        cursor.upsert(absent, bogus).unwrap();
+
+        let suspects = [good, corrupted, absent];
+        let statuses = |rebuild| {
+            check_storage_tries(tx.tx_ref(), &suspects, rebuild)
+                .unwrap()
+                .into_iter()
+                .map(|(_, status)| status)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            statuses(false),
+            vec![
+                StorageTrieStatus::Valid,
+                StorageTrieStatus::Corrupted,
+                StorageTrieStatus::Corrupted
+            ]
+        );
+        assert_eq!(
+            statuses(true),
+            vec![StorageTrieStatus::Valid, StorageTrieStatus::Rebuilt, StorageTrieStatus::Rebuilt]
+        );
+        assert_eq!(statuses(false), vec![StorageTrieStatus::Valid; 3]);
+
+        // The state root with the suspects in the prefix set matches the expected one.
+        let mut prefix_set = PrefixSetMut::default();
+        for hashed_address in suspects {
+            prefix_set.insert(Nibbles::unpack(hashed_address));
+        }
+        let root = StateRoot::from_tx(tx.tx_ref())
+            .with_prefix_sets(TriePrefixSets {
+                account_prefix_set: prefix_set.freeze(),
+                ..Default::default()
+            })
+            .root()
+            .unwrap();
+        assert_eq!(root, state_root(state));
+    }
+
+    #[test]
+    fn backfill_root_nodes_of_older_tries() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+        for i in 1..=50u8 {
+            let hashed_address = keccak256(Address::with_last_byte(i));
+            let account = Account { nonce: i as u64, ..Default::default() };
+            tx.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            for slot in 0..(i as u64 * 3) {
+                let entry =
+                    StorageEntry { key: keccak256(B256::from(U256::from(slot))), value: U256::MAX };
+                tx.tx_ref().put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+        let trie_tables = || {
+            let accounts = tx
+                .tx_ref()
+                .cursor_read::<tables::AccountsTrie>()
+                .unwrap()
+                .walk(None)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let storages = tx
+                .tx_ref()
+                .cursor_dup_read::<tables::StoragesTrie>()
+                .unwrap()
+                .walk(None)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            (accounts, storages)
+        };
+        let (accounts, storages) = trie_tables();
+        assert_eq!(backfill_root_nodes(tx.tx_ref()), Ok(0));
+
+        // Remove the root nodes as if the tries were written by an older version.
+        tx.tx_ref().delete::<tables::AccountsTrie>(StoredNibbles::default(), None).unwrap();
+        let mut storage_trie_cursor =
+            tx.tx_ref().cursor_dup_write::<tables::StoragesTrie>().unwrap();
+        let mut stripped = 0;
+        for i in 1..=50u8 {
+            let hashed_address = keccak256(Address::with_last_byte(i));
+            let root_key = StoredNibblesSubKey(Nibbles::default());
+            if storage_trie_cursor
+                .seek_by_key_subkey(hashed_address, root_key.clone())
+                .unwrap()
+                .filter(|node| node.nibbles == root_key)
+                .is_some()
+            {
+                storage_trie_cursor.delete_current().unwrap();
+                stripped += 1;
+            }
+        }
+        drop(storage_trie_cursor);
+        assert!(stripped > 0);
+        assert_ne!(trie_tables(), (accounts.clone(), storages.clone()));
+
+        assert_eq!(backfill_root_nodes(tx.tx_ref()), Ok(stripped + 1));
+        assert_eq!(trie_tables(), (accounts, storages));
+    }
+    #[test]
+    fn quick_root_check_against_stored_root() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        // The empty trie has no root node.
+        assert!(quick_root_check(tx.tx_ref(), EMPTY_ROOT_HASH).unwrap());
+        assert!(!quick_root_check(tx.tx_ref(), B256::with_last_byte(1)).unwrap());
+
+        let state = (1..=50u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                (Address::with_last_byte(i), (account, BTreeMap::<B256, U256>::new()))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (address, (account, _)) in &state {
+            tx.tx_ref().put::<tables::HashedAccounts>(keccak256(address), *account).unwrap();
+        }
+        let expected = state_root(state);
+
+        // The root node is missing until the trie is stored, the root is computed instead.
+        assert!(tx
+            .tx_ref()
+            .get::<tables::AccountsTrie>(StoredNibbles::default())
+            .unwrap()
+            .is_none());
+        assert!(quick_root_check(tx.tx_ref(), expected).unwrap());
+
+        let (root, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        assert_eq!(root, expected);
+        updates.flush(tx.tx_ref()).unwrap();
+        assert!(quick_root_check(tx.tx_ref(), expected).unwrap());
+        assert!(!quick_root_check(tx.tx_ref(), B256::with_last_byte(1)).unwrap());
+
+        // The stored root is trusted without consulting the hashed state.
+        tx.tx_ref()
+            .delete::<tables::HashedAccounts>(keccak256(Address::with_last_byte(1)), None)
+            .unwrap();
+        assert!(quick_root_check(tx.tx_ref(), expected).unwrap());
+
+        // A root node with a stale root hash fails the check.
+        let mut root_node =
+            tx.tx_ref().get::<tables::AccountsTrie>(StoredNibbles::default()).unwrap().unwrap();
+        root_node.0.root_hash = Some(B256::with_last_byte(1));
+        tx.tx_ref().put::<tables::AccountsTrie>(StoredNibbles::default(), root_node).unwrap();
+        assert!(!quick_root_check(tx.tx_ref(), expected).unwrap());
+
+        // The trie written without its root node, e.g. by an older database, is reported.
+        tx.tx_ref().delete::<tables::AccountsTrie>(StoredNibbles::default(), None).unwrap();
+        assert_eq!(
+            quick_root_check(tx.tx_ref(), expected),
+            Err(QuickRootCheckError::RootNodeMissing)
+        );
+    }
+
+    #[test]
+    fn scan_dangling_references() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let contract = keccak256(B256::ZERO);
+        for i in 0..1_000u64 {
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(B256::from(U256::from(i))), account)
+                .unwrap();
+            let entry = StorageEntry {
+                key: keccak256(B256::from(U256::from(i))),
+                value: U256::from(i + 1),
+            };
+            tx.put::<tables::HashedStorages>(contract, entry).unwrap();
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+        assert_eq!(verify_trie_references(tx).unwrap(), vec![]);
+
+        // Remove the subtree of a child of the account trie root stored in the trie.
+        let root = tx.get::<tables::AccountsTrie>(StoredNibbles::default()).unwrap().unwrap().0;
+        let stored = (0..16).find(|nibble| root.tree_mask.is_bit_set(*nibble)).unwrap();
+        let mut cursor = tx.cursor_write::<tables::AccountsTrie>().unwrap();
+        while let Some((path, _)) = cursor.seek(StoredNibbles::from(vec![stored])).unwrap() {
+            if !path.0.has_prefix(&[stored]) {
+                break
+            }
+            cursor.delete_current().unwrap();
+        }
+
+        // Remove the hashed accounts below a child of a stored node that is not stored itself.
+        let (parent, node) = cursor
+            .walk(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|(path, node)| path.0.len() == 1 && node.0.tree_mask.is_empty())
+            .unwrap();
+        let hashed = (0..16).find(|nibble| node.0.state_mask.is_bit_set(*nibble)).unwrap();
+        let mut child = parent.0.clone();
+        child.push(hashed);
+        let mut cursor = tx.cursor_write::<tables::HashedAccounts>().unwrap();
+        while let Some((key, _)) = cursor.seek(pack_key(&child)).unwrap() {
+            if !Nibbles::unpack(key).has_prefix(&child) {
+                break
+            }
+            cursor.delete_current().unwrap();
+        }
+
+        // Remove a storage trie node below the root of the storage trie.
+        let storage_root = tx
+            .cursor_dup_read::<tables::StoragesTrie>()
+            .unwrap()
+            .seek_by_key_subkey(contract, StoredNibblesSubKey(Nibbles::default()))
+            .unwrap()
+            .unwrap()
+            .node;
+        let storage_stored =
+            (0..16).find(|nibble| storage_root.tree_mask.is_bit_set(*nibble)).unwrap();
+        let mut cursor = tx.cursor_dup_write::<tables::StoragesTrie>().unwrap();
+        while let Some(entry) = cursor
+            .seek_by_key_subkey(contract, StoredNibblesSubKey::from(vec![storage_stored]))
+            .unwrap()
+        {
+            if !entry.nibbles.0.has_prefix(&[storage_stored]) {
+                break
+            }
+            cursor.delete_current().unwrap();
+        }
+
+        assert_eq!(
+            verify_trie_references(tx).unwrap(),
+            vec![
+                DanglingRef {
+                    hashed_address: None,
+                    parent: Nibbles::default(),
+                    nibble: stored,
+                    missing: MissingChild::Node,
+                },
+                DanglingRef {
+                    hashed_address: None,
+                    parent: parent.0,
+                    nibble: hashed,
+                    missing: MissingChild::Leaves,
+                },
+                DanglingRef {
+                    hashed_address: Some(contract),
+                    parent: Nibbles::default(),
+                    nibble: storage_stored,
+                    missing: MissingChild::Node,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_non_canonical_keys() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let contract = keccak256(B256::ZERO);
+        for i in 0..100u64 {
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(B256::from(U256::from(i))), account)
+                .unwrap();
+            let entry = StorageEntry {
+                key: keccak256(B256::from(U256::from(i))),
+                value: U256::from(i + 1),
+            };
+            tx.put::<tables::HashedStorages>(contract, entry).unwrap();
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+        assert_eq!(verify_trie_key_encodings(tx).unwrap(), vec![]);
+
+        // An account trie path with a byte that is not a nibble.
+        let node = BranchNodeCompact::new(0b11, 0, 0, vec![], None);
+        tx.put::<RawTable<tables::AccountsTrie>>(
+            RawKey::from_vec(vec![0x1, 0x23]),
+            RawValue::new(StoredBranchNode(node.clone())),
+        )
+        .unwrap();
+
+        // A storage trie subkey with a length that would panic when decoded.
+        let mut value =
+            RawValue::new(StorageTrieEntry { nibbles: StoredNibblesSubKey::from(vec![0x1]), node })
+                .into_value();
+        value[64] = 0xff;
+        tx.put::<RawTable<tables::StoragesTrie>>(
+            RawKey::new(contract),
+            RawValue::from_vec(value.clone()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            verify_trie_key_encodings(tx).unwrap(),
+            vec![
+                NonCanonicalKey {
+                    table: Tables::AccountsTrie,
+                    hashed_address: None,
+                    key: Bytes::from_static(&[0x1, 0x23]),
+                },
+                NonCanonicalKey {
+                    table: Tables::StoragesTrie,
+                    hashed_address: Some(contract),
+                    key: Bytes::copy_from_slice(&value[..65]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_overlong_keys() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let contract = keccak256(B256::ZERO);
+        for i in 0..100u64 {
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(B256::from(U256::from(i))), account)
+                .unwrap();
+            let entry = StorageEntry {
+                key: keccak256(B256::from(U256::from(i))),
+                value: U256::from(i + 1),
+            };
+            tx.put::<tables::HashedStorages>(contract, entry).unwrap();
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+        assert_eq!(verify_trie_key_lengths(tx).unwrap(), vec![]);
+
+        // The account and storage trie paths of the full length of a hashed key.
+        let node = BranchNodeCompact::new(0b11, 0, 0, vec![], None);
+        let path = vec![0x1; 64];
+        tx.put::<tables::AccountsTrie>(
+            StoredNibbles::from(path.clone()),
+            StoredBranchNode(node.clone()),
+        )
+        .unwrap();
+        let entry = StorageTrieEntry {
+            nibbles: StoredNibblesSubKey::from(path.clone()),
+            node: node.clone(),
+        };
+        tx.put::<tables::StoragesTrie>(contract, entry).unwrap();
+
+        // The longest path of a branch node is not reported.
+        tx.put::<tables::AccountsTrie>(
+            StoredNibbles::from(path[..63].to_vec()),
+            StoredBranchNode(node),
+        )
+        .unwrap();
+
+        assert_eq!(
+            verify_trie_key_lengths(tx).unwrap(),
+            vec![
+                OverlongKey {
+                    table: Tables::AccountsTrie,
+                    hashed_address: None,
+                    len: 64,
+                    key: Bytes::from(path.clone()),
+                },
+                OverlongKey {
+                    table: Tables::StoragesTrie,
+                    hashed_address: Some(contract),
+                    len: 64,
+                    key: Bytes::from(path),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_leaf_by_hash() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        for i in 0..100u8 {
+            let hashed_address = keccak256(Address::with_last_byte(i));
+            let account = Account { nonce: i as u64, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            for slot in 0..i % 4 {
+                let entry = StorageEntry {
+                    key: keccak256(B256::with_last_byte(slot)),
+                    value: U256::from(i + 1),
+                };
+                tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // The leaf nodes of an account and of its slot are the last nodes of their proofs.
+        let address = Address::with_last_byte(42);
+        let hashed_address = keccak256(address);
+        let slot = B256::with_last_byte(1);
+        let proof = Proof::new(tx).account_proof(address, &[slot]).unwrap();
+        let account_leaf = keccak256(proof.proof.last().unwrap());
+        let storage_leaf = keccak256(proof.storage_proofs[0].proof.last().unwrap());
+
+        let found = find_leaf(tx, account_leaf, None, &Nibbles::default()).unwrap().unwrap();
+        assert_eq!(found.key, hashed_address);
+        assert!(!found.path.is_empty());
+        assert!(Nibbles::unpack(hashed_address).has_prefix(&found.path));
+
+        // The walk bounded by a prefix of the key finds the same leaf, including the prefix with
+        // no other key under it.
+        let key = Nibbles::unpack(hashed_address);
+        for len in [1, 3, 64] {
+            let prefix = Nibbles::from_nibbles_unchecked(&key[..len]);
+            assert_eq!(find_leaf(tx, account_leaf, None, &prefix).unwrap(), Some(found.clone()));
+        }
+        let other = Nibbles::from_nibbles_unchecked([key[0], key[1], (key[2] + 1) % 16]);
+        assert_eq!(find_leaf(tx, account_leaf, None, &other).unwrap(), None);
+
+        // The storage leaf is found in the storage trie of the account only.
+        let found = find_leaf(tx, storage_leaf, Some(hashed_address), &Nibbles::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.key, keccak256(slot));
+        assert_eq!(find_leaf(tx, storage_leaf, None, &Nibbles::default()).unwrap(), None);
+        assert_eq!(
+            find_leaf(tx, B256::ZERO, Some(hashed_address), &Nibbles::default()).unwrap(),
+            None
+        );
+    }
+}
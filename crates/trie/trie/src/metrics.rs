@@ -1,9 +1,17 @@
 use crate::stats::TrieStats;
-use metrics::Histogram;
+use metrics::{Gauge, Histogram, NoopRecorder};
 use reth_metrics::Metrics;
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tracing::warn;
 
 /// Wrapper for state root metrics.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct StateRootMetrics {
     /// State trie metrics.
     pub state_trie: TrieRootMetrics,
@@ -30,19 +38,115 @@ pub struct TrieRootMetrics {
     branches_added: Histogram,
     /// The number of leaves added during trie root calculation.
     leaves_added: Histogram,
+    /// The number of stored subtrees descended into during trie root calculation.
+    subtrees_descended: Histogram,
+    /// The share of the visited stored subtrees that were reused by their hash rather than
+    /// descended into, over all calculations recorded by this instance and its clones.
+    ///
+    /// For the storage tries, this is the efficiency of the incremental storage roots: the closer
+    /// to one, the fewer storage trie nodes had to be walked.
+    skip_ratio: Gauge,
+    /// The totals recorded by this instance and its clones, read by [`TrieRootMetrics::snapshot`].
+    #[metric(skip)]
+    totals: Arc<TrieRootTotals>,
 }
 
 impl TrieRootMetrics {
     /// Create new metrics for the given trie type.
+    ///
+    /// The metrics are best-effort: if the installed recorder fails to register them, they are
+    /// registered with a no-op recorder instead and only the local totals are kept.
     pub fn new(ty: TrieType) -> Self {
-        Self::new_with_labels(&[("type", ty.as_str())])
+        let labels = [("type", ty.as_str())];
+        catch_unwind(|| Self::new_with_labels(&labels)).unwrap_or_else(|_| {
+            warn_metrics_failure();
+            metrics::with_local_recorder(&NoopRecorder, || Self::new_with_labels(&labels))
+        })
     }
 
     /// Record trie stats as metrics.
+    ///
+    /// A failure of the installed recorder is logged once and otherwise ignored, it affects
+    /// neither the root calculation nor the totals read by [`TrieRootMetrics::snapshot`].
     pub fn record(&self, stats: TrieStats) {
-        self.duration_seconds.record(stats.duration().as_secs_f64());
-        self.branches_added.record(stats.branches_added() as f64);
-        self.leaves_added.record(stats.leaves_added() as f64);
+        let recorded = catch_unwind(AssertUnwindSafe(|| {
+            self.duration_seconds.record(stats.duration().as_secs_f64());
+            self.branches_added.record(stats.branches_added() as f64);
+            self.leaves_added.record(stats.leaves_added() as f64);
+            self.subtrees_descended.record(stats.subtrees_descended() as f64);
+        }));
+        if recorded.is_err() {
+            warn_metrics_failure();
+        }
+
+        self.totals.roots_computed.fetch_add(1, Ordering::Relaxed);
+        let reused =
+            self.totals.branches_added.fetch_add(stats.branches_added(), Ordering::Relaxed) +
+                stats.branches_added();
+        self.totals.leaves_added.fetch_add(stats.leaves_added(), Ordering::Relaxed);
+        let descended = self
+            .totals
+            .subtrees_descended
+            .fetch_add(stats.subtrees_descended(), Ordering::Relaxed) +
+            stats.subtrees_descended();
+        if reused + descended > 0 {
+            let ratio = reused as f64 / (reused + descended) as f64;
+            if catch_unwind(AssertUnwindSafe(|| self.skip_ratio.set(ratio))).is_err() {
+                warn_metrics_failure();
+            }
+        }
+    }
+
+    /// Returns the totals recorded by this instance and its clones so far.
+    ///
+    /// Unlike the histograms, which are only observable through the installed recorder, the
+    /// snapshot is kept locally, so it can be read without scraping the metrics endpoint.
+    pub fn snapshot(&self) -> TrieRootMetricsSnapshot {
+        TrieRootMetricsSnapshot {
+            roots_computed: self.totals.roots_computed.load(Ordering::Relaxed),
+            branches_added: self.totals.branches_added.load(Ordering::Relaxed),
+            leaves_added: self.totals.leaves_added.load(Ordering::Relaxed),
+            subtrees_descended: self.totals.subtrees_descended.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Logs the first failure of the metrics recorder, the later ones are silently ignored.
+fn warn_metrics_failure() {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        warn!(target: "trie::metrics", "Failed to record trie metrics, ignoring metric failures");
+    }
+}
+
+/// Running totals of the recorded trie root calculations.
+#[derive(Default, Debug)]
+struct TrieRootTotals {
+    roots_computed: AtomicU64,
+    branches_added: AtomicU64,
+    leaves_added: AtomicU64,
+    subtrees_descended: AtomicU64,
+}
+
+/// The point-in-time totals of the trie root calculations recorded by [`TrieRootMetrics`].
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct TrieRootMetricsSnapshot {
+    /// The number of calculated roots.
+    pub roots_computed: u64,
+    /// The number of branch nodes added to the hash builder.
+    pub branches_added: u64,
+    /// The number of leaves added to the hash builder.
+    pub leaves_added: u64,
+    /// The number of stored subtrees descended into.
+    pub subtrees_descended: u64,
+}
+
+impl TrieRootMetricsSnapshot {
+    /// Returns the share of the visited stored subtrees that were reused by their hash, i.e. the
+    /// added branches, rather than descended into, or `None` if no stored subtree was visited.
+    pub fn skip_ratio(&self) -> Option<f64> {
+        let visited = self.branches_added + self.subtrees_descended;
+        (visited > 0).then(|| self.branches_added as f64 / visited as f64)
     }
 }
 
@@ -63,3 +167,219 @@ impl TrieType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix_set::PrefixSetMut, StateRoot, StorageRoot};
+    use metrics::{
+        Counter, GaugeFn, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{keccak256, trie::Nibbles, Account, StorageEntry, B256, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    /// A recorder whose registration or recording panics.
+    struct FailingRecorder {
+        fail_registration: bool,
+    }
+
+    struct FailingHandle;
+
+    impl HistogramFn for FailingHandle {
+        fn record(&self, _value: f64) {
+            panic!("failed to record histogram")
+        }
+    }
+
+    impl GaugeFn for FailingHandle {
+        fn increment(&self, _value: f64) {
+            panic!("failed to record gauge")
+        }
+
+        fn decrement(&self, _value: f64) {
+            panic!("failed to record gauge")
+        }
+
+        fn set(&self, _value: f64) {
+            panic!("failed to record gauge")
+        }
+    }
+
+    impl Recorder for FailingRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, _: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            assert!(!self.fail_registration, "failed to register gauge");
+            Gauge::from_arc(Arc::new(FailingHandle))
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            assert!(!self.fail_registration, "failed to register histogram");
+            Histogram::from_arc(Arc::new(FailingHandle))
+        }
+    }
+
+    #[test]
+    fn failing_recorder_does_not_affect_root() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed_address = B256::with_last_byte(1);
+        tx.put::<tables::HashedAccounts>(hashed_address, Account::default()).unwrap();
+        for slot in 1..=3 {
+            tx.put::<tables::HashedStorages>(
+                hashed_address,
+                StorageEntry { key: B256::with_last_byte(slot), value: U256::from(slot) },
+            )
+            .unwrap();
+        }
+        let expected = StateRoot::from_tx(tx).root().unwrap();
+        let expected_storage_root = StorageRoot::from_tx_hashed(tx, hashed_address).root().unwrap();
+
+        for fail_registration in [false, true] {
+            let recorder = FailingRecorder { fail_registration };
+            let (state_metrics, storage_metrics) = metrics::with_local_recorder(&recorder, || {
+                (StateRootMetrics::default(), TrieRootMetrics::new(TrieType::Storage))
+            });
+
+            let root = StateRoot::from_tx(tx).with_metrics(state_metrics.clone()).root().unwrap();
+            assert_eq!(root, expected);
+            let storage_root =
+                StorageRoot::new_hashed(tx, tx, hashed_address, storage_metrics.clone())
+                    .root()
+                    .unwrap();
+            assert_eq!(storage_root, expected_storage_root);
+
+            // The local totals are kept regardless of the failing recorder.
+            assert_eq!(state_metrics.state_trie.snapshot().roots_computed, 1);
+            assert_eq!(state_metrics.storage_trie.snapshot().roots_computed, 1);
+            assert_eq!(storage_metrics.snapshot().leaves_added, 3);
+        }
+    }
+
+    #[test]
+    fn absent_recorder_does_not_affect_root() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed_address = B256::with_last_byte(1);
+        tx.put::<tables::HashedAccounts>(hashed_address, Account::default()).unwrap();
+        for slot in 1..=3 {
+            tx.put::<tables::HashedStorages>(
+                hashed_address,
+                StorageEntry { key: B256::with_last_byte(slot), value: U256::from(slot) },
+            )
+            .unwrap();
+        }
+        let expected = StateRoot::from_tx(tx).root().unwrap();
+        let expected_storage_root = StorageRoot::from_tx_hashed(tx, hashed_address).root().unwrap();
+
+        let (state_metrics, storage_metrics) = metrics::with_local_recorder(&NoopRecorder, || {
+            (StateRootMetrics::default(), TrieRootMetrics::new(TrieType::Storage))
+        });
+
+        let root = StateRoot::from_tx(tx).with_metrics(state_metrics.clone()).root().unwrap();
+        assert_eq!(root, expected);
+        let storage_root = StorageRoot::new_hashed(tx, tx, hashed_address, storage_metrics.clone())
+            .root()
+            .unwrap();
+        assert_eq!(storage_root, expected_storage_root);
+
+        // The local totals are kept regardless of the recorder.
+        assert_eq!(state_metrics.state_trie.snapshot().roots_computed, 1);
+        assert_eq!(state_metrics.storage_trie.snapshot().roots_computed, 1);
+        assert_eq!(storage_metrics.snapshot().leaves_added, 3);
+    }
+
+    #[test]
+    fn snapshot_advances_on_root_calculation() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed_address = B256::with_last_byte(1);
+        for slot in 1..=3 {
+            tx.put::<tables::HashedStorages>(
+                hashed_address,
+                StorageEntry { key: B256::with_last_byte(slot), value: U256::from(slot) },
+            )
+            .unwrap();
+        }
+
+        let metrics = TrieRootMetrics::new(TrieType::Storage);
+        assert_eq!(metrics.snapshot(), TrieRootMetricsSnapshot::default());
+
+        StorageRoot::new_hashed(tx, tx, hashed_address, metrics.clone()).root().unwrap();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.roots_computed, 1);
+        assert_eq!(snapshot.leaves_added, 3);
+
+        StorageRoot::new_hashed(tx, tx, hashed_address, metrics.clone()).root().unwrap();
+        assert_eq!(
+            metrics.snapshot(),
+            TrieRootMetricsSnapshot {
+                roots_computed: 2,
+                branches_added: 2 * snapshot.branches_added,
+                leaves_added: 6,
+                subtrees_descended: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn snapshot_records_reused_and_descended_subtrees() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed_address = B256::with_last_byte(1);
+        for slot in 0..1_000u64 {
+            let key = keccak256(B256::from(U256::from(slot)));
+            tx.put::<tables::HashedStorages>(
+                hashed_address,
+                StorageEntry { key, value: U256::from(slot + 1) },
+            )
+            .unwrap();
+        }
+        let (_, _, updates) =
+            StorageRoot::from_tx_hashed(tx, hashed_address).calculate(true).unwrap();
+        updates.flush(tx).unwrap();
+
+        // Change a single slot, only the subtrees on its path are descended into.
+        let changed = keccak256(B256::from(U256::from(7)));
+        tx.put::<tables::HashedStorages>(
+            hashed_address,
+            StorageEntry { key: changed, value: U256::from(1_000) },
+        )
+        .unwrap();
+        let mut prefix_set = PrefixSetMut::default();
+        prefix_set.insert(Nibbles::unpack(changed));
+
+        let metrics = TrieRootMetrics::new(TrieType::Storage);
+        let root = StorageRoot::new_hashed(tx, tx, hashed_address, metrics.clone())
+            .with_prefix_set(prefix_set.freeze())
+            .root()
+            .unwrap();
+        let expected = StorageRoot::from_tx_hashed(tx, hashed_address)
+            .with_prefix_set(PrefixSetMut::all().freeze())
+            .root()
+            .unwrap();
+        assert_eq!(root, expected);
+
+        let snapshot = metrics.snapshot();
+        assert!((1..=3).contains(&snapshot.subtrees_descended), "{snapshot:?}");
+        assert!(snapshot.branches_added > 10, "{snapshot:?}");
+        assert!(snapshot.skip_ratio().unwrap() > 0.8, "{snapshot:?}");
+    }
+}
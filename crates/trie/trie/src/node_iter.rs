@@ -106,7 +106,7 @@ where
             if let Some((hashed_key, value)) = self.current_hashed_entry.take() {
                 // If the walker's key is less than the unpacked hashed key,
                 // reset the checked status and continue
-                if self.walker.key().map_or(false, |key| key < &Nibbles::unpack(hashed_key)) {
+                if self.walker.key().map_or(false, |key| nibbles_lt_key(key, &hashed_key)) {
                     self.current_walker_key_checked = false;
                     continue
                 }
@@ -139,3 +139,11 @@ where
         Ok(None)
     }
 }
+
+/// Returns `true` if the nibbles are ordered before the unpacked hashed key.
+///
+/// Equivalent to `nibbles < &Nibbles::unpack(hashed_key)`, but compares the nibbles of the key
+/// lazily instead of unpacking it for every hashed entry of the walk.
+fn nibbles_lt_key(nibbles: &[u8], hashed_key: &B256) -> bool {
+    nibbles.iter().copied().lt(hashed_key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]))
+}
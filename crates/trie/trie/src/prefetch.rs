@@ -0,0 +1,166 @@
+use crate::{
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    trie_cursor::TrieCursorFactory,
+};
+use reth_db::{database::Database, DatabaseError};
+use reth_primitives::{trie::Nibbles, B256};
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+/// The database read by the storage trie prefetcher, see
+/// [`StateRoot::with_prefetch`](crate::StateRoot::with_prefetch).
+///
+/// The prefetcher runs on its own thread, so it reads through its own read-only transaction
+/// instead of the cursor factories of the walk. The MDBX transactions are bound to the thread that
+/// opened them, the reads of a read-write transaction from another thread are rejected.
+pub(crate) trait PrefetchSource: Send + Sync {
+    /// Runs the prefetcher on a new read-only transaction until the sending half of the channel
+    /// is dropped, returning the number of prefetched storage tries.
+    fn prefetch(&self, depth: usize, positions: Receiver<B256>) -> Result<usize, DatabaseError>;
+}
+
+impl<DB: Database> PrefetchSource for DB {
+    fn prefetch(&self, depth: usize, positions: Receiver<B256>) -> Result<usize, DatabaseError> {
+        let tx = self.tx()?;
+        StorageTriePrefetcher::new(&tx, &tx, depth).run(positions)
+    }
+}
+
+impl std::fmt::Debug for dyn PrefetchSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrefetchSource").finish_non_exhaustive()
+    }
+}
+
+/// Issues read-ahead seeks for the storage tries of the accounts following the current position
+/// of the account walk.
+///
+/// The prefetcher receives the hashed addresses of the accounts as the walk reaches them and keeps
+/// at most `depth` accounts ahead of the walk warm. Only reads are performed, so the results of
+/// the walk are not affected. If the prefetcher falls behind, the positions it has missed are
+/// skipped in favor of the latest one.
+#[derive(Debug)]
+pub(crate) struct StorageTriePrefetcher<T, H> {
+    trie_cursor_factory: T,
+    hashed_cursor_factory: H,
+    depth: usize,
+}
+
+impl<T, H> StorageTriePrefetcher<T, H>
+where
+    T: TrieCursorFactory,
+    H: HashedCursorFactory,
+{
+    /// Create new prefetcher.
+    pub(crate) const fn new(
+        trie_cursor_factory: T,
+        hashed_cursor_factory: H,
+        depth: usize,
+    ) -> Self {
+        Self { trie_cursor_factory, hashed_cursor_factory, depth }
+    }
+
+    /// Runs the prefetcher until the sending half of the channel is dropped, returning the number
+    /// of prefetched storage tries.
+    pub(crate) fn run(&self, positions: Receiver<B256>) -> Result<usize, DatabaseError> {
+        let mut account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let mut prefetched = VecDeque::with_capacity(self.depth);
+        let mut prefetched_total = 0;
+        let mut exhausted = false;
+
+        while let Ok(mut position) = positions.recv() {
+            // Skip to the latest position of the walk if we fell behind.
+            loop {
+                match positions.try_recv() {
+                    Ok(next) => position = next,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(prefetched_total),
+                }
+            }
+
+            while prefetched.front().map_or(false, |key| *key <= position) {
+                prefetched.pop_front();
+            }
+
+            // Reposition the cursor if the walk has caught up with the prefetched accounts.
+            if prefetched.is_empty() && !exhausted {
+                let mut entry = account_cursor.seek(position)?;
+                if entry.as_ref().map_or(false, |(key, _)| *key == position) {
+                    entry = account_cursor.next()?;
+                }
+                match entry {
+                    Some((hashed_address, _)) => {
+                        self.prefetch_storage(hashed_address)?;
+                        prefetched.push_back(hashed_address);
+                        prefetched_total += 1;
+                    }
+                    None => exhausted = true,
+                }
+            }
+
+            while !exhausted && prefetched.len() < self.depth {
+                match account_cursor.next()? {
+                    Some((hashed_address, _)) => {
+                        self.prefetch_storage(hashed_address)?;
+                        prefetched.push_back(hashed_address);
+                        prefetched_total += 1;
+                    }
+                    None => exhausted = true,
+                }
+            }
+        }
+
+        Ok(prefetched_total)
+    }
+
+    /// Seeks the root of the storage trie and the first storage entry of the account.
+    fn prefetch_storage(&self, hashed_address: B256) -> Result<(), DatabaseError> {
+        self.trie_cursor_factory.storage_tries_cursor(hashed_address)?.seek(Nibbles::default())?;
+        self.hashed_cursor_factory.hashed_storage_cursor(hashed_address)?.seek(B256::ZERO)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{Account, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+    use std::sync::mpsc;
+
+    #[test]
+    fn prefetch_alongside_read_write_transaction() {
+        let factory = create_test_provider_factory();
+        let provider_rw = factory.provider_rw().unwrap();
+        for i in 0..10u8 {
+            let tx = provider_rw.tx_ref();
+            tx.put::<tables::HashedAccounts>(B256::with_last_byte(i), Account::default()).unwrap();
+            let entry = StorageEntry { key: B256::with_last_byte(1), value: U256::from(1) };
+            tx.put::<tables::HashedStorages>(B256::with_last_byte(i), entry).unwrap();
+        }
+        provider_rw.commit().unwrap();
+
+        // The walk holds a read-write transaction, the prefetcher reads on its own thread.
+        let _provider_rw = factory.provider_rw().unwrap();
+        let db = factory.db_ref().clone();
+        let (sender, receiver) = mpsc::sync_channel(0);
+        let prefetcher = std::thread::spawn(move || db.prefetch(4, receiver));
+        // The second send returns once the prefetcher has taken the first position.
+        sender.send(B256::with_last_byte(2)).unwrap();
+        sender.send(B256::with_last_byte(2)).unwrap();
+        drop(sender);
+        assert_eq!(prefetcher.join().unwrap(), Ok(4));
+
+        // The prefetching stops at the last account.
+        let db = factory.db_ref().clone();
+        let (sender, receiver) = mpsc::sync_channel(0);
+        let prefetcher = std::thread::spawn(move || db.prefetch(4, receiver));
+        sender.send(B256::with_last_byte(7)).unwrap();
+        sender.send(B256::with_last_byte(7)).unwrap();
+        drop(sender);
+        assert_eq!(prefetcher.join().unwrap(), Ok(2));
+    }
+}
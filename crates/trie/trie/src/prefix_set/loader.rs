@@ -7,7 +7,7 @@ use reth_db::{
     transaction::DbTx,
     DatabaseError,
 };
-use reth_primitives::{keccak256, trie::Nibbles, BlockNumber, StorageEntry, B256};
+use reth_primitives::{keccak256, trie::Nibbles, Address, BlockNumber, StorageEntry, B256};
 use std::{
     collections::{HashMap, HashSet},
     ops::RangeInclusive,
@@ -27,45 +27,140 @@ impl<'a, TX> PrefixSetLoader<'a, TX> {
 impl<'a, TX: DbTx> PrefixSetLoader<'a, TX> {
     /// Load all account and storage changes for the given block range.
     pub fn load(self, range: RangeInclusive<BlockNumber>) -> Result<TriePrefixSets, DatabaseError> {
-        // Initialize prefix sets.
-        let mut account_prefix_set = PrefixSetMut::default();
-        let mut storage_prefix_sets = HashMap::<B256, PrefixSetMut>::default();
-        let mut destroyed_accounts = HashSet::default();
-
-        // Walk account changeset and insert account prefixes.
-        let mut account_changeset_cursor = self.cursor_read::<tables::AccountChangeSets>()?;
-        let mut account_plain_state_cursor = self.cursor_read::<tables::PlainAccountState>()?;
-        for account_entry in account_changeset_cursor.walk_range(range.clone())? {
-            let (_, AccountBeforeTx { address, .. }) = account_entry?;
-            let hashed_address = keccak256(address);
-            account_prefix_set.insert(Nibbles::unpack(hashed_address));
-
-            if account_plain_state_cursor.seek_exact(address)?.is_none() {
-                destroyed_accounts.insert(hashed_address);
-            }
+        load_prefix_sets(
+            &mut self.cursor_read::<tables::AccountChangeSets>()?,
+            &mut self.cursor_read::<tables::PlainAccountState>()?,
+            &mut self.cursor_dup_read::<tables::StorageChangeSets>()?,
+            range,
+            keccak256,
+            keccak256,
+        )
+    }
+}
+
+/// Load all account and storage changes for the given block range with the given cursors, hashing
+/// the changed addresses and storage keys with the given functions.
+pub(crate) fn load_prefix_sets(
+    account_changeset_cursor: &mut impl DbCursorRO<tables::AccountChangeSets>,
+    account_plain_state_cursor: &mut impl DbCursorRO<tables::PlainAccountState>,
+    storage_changeset_cursor: &mut impl DbCursorRO<tables::StorageChangeSets>,
+    range: RangeInclusive<BlockNumber>,
+    mut hash_address: impl FnMut(Address) -> B256,
+    mut hash_storage_key: impl FnMut(B256) -> B256,
+) -> Result<TriePrefixSets, DatabaseError> {
+    // Initialize prefix sets.
+    let mut account_prefix_set = PrefixSetMut::default();
+    let mut storage_prefix_sets = HashMap::<B256, PrefixSetMut>::default();
+    let mut destroyed_accounts = HashSet::default();
+
+    // Walk account changeset and insert account prefixes.
+    for account_entry in account_changeset_cursor.walk_range(range.clone())? {
+        let (_, AccountBeforeTx { address, .. }) = account_entry?;
+        let hashed_address = hash_address(address);
+        account_prefix_set.insert(Nibbles::unpack(hashed_address));
+
+        if account_plain_state_cursor.seek_exact(address)?.is_none() {
+            destroyed_accounts.insert(hashed_address);
         }
+    }
+
+    // Walk storage changeset and insert storage prefixes as well as account prefixes if missing
+    // from the account prefix set.
+    let storage_range = BlockNumberAddress::range(range);
+    for storage_entry in storage_changeset_cursor.walk_range(storage_range)? {
+        let (BlockNumberAddress((_, address)), StorageEntry { key, .. }) = storage_entry?;
+        let hashed_address = hash_address(address);
+        account_prefix_set.insert(Nibbles::unpack(hashed_address));
+        storage_prefix_sets
+            .entry(hashed_address)
+            .or_default()
+            .insert(Nibbles::unpack(hash_storage_key(key)));
+    }
 
-        // Walk storage changeset and insert storage prefixes as well as account prefixes if missing
-        // from the account prefix set.
-        let mut storage_cursor = self.cursor_dup_read::<tables::StorageChangeSets>()?;
-        let storage_range = BlockNumberAddress::range(range);
-        for storage_entry in storage_cursor.walk_range(storage_range)? {
-            let (BlockNumberAddress((_, address)), StorageEntry { key, .. }) = storage_entry?;
-            let hashed_address = keccak256(address);
-            account_prefix_set.insert(Nibbles::unpack(hashed_address));
-            storage_prefix_sets
-                .entry(hashed_address)
-                .or_default()
-                .insert(Nibbles::unpack(keccak256(key)));
+    Ok(TriePrefixSets {
+        account_prefix_set: account_prefix_set.freeze(),
+        storage_prefix_sets: storage_prefix_sets
+            .into_iter()
+            .map(|(k, v)| (k, v.freeze()))
+            .collect(),
+        destroyed_accounts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::transaction::DbTxMut;
+    use reth_primitives::{Account, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn load_prefix_sets_accessors() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let existing = Address::with_last_byte(1);
+        let destroyed = Address::with_last_byte(2);
+        let storage_only = Address::with_last_byte(3);
+        let outside_range = Address::with_last_byte(4);
+        tx.put::<tables::PlainAccountState>(existing, Account::default()).unwrap();
+        tx.put::<tables::PlainAccountState>(storage_only, Account::default()).unwrap();
+
+        for (block, address) in [(1, existing), (2, destroyed), (2, existing), (4, outside_range)] {
+            tx.put::<tables::AccountChangeSets>(block, AccountBeforeTx { address, info: None })
+                .unwrap();
         }
+        let slot = |i: u64| B256::from(U256::from(i));
+        for (block, address, key) in [
+            (1, existing, slot(1)),
+            (2, existing, slot(2)),
+            (3, storage_only, slot(1)),
+            (4, outside_range, slot(1)),
+        ] {
+            tx.put::<tables::StorageChangeSets>(
+                BlockNumberAddress((block, address)),
+                StorageEntry { key, value: U256::from(1) },
+            )
+            .unwrap();
+        }
+
+        let prefix_sets = PrefixSetLoader::new(tx).load(1..=3).unwrap();
+
+        let mut accounts = [existing, destroyed, storage_only]
+            .map(|address| Nibbles::unpack(keccak256(address)))
+            .to_vec();
+        accounts.sort_unstable();
+        assert!(!prefix_sets.account_prefix_set().all());
+        assert_eq!(prefix_sets.account_prefix_set().iter().cloned().collect::<Vec<_>>(), accounts);
+
+        let mut existing_slots =
+            [slot(1), slot(2)].map(|key| Nibbles::unpack(keccak256(key))).to_vec();
+        existing_slots.sort_unstable();
+        let storages = prefix_sets
+            .storage_prefix_sets()
+            .map(|(hashed_address, prefix_set)| {
+                (*hashed_address, prefix_set.iter().cloned().collect::<Vec<_>>())
+            })
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            storages,
+            HashMap::from([
+                (keccak256(existing), existing_slots.clone()),
+                (keccak256(storage_only), vec![Nibbles::unpack(keccak256(slot(1)))]),
+            ])
+        );
+        assert_eq!(
+            prefix_sets
+                .storage_prefix_set(&keccak256(existing))
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>(),
+            existing_slots
+        );
+        assert!(prefix_sets.storage_prefix_set(&keccak256(destroyed)).is_empty());
+        assert!(prefix_sets.storage_prefix_set(&keccak256(outside_range)).is_empty());
 
-        Ok(TriePrefixSets {
-            account_prefix_set: account_prefix_set.freeze(),
-            storage_prefix_sets: storage_prefix_sets
-                .into_iter()
-                .map(|(k, v)| (k, v.freeze()))
-                .collect(),
-            destroyed_accounts,
-        })
+        assert_eq!(prefix_sets.destroyed_accounts, HashSet::from([keccak256(destroyed)]));
     }
 }
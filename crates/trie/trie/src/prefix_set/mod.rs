@@ -1,14 +1,15 @@
-use reth_primitives::{trie::Nibbles, B256};
+use reth_primitives::{keccak256, trie::Nibbles, AccessList, B256};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::Arc,
 };
 
 mod loader;
+pub(crate) use loader::load_prefix_sets;
 pub use loader::PrefixSetLoader;
 
 /// Collection of trie prefix sets.
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
 pub struct TriePrefixSets {
     /// A set of account prefixes that have changed.
     pub account_prefix_set: PrefixSet,
@@ -19,6 +20,183 @@ pub struct TriePrefixSets {
     pub destroyed_accounts: HashSet<B256>,
 }
 
+impl TriePrefixSets {
+    /// Returns prefix sets that mark the entire keyspace as changed.
+    ///
+    /// The state root calculation with these prefix sets descends into every subtree of the
+    /// account and storage tries instead of reusing the stored nodes, which is equivalent to
+    /// computing the root from scratch. An account prefix set that marks all keys as changed
+    /// applies to the storage tries of the accounts without an explicit storage prefix set.
+    pub fn all_changed() -> Self {
+        Self { account_prefix_set: PrefixSetMut::all().freeze(), ..Default::default() }
+    }
+
+    /// Returns the prefix sets marking the accounts and storage slots of the EIP-2930 access list
+    /// as changed, e.g. to compute the root over exactly the declared state of a block.
+    ///
+    /// The addresses and storage keys are hashed into the paths of the account and storage tries.
+    /// An account is marked as changed whether the entry lists any storage keys or not, as its
+    /// storage root depends on the slots, while the storage prefix sets are created only for the
+    /// entries with storage keys. The repeated entries and keys are merged. The access list does
+    /// not tell which accounts were destroyed, so none are.
+    pub fn from_access_list(access_list: &AccessList) -> Self {
+        let mut account_keys = Vec::new();
+        let mut storage_keys = HashMap::<B256, Vec<Nibbles>>::default();
+        for item in access_list.iter() {
+            let hashed_address = keccak256(item.address);
+            account_keys.push(Nibbles::unpack(hashed_address));
+            if !item.storage_keys.is_empty() {
+                storage_keys
+                    .entry(hashed_address)
+                    .or_default()
+                    .extend(item.storage_keys.iter().map(|key| Nibbles::unpack(keccak256(key))));
+            }
+        }
+
+        Self {
+            account_prefix_set: PrefixSet::from_keys(account_keys),
+            storage_prefix_sets: storage_keys
+                .into_iter()
+                .map(|(hashed_address, keys)| (hashed_address, PrefixSet::from_keys(keys)))
+                .collect(),
+            destroyed_accounts: HashSet::default(),
+        }
+    }
+
+    /// Returns `true` if no account or storage paths are marked as changed and no accounts are
+    /// destroyed.
+    pub fn is_empty(&self) -> bool {
+        let unchanged = |prefix_set: &PrefixSet| !prefix_set.all() && prefix_set.is_empty();
+        unchanged(&self.account_prefix_set) &&
+            self.storage_prefix_sets.values().all(unchanged) &&
+            self.destroyed_accounts.is_empty()
+    }
+
+    /// Returns `true` if both prefix sets mark exactly the same account and storage paths and the
+    /// same destroyed accounts as changed.
+    ///
+    /// The storage prefix sets are compared as returned by [`Self::storage_prefix_set`], so a
+    /// missing storage prefix set is equivalent to an empty one unless all keys are marked as
+    /// changed. See [`PrefixSet::is_equivalent`].
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        self.account_prefix_set.is_equivalent(&other.account_prefix_set) &&
+            self.destroyed_accounts == other.destroyed_accounts &&
+            self.storage_prefix_sets.keys().chain(other.storage_prefix_sets.keys()).all(
+                |hashed_address| {
+                    self.storage_prefix_set(hashed_address)
+                        .is_equivalent(&other.storage_prefix_set(hashed_address))
+                },
+            )
+    }
+
+    /// Returns the keys marked as changed by the `full` prefix sets that these prefix sets do not
+    /// cover, e.g. the keys of a complete set built from the whole state diff missing from the set
+    /// loaded from the changesets.
+    ///
+    /// A key is covered if it is marked as changed by the corresponding prefix set of `self`, see
+    /// [`Self::storage_prefix_set`] for the storage keys. The prefix sets of `full` marking all
+    /// keys as changed contribute only their explicit keys, as all keys cannot be enumerated.
+    pub fn missing_against(&self, full: &Self) -> MissingPrefixes {
+        let mut account_prefix_set = self.account_prefix_set.clone();
+        let accounts = full
+            .account_prefix_set
+            .iter()
+            .filter(|key| !account_prefix_set.contains(key))
+            .cloned()
+            .collect();
+
+        let mut storages = BTreeMap::new();
+        for (hashed_address, full_prefix_set) in &full.storage_prefix_sets {
+            let mut prefix_set = self.storage_prefix_set(hashed_address);
+            let missing = full_prefix_set
+                .iter()
+                .filter(|key| !prefix_set.contains(key))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                storages.insert(*hashed_address, missing);
+            }
+        }
+
+        let mut destroyed_accounts = full
+            .destroyed_accounts
+            .difference(&self.destroyed_accounts)
+            .copied()
+            .collect::<Vec<_>>();
+        destroyed_accounts.sort_unstable();
+
+        MissingPrefixes { accounts, storages, destroyed_accounts }
+    }
+
+    /// Returns the prefix set of the changed account paths.
+    pub const fn account_prefix_set(&self) -> &PrefixSet {
+        &self.account_prefix_set
+    }
+
+    /// Returns an iterator over the explicit storage prefix sets by the hashed address of the
+    /// account, see [`Self::changed_storage_prefixes`].
+    pub fn storage_prefix_sets(&self) -> impl Iterator<Item = (&B256, &PrefixSet)> {
+        self.storage_prefix_sets.iter()
+    }
+
+    /// Returns `true` if the storage trie of the given hashed address has any changed slots and
+    /// its storage root needs to be recomputed.
+    ///
+    /// Like [`Self::storage_prefix_set`], the accounts without an explicit storage prefix set are
+    /// considered changed if the account prefix set marks all keys as changed.
+    pub fn storage_changed(&self, hashed_address: &B256) -> bool {
+        match self.storage_prefix_sets.get(hashed_address) {
+            Some(prefix_set) => prefix_set.all() || !prefix_set.is_empty(),
+            None => self.account_prefix_set.all(),
+        }
+    }
+
+    /// Returns the explicit storage prefix set of the given hashed address, if any.
+    ///
+    /// Unlike [`Self::storage_prefix_set`], the prefix set is returned by reference and the account
+    /// prefix set marking all keys as changed is not taken into account.
+    pub fn changed_storage_prefixes(&self, hashed_address: &B256) -> Option<&PrefixSet> {
+        self.storage_prefix_sets.get(hashed_address)
+    }
+
+    /// Returns the storage prefix set for the given hashed address.
+    ///
+    /// If the account prefix set marks all keys as changed, the storage keys of the accounts
+    /// without an explicit storage prefix set are marked as changed as well.
+    pub fn storage_prefix_set(&self, hashed_address: &B256) -> PrefixSet {
+        match self.storage_prefix_sets.get(hashed_address) {
+            Some(prefix_set) => prefix_set.clone(),
+            None if self.account_prefix_set.all() => PrefixSetMut::all().freeze(),
+            None => PrefixSet::default(),
+        }
+    }
+}
+
+impl From<&AccessList> for TriePrefixSets {
+    fn from(access_list: &AccessList) -> Self {
+        Self::from_access_list(access_list)
+    }
+}
+
+/// The keys of a complete prefix set missing from another one, see
+/// [`TriePrefixSets::missing_against`].
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct MissingPrefixes {
+    /// The missing account keys, sorted.
+    pub accounts: Vec<Nibbles>,
+    /// The missing storage keys, sorted, by the hashed address of the account.
+    pub storages: BTreeMap<B256, Vec<Nibbles>>,
+    /// The hashed addresses of the destroyed accounts missing from the destroyed accounts, sorted.
+    pub destroyed_accounts: Vec<B256>,
+}
+
+impl MissingPrefixes {
+    /// Returns `true` if no keys are missing.
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storages.is_empty() && self.destroyed_accounts.is_empty()
+    }
+}
+
 /// A container for efficiently storing and checking for the presence of key prefixes.
 ///
 /// This data structure stores a set of `Nibbles` and provides methods to insert
@@ -48,6 +226,9 @@ pub struct TriePrefixSets {
 /// ```
 #[derive(Debug, Default, Clone)]
 pub struct PrefixSetMut {
+    /// Flag indicating that any entry should be considered changed.
+    /// If set, the keys will be discarded.
+    all: bool,
     keys: Vec<Nibbles>,
     sorted: bool,
     index: usize,
@@ -68,9 +249,18 @@ impl PrefixSetMut {
         Self { keys: Vec::with_capacity(capacity), ..Default::default() }
     }
 
+    /// Create [`PrefixSetMut`] that considers all keys to be changed.
+    pub fn all() -> Self {
+        Self { all: true, ..Default::default() }
+    }
+
     /// Returns `true` if any of the keys in the set has the given prefix or
     /// if the given prefix is a prefix of any key in the set.
     pub fn contains(&mut self, prefix: &[u8]) -> bool {
+        if self.all {
+            return true
+        }
+
         if !self.sorted {
             self.keys.sort();
             self.keys.dedup();
@@ -98,6 +288,10 @@ impl PrefixSetMut {
 
     /// Inserts the given `nibbles` into the set.
     pub fn insert(&mut self, nibbles: Nibbles) {
+        if self.all {
+            return
+        }
+
         self.sorted = false;
         self.keys.push(nibbles);
     }
@@ -124,7 +318,7 @@ impl PrefixSetMut {
         // we need to shrink in both the sorted and non-sorted cases because deduping may have
         // occurred either on `freeze`, or during `contains`.
         self.keys.shrink_to_fit();
-        PrefixSet { keys: Arc::new(self.keys), index: self.index }
+        PrefixSet { all: self.all, keys: Arc::new(self.keys), index: self.index }
     }
 }
 
@@ -133,15 +327,52 @@ impl PrefixSetMut {
 /// See also [`PrefixSetMut::freeze`].
 #[derive(Debug, Default, Clone)]
 pub struct PrefixSet {
+    /// Flag indicating that any entry should be considered changed.
+    all: bool,
     keys: Arc<Vec<Nibbles>>,
     index: usize,
 }
 
 impl PrefixSet {
+    /// Returns the smallest prefix set marking the given changed keys, e.g. the hashed keys of the
+    /// changed accounts or slots unpacked into nibbles.
+    ///
+    /// The keys are sorted and deduplicated, and the keys that are prefixes of other keys are
+    /// dropped, as every path matched by such a key is matched by the longer key as well. The set
+    /// therefore drives the same walk as a [`PrefixSetMut`] with the same keys inserted one by
+    /// one, without the mutable intermediate.
+    ///
+    /// The set is frozen: the keys can no longer be inserted, and the set is meant to be used by
+    /// a single walk, as its cursor only moves forward efficiently. Clone the set before the walk
+    /// to reuse it, the clones share the keys.
+    pub fn from_keys(keys: impl IntoIterator<Item = Nibbles>) -> Self {
+        let mut keys = keys.into_iter().collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+        // A key that is a prefix of other keys sorts right before them.
+        let mut minimal = Vec::with_capacity(keys.len());
+        let mut keys = keys.into_iter().peekable();
+        while let Some(key) = keys.next() {
+            if !keys.peek().is_some_and(|next| next.has_prefix(&key)) {
+                minimal.push(key);
+            }
+        }
+        Self { all: false, keys: Arc::new(minimal), index: 0 }
+    }
+
+    /// Returns `true` if all keys are considered changed.
+    pub const fn all(&self) -> bool {
+        self.all
+    }
+
     /// Returns `true` if any of the keys in the set has the given prefix or
     /// if the given prefix is a prefix of any key in the set.
     #[inline]
     pub fn contains(&mut self, prefix: &Nibbles) -> bool {
+        if self.all {
+            return true
+        }
+
         while self.index > 0 && &self.keys[self.index] > prefix {
             self.index -= 1;
         }
@@ -161,6 +392,19 @@ impl PrefixSet {
         false
     }
 
+    /// Returns `true` if both sets mark the same keys as changed.
+    ///
+    /// The keys of the frozen sets are sorted and deduplicated, so the order of insertion and the
+    /// positions of the cursors do not matter. The sets marking all keys as changed are equivalent
+    /// regardless of their keys.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        match (self.all, other.all) {
+            (true, true) => true,
+            (false, false) => self.keys == other.keys,
+            _ => false,
+        }
+    }
+
     /// Returns an iterator over reference to _all_ nibbles regardless of cursor position.
     pub fn iter(&self) -> core::slice::Iter<'_, Nibbles> {
         self.keys.iter()
@@ -233,4 +477,267 @@ mod tests {
         assert_eq!(frozen.keys.len(), 3); // Length should be 3 (excluding duplicate)
         assert_eq!(frozen.keys.capacity(), 3); // Capacity should be 3 after shrinking
     }
+
+    #[test]
+    fn test_all_contains_any_prefix() {
+        let mut prefix_set = PrefixSetMut::all();
+        prefix_set.insert(Nibbles::from_nibbles([1, 2, 3]));
+        assert!(prefix_set.contains(&[]));
+        assert!(prefix_set.contains(&[7, 8]));
+
+        let mut frozen = prefix_set.freeze();
+        assert!(frozen.all());
+        assert!(frozen.is_empty());
+        assert!(frozen.contains(&Nibbles::from_nibbles([0xf, 0xf])));
+
+        let prefix_sets = TriePrefixSets::all_changed();
+        assert!(prefix_sets.storage_prefix_set(&B256::ZERO).all());
+        assert!(!TriePrefixSets::default().storage_prefix_set(&B256::ZERO).all());
+    }
+
+    #[test]
+    fn storage_changed() {
+        let changed = B256::with_last_byte(1);
+        let empty = B256::with_last_byte(2);
+        let untouched = B256::with_last_byte(3);
+        let mut prefix_sets = TriePrefixSets {
+            storage_prefix_sets: HashMap::from([
+                (changed, PrefixSetMut::from([Nibbles::from_nibbles([1, 2])]).freeze()),
+                (empty, PrefixSet::default()),
+            ]),
+            ..Default::default()
+        };
+
+        assert!(prefix_sets.storage_changed(&changed));
+        assert!(!prefix_sets.storage_changed(&empty));
+        assert!(!prefix_sets.storage_changed(&untouched));
+        assert_eq!(prefix_sets.changed_storage_prefixes(&changed).map(PrefixSet::len), Some(1));
+        assert!(prefix_sets.changed_storage_prefixes(&empty).is_some_and(PrefixSet::is_empty));
+        assert!(prefix_sets.changed_storage_prefixes(&untouched).is_none());
+
+        // The account prefix set marking all keys as changed applies only to the accounts without
+        // an explicit storage prefix set.
+        prefix_sets.account_prefix_set = PrefixSetMut::all().freeze();
+        assert!(prefix_sets.storage_changed(&changed));
+        assert!(!prefix_sets.storage_changed(&empty));
+        assert!(prefix_sets.storage_changed(&untouched));
+        assert!(prefix_sets.changed_storage_prefixes(&untouched).is_none());
+    }
+
+    #[test]
+    fn prefix_sets_is_empty() {
+        let hashed_address = B256::with_last_byte(1);
+        let mut prefix_sets = TriePrefixSets {
+            storage_prefix_sets: HashMap::from([(hashed_address, PrefixSet::default())]),
+            ..Default::default()
+        };
+        assert!(prefix_sets.is_empty());
+
+        prefix_sets.destroyed_accounts.insert(hashed_address);
+        assert!(!prefix_sets.is_empty());
+        prefix_sets.destroyed_accounts.clear();
+
+        prefix_sets.storage_prefix_sets.insert(hashed_address, PrefixSetMut::all().freeze());
+        assert!(!prefix_sets.is_empty());
+        prefix_sets.storage_prefix_sets.clear();
+
+        assert!(!TriePrefixSets::all_changed().is_empty());
+    }
+
+    #[test]
+    fn prefix_sets_equivalence() {
+        let prefix_set = |keys: &[&[u8]]| {
+            PrefixSetMut::from(keys.iter().map(|key| Nibbles::from_nibbles(key))).freeze()
+        };
+        let address = B256::with_last_byte(1);
+        let prefix_sets = |account_keys: &[&[u8]], storage_keys: &[&[u8]]| TriePrefixSets {
+            account_prefix_set: prefix_set(account_keys),
+            storage_prefix_sets: HashMap::from([(address, prefix_set(storage_keys))]),
+            destroyed_accounts: HashSet::from([address]),
+        };
+
+        // The order of insertion and the duplicates do not matter.
+        let prefix_sets_a = prefix_sets(&[&[1, 2], &[3, 4]], &[&[5], &[6]]);
+        let mut prefix_sets_b = prefix_sets(&[&[3, 4], &[1, 2], &[3, 4]], &[&[6], &[5], &[5]]);
+        prefix_sets_b.account_prefix_set.contains(&Nibbles::from_nibbles([3, 4]));
+        assert!(prefix_sets_a.is_equivalent(&prefix_sets_b));
+        assert!(prefix_sets_b.is_equivalent(&prefix_sets_a));
+
+        // The differing account keys, storage keys or destroyed accounts.
+        assert!(!prefix_sets_a.is_equivalent(&prefix_sets(&[&[1, 2]], &[&[5], &[6]])));
+        assert!(!prefix_sets_a.is_equivalent(&prefix_sets(&[&[1, 2], &[3, 4]], &[&[5, 6]])));
+        let mut destroyed = prefix_sets(&[&[1, 2], &[3, 4]], &[&[5], &[6]]);
+        destroyed.destroyed_accounts.clear();
+        assert!(!prefix_sets_a.is_equivalent(&destroyed));
+
+        // The missing storage prefix set is equivalent to an empty one.
+        let mut empty_storage = prefix_sets(&[&[1, 2]], &[]);
+        let mut missing_storage = prefix_sets(&[&[1, 2]], &[]);
+        missing_storage.storage_prefix_sets.clear();
+        assert!(empty_storage.is_equivalent(&missing_storage));
+        empty_storage.storage_prefix_sets.insert(B256::with_last_byte(2), prefix_set(&[&[7]]));
+        assert!(!empty_storage.is_equivalent(&missing_storage));
+
+        // The all changed sentinel is equivalent only to itself, regardless of the keys.
+        let all_changed = TriePrefixSets::all_changed();
+        let mut all_with_keys = TriePrefixSets::all_changed();
+        let mut account_prefix_set = PrefixSetMut::all();
+        account_prefix_set.insert(Nibbles::from_nibbles([1, 2]));
+        all_with_keys.account_prefix_set = account_prefix_set.freeze();
+        all_with_keys.storage_prefix_sets.insert(address, PrefixSetMut::all().freeze());
+        assert!(all_changed.is_equivalent(&all_with_keys));
+        assert!(!all_changed.is_equivalent(&TriePrefixSets::default()));
+        assert!(!all_changed.is_equivalent(&prefix_sets_a));
+    }
+
+    #[test]
+    fn prefix_sets_missing_against() {
+        let prefix_set = |keys: &[&[u8]]| {
+            PrefixSetMut::from(keys.iter().map(|key| Nibbles::from_nibbles(key))).freeze()
+        };
+        let [address, other] = [1, 2].map(B256::with_last_byte);
+        let full = TriePrefixSets {
+            account_prefix_set: prefix_set(&[&[1, 2], &[3, 4], &[5, 6]]),
+            storage_prefix_sets: HashMap::from([
+                (address, prefix_set(&[&[7], &[8]])),
+                (other, prefix_set(&[&[9]])),
+            ]),
+            destroyed_accounts: HashSet::from([address, other]),
+        };
+        assert!(full.missing_against(&full).is_empty());
+        assert_eq!(
+            TriePrefixSets::all_changed().missing_against(&full),
+            MissingPrefixes { destroyed_accounts: vec![address, other], ..Default::default() }
+        );
+
+        // The incomplete set misses an account key, a storage key, a whole storage prefix set and a
+        // destroyed account.
+        let incomplete = TriePrefixSets {
+            account_prefix_set: prefix_set(&[&[1, 2], &[5, 6], &[7, 8]]),
+            storage_prefix_sets: HashMap::from([(address, prefix_set(&[&[8]]))]),
+            destroyed_accounts: HashSet::from([address]),
+        };
+        assert_eq!(
+            incomplete.missing_against(&full),
+            MissingPrefixes {
+                accounts: vec![Nibbles::from_nibbles([3, 4])],
+                storages: BTreeMap::from([
+                    (address, vec![Nibbles::from_nibbles([7])]),
+                    (other, vec![Nibbles::from_nibbles([9])]),
+                ]),
+                destroyed_accounts: vec![other],
+            }
+        );
+
+        // The keys covered by the set marking all keys as changed are not missing.
+        let mut all_changed = TriePrefixSets::all_changed();
+        all_changed.destroyed_accounts = full.destroyed_accounts.clone();
+        assert!(all_changed.missing_against(&full).is_empty());
+    }
+
+    #[test]
+    fn prefix_set_from_keys() {
+        use crate::{trie_cursor::DatabaseAccountTrieCursor, walker::TrieWalker, StateRoot};
+        use reth_db::{
+            tables,
+            transaction::{DbTx, DbTxMut},
+        };
+        use reth_primitives::{Account, U256};
+        use reth_provider::test_utils::create_test_provider_factory;
+
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+        let hashed_addresses =
+            (0..1_000u64).map(|i| keccak256(B256::from(U256::from(i)))).collect::<Vec<_>>();
+        for (i, hashed_address) in hashed_addresses.iter().enumerate() {
+            let account = Account { nonce: i as u64, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(*hashed_address, account).unwrap();
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // Unsorted and repeated keys along with a prefix of one of them.
+        let changed = [700, 3, 512, 3, 999, 42].map(|i| Nibbles::unpack(hashed_addresses[i]));
+        let mut keys = changed.to_vec();
+        keys.push(Nibbles::from_nibbles_unchecked(&changed[2][..3]));
+        let from_keys = PrefixSet::from_keys(keys.clone());
+        assert_eq!(from_keys.len(), 5);
+        assert!(from_keys.iter().zip(from_keys.iter().skip(1)).all(|(a, b)| a < b));
+        let mut one_by_one = PrefixSetMut::default();
+        for key in keys {
+            one_by_one.insert(key);
+        }
+        let one_by_one = one_by_one.freeze();
+
+        let walk = |prefix_set: PrefixSet| {
+            let cursor =
+                DatabaseAccountTrieCursor::new(tx.cursor_read::<tables::AccountsTrie>().unwrap());
+            let mut walker = TrieWalker::new(cursor, prefix_set);
+            let mut walked = vec![(walker.key().cloned(), walker.can_skip_current_node)];
+            while walker.advance().unwrap().is_some() {
+                walked.push((walker.key().cloned(), walker.can_skip_current_node));
+            }
+            walked
+        };
+        assert_eq!(walk(from_keys.clone()), walk(one_by_one));
+        let account_prefix_set =
+            |prefix_set| TriePrefixSets { account_prefix_set: prefix_set, ..Default::default() };
+        assert_eq!(
+            StateRoot::from_tx(tx)
+                .with_prefix_sets(account_prefix_set(from_keys))
+                .root_with_updates()
+                .unwrap(),
+            StateRoot::from_tx(tx)
+                .with_prefix_sets(account_prefix_set(PrefixSetMut::from(changed).freeze()))
+                .root_with_updates()
+                .unwrap()
+        );
+        assert!(PrefixSet::from_keys(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn prefix_sets_from_access_list() {
+        use reth_primitives::{AccessListItem, Address};
+
+        let [with_storage, account_only, storage_only] = [1, 2, 3].map(Address::with_last_byte);
+        let [slot_a, slot_b] = [1, 2].map(B256::with_last_byte);
+        let access_list = AccessList(vec![
+            AccessListItem { address: with_storage, storage_keys: vec![slot_a, slot_b, slot_a] },
+            AccessListItem { address: account_only, storage_keys: vec![] },
+            AccessListItem { address: storage_only, storage_keys: vec![slot_b] },
+            // The repeated entries are merged with the first ones.
+            AccessListItem { address: with_storage, storage_keys: vec![slot_b] },
+            AccessListItem { address: account_only, storage_keys: vec![] },
+        ]);
+        let prefix_sets = TriePrefixSets::from(&access_list);
+
+        let hashed = |key: &[u8]| Nibbles::unpack(keccak256(key));
+        let prefix_set = |keys: &[Nibbles]| PrefixSetMut::from(keys.iter().cloned()).freeze();
+        let expected = TriePrefixSets {
+            account_prefix_set: prefix_set(&[
+                hashed(with_storage.as_slice()),
+                hashed(account_only.as_slice()),
+                hashed(storage_only.as_slice()),
+            ]),
+            storage_prefix_sets: HashMap::from([
+                (
+                    keccak256(with_storage),
+                    prefix_set(&[hashed(slot_a.as_slice()), hashed(slot_b.as_slice())]),
+                ),
+                (keccak256(storage_only), prefix_set(&[hashed(slot_b.as_slice())])),
+            ]),
+            destroyed_accounts: HashSet::default(),
+        };
+        assert!(prefix_sets.is_equivalent(&expected));
+        assert_eq!(prefix_sets.account_prefix_set.len(), 3);
+        assert_eq!(prefix_sets.storage_prefix_sets.len(), 2);
+        assert!(prefix_sets.changed_storage_prefixes(&keccak256(account_only)).is_none());
+        assert!(prefix_sets.destroyed_accounts.is_empty());
+
+        // The prefix sets cover exactly the contents of the access list.
+        assert!(prefix_sets.missing_against(&expected).is_empty());
+        assert!(expected.missing_against(&prefix_sets).is_empty());
+        assert!(TriePrefixSets::from_access_list(&AccessList::default()).is_empty());
+    }
 }
@@ -1,5 +1,6 @@
 use crate::{trie_cursor::CursorSubNode, updates::TrieUpdates};
 use reth_primitives::{stage::MerkleCheckpoint, trie::hash_builder::HashBuilder, B256};
+use std::time::Duration;
 
 /// The progress of the state root computation.
 #[derive(Debug)]
@@ -11,6 +12,22 @@ pub enum StateRootProgress {
     Progress(Box<IntermediateStateRootState>, usize, TrieUpdates),
 }
 
+impl StateRootProgress {
+    /// Returns the rough estimate of the completed fraction of the computation, between `0.0` and
+    /// `1.0`.
+    ///
+    /// The hashed keys are uniformly distributed, so the fraction of the keyspace preceding the
+    /// last processed account approximates the fraction of the accounts walked. This is only an
+    /// estimate, the cost of the accounts varies with their storage and the unchanged subtries
+    /// that are skipped by the walk.
+    pub fn percent_estimate(&self) -> f64 {
+        match self {
+            Self::Complete(..) => 1.0,
+            Self::Progress(state, ..) => state.percent_estimate(),
+        }
+    }
+}
+
 /// The intermediate state of the state root computation.
 #[derive(Debug)]
 pub struct IntermediateStateRootState {
@@ -22,6 +39,78 @@ pub struct IntermediateStateRootState {
     pub last_account_key: B256,
 }
 
+impl IntermediateStateRootState {
+    /// Returns the fraction of the hashed keyspace up to and including the last processed account,
+    /// see [`StateRootProgress::percent_estimate`].
+    pub fn percent_estimate(&self) -> f64 {
+        keyspace_fraction(&self.last_account_key)
+    }
+}
+
+/// Returns the fraction of the hashed keyspace up to and including the given key.
+fn keyspace_fraction(key: &B256) -> f64 {
+    // The leading bytes are more than enough for the precision of the estimate.
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&key[..8]);
+    u64::from_be_bytes(prefix) as f64 / u64::MAX as f64
+}
+
+/// The progress of a running state root calculation, passed to the [`ProgressReporter`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct ProgressReport {
+    /// The number of accounts processed by the calculation so far.
+    pub accounts_processed: usize,
+    /// The number of hashed entries walked so far, counted as in [`StateRootProgress`].
+    pub hashed_entries_walked: usize,
+    /// The hashed address of the last processed account, if any.
+    pub last_account_key: Option<B256>,
+    /// Whether the calculation has completed.
+    pub complete: bool,
+}
+
+impl ProgressReport {
+    /// Returns the rough estimate of the completed fraction of the calculation, between `0.0` and
+    /// `1.0`, see [`StateRootProgress::percent_estimate`].
+    pub fn percent_estimate(&self) -> f64 {
+        if self.complete {
+            return 1.0
+        }
+        self.last_account_key.as_ref().map_or(0.0, keyspace_fraction)
+    }
+}
+
+/// The observer of the progress of the state root calculation, e.g. a progress bar or a metrics
+/// exporter, see [`StateRoot::with_progress_reporter`](crate::StateRoot::with_progress_reporter).
+///
+/// The reporter is called on the thread of the calculation, between the accounts, so it must be
+/// cheap. The calls are rate-limited to one per [`Self::interval`], except for the final report
+/// of the calculation, which is always delivered. The counts are those of a single call of the
+/// calculation, a calculation resumed from the intermediate state starts counting from zero.
+pub trait ProgressReporter: Send + Sync {
+    /// Reports the progress of the calculation.
+    fn report(&self, progress: &ProgressReport);
+
+    /// Returns the minimum time between two intermediate reports.
+    fn interval(&self) -> Duration {
+        Duration::from_millis(100)
+    }
+}
+
+impl<F> ProgressReporter for F
+where
+    F: Fn(&ProgressReport) + Send + Sync,
+{
+    fn report(&self, progress: &ProgressReport) {
+        self(progress)
+    }
+}
+
+impl std::fmt::Debug for dyn ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter").field("interval", &self.interval()).finish()
+    }
+}
+
 impl From<MerkleCheckpoint> for IntermediateStateRootState {
     fn from(value: MerkleCheckpoint) -> Self {
         Self {
@@ -31,3 +120,100 @@ impl From<MerkleCheckpoint> for IntermediateStateRootState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StateRoot;
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{keccak256, Account, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct RecordingReporter {
+        interval: Duration,
+        reports: Mutex<Vec<ProgressReport>>,
+    }
+
+    impl ProgressReporter for RecordingReporter {
+        fn report(&self, progress: &ProgressReport) {
+            self.reports.lock().unwrap().push(*progress);
+        }
+
+        fn interval(&self) -> Duration {
+            self.interval
+        }
+    }
+
+    #[test]
+    fn percent_estimate_monotonic() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+        for i in 0..1_000u64 {
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(B256::from(U256::from(i))), account)
+                .unwrap();
+        }
+
+        let mut estimates = Vec::new();
+        let mut intermediate_state = None;
+        loop {
+            let progress = StateRoot::from_tx(tx)
+                .with_threshold(10)
+                .with_intermediate_state(intermediate_state)
+                .root_with_progress()
+                .unwrap();
+            estimates.push(progress.percent_estimate());
+            match progress {
+                StateRootProgress::Progress(state, _, _) => intermediate_state = Some(*state),
+                StateRootProgress::Complete(..) => break,
+            }
+        }
+
+        assert!(estimates.len() > 2);
+        assert!(estimates.iter().all(|estimate| (0.0..=1.0).contains(estimate)));
+        assert!(estimates.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(estimates.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn progress_reporter_monotonic() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+        for i in 0..1_000u64 {
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(B256::from(U256::from(i))), account)
+                .unwrap();
+        }
+
+        let reporter = Arc::new(RecordingReporter::default());
+        let root = StateRoot::from_tx(tx).with_progress_reporter(reporter.clone()).root().unwrap();
+        assert_eq!(root, StateRoot::from_tx(tx).root().unwrap());
+
+        let reports = reporter.reports.lock().unwrap();
+        assert_eq!(reports.len(), 1_001);
+        assert!(reports.windows(2).all(|pair| {
+            pair[0].accounts_processed <= pair[1].accounts_processed &&
+                pair[0].hashed_entries_walked <= pair[1].hashed_entries_walked &&
+                pair[0].last_account_key <= pair[1].last_account_key &&
+                pair[0].percent_estimate() <= pair[1].percent_estimate()
+        }));
+        assert!(reports[..1_000].iter().all(|report| !report.complete));
+        let last = reports.last().unwrap();
+        assert!(last.complete);
+        assert_eq!(last.accounts_processed, 1_000);
+        assert_eq!(last.percent_estimate(), 1.0);
+
+        // The intermediate reports are rate-limited, the final one is always delivered.
+        let reporter =
+            Arc::new(RecordingReporter { interval: Duration::MAX, ..Default::default() });
+        StateRoot::from_tx(tx).with_progress_reporter(reporter.clone()).root().unwrap();
+        let reports = reporter.reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].complete);
+        assert_eq!(reports[0].accounts_processed, 1_000);
+    }
+}
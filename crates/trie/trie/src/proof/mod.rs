@@ -1,20 +1,73 @@
 use crate::{
-    hashed_cursor::{HashedCursorFactory, HashedStorageCursor},
+    hashed_cursor::{HashedCursorFactory, HashedPostStateCursorFactory, HashedStorageCursor},
     node_iter::{TrieElement, TrieNodeIter},
     prefix_set::PrefixSetMut,
-    trie_cursor::{DatabaseAccountTrieCursor, DatabaseStorageTrieCursor},
+    trie_cursor::{
+        InMemoryAccountTrieCursor, InMemoryStorageTrieCursor, TrieCursor, TrieCursorFactory,
+    },
+    updates::TrieUpdatesSorted,
     walker::TrieWalker,
+    HashedPostStateSorted,
 };
 use alloy_rlp::{BufMut, Encodable};
-use reth_db::{tables, transaction::DbTx};
+use reth_db::{tables, transaction::DbTx, DatabaseError};
 use reth_execution_errors::{StateRootError, StorageRootError};
 use reth_primitives::{
     constants::EMPTY_ROOT_HASH,
     keccak256,
     trie::{proof::ProofRetainer, AccountProof, HashBuilder, Nibbles, StorageProof, TrieAccount},
-    Address, B256,
+    Account, Address, Bytes, B256, KECCAK_EMPTY,
 };
 
+mod multi;
+mod node;
+mod path;
+mod range;
+
+pub use self::{
+    multi::{storage_multiproof_parallel, MultiProof, MultiProofError},
+    path::{
+        decode_proof_path, path_to, proof_node_positions, DecodedChild, DecodedNode, ProofNodeKind,
+        ProofNodePosition, TriePathError,
+    },
+    range::{range_proof, verify_range_proof, RangeProof, RangeProofError},
+};
+
+/// Generate the proof of the storage root of the account, i.e. the account proof down to the
+/// account leaf without any storage slots.
+///
+/// The [`AccountProof::storage_root`] is proven by [`AccountProof::verify`] against the state root,
+/// which is all a verifier needs to check the storage root of a contract without the nodes of its
+/// storage trie. The proof of an absent account proves its absence with the empty storage root.
+pub fn storage_root_proof<TX: DbTx>(
+    tx: &TX,
+    address: Address,
+) -> Result<AccountProof, StateRootError> {
+    Proof::new(tx).account_proof(address, &[])
+}
+
+/// Generate the proof of the account and the given storage slots as of the pending block, i.e.
+/// with the hashed state and the trie updates of the block layered over the database, e.g. to
+/// serve `eth_getProof` at the pending block tag.
+///
+/// The trie updates have to be the ones computed along with the pending state root from the same
+/// hashed state on top of the database, see
+/// [`HashedPostState::state_root_with_updates`](crate::HashedPostState::state_root_with_updates).
+/// The proof then verifies against the pending state root and is the same as the one generated
+/// after committing the pending state and the trie updates.
+pub fn account_proof_pending<TX: DbTx>(
+    tx: &TX,
+    pending_state: &HashedPostStateSorted,
+    pending_updates: &TrieUpdatesSorted,
+    address: Address,
+    slots: &[B256],
+) -> Result<AccountProof, StateRootError> {
+    Proof::new(tx)
+        .with_hashed_cursor_factory(HashedPostStateCursorFactory::new(tx, pending_state))
+        .with_trie_updates(pending_updates)
+        .account_proof(address, slots)
+}
+
 /// A struct for generating merkle proofs.
 ///
 /// Proof generator adds the target address and slots to the prefix set, enables the proof retainer
@@ -26,12 +79,68 @@ pub struct Proof<'a, TX, H> {
     tx: &'a TX,
     /// The factory for hashed cursors.
     hashed_cursor_factory: H,
+    /// Flag indicating whether to include the bytecode of the target account in the proof.
+    include_code: bool,
+    /// The trie updates layered over the stored trie nodes.
+    trie_updates: Option<&'a TrieUpdatesSorted>,
 }
 
 impl<'a, TX> Proof<'a, TX, &'a TX> {
     /// Create a new [Proof] instance.
     pub const fn new(tx: &'a TX) -> Self {
-        Self { tx, hashed_cursor_factory: tx }
+        Self { tx, hashed_cursor_factory: tx, include_code: false, trie_updates: None }
+    }
+}
+
+impl<'a, TX, H> Proof<'a, TX, H> {
+    /// Set the flag indicating whether to include the bytecode of the target account in the
+    /// account proof. The bytecode is never included for accounts without code.
+    pub const fn with_code(mut self, include_code: bool) -> Self {
+        self.include_code = include_code;
+        self
+    }
+
+    /// Set the trie updates to be layered over the stored trie nodes, e.g. the updates of a block
+    /// that are not committed yet, see [`account_proof_pending`].
+    pub const fn with_trie_updates(mut self, trie_updates: &'a TrieUpdatesSorted) -> Self {
+        self.trie_updates = Some(trie_updates);
+        self
+    }
+
+    /// Set the hashed cursor factory.
+    pub fn with_hashed_cursor_factory<HF>(self, hashed_cursor_factory: HF) -> Proof<'a, TX, HF> {
+        Proof {
+            tx: self.tx,
+            hashed_cursor_factory,
+            include_code: self.include_code,
+            trie_updates: self.trie_updates,
+        }
+    }
+}
+
+impl<'a, TX: DbTx, H> Proof<'a, TX, H> {
+    /// Returns the cursor over the account trie, layered with the trie updates if any.
+    fn account_trie_cursor(&self) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.tx.account_trie_cursor()?;
+        Ok(match self.trie_updates {
+            Some(trie_updates) => Box::new(InMemoryAccountTrieCursor::new(cursor, trie_updates)),
+            None => cursor,
+        })
+    }
+
+    /// Returns the cursor over the storage trie of the account, layered with the trie updates if
+    /// any.
+    fn storage_trie_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.tx.storage_tries_cursor(hashed_address)?;
+        Ok(match self.trie_updates {
+            Some(trie_updates) => {
+                Box::new(InMemoryStorageTrieCursor::new(cursor, trie_updates, hashed_address))
+            }
+            None => cursor,
+        })
     }
 }
 
@@ -51,8 +160,7 @@ where
         let mut account_proof = AccountProof::new(address);
 
         let hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
-        let trie_cursor =
-            DatabaseAccountTrieCursor::new(self.tx.cursor_read::<tables::AccountsTrie>()?);
+        let trie_cursor = self.account_trie_cursor()?;
 
         // Create the walker.
         let mut prefix_set = PrefixSetMut::default();
@@ -75,6 +183,11 @@ where
                         let (storage_root, storage_proofs) =
                             self.storage_root_with_proofs(hashed_address, slots)?;
                         account_proof.set_account(account, storage_root, storage_proofs);
+                        if self.include_code {
+                            if let Some(code) = self.account_code(&account)? {
+                                account_proof.set_code(code);
+                            }
+                        }
                         storage_root
                     } else {
                         self.storage_root(hashed_address)?
@@ -94,9 +207,28 @@ where
         let proofs = hash_builder.take_proofs();
         account_proof.set_proof(proofs.values().cloned().collect());
 
+        // The storage of an absent account is empty, so the requested slots are proven absent by
+        // the empty storage root without any nodes.
+        if account_proof.info.is_none() {
+            account_proof.storage_proofs = slots.iter().copied().map(StorageProof::new).collect();
+        }
+
         Ok(account_proof)
     }
 
+    /// Look up the non-empty bytecode of the account by its code hash.
+    fn account_code(&self, account: &Account) -> Result<Option<Bytes>, DatabaseError> {
+        let code_hash = account.get_bytecode_hash();
+        if code_hash == KECCAK_EMPTY {
+            return Ok(None)
+        }
+        Ok(self
+            .tx
+            .get::<tables::Bytecodes>(code_hash)?
+            .map(|bytecode| bytecode.original_bytes())
+            .filter(|code| !code.is_empty()))
+    }
+
     /// Compute storage root.
     pub fn storage_root(&self, hashed_address: B256) -> Result<B256, StorageRootError> {
         let (storage_root, _) = self.storage_root_with_proofs(hashed_address, &[])?;
@@ -121,10 +253,7 @@ where
 
         let target_nibbles = proofs.iter().map(|p| p.nibbles.clone()).collect::<Vec<_>>();
         let prefix_set = PrefixSetMut::from(target_nibbles.clone()).freeze();
-        let trie_cursor = DatabaseStorageTrieCursor::new(
-            self.tx.cursor_dup_read::<tables::StoragesTrie>()?,
-            hashed_address,
-        );
+        let trie_cursor = self.storage_trie_cursor(hashed_address)?;
         let walker = TrieWalker::new(trie_cursor, prefix_set);
 
         let retainer = ProofRetainer::from_iter(target_nibbles);
@@ -135,6 +264,8 @@ where
                 TrieElement::Branch(node) => {
                     hash_builder.add_branch(node.key, node.value, node.children_are_in_trie);
                 }
+                // The zero slots are not part of the trie, as in `StorageRoot`.
+                TrieElement::Leaf(_, value) if value.is_zero() => {}
                 TrieElement::Leaf(hashed_slot, value) => {
                     let nibbles = Nibbles::unpack(hashed_slot);
                     if let Some(proof) = proofs.iter_mut().find(|proof| proof.nibbles == nibbles) {
@@ -165,13 +296,29 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::StateRoot;
+    use crate::{
+        account_exists,
+        updates::{TrieKey, TrieOp},
+        HashedPostState, HashedStorage, StateRoot, StorageRoot,
+    };
     use once_cell::sync::Lazy;
-    use reth_db::database::Database;
-    use reth_primitives::{Account, Bytes, Chain, ChainSpec, StorageEntry, HOLESKY, MAINNET, U256};
+    use proptest::{
+        collection::{btree_map, vec},
+        prelude::{any, prop_assert, prop_assert_eq, ProptestConfig, Strategy},
+        proptest,
+        sample::Index,
+    };
+    use reth_db::{
+        cursor::{DbCursorRW, DbDupCursorRO},
+        database::Database,
+        transaction::DbTxMut,
+    };
+    use reth_primitives::{
+        trie::proof::verify_proof, Bytecode, Chain, ChainSpec, StorageEntry, HOLESKY, MAINNET, U256,
+    };
     use reth_provider::{test_utils::create_test_provider_factory, HashingWriter, ProviderFactory};
     use reth_storage_errors::provider::ProviderResult;
-    use std::{str::FromStr, sync::Arc};
+    use std::{collections::BTreeMap, str::FromStr, sync::Arc};
 
     /*
         World State (sampled from <https://ethereum.stackexchange.com/questions/268/ethereum-block-architecture/6413#6413>)
@@ -187,7 +334,7 @@ mod tests {
     static TEST_SPEC: Lazy<Arc<ChainSpec>> = Lazy::new(|| {
         ChainSpec {
             chain: Chain::from_id(12345),
-            genesis: serde_json::from_str(include_str!("../testdata/proof-genesis.json"))
+            genesis: serde_json::from_str(include_str!("../../testdata/proof-genesis.json"))
                 .expect("Can't deserialize test genesis json"),
             ..Default::default()
         }
@@ -198,6 +345,17 @@ mod tests {
         path.into_iter().map(Bytes::from_str).collect::<Result<Vec<_>, _>>().unwrap()
     }
 
+    /// Computes the root of the trie with the given leaves and the proof of the target.
+    fn root_with_proof(leaves: &BTreeMap<B256, Vec<u8>>, target: B256) -> (B256, Vec<Bytes>) {
+        let retainer = ProofRetainer::from_iter([Nibbles::unpack(target)]);
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(retainer);
+        for (key, value) in leaves {
+            hash_builder.add_leaf(Nibbles::unpack(key), value);
+        }
+        let root = hash_builder.root();
+        (root, hash_builder.take_proofs().into_values().collect())
+    }
+
     fn insert_genesis<DB: Database>(
         provider_factory: &ProviderFactory<DB>,
         chain_spec: Arc<ChainSpec>,
@@ -293,6 +451,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn testspec_path_to() {
+        // Create test database and insert genesis accounts.
+        let factory = create_test_provider_factory();
+        insert_genesis(&factory, TEST_SPEC.clone()).unwrap();
+        let provider = factory.provider().unwrap();
+
+        // The path to an existing account ends with its leaf.
+        let target = Address::from_str("0x33f0fc440b8477fcfbe9d0bf8649e7dea9baedb2").unwrap();
+        let key = Nibbles::unpack(keccak256(target));
+        let nodes = path_to(provider.tx_ref(), target).unwrap();
+        assert_eq!(
+            nodes.iter().map(DecodedNode::kind).collect::<Vec<_>>(),
+            vec![
+                ProofNodeKind::Extension,
+                ProofNodeKind::Branch,
+                ProofNodeKind::Extension,
+                ProofNodeKind::Branch,
+                ProofNodeKind::Leaf,
+            ]
+        );
+        let DecodedNode::Leaf { path, key: leaf_key, value } = nodes.last().unwrap() else {
+            panic!("path does not end with a leaf")
+        };
+        assert_eq!([path.to_vec(), leaf_key.to_vec()].concat(), key.to_vec());
+        let account = Account { balance: U256::from(1), ..Default::default() };
+        let expected = alloy_rlp::encode(TrieAccount::from((account, EMPTY_ROOT_HASH)));
+        assert_eq!(value.as_ref(), expected.as_slice());
+
+        // The path to a missing account ends with the node at which its key diverges.
+        let target = Address::ZERO;
+        let key = Nibbles::unpack(keccak256(target));
+        let nodes = path_to(provider.tx_ref(), target).unwrap();
+        match nodes.last().unwrap() {
+            DecodedNode::Branch { path, children } => {
+                assert!(children[key[path.len()] as usize].is_none())
+            }
+            DecodedNode::Extension { path, key: extension_key, .. } => {
+                assert!(!key[path.len()..].starts_with(extension_key))
+            }
+            DecodedNode::Leaf { path, key: leaf_key, .. } => {
+                assert_ne!([path.to_vec(), leaf_key.to_vec()].concat(), key.to_vec())
+            }
+        }
+    }
+
     #[test]
     fn testspec_empty_storage_proof() {
         // Create test database and insert genesis accounts.
@@ -315,6 +519,294 @@ mod tests {
         assert_eq!(account_proof.verify(root), Ok(()));
     }
 
+    #[test]
+    fn empty_storage_contract_proof() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // The contract with code but without any storage slots, next to the accounts with storage.
+        let contract = Address::with_last_byte(0xcc);
+        let code_hash = keccak256([0x60, 0x00, 0x60, 0x00, 0xf3]);
+        let contract_account =
+            Account { nonce: 1, bytecode_hash: Some(code_hash), ..Default::default() };
+        let mut accounts = BTreeMap::from([(contract, (contract_account, BTreeMap::new()))]);
+        for i in 0..10u8 {
+            let storage =
+                BTreeMap::from([(B256::with_last_byte(i), U256::from(i) + U256::from(1))]);
+            accounts.insert(Address::with_last_byte(i), (Account::default(), storage));
+        }
+        for (address, (account, storage)) in &accounts {
+            let hashed_address = keccak256(address);
+            tx.put::<tables::HashedAccounts>(hashed_address, *account).unwrap();
+            for (slot, value) in storage {
+                let entry = StorageEntry { key: keccak256(slot), value: *value };
+                tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+            }
+        }
+
+        // The contract has the empty storage root and its code hash is part of the leaf.
+        let (root, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        assert_eq!(root, crate::test_utils::state_root(accounts));
+        assert_eq!(updates.get(&TrieKey::StorageTrie(keccak256(contract))), Some(&TrieOp::Delete));
+        updates.flush(tx).unwrap();
+
+        let slot = B256::with_last_byte(1);
+        let account_proof = Proof::new(tx).account_proof(contract, &[slot]).unwrap();
+        assert_eq!(account_proof.info, Some(contract_account));
+        assert_eq!(account_proof.storage_root, EMPTY_ROOT_HASH);
+        assert_eq!(account_proof.storage_proofs, vec![StorageProof::new(slot)]);
+        assert_eq!(account_proof.verify(root), Ok(()));
+
+        // The proof with the code hash left out does not verify.
+        let mut without_code = account_proof;
+        without_code.info = Some(Account { bytecode_hash: None, ..contract_account });
+        assert!(without_code.verify(root).is_err());
+    }
+
+    #[test]
+    fn account_proof_by_existence() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let absent = Address::with_last_byte(1);
+        let empty_storage = Address::with_last_byte(2);
+        let with_storage = Address::with_last_byte(3);
+        let account = Account { nonce: 1, ..Default::default() };
+        let storage = BTreeMap::from([(B256::with_last_byte(1), U256::from(1))]);
+        let accounts = BTreeMap::from([
+            (empty_storage, (account, BTreeMap::new())),
+            (with_storage, (account, storage.clone())),
+        ]);
+        for (address, (account, storage)) in &accounts {
+            tx.put::<tables::HashedAccounts>(keccak256(address), *account).unwrap();
+            for (slot, value) in storage {
+                let entry = StorageEntry { key: keccak256(slot), value: *value };
+                tx.put::<tables::HashedStorages>(keccak256(address), entry).unwrap();
+            }
+        }
+        let (root, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        let storage_root = crate::test_utils::storage_root(storage.into_iter());
+        for (address, exists, expected_storage_root) in [
+            (absent, false, None),
+            (empty_storage, true, Some(EMPTY_ROOT_HASH)),
+            (with_storage, true, Some(storage_root)),
+        ] {
+            assert_eq!(account_exists(tx, address).unwrap(), exists);
+            assert_eq!(
+                StorageRoot::from_tx(tx, address).root_if_exists().unwrap(),
+                expected_storage_root
+            );
+            assert_eq!(
+                StorageRoot::from_tx(tx, address).root().unwrap(),
+                expected_storage_root.unwrap_or(EMPTY_ROOT_HASH)
+            );
+
+            // Every requested slot has a proof, for the absent account against the empty root.
+            let slots = [B256::with_last_byte(1), B256::with_last_byte(2)];
+            let account_proof = Proof::new(tx).account_proof(address, &slots).unwrap();
+            assert_eq!(account_proof.info.is_some(), exists);
+            assert_eq!(
+                account_proof.storage_proofs.iter().map(|proof| proof.key).collect::<Vec<_>>(),
+                slots
+            );
+            assert_eq!(account_proof.verify(root), Ok(()));
+        }
+
+        let account_proof = Proof::new(tx).account_proof(absent, &[B256::ZERO]).unwrap();
+        assert_eq!(account_proof.storage_root, EMPTY_ROOT_HASH);
+        assert_eq!(account_proof.storage_proofs, vec![StorageProof::new(B256::ZERO)]);
+    }
+
+    #[test]
+    fn account_proof_of_pending_block() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let slot = B256::with_last_byte;
+        for i in 0..50u8 {
+            let hashed_address = keccak256(Address::with_last_byte(i));
+            let account = Account { nonce: i as u64, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            for j in 0..i % 4 {
+                let entry = StorageEntry { key: keccak256(slot(j)), value: U256::from(i + 1) };
+                tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // The pending block updates an account and its slots, creates and destroys accounts.
+        let (updated, created, destroyed, unchanged) = (
+            Address::with_last_byte(7),
+            Address::with_last_byte(200),
+            Address::with_last_byte(22),
+            Address::with_last_byte(3),
+        );
+        let mut pending_state = HashedPostState::default().with_accounts([
+            (keccak256(updated), Some(Account { nonce: 100, ..Default::default() })),
+            (keccak256(created), Some(Account { nonce: 1, ..Default::default() })),
+        ]);
+        pending_state.storages.insert(
+            keccak256(updated),
+            HashedStorage::from_iter(
+                false,
+                [(keccak256(slot(1)), U256::from(10)), (keccak256(slot(5)), U256::from(11))],
+            ),
+        );
+        pending_state.delete_storage(keccak256(updated), keccak256(slot(0)));
+        pending_state.delete_account(keccak256(destroyed));
+        let (pending_root, pending_updates) = pending_state.state_root_with_updates(tx).unwrap();
+
+        // The proofs against the pending state root, without committing the block.
+        let sorted_state = pending_state.clone().into_sorted();
+        let sorted_updates = pending_updates.clone().into_sorted();
+        let slots = [slot(0), slot(1), slot(5)];
+        let addresses = [updated, created, destroyed, unchanged];
+        let pending_proofs = addresses.map(|address| {
+            let proof =
+                account_proof_pending(tx, &sorted_state, &sorted_updates, address, &slots).unwrap();
+            assert_eq!(proof.verify(pending_root), Ok(()));
+            proof
+        });
+        assert_eq!(pending_proofs[0].info.unwrap().nonce, 100);
+        assert_eq!(pending_proofs[0].storage_proofs[0].value, U256::ZERO);
+        assert_eq!(pending_proofs[0].storage_proofs[1].value, U256::from(10));
+        assert!(pending_proofs[2].info.is_none());
+
+        // The proofs after committing the block are the same.
+        for (hashed_address, account) in &pending_state.accounts {
+            match account {
+                Some(account) => tx.put::<tables::HashedAccounts>(*hashed_address, *account),
+                None => tx.delete::<tables::HashedAccounts>(*hashed_address, None).map(|_| ()),
+            }
+            .unwrap();
+        }
+        let mut storage_cursor = tx.cursor_dup_write::<tables::HashedStorages>().unwrap();
+        for (hashed_address, storage) in &pending_state.storages {
+            if storage.wiped {
+                tx.delete::<tables::HashedStorages>(*hashed_address, None).unwrap();
+            }
+            for (hashed_slot, value) in &storage.storage {
+                if storage_cursor
+                    .seek_by_key_subkey(*hashed_address, *hashed_slot)
+                    .unwrap()
+                    .is_some_and(|entry| entry.key == *hashed_slot)
+                {
+                    storage_cursor.delete_current().unwrap();
+                }
+                if !value.is_zero() {
+                    let entry = StorageEntry { key: *hashed_slot, value: *value };
+                    tx.put::<tables::HashedStorages>(*hashed_address, entry).unwrap();
+                }
+            }
+        }
+        pending_updates.flush(tx).unwrap();
+        assert_eq!(StateRoot::from_tx(tx).root().unwrap(), pending_root);
+        for (address, pending_proof) in addresses.into_iter().zip(pending_proofs) {
+            assert_eq!(Proof::new(tx).account_proof(address, &slots).unwrap(), pending_proof);
+        }
+    }
+
+    #[test]
+    fn holesky_deposit_contract_storage_root_proof() {
+        let factory = create_test_provider_factory();
+        let root = insert_genesis(&factory, HOLESKY.clone()).unwrap();
+        let provider = factory.provider().unwrap();
+        let tx = provider.tx_ref();
+
+        let target = Address::from_str("0x4242424242424242424242424242424242424242").unwrap();
+        let account_proof = storage_root_proof(tx, target).unwrap();
+        assert!(account_proof.info.is_some());
+        assert!(account_proof.storage_proofs.is_empty());
+        assert_ne!(account_proof.storage_root, EMPTY_ROOT_HASH);
+        assert_eq!(account_proof.storage_root, StorageRoot::from_tx(tx, target).root().unwrap());
+        assert_eq!(account_proof.verify(root), Ok(()));
+
+        // The proof of the storage root of an absent account proves its absence.
+        let absent = Address::with_last_byte(1);
+        let account_proof = storage_root_proof(tx, absent).unwrap();
+        assert_eq!(account_proof.info, None);
+        assert_eq!(account_proof.storage_root, EMPTY_ROOT_HASH);
+        assert_eq!(account_proof.verify(root), Ok(()));
+
+        // A proof with a forged storage root does not verify.
+        let mut forged = storage_root_proof(tx, target).unwrap();
+        forged.storage_root = EMPTY_ROOT_HASH;
+        assert!(forged.verify(root).is_err());
+    }
+
+    #[test]
+    fn account_proof_with_code() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let code = Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xf3]);
+        let code_hash = keccak256(&code);
+        tx.put::<tables::Bytecodes>(code_hash, Bytecode::new_raw(code.clone())).unwrap();
+
+        let contract = Address::with_last_byte(1);
+        let eoa = Address::with_last_byte(2);
+        let empty_code = Address::with_last_byte(3);
+        for (address, bytecode_hash) in
+            [(contract, Some(code_hash)), (eoa, None), (empty_code, Some(KECCAK_EMPTY))]
+        {
+            let account = Account { nonce: 1, bytecode_hash, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(address), account).unwrap();
+        }
+        let (root, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // The code is included only when requested.
+        let account_proof = Proof::new(tx).account_proof(contract, &[]).unwrap();
+        assert_eq!(account_proof.code, None);
+        let account_proof = Proof::new(tx).with_code(true).account_proof(contract, &[]).unwrap();
+        assert_eq!(account_proof.code.as_ref().map(keccak256), Some(code_hash));
+        assert_eq!(account_proof.verify(root), Ok(()));
+
+        // The accounts without code and the missing account have no code.
+        for address in [eoa, empty_code, Address::with_last_byte(4)] {
+            let account_proof = Proof::new(tx).with_code(true).account_proof(address, &[]).unwrap();
+            assert_eq!(account_proof.code, None);
+            assert_eq!(account_proof.verify(root), Ok(()));
+        }
+    }
+
+    #[test]
+    fn storage_proof_skips_zero_slots() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let address = Address::with_last_byte(1);
+        let hashed_address = keccak256(address);
+        tx.put::<tables::HashedAccounts>(hashed_address, Account::default()).unwrap();
+        let zero_slot = B256::with_last_byte(2);
+        for (slot, value) in [
+            (B256::with_last_byte(1), U256::from(1)),
+            (zero_slot, U256::ZERO),
+            (B256::with_last_byte(3), U256::from(3)),
+        ] {
+            let entry = StorageEntry { key: keccak256(slot), value };
+            tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+        }
+        let storage_root = StorageRoot::from_tx_hashed(tx, hashed_address).root().unwrap();
+        let root = StateRoot::from_tx(tx).root().unwrap();
+
+        let (proof_root, _) =
+            Proof::new(tx).storage_root_with_proofs(hashed_address, &[zero_slot]).unwrap();
+        assert_eq!(proof_root, storage_root);
+        let account_proof = Proof::new(tx).account_proof(address, &[zero_slot]).unwrap();
+        assert_eq!(account_proof.storage_root, storage_root);
+        assert_eq!(account_proof.storage_proofs[0].value, U256::ZERO);
+        assert_eq!(account_proof.verify(root), Ok(()));
+    }
+
     #[test]
     fn mainnet_genesis_account_proof() {
         // Create test database and insert genesis accounts.
@@ -400,6 +892,7 @@ mod tests {
                 "0xf901f1a09da7d9755fe0c558b3c3de9fdcdf9f28ae641f38c9787b05b73ab22ae53af3e2a0d9990bf0b810d1145ecb2b011fd68c63cc85564e6724166fd4a9520180706e5fa05f5f09855df46330aa310e8d6be5fb82d1a4b975782d9b29acf06ac8d3e72b1ca0ca976997ddaf06f18992f6207e4f6a05979d07acead96568058789017cc6d06ba04d78166b48044fdc28ed22d2fd39c8df6f8aaa04cb71d3a17286856f6893ff83a004f8c7cc4f1335182a1709fb28fc67d52e59878480210abcba864d5d1fd4a066a0fc3b71c33e2e6b77c5e494c1db7fdbb447473f003daf378c7a63ba9bf3f0049d80a07b8e7a21c1178d28074f157b50fca85ee25c12568ff8e9706dcbcdacb77bf854a0973274526811393ea0bf4811ca9077531db00d06b86237a2ecd683f55ba4bcb0a03a93d726d7487874e51b52d8d534c63aa2a689df18e3b307c0d6cb0a388b00f3a06aa67101d011d1c22fe739ef83b04b5214a3e2f8e1a2625d8bfdb116b447e86fa02dd545b33c62d33a183e127a08a4767fba891d9f3b94fc20a2ca02600d6d1fffa0f3b039a4f32349e85c782d1164c1890e5bf16badc9ee4cf827db6afd2229dde6a0d9240a9d2d5851d05a97ff3305334dfdb0101e1e321fc279d2bb3cad6afa8fc8a01b69c6ab5173de8a8ec53a6ebba965713a4cc7feb86cb3e230def37c230ca2b280",
                 "0xf869a0202a47fc6863b89a6b51890ef3c1550d560886c027141d2058ba1e2d4c66d99ab846f8448080a0556a482068355939c95a3412bdb21213a301483edb1b64402fb66ac9f3583599a02034f79e0e33b0ae6bef948532021baceb116adf2616478703bec6b17329f1cc"
             ]),
+            code: None,
             storage_proofs: Vec::from([
                 StorageProof {
                     key: slot_22,
@@ -448,4 +941,81 @@ mod tests {
         similar_asserts::assert_eq!(account_proof, expected);
         assert_eq!(account_proof.verify(root), Ok(()));
     }
+
+    #[test]
+    fn fuzz_verify_proof() {
+        proptest!(ProptestConfig::with_cases(100), |(
+            // The 32 byte values keep every node referenced by its hash.
+            leaves in btree_map(any::<B256>(), any::<B256>().prop_map(alloy_rlp::encode), 1..100),
+            absent: B256,
+            target: Index,
+            mutation in any::<(Index, Index, Index)>(),
+            garbage in vec(vec(any::<u8>(), 0..600), 1..5),
+        )| {
+            let keys = leaves.keys().copied().collect::<Vec<_>>();
+            let target = keys[target.index(keys.len())];
+            for key in [target, absent] {
+                let (root, proof) = root_with_proof(&leaves, key);
+                let nibbles = Nibbles::unpack(key);
+                let expected = leaves.get(&key).cloned();
+                let verify = |proof: &[Bytes], expected: Option<Vec<u8>>| {
+                    verify_proof(root, nibbles.clone(), expected, proof)
+                };
+
+                // The valid proof of the seed trie is accepted only with the proven value.
+                prop_assert_eq!(verify(&proof, expected.clone()), Ok(()));
+                let wrong = match expected {
+                    Some(_) => None,
+                    None => Some(alloy_rlp::encode(B256::ZERO)),
+                };
+                prop_assert!(verify(&proof, wrong).is_err());
+
+                let (node, other, position) = mutation;
+                let i = node.index(proof.len());
+
+                // The truncated node.
+                let mut truncated = proof.clone();
+                truncated[i] = Bytes::copy_from_slice(&proof[i][..position.index(proof[i].len())]);
+                prop_assert!(verify(&truncated, expected.clone()).is_err());
+
+                // The wrong child hash.
+                if let Some(start) = proof[i].iter().skip(1).position(|byte| *byte == 0xa0) {
+                    let start = start + 2;
+                    if start + 32 <= proof[i].len() {
+                        let mut node = proof[i].to_vec();
+                        let hash = keccak256(&node[start..start + 32]);
+                        node[start..start + 32].copy_from_slice(hash.as_slice());
+                        let mut corrupted = proof.clone();
+                        corrupted[i] = node.into();
+                        prop_assert!(verify(&corrupted, expected.clone()).is_err());
+                    }
+                }
+
+                // The reordered nodes.
+                let j = other.index(proof.len());
+                if proof[i] != proof[j] {
+                    let mut reordered = proof.clone();
+                    reordered.swap(i, j);
+                    prop_assert!(verify(&reordered, expected.clone()).is_err());
+                }
+
+                // The inclusion proof missing its leaf.
+                if expected.is_some() {
+                    prop_assert!(verify(&proof[..proof.len() - 1], expected.clone()).is_err());
+                }
+
+                // The node replaced by garbage.
+                let garbage = garbage.iter().cloned().map(Bytes::from).collect::<Vec<_>>();
+                if garbage[0] != proof[i] {
+                    let mut replaced = proof.clone();
+                    replaced[i] = garbage[0].clone();
+                    prop_assert!(verify(&replaced, expected.clone()).is_err());
+                }
+
+                // The appended and arbitrary nodes must not panic the verifier.
+                let _ = verify(&[proof.clone(), garbage.clone()].concat(), expected.clone());
+                let _ = verify(&garbage, expected);
+            }
+        });
+    }
 }
@@ -0,0 +1,410 @@
+use super::{
+    node::{NodeRef, TrieNode},
+    Proof,
+};
+use crate::{
+    hashed_cursor::{HashedCursorFactory, HashedStorageCursor},
+    node_iter::{TrieElement, TrieNodeIter},
+    prefix_set::PrefixSetMut,
+    walker::TrieWalker,
+};
+use alloy_rlp::{BufMut, Encodable};
+use rayon::{
+    prelude::{IntoParallelIterator, ParallelIterator},
+    ThreadPool,
+};
+use reth_db::{database::Database, transaction::DbTx};
+use reth_execution_errors::{StateRootError, StorageRootError};
+use reth_primitives::{
+    constants::EMPTY_ROOT_HASH,
+    keccak256,
+    trie::{proof::ProofRetainer, HashBuilder, Nibbles, TrieAccount},
+    Address, Bytes, GotExpected, B256,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The proof of multiple accounts of the account trie or multiple slots of a storage trie, sharing
+/// the nodes on the common parts of their paths.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct MultiProof {
+    /// The hashed addresses of the proven accounts or the hashed proven slots.
+    pub targets: Vec<B256>,
+    /// The proof nodes keyed by their path in the trie.
+    pub nodes: BTreeMap<Nibbles, Bytes>,
+}
+
+impl MultiProof {
+    /// Verifies that the nodes are sufficient and minimal to prove the inclusion or exclusion of
+    /// every target against the given root.
+    ///
+    /// The path of each target is walked from the root through the nodes, checking the hash of
+    /// every node against the reference of its parent, until the target is resolved to a leaf or
+    /// to the node at which its key diverges. The leaf values are not checked.
+    pub fn verify_complete(&self, root: B256) -> Result<(), MultiProofError> {
+        self.leaf_values(root).map(|_| ())
+    }
+
+    /// Verifies the proof like [`Self::verify_complete`] and returns the value of the leaf of
+    /// every target, or `None` for the targets proven to be absent.
+    pub fn leaf_values(
+        &self,
+        root: B256,
+    ) -> Result<BTreeMap<B256, Option<&[u8]>>, MultiProofError> {
+        let (values, used) = self.walk_targets(root, &self.targets)?;
+        match self.nodes.keys().find(|path| !used.contains(*path)) {
+            Some(path) => Err(MultiProofError::UnusedNode(path.clone())),
+            None => Ok(values),
+        }
+    }
+
+    /// Walks the paths of the given targets from the root through the nodes, returning the value
+    /// of the leaf of every target along with the paths of the nodes on the way, see
+    /// [`Self::leaf_values`]. The nodes off the paths are not checked.
+    pub(crate) fn walk_targets(
+        &self,
+        root: B256,
+        targets: &[B256],
+    ) -> Result<(BTreeMap<B256, Option<&[u8]>>, BTreeSet<Nibbles>), MultiProofError> {
+        let mut used = BTreeSet::new();
+        let mut values = BTreeMap::new();
+        for target in targets {
+            let key = Nibbles::unpack(target);
+            let mut path = Vec::with_capacity(key.len());
+            let mut node_ref = NodeRef::Hash(root);
+            let mut value = None;
+            loop {
+                let node_path = Nibbles::from_nibbles_unchecked(&path);
+                let rlp = match node_ref {
+                    // The empty trie has no nodes to prove the exclusion with.
+                    NodeRef::Hash(hash) if hash == EMPTY_ROOT_HASH && path.is_empty() => break,
+                    NodeRef::Hash(hash) => {
+                        let node = self.nodes.get(&node_path).ok_or_else(|| {
+                            MultiProofError::MissingNode {
+                                target: *target,
+                                path: node_path.clone(),
+                            }
+                        })?;
+                        let got = keccak256(node);
+                        if got != hash {
+                            return Err(MultiProofError::HashMismatch {
+                                path: node_path,
+                                hash: GotExpected::new(got, hash),
+                            })
+                        }
+                        node.as_ref()
+                    }
+                    NodeRef::Inline(rlp) => rlp,
+                };
+                used.insert(node_path);
+
+                match TrieNode::decode(rlp)? {
+                    TrieNode::Branch(children) => {
+                        let Some(child) = key.get(path.len()).and_then(|n| children[*n as usize])
+                        else {
+                            break
+                        };
+                        path.push(key[path.len()]);
+                        node_ref = child;
+                    }
+                    TrieNode::Extension { key: extension_key, child } => {
+                        if !key[path.len()..].starts_with(&extension_key) {
+                            break
+                        }
+                        path.extend_from_slice(&extension_key);
+                        node_ref = child;
+                    }
+                    TrieNode::Leaf { key: leaf_key, value: leaf_value } => {
+                        if key[path.len()..] == leaf_key[..] {
+                            value = Some(leaf_value);
+                        }
+                        break
+                    }
+                }
+            }
+            values.insert(*target, value);
+        }
+        Ok((values, used))
+    }
+}
+
+/// The error returned when a multiproof is not complete.
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum MultiProofError {
+    /// The node on the path of the target is missing.
+    #[error("proof node at {path:?} on the path of {target} is missing")]
+    MissingNode {
+        /// The hashed address of the target.
+        target: B256,
+        /// The path of the missing node.
+        path: Nibbles,
+    },
+    /// The hash of the node does not match the reference of its parent.
+    #[error("proof node at {path:?} hash mismatch: {hash}")]
+    HashMismatch {
+        /// The path of the node.
+        path: Nibbles,
+        /// The hash of the node and the hash referenced by its parent.
+        hash: GotExpected<B256>,
+    },
+    /// The node is not on the path of any target.
+    #[error("proof node at {0:?} is not on the path of any target")]
+    UnusedNode(Nibbles),
+    /// The proof node failed to decode.
+    #[error("failed to decode proof node: {0}")]
+    Decode(#[from] alloy_rlp::Error),
+}
+
+impl<'a, TX, H> Proof<'a, TX, H>
+where
+    TX: DbTx,
+    H: HashedCursorFactory + Clone,
+{
+    /// Generate a proof of all the given accounts.
+    ///
+    /// The hash builder retains the nodes along the paths of all targets in a single walk, so the
+    /// nodes shared by the paths are included only once.
+    pub fn multiproof(&self, targets: &[Address]) -> Result<MultiProof, StateRootError> {
+        let targets = targets.iter().map(keccak256).collect::<Vec<_>>();
+        let target_nibbles = targets.iter().map(Nibbles::unpack).collect::<Vec<_>>();
+
+        let hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let trie_cursor = self.account_trie_cursor()?;
+
+        // Create the walker descending along the paths of all targets.
+        let prefix_set = PrefixSetMut::from(target_nibbles.clone());
+        let walker = TrieWalker::new(trie_cursor, prefix_set.freeze());
+
+        let retainer = ProofRetainer::from_iter(target_nibbles);
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(retainer);
+
+        let mut account_rlp = Vec::with_capacity(128);
+        let mut account_node_iter = TrieNodeIter::new(walker, hashed_account_cursor);
+        while let Some(account_node) = account_node_iter.try_next()? {
+            match account_node {
+                TrieElement::Branch(node) => {
+                    hash_builder.add_branch(node.key, node.value, node.children_are_in_trie);
+                }
+                TrieElement::Leaf(hashed_address, account) => {
+                    let storage_root = self.storage_root(hashed_address)?;
+                    account_rlp.clear();
+                    let account = TrieAccount::from((account, storage_root));
+                    account.encode(&mut account_rlp as &mut dyn BufMut);
+                    hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp);
+                }
+            }
+        }
+
+        let _ = hash_builder.root();
+
+        Ok(MultiProof { targets, nodes: hash_builder.take_proofs() })
+    }
+
+    /// Generate a proof of all the given slots of the storage trie of the account.
+    ///
+    /// The storage trie counterpart of [`Self::multiproof`], see also
+    /// [`storage_multiproof_parallel`].
+    pub fn storage_multiproof(
+        &self,
+        hashed_address: B256,
+        slots: &[B256],
+    ) -> Result<MultiProof, StorageRootError> {
+        let targets = slots.iter().map(keccak256).collect::<Vec<_>>();
+        let nodes = self.storage_proof_nodes(hashed_address, &targets)?;
+        Ok(MultiProof { targets, nodes })
+    }
+
+    /// Returns the nodes of the storage trie of the account along the paths of the hashed slots.
+    fn storage_proof_nodes(
+        &self,
+        hashed_address: B256,
+        targets: &[B256],
+    ) -> Result<BTreeMap<Nibbles, Bytes>, StorageRootError> {
+        let mut hashed_storage_cursor =
+            self.hashed_cursor_factory.hashed_storage_cursor(hashed_address)?;
+
+        // The empty trie has no nodes to prove the exclusion with.
+        if hashed_storage_cursor.is_storage_empty()? {
+            return Ok(BTreeMap::new())
+        }
+
+        let target_nibbles = targets.iter().map(Nibbles::unpack).collect::<Vec<_>>();
+        let trie_cursor = self.storage_trie_cursor(hashed_address)?;
+        let prefix_set = PrefixSetMut::from(target_nibbles.clone());
+        let walker = TrieWalker::new(trie_cursor, prefix_set.freeze());
+
+        let retainer = ProofRetainer::from_iter(target_nibbles);
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(retainer);
+        let mut storage_node_iter = TrieNodeIter::new(walker, hashed_storage_cursor);
+        while let Some(node) = storage_node_iter.try_next()? {
+            match node {
+                TrieElement::Branch(node) => {
+                    hash_builder.add_branch(node.key, node.value, node.children_are_in_trie);
+                }
+                // The zero slots are not part of the trie, as in `StorageRoot`.
+                TrieElement::Leaf(_, value) if value.is_zero() => {}
+                TrieElement::Leaf(hashed_slot, value) => {
+                    hash_builder.add_leaf(
+                        Nibbles::unpack(hashed_slot),
+                        alloy_rlp::encode_fixed_size(&value).as_ref(),
+                    );
+                }
+            }
+        }
+
+        let _ = hash_builder.root();
+
+        Ok(hash_builder.take_proofs())
+    }
+}
+
+/// Generate a proof of all the given slots of the storage trie of the account, proving the slots
+/// under each child of the storage root concurrently on the given thread pool.
+///
+/// The hashed slots are partitioned by their first nibble and every partition is proven with a
+/// walk of its own that descends only into the subtrees of its slots, reading the database
+/// through a read-only transaction opened on the worker thread. The proofs share at most the
+/// nodes above the partitions, which are the same in all of them, so the merged nodes are the same
+/// as the ones of [`Proof::storage_multiproof`]. This pays off for contracts with large storage
+/// and many slots to prove, e.g. in RPC.
+///
+/// The transactions of the partitions are opened independently, so the database must not be
+/// written to concurrently for the proof to be consistent.
+pub fn storage_multiproof_parallel<DB: Database>(
+    db: &DB,
+    hashed_address: B256,
+    slots: &[B256],
+    pool: &ThreadPool,
+) -> Result<MultiProof, StorageRootError> {
+    let targets = slots.iter().map(keccak256).collect::<Vec<_>>();
+    let mut partitions = vec![Vec::new(); 16];
+    for target in &targets {
+        partitions[(target[0] >> 4) as usize].push(*target);
+    }
+    partitions.retain(|partition| !partition.is_empty());
+
+    let nodes = pool.install(|| {
+        partitions
+            .into_par_iter()
+            .map(|partition| {
+                let tx = db.tx()?;
+                Proof::new(&tx).storage_proof_nodes(hashed_address, &partition)
+            })
+            .try_reduce(BTreeMap::new, |mut nodes, partition_nodes| {
+                nodes.extend(partition_nodes);
+                Ok(nodes)
+            })
+    })?;
+
+    Ok(MultiProof { targets, nodes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::storage_root_prehashed, StateRoot};
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{Account, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn multiproof_verify_complete() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // The exclusion of any account from the empty trie needs no nodes.
+        let proof = Proof::new(tx).multiproof(&[Address::with_last_byte(1)]).unwrap();
+        assert!(proof.nodes.is_empty());
+        assert_eq!(proof.verify_complete(EMPTY_ROOT_HASH), Ok(()));
+
+        for i in 0..100u8 {
+            let account = Account { nonce: i as u64, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(Address::with_last_byte(i)), account)
+                .unwrap();
+        }
+        let (root, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // The existing and the missing accounts.
+        let targets = [3u8, 42, 99, 100, 200].map(Address::with_last_byte);
+        let proof = Proof::new(tx).multiproof(&targets).unwrap();
+        assert_eq!(proof.verify_complete(root), Ok(()));
+        assert!(matches!(
+            proof.verify_complete(B256::ZERO),
+            Err(MultiProofError::HashMismatch { .. })
+        ));
+
+        // Every node of the single account proofs is part of the multiproof.
+        for target in targets {
+            let account_proof = Proof::new(tx).account_proof(target, &[]).unwrap();
+            for node in account_proof.proof {
+                assert!(proof.nodes.values().any(|multiproof_node| *multiproof_node == node));
+            }
+        }
+
+        // The pruned node is detected.
+        let mut pruned = proof.clone();
+        let (path, _) = pruned.nodes.pop_last().unwrap();
+        assert!(matches!(
+            pruned.verify_complete(root),
+            Err(MultiProofError::MissingNode { path: missing, .. }) if missing == path
+        ));
+
+        // The node not needed by any target is detected.
+        let mut redundant = Proof::new(tx).multiproof(&targets[..1]).unwrap();
+        let (path, node) =
+            proof.nodes.iter().find(|(path, _)| !redundant.nodes.contains_key(*path)).unwrap();
+        redundant.nodes.insert(path.clone(), node.clone());
+        assert_eq!(redundant.verify_complete(root), Err(MultiProofError::UnusedNode(path.clone())));
+    }
+
+    #[test]
+    fn storage_multiproof_parallel_matches_serial() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed_address = keccak256(Address::with_last_byte(1));
+        tx.put::<tables::HashedAccounts>(hashed_address, Account::default()).unwrap();
+        let storage = (0..2_000u64)
+            .map(|slot| (keccak256(B256::from(U256::from(slot))), U256::from(slot + 1)))
+            .collect::<BTreeMap<_, _>>();
+        for (hashed_slot, value) in &storage {
+            tx.put::<tables::HashedStorages>(
+                hashed_address,
+                StorageEntry { key: *hashed_slot, value: *value },
+            )
+            .unwrap();
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+        provider.commit().unwrap();
+        let storage_root = storage_root_prehashed(storage);
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let provider = factory.provider().unwrap();
+        let proof = Proof::new(provider.tx_ref());
+
+        // The existing and the missing slots spread over many partitions.
+        let slots = (0..100u64)
+            .map(|slot| B256::from(U256::from(slot * 37)))
+            .chain([B256::with_last_byte(0xff); 2])
+            .collect::<Vec<_>>();
+        let serial = proof.storage_multiproof(hashed_address, &slots).unwrap();
+        let parallel =
+            storage_multiproof_parallel(factory.db_ref(), hashed_address, &slots, &pool).unwrap();
+        assert_eq!(parallel, serial);
+        assert_eq!(parallel.verify_complete(storage_root), Ok(()));
+
+        // The single partition and the account without storage.
+        let slots = &slots[..1];
+        assert_eq!(
+            storage_multiproof_parallel(factory.db_ref(), hashed_address, slots, &pool),
+            proof.storage_multiproof(hashed_address, slots)
+        );
+        let empty = keccak256(Address::with_last_byte(2));
+        let proof = storage_multiproof_parallel(factory.db_ref(), empty, slots, &pool).unwrap();
+        assert!(proof.nodes.is_empty());
+        assert_eq!(proof.verify_complete(EMPTY_ROOT_HASH), Ok(()));
+    }
+}
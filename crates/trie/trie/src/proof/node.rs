@@ -0,0 +1,128 @@
+use alloy_rlp::{Error, Header};
+use reth_primitives::{trie::Nibbles, B256};
+
+/// The reference to a child node as found in the encoding of its parent node.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum NodeRef<'a> {
+    /// The child node is referenced by the hash of its RLP encoding.
+    Hash(B256),
+    /// The child node is embedded, its RLP encoding is shorter than 32 bytes.
+    Inline(&'a [u8]),
+}
+
+/// The trie node decoded from its RLP encoding as it appears in a proof.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub(crate) enum TrieNode<'a> {
+    /// The branch node with the references to its children indexed by nibble.
+    Branch([Option<NodeRef<'a>>; 16]),
+    /// The extension node with the shared key and the reference to its child node.
+    Extension {
+        /// The key shared by all the leaves below the node.
+        key: Nibbles,
+        /// The reference to the child node.
+        child: NodeRef<'a>,
+    },
+    /// The leaf node with the remainder of the key and the leaf value.
+    Leaf {
+        /// The remainder of the key of the leaf.
+        key: Nibbles,
+        /// The value of the leaf.
+        value: &'a [u8],
+    },
+}
+
+impl<'a> TrieNode<'a> {
+    /// Decodes the trie node from its RLP encoding.
+    pub(crate) fn decode(rlp: &'a [u8]) -> Result<Self, Error> {
+        let items = list_items(rlp)?;
+        match items.len() {
+            17 => {
+                let mut children = [None; 16];
+                for (child, item) in children.iter_mut().zip(items) {
+                    *child = decode_ref(item)?;
+                }
+                Ok(Self::Branch(children))
+            }
+            2 => {
+                let (key, is_leaf) = decode_path(string_payload(items[0])?)?;
+                if is_leaf {
+                    Ok(Self::Leaf { key, value: string_payload(items[1])? })
+                } else if key.is_empty() {
+                    Err(Error::Custom("empty extension node key"))
+                } else {
+                    let child =
+                        decode_ref(items[1])?.ok_or(Error::Custom("missing extension child"))?;
+                    Ok(Self::Extension { key, child })
+                }
+            }
+            _ => Err(Error::Custom("invalid number of trie node items")),
+        }
+    }
+}
+
+/// Splits the RLP encoded list into the encodings of its items.
+fn list_items(mut buf: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    let header = Header::decode(&mut buf)?;
+    if !header.list {
+        return Err(Error::UnexpectedString)
+    }
+    if buf.len() != header.payload_length {
+        return Err(Error::UnexpectedLength)
+    }
+
+    let mut items = Vec::with_capacity(17);
+    while !buf.is_empty() {
+        let mut payload = buf;
+        let item_header = Header::decode(&mut payload)?;
+        let item_len = buf.len() - payload.len() + item_header.payload_length;
+        if item_len > buf.len() {
+            return Err(Error::InputTooShort)
+        }
+        let (item, rest) = buf.split_at(item_len);
+        items.push(item);
+        buf = rest;
+    }
+    Ok(items)
+}
+
+/// Returns the payload of the RLP encoded string.
+fn string_payload(mut item: &[u8]) -> Result<&[u8], Error> {
+    let header = Header::decode(&mut item)?;
+    if header.list {
+        return Err(Error::UnexpectedList)
+    }
+    Ok(item)
+}
+
+/// Decodes the reference to a child node, returning `None` for an empty slot.
+fn decode_ref(item: &[u8]) -> Result<Option<NodeRef<'_>>, Error> {
+    if Header::decode(&mut &item[..])?.list {
+        return Ok(Some(NodeRef::Inline(item)))
+    }
+    let payload = string_payload(item)?;
+    match payload.len() {
+        0 => Ok(None),
+        32 => Ok(Some(NodeRef::Hash(B256::from_slice(payload)))),
+        _ => Err(Error::UnexpectedLength),
+    }
+}
+
+/// Decodes the hex-prefix encoded path of a leaf or an extension node, returning the nibbles and
+/// whether the path belongs to a leaf node.
+fn decode_path(path: &[u8]) -> Result<(Nibbles, bool), Error> {
+    let Some((&first, rest)) = path.split_first() else { return Err(Error::InputTooShort) };
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(Error::Custom("invalid hex-prefix flag"))
+    }
+
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    for byte in rest {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0x0f);
+    }
+    Ok((Nibbles::from_nibbles_unchecked(nibbles), flag & 2 == 2))
+}
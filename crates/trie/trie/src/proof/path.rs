@@ -0,0 +1,256 @@
+use super::{
+    node::{NodeRef, TrieNode},
+    Proof,
+};
+use reth_db::transaction::DbTx;
+use reth_execution_errors::StateRootError;
+use reth_primitives::{keccak256, trie::Nibbles, Address, Bytes, B256};
+
+/// The kind of a trie node of a proof.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ProofNodeKind {
+    /// The branch node, the proven path continues at the child of the next nibble of the key.
+    Branch,
+    /// The extension node, the proven path continues below the key of the node.
+    Extension,
+    /// The leaf node, the proven path ends at the node.
+    Leaf,
+}
+
+/// The position of a trie node of a proof along the proven key.
+///
+/// The paths are plain nibble arrays, one nibble per byte, for the consumption outside of Rust.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ProofNodePosition {
+    /// The kind of the node.
+    pub kind: ProofNodeKind,
+    /// The nibbles of the path from the root to the node.
+    pub path: Vec<u8>,
+    /// The nibbles of the key stored in the extension or leaf node, empty for branch nodes.
+    pub key: Vec<u8>,
+}
+
+/// The reference to a child node of a [`DecodedNode`].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum DecodedChild {
+    /// The child node is referenced by the hash of its RLP encoding.
+    Hash(B256),
+    /// The RLP encoding of the embedded child node, shorter than 32 bytes.
+    Inline(Bytes),
+}
+
+impl From<NodeRef<'_>> for DecodedChild {
+    fn from(node_ref: NodeRef<'_>) -> Self {
+        match node_ref {
+            NodeRef::Hash(hash) => Self::Hash(hash),
+            NodeRef::Inline(rlp) => Self::Inline(Bytes::copy_from_slice(rlp)),
+        }
+    }
+}
+
+/// The trie node on the path to a key, decoded from its RLP encoding.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum DecodedNode {
+    /// The branch node.
+    Branch {
+        /// The path from the root to the node.
+        path: Nibbles,
+        /// The references to the children of the node, indexed by nibble.
+        children: [Option<DecodedChild>; 16],
+    },
+    /// The extension node.
+    Extension {
+        /// The path from the root to the node.
+        path: Nibbles,
+        /// The key shared by all the leaves below the node.
+        key: Nibbles,
+        /// The reference to the child node.
+        child: DecodedChild,
+    },
+    /// The leaf node.
+    Leaf {
+        /// The path from the root to the node.
+        path: Nibbles,
+        /// The remainder of the key of the leaf.
+        key: Nibbles,
+        /// The value of the leaf.
+        value: Bytes,
+    },
+}
+
+impl DecodedNode {
+    /// Returns the kind of the node.
+    pub const fn kind(&self) -> ProofNodeKind {
+        match self {
+            Self::Branch { .. } => ProofNodeKind::Branch,
+            Self::Extension { .. } => ProofNodeKind::Extension,
+            Self::Leaf { .. } => ProofNodeKind::Leaf,
+        }
+    }
+
+    /// Returns the path from the root to the node.
+    pub const fn path(&self) -> &Nibbles {
+        match self {
+            Self::Branch { path, .. } | Self::Extension { path, .. } | Self::Leaf { path, .. } => {
+                path
+            }
+        }
+    }
+
+    /// Returns the key stored in the extension or leaf node.
+    pub const fn key(&self) -> Option<&Nibbles> {
+        match self {
+            Self::Branch { .. } => None,
+            Self::Extension { key, .. } | Self::Leaf { key, .. } => Some(key),
+        }
+    }
+}
+
+/// The error returned when the path to a key cannot be decoded.
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum TriePathError {
+    /// The proof of the key could not be generated.
+    #[error(transparent)]
+    StateRoot(#[from] StateRootError),
+    /// The proof node failed to decode.
+    #[error("failed to decode proof node: {0}")]
+    Decode(#[from] alloy_rlp::Error),
+}
+
+/// Returns the decoded nodes of the account trie on the path from the root to the account.
+///
+/// If the account does not exist, the path ends with the node at which the key diverges: a branch
+/// without the child of the next nibble, an extension with a different key or a leaf of another
+/// account.
+pub fn path_to<TX: DbTx>(tx: &TX, address: Address) -> Result<Vec<DecodedNode>, TriePathError> {
+    let key = Nibbles::unpack(keccak256(address));
+    let proof = Proof::new(tx).account_proof(address, &[])?;
+    Ok(decode_proof_path(&key, &proof.proof)?)
+}
+
+/// Decodes the nodes of the proof of the key, in the order of the proof nodes.
+///
+/// The nodes are decoded from the proof alone, the proof is not verified. Proofs of absence end
+/// with the node whose path diverges from the key.
+///
+/// # Errors
+///
+/// If a proof node cannot be decoded or a node follows the node at which the key diverges from
+/// the proof path.
+pub fn decode_proof_path(
+    key: &Nibbles,
+    proof: &[Bytes],
+) -> Result<Vec<DecodedNode>, alloy_rlp::Error> {
+    let mut nodes = Vec::with_capacity(proof.len());
+    let mut path = Vec::with_capacity(key.len());
+    let mut diverged = false;
+    for encoded in proof {
+        if diverged {
+            return Err(alloy_rlp::Error::Custom("proof node below a diverging node"))
+        }
+
+        let node_path = Nibbles::from_nibbles_unchecked(&path);
+        let node = match TrieNode::decode(encoded)? {
+            TrieNode::Branch(children) => {
+                match key.get(path.len()) {
+                    Some(nibble) => path.push(*nibble),
+                    None => diverged = true,
+                }
+                DecodedNode::Branch {
+                    path: node_path,
+                    children: children.map(|child| child.map(DecodedChild::from)),
+                }
+            }
+            TrieNode::Extension { key: node_key, child } => {
+                diverged = !key.get(path.len()..).is_some_and(|rest| rest.starts_with(&node_key));
+                path.extend_from_slice(&node_key);
+                DecodedNode::Extension { path: node_path, key: node_key, child: child.into() }
+            }
+            TrieNode::Leaf { key: node_key, value } => {
+                diverged = true;
+                DecodedNode::Leaf {
+                    path: node_path,
+                    key: node_key,
+                    value: Bytes::copy_from_slice(value),
+                }
+            }
+        };
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+/// Returns the positions of the nodes of the proof along the proven key, in the order of the
+/// proof nodes.
+///
+/// See [`decode_proof_path`] for the decoded nodes themselves.
+///
+/// # Errors
+///
+/// If a proof node cannot be decoded or a node follows the node at which the key diverges from
+/// the proof path.
+pub fn proof_node_positions(
+    key: &Nibbles,
+    proof: &[Bytes],
+) -> Result<Vec<ProofNodePosition>, alloy_rlp::Error> {
+    let positions = decode_proof_path(key, proof)?
+        .into_iter()
+        .map(|node| ProofNodePosition {
+            kind: node.kind(),
+            path: node.path().to_vec(),
+            key: node.key().map(|key| key.to_vec()).unwrap_or_default(),
+        })
+        .collect();
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::hex_literal::hex;
+    use std::str::FromStr;
+
+    #[test]
+    fn account_proof_positions() {
+        // The proof of the `0x33f0fc440b8477fcfbe9d0bf8649e7dea9baedb2` account of the test
+        // genesis, see the proof tests.
+        let proof = [
+            "0xe48200a7a040f916999be583c572cc4dd369ec53b0a99f7de95f13880cf203d98f935ed1b3",
+            "0xf87180a04fb9bab4bb88c062f32452b7c94c8f64d07b5851d44a39f1e32ba4b1829fdbfb8080808080a0b61eeb2eb82808b73c4ad14140a2836689f4ab8445d69dd40554eaf1fce34bc080808080808080a0dea230ff2026e65de419288183a340125b04b8405cc61627b3b4137e2260a1e880",
+            "0xe48200d3a0ef957210bca5b9b402d614eb8408c88cfbf4913eb6ab83ca233c8b8f0e626b54",
+            "0xf851808080a02743a5addaf4cf9b8c0c073e1eaa555deaaf8c41cb2b41958e88624fa45c2d908080808080a0bfbf6937911dfb88113fecdaa6bde822e4e99dae62489fcf61a91cb2f36793d680808080808080",
+            "0xf8679e207781e762f3577784bab7491fcc43e291ce5a356b9bc517ac52eed3a37ab846f8448001a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a0c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+        ]
+        .map(|node| Bytes::from_str(node).unwrap());
+        let address = Address::from_str("0x33f0fc440b8477fcfbe9d0bf8649e7dea9baedb2").unwrap();
+        let key = Nibbles::unpack(keccak256(address));
+
+        let positions = proof_node_positions(&key, &proof).unwrap();
+        let summary = positions
+            .iter()
+            .map(|position| (position.kind, position.path.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (ProofNodeKind::Extension, vec![]),
+                (ProofNodeKind::Branch, vec![0xa, 0x7]),
+                (ProofNodeKind::Extension, vec![0xa, 0x7, 0x7]),
+                (ProofNodeKind::Branch, vec![0xa, 0x7, 0x7, 0xd, 0x3]),
+                (ProofNodeKind::Leaf, vec![0xa, 0x7, 0x7, 0xd, 0x3, 0x3]),
+            ]
+        );
+        assert_eq!(positions[0].key, vec![0xa, 0x7]);
+        assert_eq!(positions[2].key, vec![0xd, 0x3]);
+        assert!(positions[1].key.is_empty());
+
+        // The leaf key completes the path to the full key.
+        let leaf = positions.last().unwrap();
+        assert_eq!([leaf.path.clone(), leaf.key.clone()].concat(), key.to_vec());
+
+        // No node can follow the leaf.
+        let mut extended = proof.to_vec();
+        extended.push(Bytes::from(hex!("c58320616263").to_vec()));
+        assert!(proof_node_positions(&key, &extended).is_err());
+    }
+}
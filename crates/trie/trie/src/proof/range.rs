@@ -0,0 +1,546 @@
+use super::{
+    node::{NodeRef, TrieNode},
+    Proof,
+};
+use crate::{
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    node_iter::{TrieElement, TrieNodeIter},
+    prefix_set::PrefixSetMut,
+    trie_cursor::DatabaseAccountTrieCursor,
+    walker::TrieWalker,
+};
+use alloy_rlp::{BufMut, Encodable};
+use reth_db::{tables, transaction::DbTx};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{
+    constants::EMPTY_ROOT_HASH,
+    keccak256,
+    trie::{proof::ProofRetainer, HashBuilder, Nibbles, TrieAccount},
+    Bytes, GotExpected, B256,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// The proof of all leaves of the account trie within a range of hashed keys.
+///
+/// The leaves together with the proofs of both ends of the range are sufficient to confirm
+/// against the state root that no leaf within the range was omitted.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct RangeProof {
+    /// The hashed addresses and the RLP encoded accounts of the leaves within the range in
+    /// ascending order.
+    pub leaves: Vec<(B256, Bytes)>,
+    /// The proof of inclusion or exclusion of the start of the range.
+    pub start_proof: Vec<Bytes>,
+    /// The proof of inclusion or exclusion of the end of the range.
+    pub end_proof: Vec<Bytes>,
+}
+
+impl RangeProof {
+    /// Verifies that the leaves are exactly the leaves within `[start, end]` of the trie with the
+    /// given root.
+    pub fn verify(&self, root: B256, start: B256, end: B256) -> Result<(), RangeProofError> {
+        if start > end {
+            return Err(RangeProofError::InvalidRange)
+        }
+        let leaves = self.leaves.iter().map(|(key, value)| (*key, value.as_ref()));
+        let proof = self.start_proof.iter().chain(&self.end_proof);
+        verify_range(root, start, Some(end), leaves, proof).map(|_| ())
+    }
+}
+
+/// The error returned when a range proof cannot be verified.
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum RangeProofError {
+    /// The start of the range is greater than its end.
+    #[error("start of the range is greater than its end")]
+    InvalidRange,
+    /// The number of keys does not match the number of values.
+    #[error("number of keys {keys} does not match number of values {values}")]
+    LengthMismatch {
+        /// The number of keys.
+        keys: usize,
+        /// The number of values.
+        values: usize,
+    },
+    /// The leaf keys are not strictly increasing.
+    #[error("leaf keys are not strictly increasing")]
+    UnorderedLeaves,
+    /// The value of the leaf is empty.
+    #[error("leaf {0} has an empty value")]
+    EmptyValue(B256),
+    /// The leaf is outside of the range.
+    #[error("leaf {0} is outside of the range")]
+    LeafOutOfRange(B256),
+    /// The leaf is placed below a subtree outside of the range.
+    #[error("leaf {0} overlaps a subtree outside of the range")]
+    OverlappingLeaf(B256),
+    /// The node on the path of an edge of the range is missing from the proof.
+    #[error("proof node {0} is missing")]
+    MissingNode(B256),
+    /// The proof node failed to decode.
+    #[error("failed to decode proof node: {0}")]
+    Decode(#[from] alloy_rlp::Error),
+    /// The subtree outside of the range is an embedded node other than a leaf.
+    #[error("unsupported embedded node outside of the range")]
+    UnsupportedEmbeddedNode,
+    /// The root computed from the leaves and the proofs does not match the expected one.
+    #[error("range proof root mismatch: {0}")]
+    RootMismatch(GotExpected<B256>),
+}
+
+/// Verifies that the keys and values are exactly the leaves within `[start, last_key]` of the trie
+/// with the given root and returns whether the trie has any keys beyond the last key.
+///
+/// The edge proofs are the proof nodes of the start and of the last key of the range in any order,
+/// as served in snap sync responses. If no keys are supplied, the edge proofs have to prove that no
+/// keys exist from the start onwards. If no edge proofs are supplied, the keys have to make up the
+/// whole trie.
+pub fn verify_range_proof<V: AsRef<[u8]>>(
+    root: B256,
+    start: B256,
+    keys: &[B256],
+    values: &[V],
+    edge_proofs: &[Bytes],
+) -> Result<bool, RangeProofError> {
+    if keys.len() != values.len() {
+        return Err(RangeProofError::LengthMismatch { keys: keys.len(), values: values.len() })
+    }
+    let leaves = keys.iter().copied().zip(values.iter().map(AsRef::as_ref));
+    verify_range(root, start, keys.last().copied(), leaves, edge_proofs)
+}
+
+/// Generate a proof of all account leaves within `[start, end]`.
+///
+/// See [`Proof::account_range_proof`] for more info.
+pub fn range_proof<TX: DbTx>(
+    tx: &TX,
+    start: B256,
+    end: B256,
+) -> Result<RangeProof, StateRootError> {
+    Proof::new(tx).account_range_proof(start, end)
+}
+
+impl<'a, TX, H> Proof<'a, TX, H>
+where
+    TX: DbTx,
+    H: HashedCursorFactory + Clone,
+{
+    /// Generate a proof of all account leaves within `[start, end]`.
+    ///
+    /// The leaves are read from the hashed state, while the proofs of the ends of the range are
+    /// retained by the hash builder walking the paths of both keys. If no leaf lies within the
+    /// range, the edge proofs prove the exclusion of the keys.
+    pub fn account_range_proof(
+        &self,
+        start: B256,
+        end: B256,
+    ) -> Result<RangeProof, StateRootError> {
+        let start_nibbles = Nibbles::unpack(start);
+        let end_nibbles = Nibbles::unpack(end);
+
+        let mut leaves = Vec::new();
+        let mut hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let mut entry = hashed_account_cursor.seek(start)?;
+        while let Some((hashed_address, account)) = entry.filter(|(key, _)| *key <= end) {
+            let account = TrieAccount::from((account, self.storage_root(hashed_address)?));
+            leaves.push((hashed_address, Bytes::from(alloy_rlp::encode(account))));
+            entry = hashed_account_cursor.next()?;
+        }
+
+        let hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let trie_cursor =
+            DatabaseAccountTrieCursor::new(self.tx.cursor_read::<tables::AccountsTrie>()?);
+
+        // Create the walker descending along the paths of both ends of the range.
+        let prefix_set = PrefixSetMut::from([start_nibbles.clone(), end_nibbles.clone()]);
+        let walker = TrieWalker::new(trie_cursor, prefix_set.freeze());
+
+        let retainer = ProofRetainer::from_iter([start_nibbles.clone(), end_nibbles.clone()]);
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(retainer);
+
+        let mut account_rlp = Vec::with_capacity(128);
+        let mut account_node_iter = TrieNodeIter::new(walker, hashed_account_cursor);
+        while let Some(account_node) = account_node_iter.try_next()? {
+            match account_node {
+                TrieElement::Branch(node) => {
+                    hash_builder.add_branch(node.key, node.value, node.children_are_in_trie);
+                }
+                TrieElement::Leaf(hashed_address, account) => {
+                    let storage_root = self.storage_root(hashed_address)?;
+                    account_rlp.clear();
+                    let account = TrieAccount::from((account, storage_root));
+                    account.encode(&mut account_rlp as &mut dyn BufMut);
+                    hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp);
+                }
+            }
+        }
+
+        let _ = hash_builder.root();
+
+        let proofs = hash_builder.take_proofs();
+        let edge_proof = |target: &Nibbles| {
+            proofs
+                .iter()
+                .filter(|(path, _)| target.starts_with(path))
+                .map(|(_, node)| node.clone())
+                .collect::<Vec<_>>()
+        };
+
+        Ok(RangeProof {
+            leaves,
+            start_proof: edge_proof(&start_nibbles),
+            end_proof: edge_proof(&end_nibbles),
+        })
+    }
+}
+
+/// Verifies that the leaves are exactly the leaves within the range of the trie with the given
+/// root and returns whether the trie has any leaves beyond the end of the range.
+///
+/// The proof nodes along the paths of both ends of the range are walked from the root to collect
+/// the subtrees that lie entirely outside of the range. The root is then recomputed from the hashes
+/// of these subtrees and the supplied leaves, so it can only match if the leaves within the range
+/// are complete. If `end` is `None`, the range is unbounded. If no proof nodes are supplied, the
+/// leaves are expected to make up the whole trie.
+fn verify_range<'a>(
+    root: B256,
+    start: B256,
+    end: Option<B256>,
+    leaves: impl IntoIterator<Item = (B256, &'a [u8])>,
+    proof: impl IntoIterator<Item = &'a Bytes>,
+) -> Result<bool, RangeProofError> {
+    let nodes: HashMap<_, _> =
+        proof.into_iter().map(|node| (keccak256(node), node.as_ref())).collect();
+    let start_nibbles = Nibbles::unpack(start);
+    let end_nibbles = end.map(Nibbles::unpack);
+
+    // Collect the subtrees outside of the range keyed by their position in the trie.
+    let mut has_more = false;
+    let mut outside = BTreeMap::new();
+    let edges = std::iter::once(&start_nibbles).chain(end_nibbles.as_ref());
+    for edge in edges.filter(|_| !nodes.is_empty()) {
+        for (position, path, node) in edge_subtrees(root, edge, &nodes)? {
+            let is_right = end_nibbles.as_ref().is_some_and(|end| is_right_of(&path, end));
+            if is_left_of(&path, &start_nibbles) || is_right {
+                has_more |= is_right;
+                outside.insert(position, node);
+            }
+        }
+    }
+
+    let mut entries = Vec::with_capacity(outside.len());
+    let mut last_key = None;
+    for (key, value) in leaves {
+        if last_key.is_some_and(|last| last >= key) {
+            return Err(RangeProofError::UnorderedLeaves)
+        }
+        if key < start || end.is_some_and(|end| key > end) {
+            return Err(RangeProofError::LeafOutOfRange(key))
+        }
+        if value.is_empty() {
+            return Err(RangeProofError::EmptyValue(key))
+        }
+        last_key = Some(key);
+        entries.push((Nibbles::unpack(key), Some(key), RangeEntry::Leaf(value)));
+    }
+    entries.extend(
+        outside.into_iter().map(|(position, node)| (position, None, RangeEntry::Subtree(node))),
+    );
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    // A leaf may not be placed below any of the subtrees.
+    for pair in entries.windows(2) {
+        if let (Some(key), true) = (pair[1].1, pair[1].0.starts_with(&pair[0].0)) {
+            return Err(RangeProofError::OverlappingLeaf(key))
+        }
+    }
+
+    let computed = match entries.as_slice() {
+        // The root node itself lies outside of the range.
+        [(position, None, RangeEntry::Subtree(NodeRef::Hash(hash)))] if position.is_empty() => {
+            *hash
+        }
+        _ => {
+            let mut hash_builder = HashBuilder::default();
+            for (position, _, entry) in entries {
+                match entry {
+                    RangeEntry::Leaf(value) => hash_builder.add_leaf(position, value),
+                    RangeEntry::Subtree(NodeRef::Hash(hash)) => {
+                        hash_builder.add_branch(position, hash, false)
+                    }
+                    RangeEntry::Subtree(NodeRef::Inline(rlp)) => match TrieNode::decode(rlp)? {
+                        TrieNode::Leaf { key, value } => {
+                            let mut path = position.to_vec();
+                            path.extend_from_slice(&key);
+                            hash_builder.add_leaf(Nibbles::from_nibbles_unchecked(path), value)
+                        }
+                        _ => return Err(RangeProofError::UnsupportedEmbeddedNode),
+                    },
+                }
+            }
+            hash_builder.root()
+        }
+    };
+
+    if computed != root {
+        return Err(RangeProofError::RootMismatch(GotExpected::new(computed, root)))
+    }
+    Ok(has_more)
+}
+
+/// The entry the root of a range is recomputed from.
+#[derive(Debug)]
+enum RangeEntry<'a> {
+    /// The leaf within the range with its value.
+    Leaf(&'a [u8]),
+    /// The subtree outside of the range.
+    Subtree(NodeRef<'a>),
+}
+
+/// Walks the path of the key from the root through the proof nodes and returns the subtrees
+/// branching off the path. Each subtree is returned with its position in the trie, the path it
+/// is ordered by against the key and the reference to its root node.
+///
+/// The path of a branch child is its position, while the path of the leaf or extension node the
+/// key diverges from is extended by the key of the node.
+fn edge_subtrees<'a>(
+    root: B256,
+    key: &Nibbles,
+    nodes: &HashMap<B256, &'a [u8]>,
+) -> Result<Vec<(Nibbles, Nibbles, NodeRef<'a>)>, RangeProofError> {
+    let mut subtrees = Vec::new();
+    if root == EMPTY_ROOT_HASH {
+        return Ok(subtrees)
+    }
+
+    let mut position = Vec::with_capacity(key.len());
+    let mut node_ref = NodeRef::Hash(root);
+    loop {
+        let rlp = match node_ref {
+            NodeRef::Hash(hash) => *nodes.get(&hash).ok_or(RangeProofError::MissingNode(hash))?,
+            NodeRef::Inline(rlp) => rlp,
+        };
+        match TrieNode::decode(rlp)? {
+            TrieNode::Branch(children) => {
+                let next = key.get(position.len()).copied();
+                for (nibble, child) in (0u8..).zip(children) {
+                    if let Some(child) = child.filter(|_| Some(nibble) != next) {
+                        let mut child_position = position.clone();
+                        child_position.push(nibble);
+                        let child_position = Nibbles::from_nibbles_unchecked(child_position);
+                        subtrees.push((child_position.clone(), child_position, child));
+                    }
+                }
+
+                let Some((nibble, child)) =
+                    next.and_then(|nibble| Some((nibble, children[nibble as usize]?)))
+                else {
+                    break
+                };
+                position.push(nibble);
+                node_ref = child;
+            }
+            TrieNode::Extension { key: extension_key, child } => {
+                if key.get(position.len()..).is_some_and(|rest| rest.starts_with(&extension_key)) {
+                    position.extend_from_slice(&extension_key);
+                    node_ref = child;
+                    continue
+                }
+                let mut path = position.clone();
+                path.extend_from_slice(&extension_key);
+                subtrees.push((
+                    Nibbles::from_nibbles_unchecked(position),
+                    Nibbles::from_nibbles_unchecked(path),
+                    node_ref,
+                ));
+                break
+            }
+            TrieNode::Leaf { key: leaf_key, .. } => {
+                let mut path = position.clone();
+                path.extend_from_slice(&leaf_key);
+                subtrees.push((
+                    Nibbles::from_nibbles_unchecked(position),
+                    Nibbles::from_nibbles_unchecked(path),
+                    node_ref,
+                ));
+                break
+            }
+        }
+    }
+    Ok(subtrees)
+}
+
+/// Returns `true` if all keys with the given path are less than the key.
+fn is_left_of(path: &[u8], key: &[u8]) -> bool {
+    path < &key[..path.len().min(key.len())]
+}
+
+/// Returns `true` if all keys with the given path are greater than the key.
+fn is_right_of(path: &[u8], key: &[u8]) -> bool {
+    path > &key[..path.len().min(key.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StateRoot;
+    use proptest::{
+        collection::btree_map,
+        prelude::{any, prop_assert, prop_assert_eq, ProptestConfig, Strategy},
+        proptest,
+        sample::Index,
+    };
+    use reth_db::transaction::DbTxMut;
+    use reth_primitives::{Account, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    /// Computes the root of the trie with the given leaves and retains the proofs of the targets.
+    fn root_with_proofs(
+        leaves: &BTreeMap<B256, Vec<u8>>,
+        targets: impl IntoIterator<Item = B256>,
+    ) -> (B256, Vec<Bytes>) {
+        let retainer = ProofRetainer::from_iter(targets.into_iter().map(Nibbles::unpack));
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(retainer);
+        for (key, value) in leaves {
+            hash_builder.add_leaf(Nibbles::unpack(key), value);
+        }
+        let root = hash_builder.root();
+        (root, hash_builder.take_proofs().into_values().collect())
+    }
+
+    #[test]
+    fn account_range_proof() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // The range proof of the empty trie proves the absence of any leaves.
+        let proof = range_proof(tx, B256::ZERO, B256::repeat_byte(0xff)).unwrap();
+        assert!(proof.leaves.is_empty());
+        assert_eq!(proof.verify(EMPTY_ROOT_HASH, B256::ZERO, B256::repeat_byte(0xff)), Ok(()));
+
+        let mut keys = (0..1_000u64)
+            .map(|i| {
+                let hashed_address = keccak256(B256::from(U256::from(i)));
+                let account = Account { nonce: i, ..Default::default() };
+                tx.put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+                hashed_address
+            })
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        let (root, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // The range starting and ending between the keys.
+        let start = B256::from(U256::from_be_bytes(keys[100].0) + U256::from(1));
+        let end = B256::from(U256::from_be_bytes(keys[200].0) - U256::from(1));
+        let proof = range_proof(tx, start, end).unwrap();
+        assert_eq!(
+            proof.leaves.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            keys[101..200].to_vec()
+        );
+        assert_eq!(proof.verify(root, start, end), Ok(()));
+        assert!(proof.verify(root, start, keys[200]).is_err());
+
+        // Omitting or altering any of the leaves is detected.
+        let mut omitted = proof.clone();
+        omitted.leaves.remove(50);
+        assert!(matches!(omitted.verify(root, start, end), Err(RangeProofError::RootMismatch(_))));
+        let mut altered = proof.clone();
+        altered.leaves[0].1 = Bytes::from(alloy_rlp::encode(TrieAccount::default()));
+        assert!(matches!(altered.verify(root, start, end), Err(RangeProofError::RootMismatch(_))));
+
+        // The single element range.
+        let proof = range_proof(tx, keys[500], keys[500]).unwrap();
+        assert_eq!(proof.leaves.len(), 1);
+        assert_eq!(proof.start_proof, proof.end_proof);
+        assert_eq!(proof.verify(root, keys[500], keys[500]), Ok(()));
+
+        // The empty range between two adjacent keys.
+        let start = B256::from(U256::from_be_bytes(keys[500].0) + U256::from(1));
+        let proof = range_proof(tx, start, start).unwrap();
+        assert!(proof.leaves.is_empty());
+        assert_eq!(proof.verify(root, start, start), Ok(()));
+
+        // The range covering the whole trie.
+        let proof = range_proof(tx, B256::ZERO, B256::repeat_byte(0xff)).unwrap();
+        assert_eq!(proof.leaves.len(), keys.len());
+        assert_eq!(proof.verify(root, B256::ZERO, B256::repeat_byte(0xff)), Ok(()));
+    }
+
+    #[test]
+    fn verify_range_proof_edge_cases() {
+        let leaves = (0..100u64)
+            .map(|i| (keccak256(B256::from(U256::from(i))), alloy_rlp::encode(U256::from(i + 1))))
+            .collect::<BTreeMap<_, _>>();
+        let keys = leaves.keys().copied().collect::<Vec<_>>();
+        let values = leaves.values().cloned().collect::<Vec<_>>();
+
+        // The range of the whole trie needs no edge proofs.
+        let (root, _) = root_with_proofs(&leaves, []);
+        assert_eq!(verify_range_proof(root, B256::ZERO, &keys, &values, &[]), Ok(false));
+        assert!(verify_range_proof(root, B256::ZERO, &keys[1..], &values[1..], &[]).is_err());
+
+        // The exclusion proof of the start beyond the last key proves the absence of any keys.
+        let start = B256::from(U256::from_be_bytes(keys[99].0) + U256::from(1));
+        let (root, proof) = root_with_proofs(&leaves, [start]);
+        assert_eq!(verify_range_proof::<Vec<u8>>(root, start, &[], &[], &proof), Ok(false));
+        let (root, proof) = root_with_proofs(&leaves, [keys[50]]);
+        assert!(verify_range_proof::<Vec<u8>>(root, keys[50], &[], &[], &proof).is_err());
+
+        // The range in the middle of the trie reports the keys beyond it.
+        let (root, proof) = root_with_proofs(&leaves, [keys[0], keys[10]]);
+        assert_eq!(
+            verify_range_proof(root, keys[0], &keys[..=10], &values[..=10], &proof),
+            Ok(true)
+        );
+        assert_eq!(
+            verify_range_proof(root, keys[0], &keys[..=10], &values[..10], &proof),
+            Err(RangeProofError::LengthMismatch { keys: 11, values: 10 })
+        );
+    }
+
+    #[test]
+    fn arbitrary_range_proofs() {
+        proptest!(ProptestConfig::with_cases(100), |(
+            leaves in btree_map(
+                any::<B256>(),
+                any::<U256>()
+                    .prop_filter("non zero value", |value| !value.is_zero())
+                    .prop_map(alloy_rlp::encode),
+                1..200,
+            ),
+            bounds in any::<(Index, Index)>(),
+        )| {
+            let keys = leaves.keys().copied().collect::<Vec<_>>();
+            let values = leaves.values().cloned().collect::<Vec<_>>();
+            let (first, last) = {
+                let (a, b) = (bounds.0.index(keys.len()), bounds.1.index(keys.len()));
+                (a.min(b), a.max(b))
+            };
+
+            let (root, proof) = root_with_proofs(&leaves, [keys[first], keys[last]]);
+            let range = first..=last;
+            prop_assert_eq!(
+                verify_range_proof(root, keys[first], &keys[range.clone()], &values[range], &proof),
+                Ok(last + 1 < keys.len())
+            );
+
+            // Omitting a leaf from the middle of the range is detected.
+            if last - first >= 2 {
+                let mut range_keys = keys[first..=last].to_vec();
+                let mut range_values = values[first..=last].to_vec();
+                range_keys.remove(1);
+                range_values.remove(1);
+                prop_assert!(verify_range_proof(
+                    root,
+                    keys[first],
+                    &range_keys,
+                    &range_values,
+                    &proof
+                )
+                .is_err());
+            }
+        });
+    }
+}
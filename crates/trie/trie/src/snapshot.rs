@@ -0,0 +1,113 @@
+use crate::{proof::Proof, StateRoot, StorageRoot};
+use reth_db::{database::Database, transaction::DbTx, DatabaseError};
+use reth_primitives::Address;
+
+/// A stable read view of the database for the computation of roots and proofs.
+///
+/// The snapshot holds a read-only database transaction. MDBX read transactions are isolated at
+/// the start of the transaction, so every root and proof computed from the snapshot sees the
+/// hashed state and the trie as of the moment the snapshot was taken, regardless of the blocks
+/// committed by the writer in the meantime. The snapshots can be shared between threads and read
+/// concurrently with the writer, none of them blocks the others.
+///
+/// The pages of the database referenced by an open snapshot cannot be reused by the writer, so
+/// the database grows as long as the snapshot is held. The snapshots are meant to be short-lived,
+/// e.g. one per RPC request, and are closed on drop.
+#[derive(Debug)]
+pub struct SnapshotFactory<TX> {
+    /// The read-only transaction pinning the view of the database.
+    tx: TX,
+}
+
+impl<TX: DbTx> SnapshotFactory<TX> {
+    /// Takes a snapshot of the current state of the database.
+    pub fn new<DB: Database<TX = TX>>(db: &DB) -> Result<Self, DatabaseError> {
+        Ok(Self { tx: db.tx()? })
+    }
+
+    /// Disables the timeout and the backtrace recording of the long-lived read transactions for
+    /// the snapshots expected to be held for a long time.
+    pub fn with_long_read_transaction_safety_disabled(mut self) -> Self {
+        self.tx.disable_long_read_transaction_safety();
+        self
+    }
+
+    /// Returns the transaction of the snapshot, which is both the trie and the hashed cursor
+    /// factory for the custom computations.
+    pub const fn tx(&self) -> &TX {
+        &self.tx
+    }
+
+    /// Returns the state root calculator over the snapshot.
+    pub fn state_root(&self) -> StateRoot<&TX, &TX> {
+        StateRoot::from_tx(&self.tx)
+    }
+
+    /// Returns the storage root calculator of the account with the given raw address over the
+    /// snapshot.
+    pub fn storage_root(&self, address: Address) -> StorageRoot<&TX, &TX> {
+        StorageRoot::from_tx(&self.tx, address)
+    }
+
+    /// Returns the proof generator over the snapshot.
+    pub fn proof(&self) -> Proof<'_, TX, &TX> {
+        Proof::new(&self.tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{state_root, storage_root};
+    use reth_db::{tables, test_utils::create_test_rw_db, transaction::DbTxMut};
+    use reth_primitives::{keccak256, Account, StorageEntry, B256, U256};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn snapshot_reflects_state_before_commit() {
+        let db = create_test_rw_db();
+        let address = Address::with_last_byte(1);
+        let account = Account { nonce: 1, ..Default::default() };
+        let storage = (0..100u64)
+            .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + 1)))
+            .collect::<BTreeMap<_, _>>();
+        let updated =
+            storage.keys().map(|slot| (*slot, U256::from(1_000))).collect::<BTreeMap<_, _>>();
+        let write = |storage: &BTreeMap<B256, U256>| {
+            db.update(|tx| {
+                tx.put::<tables::HashedAccounts>(keccak256(address), account).unwrap();
+                tx.delete::<tables::HashedStorages>(keccak256(address), None).unwrap();
+                for (slot, value) in storage {
+                    let entry = StorageEntry { key: keccak256(slot), value: *value };
+                    tx.put::<tables::HashedStorages>(keccak256(address), entry).unwrap();
+                }
+            })
+            .unwrap();
+        };
+        write(&storage);
+
+        // The new block is committed while the storage root of the snapshot is being computed.
+        let snapshot = SnapshotFactory::new(&db).unwrap();
+        let mut walked = 0;
+        let root = snapshot
+            .storage_root(address)
+            .on_slot(|_, _| {
+                if walked == 0 {
+                    std::thread::scope(|scope| scope.spawn(|| write(&updated)).join()).unwrap();
+                }
+                walked += 1;
+            })
+            .root()
+            .unwrap();
+        assert_eq!(walked, storage.len());
+        assert_eq!(root, storage_root(storage.clone()));
+        assert_eq!(
+            snapshot.state_root().root().unwrap(),
+            state_root([(address, (account, storage.clone()))])
+        );
+
+        // A new snapshot sees the committed block.
+        let snapshot = SnapshotFactory::new(&db).unwrap();
+        assert_eq!(snapshot.storage_root(address).root().unwrap(), storage_root(updated));
+    }
+}
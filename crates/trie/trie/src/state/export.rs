@@ -0,0 +1,324 @@
+use crate::{
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    prefix_set::TriePrefixSets,
+    StateRoot,
+};
+use reth_db::{
+    cursor::DbCursorRW,
+    tables,
+    transaction::{DbTx, DbTxMut},
+    DatabaseError,
+};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{Account, GotExpected, StorageEntry, B256, U256};
+use std::io::{Read, Write};
+
+/// The magic bytes at the start of a hashed state dump.
+pub const HASHED_STATE_MAGIC: [u8; 4] = *b"rhst";
+
+/// The version of the hashed state dump format written by [`export_hashed_state`].
+pub const HASHED_STATE_VERSION: u8 = 1;
+
+/// The record terminating the dump, followed by the number of accounts and storage slots.
+const END_RECORD: u8 = 0;
+/// The account record, followed by the hashed address and the account.
+const ACCOUNT_RECORD: u8 = 1;
+/// The storage slot record of the preceding account, followed by the hashed slot and the value.
+const STORAGE_RECORD: u8 = 2;
+
+/// The number of entries in a hashed state dump.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct HashedStateCounts {
+    /// The number of accounts.
+    pub accounts: u64,
+    /// The number of storage slots of all accounts.
+    pub storage_slots: u64,
+}
+
+/// The error returned when the hashed state cannot be exported or imported.
+#[derive(thiserror::Error, Debug)]
+pub enum HashedStateDumpError {
+    /// The hashed state could not be read or written.
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+    /// The dump could not be read or written.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The dump does not start with [`HASHED_STATE_MAGIC`].
+    #[error("not a hashed state dump")]
+    InvalidMagic,
+    /// The dump was written in a version of the format that is not supported.
+    #[error("unsupported hashed state dump version {0}")]
+    UnsupportedVersion(u8),
+    /// The dump is not a valid encoding of a hashed state.
+    #[error("malformed hashed state dump: {0}")]
+    Malformed(&'static str),
+    /// The state root of the imported state could not be computed.
+    #[error(transparent)]
+    StateRoot(#[from] StateRootError),
+    /// The state root of the imported state does not match the expected one.
+    #[error("imported state root mismatch: {0}")]
+    RootMismatch(GotExpected<B256>),
+}
+
+/// Streams all accounts and their storage slots of the hashed state to the writer.
+///
+/// The dump captures the plain key-value state, not the trie nodes, so it can be imported into a
+/// fresh database with [`import_hashed_state`] and the trie rebuilt from it. The entries are
+/// written in the order of the hashed addresses and the hashed slots as they are read from the
+/// cursors, so the memory usage does not depend on the size of the state. The writer is not
+/// buffered nor flushed.
+///
+/// The format starts with [`HASHED_STATE_MAGIC`] and [`HASHED_STATE_VERSION`], followed by an
+/// account record for every account, each followed by the storage slot records of the account,
+/// and ends with the number of accounts and storage slots written.
+pub fn export_hashed_state<H: HashedCursorFactory>(
+    factory: H,
+    writer: &mut impl Write,
+) -> Result<HashedStateCounts, HashedStateDumpError> {
+    writer.write_all(&HASHED_STATE_MAGIC)?;
+    writer.write_all(&[HASHED_STATE_VERSION])?;
+
+    let mut counts = HashedStateCounts::default();
+    let mut account_cursor = factory.hashed_account_cursor()?;
+    let mut next_account = account_cursor.seek(B256::ZERO)?;
+    while let Some((hashed_address, account)) = next_account {
+        writer.write_all(&[ACCOUNT_RECORD])?;
+        writer.write_all(hashed_address.as_slice())?;
+        write_account(writer, &account)?;
+        counts.accounts += 1;
+
+        let mut storage_cursor = factory.hashed_storage_cursor(hashed_address)?;
+        let mut next_slot = storage_cursor.seek(B256::ZERO)?;
+        while let Some((hashed_slot, value)) = next_slot {
+            writer.write_all(&[STORAGE_RECORD])?;
+            writer.write_all(hashed_slot.as_slice())?;
+            writer.write_all(&value.to_be_bytes::<32>())?;
+            counts.storage_slots += 1;
+            next_slot = storage_cursor.next()?;
+        }
+
+        next_account = account_cursor.next()?;
+    }
+
+    writer.write_all(&[END_RECORD])?;
+    writer.write_all(&counts.accounts.to_be_bytes())?;
+    writer.write_all(&counts.storage_slots.to_be_bytes())?;
+    Ok(counts)
+}
+
+/// Imports the hashed state dump written by [`export_hashed_state`] into the hashed state tables.
+///
+/// The tables are expected to be empty, the accounts are appended in the order of the dump. The
+/// trie tables are not touched, the trie has to be rebuilt from the imported state, e.g. by
+/// computing the state root with [`StateRoot`](crate::StateRoot) and writing the trie updates.
+/// The dump is validated as it is read and the import fails on the first malformed record, in
+/// which case the transaction should not be committed.
+pub fn import_hashed_state<TX: DbTx + DbTxMut>(
+    tx: &TX,
+    reader: &mut impl Read,
+) -> Result<HashedStateCounts, HashedStateDumpError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != HASHED_STATE_MAGIC {
+        return Err(HashedStateDumpError::InvalidMagic)
+    }
+    let [version] = read_array::<1>(reader)?;
+    if version != HASHED_STATE_VERSION {
+        return Err(HashedStateDumpError::UnsupportedVersion(version))
+    }
+
+    let mut account_cursor = tx.cursor_write::<tables::HashedAccounts>()?;
+    let mut storage_cursor = tx.cursor_dup_write::<tables::HashedStorages>()?;
+    let mut counts = HashedStateCounts::default();
+    let mut last_account: Option<B256> = None;
+    let mut last_slot: Option<B256> = None;
+    loop {
+        let [record] = read_array::<1>(reader)?;
+        match record {
+            ACCOUNT_RECORD => {
+                let hashed_address = B256::from(read_array::<32>(reader)?);
+                if last_account.map_or(false, |last| last >= hashed_address) {
+                    return Err(HashedStateDumpError::Malformed("accounts out of order"))
+                }
+                let account = read_account(reader)?;
+                account_cursor.append(hashed_address, account)?;
+                counts.accounts += 1;
+                last_account = Some(hashed_address);
+                last_slot = None;
+            }
+            STORAGE_RECORD => {
+                let Some(hashed_address) = last_account else {
+                    return Err(HashedStateDumpError::Malformed("storage slot without account"))
+                };
+                let hashed_slot = B256::from(read_array::<32>(reader)?);
+                if last_slot.map_or(false, |last| last >= hashed_slot) {
+                    return Err(HashedStateDumpError::Malformed("storage slots out of order"))
+                }
+                let value = U256::from_be_bytes(read_array::<32>(reader)?);
+                if value.is_zero() {
+                    return Err(HashedStateDumpError::Malformed("zero storage value"))
+                }
+                storage_cursor.upsert(hashed_address, StorageEntry { key: hashed_slot, value })?;
+                counts.storage_slots += 1;
+                last_slot = Some(hashed_slot);
+            }
+            END_RECORD => {
+                let expected = HashedStateCounts {
+                    accounts: u64::from_be_bytes(read_array(reader)?),
+                    storage_slots: u64::from_be_bytes(read_array(reader)?),
+                };
+                if counts != expected {
+                    return Err(HashedStateDumpError::Malformed("entry count mismatch"))
+                }
+                return Ok(counts)
+            }
+            _ => return Err(HashedStateDumpError::Malformed("unknown record")),
+        }
+    }
+}
+
+/// Imports the hashed state dump like [`import_hashed_state`] and checks the state root of the
+/// imported state against the expected one.
+///
+/// The root is computed from scratch within the same transaction, ignoring the stored trie, so a
+/// truncated or corrupted dump that still decodes is detected before the transaction is committed.
+pub fn import_hashed_state_verified<TX: DbTx + DbTxMut>(
+    tx: &TX,
+    reader: &mut impl Read,
+    expected_root: B256,
+) -> Result<HashedStateCounts, HashedStateDumpError> {
+    let counts = import_hashed_state(tx, reader)?;
+    let root = StateRoot::from_tx(tx).with_prefix_sets(TriePrefixSets::all_changed()).root()?;
+    if root != expected_root {
+        return Err(HashedStateDumpError::RootMismatch(GotExpected::new(root, expected_root)))
+    }
+    Ok(counts)
+}
+
+fn write_account(writer: &mut impl Write, account: &Account) -> std::io::Result<()> {
+    writer.write_all(&account.nonce.to_be_bytes())?;
+    writer.write_all(&account.balance.to_be_bytes::<32>())?;
+    match account.bytecode_hash {
+        Some(bytecode_hash) => {
+            writer.write_all(&[1])?;
+            writer.write_all(bytecode_hash.as_slice())
+        }
+        None => writer.write_all(&[0]),
+    }
+}
+
+fn read_account(reader: &mut impl Read) -> Result<Account, HashedStateDumpError> {
+    let nonce = u64::from_be_bytes(read_array(reader)?);
+    let balance = U256::from_be_bytes(read_array::<32>(reader)?);
+    let bytecode_hash = match read_array::<1>(reader)? {
+        [0] => None,
+        [1] => Some(B256::from(read_array::<32>(reader)?)),
+        _ => return Err(HashedStateDumpError::Malformed("invalid bytecode hash flag")),
+    };
+    Ok(Account { nonce, balance, bytecode_hash })
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::cursor::DbCursorRO;
+    use reth_primitives::keccak256;
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    /// Returns the dump of a small hashed state along with its counts and state root.
+    fn exported_state() -> (Vec<u8>, HashedStateCounts, B256) {
+        let source = create_test_provider_factory();
+        let source = source.provider_rw().unwrap();
+        for i in 0..100u64 {
+            let hashed_address = keccak256(B256::from(U256::from(i)));
+            let bytecode_hash = (i % 3 == 0).then(|| keccak256(hashed_address));
+            let account = Account { nonce: i, balance: U256::from(i) << 100, bytecode_hash };
+            source.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            for slot in 0..i % 7 {
+                let entry = StorageEntry {
+                    key: keccak256(B256::from(U256::from(slot))),
+                    value: U256::from(i * 100 + slot + 1),
+                };
+                source.tx_ref().put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+            }
+        }
+        let root = StateRoot::from_tx(source.tx_ref()).root().unwrap();
+
+        let mut dump = Vec::new();
+        let counts = export_hashed_state(source.tx_ref(), &mut dump).unwrap();
+        (dump, counts, root)
+    }
+
+    #[test]
+    fn hashed_state_round_trip() {
+        let (dump, counts, expected_root) = exported_state();
+        assert_eq!(counts, HashedStateCounts { accounts: 100, storage_slots: 295 });
+
+        let target = create_test_provider_factory();
+        let target = target.provider_rw().unwrap();
+        assert_eq!(import_hashed_state(target.tx_ref(), &mut dump.as_slice()).unwrap(), counts);
+        assert_eq!(StateRoot::from_tx(target.tx_ref()).root().unwrap(), expected_root);
+
+        // The unsupported, truncated and tampered dumps are rejected.
+        let fresh = create_test_provider_factory();
+        let mut unsupported = dump.clone();
+        unsupported[4] = HASHED_STATE_VERSION + 1;
+        assert!(matches!(
+            import_hashed_state(fresh.provider_rw().unwrap().tx_ref(), &mut unsupported.as_slice()),
+            Err(HashedStateDumpError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            import_hashed_state(
+                fresh.provider_rw().unwrap().tx_ref(),
+                &mut &dump[..dump.len() - 1]
+            ),
+            Err(HashedStateDumpError::Io(_))
+        ));
+        let mut tampered = dump.clone();
+        let len = tampered.len();
+        tampered[len - 1] ^= 1;
+        assert!(matches!(
+            import_hashed_state(fresh.provider_rw().unwrap().tx_ref(), &mut tampered.as_slice()),
+            Err(HashedStateDumpError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn verified_import_rejects_tampered_state() {
+        let (dump, counts, expected_root) = exported_state();
+        let target = create_test_provider_factory();
+
+        // Flip the last byte of the balance of the first account, the dump still decodes.
+        let mut tampered = dump.clone();
+        tampered[HASHED_STATE_MAGIC.len() + 1 + 1 + 32 + 8 + 31] ^= 1;
+        let provider = target.provider_rw().unwrap();
+        assert!(matches!(
+            import_hashed_state_verified(
+                provider.tx_ref(),
+                &mut tampered.as_slice(),
+                expected_root
+            ),
+            Err(HashedStateDumpError::RootMismatch(root)) if root.expected == expected_root
+        ));
+        // The failed import is rolled back.
+        drop(provider);
+        let provider = target.provider_rw().unwrap();
+        assert_eq!(
+            provider.tx_ref().cursor_read::<tables::HashedAccounts>().unwrap().first().unwrap(),
+            None
+        );
+
+        assert_eq!(
+            import_hashed_state_verified(provider.tx_ref(), &mut dump.as_slice(), expected_root)
+                .unwrap(),
+            counts
+        );
+    }
+}
@@ -23,6 +23,14 @@ use std::{
     ops::RangeInclusive,
 };
 
+/// The export and import of the hashed state.
+mod export;
+pub use export::*;
+
+/// The hashed state of the changes staged in a write-ahead log.
+mod wal;
+pub use wal::*;
+
 /// Representation of in-memory hashed state.
 #[derive(PartialEq, Eq, Clone, Default, Debug)]
 pub struct HashedPostState {
@@ -132,6 +140,26 @@ impl HashedPostState {
         self
     }
 
+    /// Marks the account as deleted, e.g. after it self-destructed.
+    ///
+    /// Unlike an account absent from the post state, which is read from the database, the
+    /// tombstone hides the account and all of its storage in the database from the overlay, so
+    /// the account is left out of the post state root.
+    pub fn delete_account(&mut self, hashed_address: B256) {
+        self.accounts.insert(hashed_address, None);
+        self.storages.insert(hashed_address, HashedStorage::new(true));
+    }
+
+    /// Marks the storage slot of the account as deleted, hiding the slot in the database from the
+    /// overlay. The other slots of the account are still read from the database.
+    pub fn delete_storage(&mut self, hashed_address: B256, hashed_slot: B256) {
+        self.storages
+            .entry(hashed_address)
+            .or_insert_with(|| HashedStorage::new(false))
+            .storage
+            .insert(hashed_slot, U256::ZERO);
+    }
+
     /// Extend this hashed post state with contents of another.
     /// Entries in the second hashed post state take precedence.
     pub fn extend(&mut self, other: Self) {
@@ -334,13 +362,75 @@ pub struct HashedStorageSorted {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use reth_db::{database::Database, test_utils::create_test_rw_db};
-    use reth_primitives::hex;
+    use crate::hashed_cursor::{HashedCursor, HashedCursorFactory};
+    use reth_db::{
+        database::Database, tables, test_utils::create_test_rw_db, transaction::DbTxMut,
+    };
+    use reth_primitives::{hex, keccak256, StorageEntry};
+    use reth_provider::test_utils::create_test_provider_factory;
     use revm::{
         db::states::BundleState,
         primitives::{AccountInfo, HashMap},
     };
 
+    /// Writes the hashed state, without the deleted account and slot if `deleted` is set.
+    fn write_tombstoned_state(tx: &(impl DbTx + DbTxMut), deleted: bool) {
+        for i in 0..10u64 {
+            let hashed_address = keccak256(B256::from(U256::from(i)));
+            if i == 3 && deleted {
+                continue
+            }
+            let account = Account { nonce: i, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+            for slot in 1..=3u8 {
+                if i == 5 && slot == 2 && deleted {
+                    continue
+                }
+                let entry =
+                    StorageEntry { key: B256::with_last_byte(slot), value: U256::from(slot) };
+                tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn tombstones_hide_database_entries() {
+        let deleted_account = keccak256(B256::from(U256::from(3)));
+        let changed_account = keccak256(B256::from(U256::from(5)));
+
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+        write_tombstoned_state(tx, false);
+
+        let mut post_state = HashedPostState::default();
+        post_state.delete_account(deleted_account);
+        post_state.delete_storage(changed_account, B256::with_last_byte(2));
+
+        // The overlay cursors skip the tombstoned entries that exist in the database.
+        let sorted = post_state.clone().into_sorted();
+        let overlay = HashedPostStateCursorFactory::new(tx, &sorted);
+        let mut account_cursor = overlay.hashed_account_cursor().unwrap();
+        let next = account_cursor.seek(deleted_account).unwrap().map(|(key, _)| key);
+        assert_ne!(next, Some(deleted_account));
+        let mut storage_cursor = overlay.hashed_storage_cursor(deleted_account).unwrap();
+        assert_eq!(storage_cursor.seek(B256::ZERO).unwrap(), None);
+        let mut storage_cursor = overlay.hashed_storage_cursor(changed_account).unwrap();
+        let slots = [
+            storage_cursor.seek(B256::ZERO).unwrap().unwrap().0,
+            storage_cursor.next().unwrap().unwrap().0,
+        ];
+        assert_eq!(slots, [B256::with_last_byte(1), B256::with_last_byte(3)]);
+        assert_eq!(storage_cursor.next().unwrap(), None);
+
+        // The overlay root matches the root of the database with the entries deleted.
+        let root = post_state.state_root(tx).unwrap();
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        write_tombstoned_state(provider.tx_ref(), true);
+        assert_eq!(root, StateRoot::from_tx(provider.tx_ref()).root().unwrap());
+    }
+
     #[test]
     fn hashed_state_wiped_extension() {
         let hashed_address = B256::default();
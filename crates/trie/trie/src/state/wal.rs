@@ -0,0 +1,217 @@
+use super::{HashedPostState, HashedStorage};
+use crate::updates::TrieUpdates;
+use reth_db::transaction::DbTx;
+use reth_execution_errors::StateRootError;
+use reth_primitives::{keccak256, Account, Address, B256, U256};
+
+/// A change to the plain state recorded in a write-ahead log before it is committed to the
+/// database, see [`HashedPostState::from_wal`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum WalEntry {
+    /// The account is created or updated.
+    UpsertAccount {
+        /// The address of the account.
+        address: Address,
+        /// The new account.
+        account: Account,
+    },
+    /// The account is destroyed along with its storage.
+    DeleteAccount {
+        /// The address of the account.
+        address: Address,
+    },
+    /// The storage slot of the account is set. A zero value deletes the slot.
+    UpsertStorage {
+        /// The address of the account.
+        address: Address,
+        /// The storage slot.
+        slot: B256,
+        /// The new value of the slot.
+        value: U256,
+    },
+    /// The storage slot of the account is deleted.
+    DeleteStorage {
+        /// The address of the account.
+        address: Address,
+        /// The storage slot.
+        slot: B256,
+    },
+}
+
+impl HashedPostState {
+    /// Initialize [`HashedPostState`] from the entries of a write-ahead log, replayed in order.
+    ///
+    /// The resulting state overlays the hashed tables with the staged changes, so the root of
+    /// the post-WAL state and its trie updates are computed by [`Self::state_root_with_updates`]
+    /// without writing the changes to the hashed tables first. The trie updates are the same as
+    /// the ones computed after committing the changes.
+    pub fn from_wal(entries: impl IntoIterator<Item = WalEntry>) -> Self {
+        let mut state = Self::default();
+        for entry in entries {
+            state.apply_wal_entry(entry);
+        }
+        state
+    }
+
+    /// Applies a single entry of a write-ahead log on top of the state, see [`Self::from_wal`].
+    ///
+    /// The later changes take precedence. A destroyed account has its storage wiped, so the
+    /// account recreated by a later entry starts with empty storage.
+    pub fn apply_wal_entry(&mut self, entry: WalEntry) {
+        match entry {
+            WalEntry::UpsertAccount { address, account } => {
+                self.accounts.insert(keccak256(address), Some(account));
+            }
+            WalEntry::DeleteAccount { address } => self.delete_account(keccak256(address)),
+            WalEntry::UpsertStorage { address, slot, value } => {
+                self.storages
+                    .entry(keccak256(address))
+                    .or_insert_with(|| HashedStorage::new(false))
+                    .storage
+                    .insert(keccak256(slot), value);
+            }
+            WalEntry::DeleteStorage { address, slot } => {
+                self.delete_storage(keccak256(address), keccak256(slot))
+            }
+        }
+    }
+}
+
+/// Computes the state root of the state after the entries of the write-ahead log along with the
+/// trie updates, without committing the entries to the hashed tables, see
+/// [`HashedPostState::from_wal`].
+pub fn wal_root_with_updates<TX: DbTx>(
+    tx: &TX,
+    entries: impl IntoIterator<Item = WalEntry>,
+) -> Result<(B256, TrieUpdates), StateRootError> {
+    HashedPostState::from_wal(entries).state_root_with_updates(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StateRoot;
+    use reth_db::{
+        cursor::{DbCursorRW, DbDupCursorRO},
+        tables,
+        transaction::DbTxMut,
+    };
+    use reth_primitives::StorageEntry;
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    fn write_storage(tx: &impl DbTxMut, address: Address, slot: B256, value: U256) {
+        let hashed_address = keccak256(address);
+        let hashed_slot = keccak256(slot);
+        let mut cursor = tx.cursor_dup_write::<tables::HashedStorages>().unwrap();
+        if cursor
+            .seek_by_key_subkey(hashed_address, hashed_slot)
+            .unwrap()
+            .is_some_and(|entry| entry.key == hashed_slot)
+        {
+            cursor.delete_current().unwrap();
+        }
+        if !value.is_zero() {
+            tx.put::<tables::HashedStorages>(
+                hashed_address,
+                StorageEntry { key: hashed_slot, value },
+            )
+            .unwrap();
+        }
+    }
+
+    /// Writes the initial hashed state and its trie.
+    fn write_initial_state(tx: &(impl DbTx + DbTxMut)) {
+        for i in 0..50u8 {
+            let account = Account { nonce: i as u64, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(keccak256(Address::with_last_byte(i)), account)
+                .unwrap();
+            for slot in 0..(i as u64 % 5) {
+                write_storage(
+                    tx,
+                    Address::with_last_byte(i),
+                    B256::from(U256::from(slot)),
+                    U256::from(slot + 1),
+                );
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+    }
+
+    #[test]
+    fn wal_root_matches_committed_root() {
+        let slot = |i: u64| B256::from(U256::from(i));
+        let wal = [
+            WalEntry::UpsertAccount {
+                address: Address::with_last_byte(1),
+                account: Account { nonce: 100, balance: U256::from(1), ..Default::default() },
+            },
+            WalEntry::UpsertAccount {
+                address: Address::with_last_byte(200),
+                account: Account { nonce: 1, ..Default::default() },
+            },
+            WalEntry::UpsertStorage {
+                address: Address::with_last_byte(200),
+                slot: slot(1),
+                value: U256::from(7),
+            },
+            WalEntry::UpsertStorage {
+                address: Address::with_last_byte(4),
+                slot: slot(0),
+                value: U256::from(9),
+            },
+            WalEntry::DeleteStorage { address: Address::with_last_byte(4), slot: slot(1) },
+            WalEntry::UpsertStorage {
+                address: Address::with_last_byte(3),
+                slot: slot(2),
+                value: U256::ZERO,
+            },
+            WalEntry::DeleteAccount { address: Address::with_last_byte(9) },
+            // The later entries take precedence.
+            WalEntry::UpsertStorage {
+                address: Address::with_last_byte(4),
+                slot: slot(0),
+                value: U256::from(10),
+            },
+        ];
+
+        // The root of the staged changes, computed without writing them.
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+        write_initial_state(tx);
+        let hashed_accounts = tx.entries::<tables::HashedAccounts>().unwrap();
+        let (wal_root, wal_updates) = wal_root_with_updates(tx, wal).unwrap();
+        assert_eq!(tx.entries::<tables::HashedAccounts>().unwrap(), hashed_accounts);
+
+        // The root computed after committing the changes to the hashed tables.
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+        write_initial_state(tx);
+        for entry in wal {
+            match entry {
+                WalEntry::UpsertAccount { address, account } => {
+                    tx.put::<tables::HashedAccounts>(keccak256(address), account).unwrap()
+                }
+                WalEntry::DeleteAccount { address } => {
+                    tx.delete::<tables::HashedAccounts>(keccak256(address), None).unwrap();
+                    tx.delete::<tables::HashedStorages>(keccak256(address), None).unwrap();
+                }
+                WalEntry::UpsertStorage { address, slot, value } => {
+                    write_storage(tx, address, slot, value)
+                }
+                WalEntry::DeleteStorage { address, slot } => {
+                    write_storage(tx, address, slot, U256::ZERO)
+                }
+            }
+        }
+        let prefix_sets = HashedPostState::from_wal(wal).construct_prefix_sets();
+        let (committed_root, committed_updates) =
+            StateRoot::from_tx(tx).with_prefix_sets(prefix_sets).root_with_updates().unwrap();
+
+        assert_eq!(wal_root, committed_root);
+        assert_eq!(wal_updates, committed_updates);
+        assert_eq!(committed_root, StateRoot::from_tx(tx).root_with_summary().unwrap().0);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::HashedPostState;
+use reth_db::{cursor::DbCursorRO, tables, transaction::DbTx};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{BlockNumber, B256};
+
+/// Computes the state root as of the given past block.
+///
+/// The changesets of the blocks after the target block are collected into a reverse diff, which is
+/// overlaid onto the current hashed state, and the root is computed on top of the stored trie with
+/// the reverted paths marked as changed, see [`HashedPostState::from_revert_range`]. The hashed
+/// state and the stored trie are expected to be at the block of the latest changeset.
+///
+/// The cost is proportional to the number of account and storage changes since the target block
+/// rather than to the size of the state, so the roots of recent blocks are cheap while the roots of
+/// blocks far behind the tip approach the cost of computing the root from scratch. If there are no
+/// changes after the target block, the current state root is returned.
+pub fn root_at<TX: DbTx>(tx: &TX, block_number: BlockNumber) -> Result<B256, StateRootError> {
+    let last_account_change =
+        tx.cursor_read::<tables::AccountChangeSets>()?.last()?.map(|(block, _)| block);
+    let last_storage_change =
+        tx.cursor_read::<tables::StorageChangeSets>()?.last()?.map(|(key, _)| key.block_number());
+    let reverts = match last_account_change.max(last_storage_change) {
+        Some(tip) if tip > block_number => {
+            HashedPostState::from_revert_range(tx, block_number + 1..=tip)?
+        }
+        _ => HashedPostState::default(),
+    };
+    reverts.state_root(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::state_root, StateRoot};
+    use reth_db::{
+        models::{AccountBeforeTx, BlockNumberAddress},
+        transaction::DbTxMut,
+    };
+    use reth_primitives::{keccak256, Account, Address, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn historical_roots() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // The genesis state, block zero has no changesets.
+        let mut state = (0..10u8)
+            .map(|i| {
+                let storage = (1..=i).map(|slot| (B256::with_last_byte(slot), U256::from(slot)));
+                let account = Account { nonce: i as u64, ..Default::default() };
+                (Address::with_last_byte(i), (account, storage.collect::<BTreeMap<_, _>>()))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (address, (account, storage)) in &state {
+            tx.put::<tables::HashedAccounts>(keccak256(address), *account).unwrap();
+            for (slot, value) in storage {
+                let entry = StorageEntry { key: keccak256(slot), value: *value };
+                tx.put::<tables::HashedStorages>(keccak256(address), entry).unwrap();
+            }
+        }
+        let mut roots = vec![state_root(state.clone())];
+
+        for block in 1..=5u64 {
+            // Update an account, create an account and destroy another one.
+            let mut changes = vec![
+                (Address::with_last_byte(block as u8), false),
+                (Address::with_last_byte(100 + block as u8), false),
+            ];
+            if block % 2 == 0 {
+                changes.push((Address::with_last_byte(10 - block as u8), true));
+            }
+
+            for (address, destroy) in changes {
+                let hashed_address = keccak256(address);
+                let before = state.get(&address).map(|(account, _)| *account);
+                tx.put::<tables::AccountChangeSets>(
+                    block,
+                    AccountBeforeTx { address, info: before },
+                )
+                .unwrap();
+
+                let (account, storage) = state.entry(address).or_default();
+                let reverted_slots = if destroy {
+                    std::mem::take(storage).into_iter().collect::<Vec<_>>()
+                } else {
+                    let slot = B256::with_last_byte(block as u8);
+                    let value = U256::from(block * 100);
+                    let before = storage.insert(slot, value).unwrap_or_default();
+                    if !before.is_zero() {
+                        let entry = StorageEntry { key: keccak256(slot), value: before };
+                        tx.delete::<tables::HashedStorages>(hashed_address, Some(entry)).unwrap();
+                    }
+                    let entry = StorageEntry { key: keccak256(slot), value };
+                    tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+                    vec![(slot, before)]
+                };
+                for (slot, value) in reverted_slots {
+                    tx.put::<tables::StorageChangeSets>(
+                        BlockNumberAddress((block, address)),
+                        StorageEntry { key: slot, value },
+                    )
+                    .unwrap();
+                }
+
+                if destroy {
+                    state.remove(&address);
+                    tx.delete::<tables::HashedAccounts>(hashed_address, None).unwrap();
+                    tx.delete::<tables::HashedStorages>(hashed_address, None).unwrap();
+                } else {
+                    account.nonce += 1;
+                    tx.put::<tables::HashedAccounts>(hashed_address, *account).unwrap();
+                }
+            }
+            roots.push(state_root(state.clone()));
+        }
+
+        // The stored trie reflects the tip.
+        let (root, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        assert_eq!(root, roots[5]);
+        updates.flush(tx).unwrap();
+
+        for (block, expected) in roots.into_iter().enumerate() {
+            assert_eq!(root_at(tx, block as BlockNumber), Ok(expected), "block {block}");
+        }
+        assert_eq!(root_at(tx, 10), Ok(root));
+    }
+}
@@ -1,4 +1,8 @@
-use std::time::{Duration, Instant};
+use reth_primitives::{trie::Nibbles, Account, Bytes, U256};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
 
 /// Trie stats.
 #[derive(Clone, Copy, Debug)]
@@ -6,6 +10,9 @@ pub struct TrieStats {
     duration: Duration,
     branches_added: u64,
     leaves_added: u64,
+    subtrees_descended: u64,
+    nodes_hashed: u64,
+    bytes_hashed: u64,
 }
 
 impl TrieStats {
@@ -20,9 +27,29 @@ impl TrieStats {
     }
 
     /// Number of branches added to the hash builder during the calculation.
+    ///
+    /// Every added branch is a stored subtree reused by its hash without being walked.
     pub const fn branches_added(&self) -> u64 {
         self.branches_added
     }
+
+    /// Number of stored subtrees descended into during the calculation, as their hashes could not
+    /// be reused because of the changes below them.
+    pub const fn subtrees_descended(&self) -> u64 {
+        self.subtrees_descended
+    }
+
+    /// Number of nodes hashed during the calculation, only accounted if enabled with
+    /// [`StorageRoot::with_hash_accounting`](crate::StorageRoot::with_hash_accounting).
+    pub const fn nodes_hashed(&self) -> u64 {
+        self.nodes_hashed
+    }
+
+    /// Number of bytes of the encoded nodes hashed during the calculation, only accounted along
+    /// with [`Self::nodes_hashed`].
+    pub const fn bytes_hashed(&self) -> u64 {
+        self.bytes_hashed
+    }
 }
 
 /// Trie metrics tracker.
@@ -31,11 +58,21 @@ pub struct TrieTracker {
     started_at: Instant,
     branches_added: u64,
     leaves_added: u64,
+    subtrees_descended: u64,
+    nodes_hashed: u64,
+    bytes_hashed: u64,
 }
 
 impl Default for TrieTracker {
     fn default() -> Self {
-        Self { started_at: Instant::now(), branches_added: 0, leaves_added: 0 }
+        Self {
+            started_at: Instant::now(),
+            branches_added: 0,
+            leaves_added: 0,
+            subtrees_descended: 0,
+            nodes_hashed: 0,
+            bytes_hashed: 0,
+        }
     }
 }
 
@@ -50,12 +87,67 @@ impl TrieTracker {
         self.leaves_added += 1;
     }
 
+    /// Record the number of stored subtrees descended into during the calculation, see
+    /// [`TrieWalker::subtrees_descended`](crate::walker::TrieWalker::subtrees_descended).
+    pub fn set_subtrees_descended(&mut self, subtrees_descended: u64) {
+        self.subtrees_descended = subtrees_descended;
+    }
+
+    /// Record the nodes hashed during the calculation, given all encoded nodes built by the hash
+    /// builder keyed by their paths.
+    ///
+    /// The nodes with encodings shorter than a hash are embedded into their parents instead of
+    /// being hashed, except for the root node, which is the first one in the order of the paths.
+    pub fn record_hashed_nodes(&mut self, nodes: &BTreeMap<Nibbles, Bytes>) {
+        for (index, node) in nodes.values().enumerate() {
+            if index == 0 || node.len() >= 32 {
+                self.nodes_hashed += 1;
+                self.bytes_hashed += node.len() as u64;
+            }
+        }
+    }
+
     /// Called when root calculation is finished to return trie statistics.
     pub fn finish(self) -> TrieStats {
         TrieStats {
             duration: self.started_at.elapsed(),
             branches_added: self.branches_added,
             leaves_added: self.leaves_added,
+            subtrees_descended: self.subtrees_descended,
+            nodes_hashed: self.nodes_hashed,
+            bytes_hashed: self.bytes_hashed,
         }
     }
 }
+
+/// Summary of the accounts aggregated during the state root calculation.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct StateSummary {
+    accounts: u64,
+    total_balance: Option<U256>,
+}
+
+impl Default for StateSummary {
+    fn default() -> Self {
+        Self { accounts: 0, total_balance: Some(U256::ZERO) }
+    }
+}
+
+impl StateSummary {
+    /// Records the account leaf.
+    pub fn record(&mut self, account: &Account) {
+        self.accounts += 1;
+        self.total_balance =
+            self.total_balance.and_then(|total| total.checked_add(account.balance));
+    }
+
+    /// Number of accounts in the state.
+    pub const fn accounts(&self) -> u64 {
+        self.accounts
+    }
+
+    /// Sum of the balances of all accounts or `None` if the sum overflows [`U256`].
+    pub const fn total_balance(&self) -> Option<U256> {
+        self.total_balance
+    }
+}
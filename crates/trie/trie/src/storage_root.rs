@@ -0,0 +1,189 @@
+use crate::hashed_cursor::{HashedCursor, HashedCursorFactory};
+use reth_db::DatabaseError;
+use reth_primitives::{proofs, B256, U256};
+use std::collections::BTreeMap;
+
+/// Computes the root of the storage trie with the given hashed slots and their values in memory,
+/// with no database and no account involved.
+///
+/// The slots with zero values are left out, as the zeroed slots are removed from the storage. The
+/// root is the same as the one computed by [`StorageRoot`](crate::StorageRoot) from the equivalent
+/// hashed storage, which makes this the reference for testing the storage trie in isolation, see
+/// [`root_from_sorted_leaves`](proofs::root_from_sorted_leaves) for the account trie.
+pub fn root_of_slots(slots: BTreeMap<B256, U256>) -> B256 {
+    proofs::storage_root(slots.into_iter().filter(|(_, value)| !value.is_zero()))
+}
+
+/// Histogram of the leaf depths of a trie bucketed by nibble depth.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct DepthHistogram {
+    /// The number of leaves at each depth, indexed by the depth.
+    buckets: Vec<u64>,
+}
+
+impl DepthHistogram {
+    /// Records a leaf at the given nibble depth.
+    pub fn record(&mut self, depth: usize) {
+        if self.buckets.len() <= depth {
+            self.buckets.resize(depth + 1, 0);
+        }
+        self.buckets[depth] += 1;
+    }
+
+    /// Returns the number of leaves at the given nibble depth.
+    pub fn count(&self, depth: usize) -> u64 {
+        self.buckets.get(depth).copied().unwrap_or_default()
+    }
+
+    /// Returns the total number of leaves.
+    pub fn leaves(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Returns the depth of the deepest leaf or `None` if the trie is empty.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.buckets.iter().rposition(|count| *count > 0)
+    }
+
+    /// Returns an iterator over the non-empty buckets as pairs of depth and number of leaves in
+    /// ascending order of depth.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.buckets.iter().copied().enumerate().filter(|(_, count)| *count > 0)
+    }
+}
+
+/// Computes the histogram of the leaf depths of the storage trie of the given account.
+///
+/// The depth of a leaf is the number of nibbles of its path that are consumed by the branch and
+/// extension nodes above it, so a storage trie with a single slot has its only leaf at depth zero.
+/// The depths are derived from the hashed storage slots in a single walk, the stored trie nodes
+/// are not consulted.
+pub fn depth_histogram<H: HashedCursorFactory>(
+    hashed_cursor_factory: H,
+    hashed_address: B256,
+) -> Result<DepthHistogram, DatabaseError> {
+    let mut histogram = DepthHistogram::default();
+    let mut cursor = hashed_cursor_factory.hashed_storage_cursor(hashed_address)?;
+
+    // A leaf hangs off the branch at the length of the longest prefix shared with its neighbors.
+    let mut previous: Option<(B256, usize)> = None;
+    let mut entry = cursor.seek(B256::ZERO)?;
+    while let Some((hashed_slot, _)) = entry {
+        let shared = previous.as_ref().map_or(0, |(key, _)| common_prefix_len(key, &hashed_slot));
+        if let Some((_, previous_shared)) = previous {
+            histogram.record(previous_shared.max(shared) + 1);
+        }
+        previous = Some((hashed_slot, shared));
+        entry = cursor.next()?;
+    }
+
+    if let Some((_, shared)) = previous {
+        let single_leaf = histogram.leaves() == 0;
+        histogram.record(if single_leaf { 0 } else { shared + 1 });
+    }
+
+    Ok(histogram)
+}
+
+/// Returns the number of leading nibbles shared by the two keys.
+fn common_prefix_len(a: &B256, b: &B256) -> usize {
+    let mut len = 0;
+    for (a, b) in a.iter().zip(b.iter()) {
+        if a == b {
+            len += 2;
+        } else {
+            if a >> 4 == b >> 4 {
+                len += 1;
+            }
+            break
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::storage_root_prehashed, StorageRoot};
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{constants::EMPTY_ROOT_HASH, hex_literal::hex, keccak256, StorageEntry};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn root_of_slots_matches_database() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let single = BTreeMap::from([(keccak256(B256::ZERO), U256::from(1))]);
+        let many = (0..1_000u64)
+            .map(|slot| (keccak256(B256::from(U256::from(slot))), U256::from(slot + 1)))
+            .collect::<BTreeMap<_, _>>();
+        for (i, slots) in [BTreeMap::new(), single, many].into_iter().enumerate() {
+            let hashed_address = B256::with_last_byte(i as u8);
+            for (key, value) in &slots {
+                let entry = StorageEntry { key: *key, value: *value };
+                tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+            }
+            let root = root_of_slots(slots.clone());
+            assert_eq!(root, StorageRoot::from_tx_hashed(tx, hashed_address).root().unwrap());
+            assert_eq!(root, storage_root_prehashed(slots));
+        }
+        assert_eq!(root_of_slots(BTreeMap::new()), EMPTY_ROOT_HASH);
+
+        // The zeroed slots are not part of the trie, leaving the single slot.
+        let zeroed = BTreeMap::from([
+            (keccak256(B256::ZERO), U256::from(1)),
+            (keccak256(B256::with_last_byte(1)), U256::ZERO),
+        ]);
+        assert_eq!(
+            root_of_slots(zeroed),
+            StorageRoot::from_tx_hashed(tx, B256::with_last_byte(1)).root().unwrap()
+        );
+    }
+
+    #[test]
+    fn storage_depth_histogram() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let empty = B256::with_last_byte(1);
+        let single = B256::with_last_byte(2);
+        let layered = B256::with_last_byte(3);
+
+        tx.put::<tables::HashedStorages>(
+            single,
+            StorageEntry { key: B256::with_last_byte(1), value: U256::from(1) },
+        )
+        .unwrap();
+        for key in [
+            hex!("0000000000000000000000000000000000000000000000000000000000000000"),
+            hex!("0100000000000000000000000000000000000000000000000000000000000000"),
+            hex!("0101100000000000000000000000000000000000000000000000000000000000"),
+            hex!("0101200000000000000000000000000000000000000000000000000000000000"),
+            hex!("1000000000000000000000000000000000000000000000000000000000000000"),
+        ] {
+            tx.put::<tables::HashedStorages>(
+                layered,
+                StorageEntry { key: B256::from(key), value: U256::from(1) },
+            )
+            .unwrap();
+        }
+
+        let histogram = depth_histogram(tx, empty).unwrap();
+        assert_eq!(histogram.leaves(), 0);
+        assert_eq!(histogram.max_depth(), None);
+
+        let histogram = depth_histogram(tx, single).unwrap();
+        assert_eq!(histogram.iter().collect::<Vec<_>>(), vec![(0, 1)]);
+
+        // The root branch splits on the first nibble and the `0x0` branch on the second one. The
+        // `0x010` branch is reached through an extension node and splits on the fourth nibble,
+        // while the `0x0101` branch splits on the fifth one.
+        let histogram = depth_histogram(tx, layered).unwrap();
+        assert_eq!(histogram.iter().collect::<Vec<_>>(), vec![(1, 1), (2, 1), (4, 1), (5, 2)]);
+        assert_eq!(histogram.leaves(), 5);
+        assert_eq!(histogram.max_depth(), Some(5));
+    }
+}
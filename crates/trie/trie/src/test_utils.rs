@@ -1,7 +1,13 @@
+use crate::{
+    hashed_cursor::{HashedCursor, HashedCursorFactory},
+    updates::{TrieKey, TrieUpdates},
+};
 use alloy_rlp::encode_fixed_size;
+use reth_db::DatabaseError;
 use reth_primitives::{
     proofs::triehash::KeccakHasher, trie::TrieAccount, Account, Address, B256, U256,
 };
+use std::collections::BTreeSet;
 
 /// Re-export of [triehash].
 pub use triehash;
@@ -47,3 +53,243 @@ pub fn storage_root_prehashed<I: IntoIterator<Item = (B256, U256)>>(storage: I)
     let encoded_storage = storage.into_iter().map(|(k, v)| (k, encode_fixed_size(&v)));
     triehash::trie_root::<KeccakHasher, _, _, _>(encoded_storage)
 }
+
+/// Asserts that the hashed accounts and storages of the two factories are the same.
+///
+/// # Panics
+///
+/// With the first difference found by [`hashed_states_difference`] in the message.
+#[track_caller]
+pub fn assert_hashed_states_eq<A: HashedCursorFactory, B: HashedCursorFactory>(left: A, right: B) {
+    if let Some(difference) = hashed_states_difference(&left, &right).unwrap() {
+        panic!("hashed states differ: {difference}");
+    }
+}
+
+/// Walks the hashed accounts and storages of the two factories in order and describes the first
+/// difference, i.e. the hashed key of the account or the slot only present on one side or the field
+/// of the account or the value of the slot that differs.
+///
+/// The storages are compared for the accounts present on both sides.
+pub fn hashed_states_difference<A: HashedCursorFactory, B: HashedCursorFactory>(
+    left: &A,
+    right: &B,
+) -> Result<Option<String>, DatabaseError> {
+    let mut left_cursor = left.hashed_account_cursor()?;
+    let mut right_cursor = right.hashed_account_cursor()?;
+    let mut left_entry = left_cursor.seek(B256::ZERO)?;
+    let mut right_entry = right_cursor.seek(B256::ZERO)?;
+    loop {
+        let (left_account, right_account) = match (left_entry, right_entry) {
+            (None, None) => return Ok(None),
+            (Some((key, _)), right) if right.map_or(true, |(right_key, _)| key < right_key) => {
+                return Ok(Some(format!("account {key} is only on the left")))
+            }
+            (left, Some((key, _))) if left.map_or(true, |(left_key, _)| key < left_key) => {
+                return Ok(Some(format!("account {key} is only on the right")))
+            }
+            (Some(left), Some(right)) => (left, right),
+            _ => unreachable!("the entries with different keys are handled above"),
+        };
+
+        let (key, left_account) = left_account;
+        let (_, right_account) = right_account;
+        if let Some(difference) = account_difference(&left_account, &right_account) {
+            return Ok(Some(format!("account {key} {difference}")))
+        }
+        if let Some(difference) = storage_difference(left, right, key)? {
+            return Ok(Some(format!("storage of account {key}: {difference}")))
+        }
+
+        left_entry = left_cursor.next()?;
+        right_entry = right_cursor.next()?;
+    }
+}
+
+/// Describes the first field that differs between the two accounts.
+fn account_difference(left: &Account, right: &Account) -> Option<String> {
+    if left.nonce != right.nonce {
+        return Some(format!("nonce differs: {} != {}", left.nonce, right.nonce))
+    }
+    if left.balance != right.balance {
+        return Some(format!("balance differs: {} != {}", left.balance, right.balance))
+    }
+    if left.bytecode_hash != right.bytecode_hash {
+        return Some(format!(
+            "bytecode hash differs: {:?} != {:?}",
+            left.bytecode_hash, right.bytecode_hash
+        ))
+    }
+    None
+}
+
+/// Describes the first slot that differs between the storages of the account.
+fn storage_difference<A: HashedCursorFactory, B: HashedCursorFactory>(
+    left: &A,
+    right: &B,
+    hashed_address: B256,
+) -> Result<Option<String>, DatabaseError> {
+    let mut left_cursor = left.hashed_storage_cursor(hashed_address)?;
+    let mut right_cursor = right.hashed_storage_cursor(hashed_address)?;
+    let mut left_entry = left_cursor.seek(B256::ZERO)?;
+    let mut right_entry = right_cursor.seek(B256::ZERO)?;
+    loop {
+        match (left_entry, right_entry) {
+            (None, None) => return Ok(None),
+            (Some((key, _)), right) if right.map_or(true, |(right_key, _)| key < right_key) => {
+                return Ok(Some(format!("slot {key} is only on the left")))
+            }
+            (left, Some((key, _))) if left.map_or(true, |(left_key, _)| key < left_key) => {
+                return Ok(Some(format!("slot {key} is only on the right")))
+            }
+            (Some((key, left_value)), Some((_, right_value))) if left_value != right_value => {
+                return Ok(Some(format!("slot {key} value differs: {left_value} != {right_value}")))
+            }
+            _ => {}
+        }
+        left_entry = left_cursor.next()?;
+        right_entry = right_cursor.next()?;
+    }
+}
+
+impl TrieUpdates {
+    /// Asserts that the trie updates are the same as the other ones, e.g. of a reference run.
+    ///
+    /// # Panics
+    ///
+    /// With every difference found by [`trie_updates_differences`] in the message.
+    #[track_caller]
+    pub fn assert_eq_detailed(&self, other: &Self) {
+        let differences = trie_updates_differences(self, other);
+        if !differences.is_empty() {
+            panic!("trie updates differ:\n{}", differences.join("\n"));
+        }
+    }
+}
+
+/// Describes every entry that differs between the two trie updates in ascending order of the trie
+/// keys, i.e. the trie key only present on one side or the operations that differ.
+pub fn trie_updates_differences(left: &TrieUpdates, right: &TrieUpdates) -> Vec<String> {
+    let keys = left.keys().chain(right.keys()).collect::<BTreeSet<_>>();
+    keys.into_iter()
+        .filter_map(|key| {
+            let difference = match (left.get(key), right.get(key)) {
+                (Some(_), None) => "is only on the left".to_string(),
+                (None, Some(_)) => "is only on the right".to_string(),
+                (Some(left), Some(right)) if left != right => {
+                    format!("differs: {left:?} != {right:?}")
+                }
+                _ => return None,
+            };
+            let key = match key {
+                TrieKey::AccountNode(path) => format!("account node {:?}", path.0),
+                TrieKey::StorageNode(hashed_address, path) => {
+                    format!("storage node {:?} of account {hashed_address}", path.0)
+                }
+                TrieKey::StorageTrie(hashed_address) => {
+                    format!("storage trie of account {hashed_address}")
+                }
+            };
+            Some(format!("{key} {difference}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hashed_cursor::HashedPostStateCursorFactory, updates::TrieOp, HashedPostState,
+        HashedStorage,
+    };
+    use reth_db::{database::Database, test_utils::create_test_rw_db};
+    use reth_primitives::trie::{BranchNodeCompact, Nibbles, StoredNibbles, StoredNibblesSubKey};
+
+    #[test]
+    fn hashed_state_differences() {
+        let db = create_test_rw_db();
+        let tx = db.tx().unwrap();
+
+        let address = B256::with_last_byte(1);
+        let account = Account { nonce: 1, ..Default::default() };
+        let slot = B256::with_last_byte(2);
+        let state = HashedPostState::default()
+            .with_accounts([(address, Some(account))])
+            .with_storages([(address, HashedStorage::from_iter(false, [(slot, U256::from(1))]))]);
+        let difference = |other: HashedPostState| {
+            let (state, other) = (state.clone().into_sorted(), other.into_sorted());
+            hashed_states_difference(
+                &HashedPostStateCursorFactory::new(&tx, &state),
+                &HashedPostStateCursorFactory::new(&tx, &other),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(difference(state.clone()), None);
+        assert_hashed_states_eq(&tx, &tx);
+
+        // The account level differences.
+        let mut other = state.clone();
+        other.accounts.insert(address, Some(Account { nonce: 2, ..account }));
+        assert_eq!(difference(other), Some(format!("account {address} nonce differs: 1 != 2")));
+        let mut other = state.clone();
+        other.accounts.insert(B256::ZERO, Some(account));
+        assert_eq!(difference(other), Some(format!("account {} is only on the right", B256::ZERO)));
+
+        // The slot level differences.
+        let mut other = state.clone();
+        other.storages.get_mut(&address).unwrap().storage.insert(slot, U256::from(3));
+        assert_eq!(
+            difference(other),
+            Some(format!("storage of account {address}: slot {slot} value differs: 1 != 3"))
+        );
+        let mut other = state.clone();
+        other.storages.get_mut(&address).unwrap().storage.insert(slot, U256::ZERO);
+        assert_eq!(
+            difference(other),
+            Some(format!("storage of account {address}: slot {slot} is only on the left"))
+        );
+    }
+
+    #[test]
+    fn trie_updates_detailed_differences() {
+        let node = |hash: u8| {
+            BranchNodeCompact::new(0b11, 0, 0b11, vec![B256::repeat_byte(hash); 2], None)
+        };
+        let path = Nibbles::from_nibbles_unchecked([0x1, 0xa]);
+        let hashed_address = B256::with_last_byte(1);
+        let updates = TrieUpdates::from([
+            (TrieKey::AccountNode(StoredNibbles(Nibbles::default())), TrieOp::Update(node(1))),
+            (TrieKey::AccountNode(StoredNibbles(path.clone())), TrieOp::Update(node(2))),
+            (TrieKey::StorageTrie(hashed_address), TrieOp::Delete),
+        ]);
+        assert!(trie_updates_differences(&updates, &updates.clone()).is_empty());
+        updates.assert_eq_detailed(&updates.clone());
+
+        // A single differing node is pinpointed by its key.
+        let mut other = updates.clone();
+        other.extend([(TrieKey::AccountNode(StoredNibbles(path.clone())), TrieOp::Delete)]);
+        let differences = trie_updates_differences(&updates, &other);
+        assert_eq!(
+            differences,
+            vec![format!("account node {path:?} differs: {:?} != Delete", TrieOp::Update(node(2)))]
+        );
+        let message = std::panic::catch_unwind(|| updates.assert_eq_detailed(&other))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert_eq!(*message, format!("trie updates differ:\n{}", differences[0]));
+
+        // The entries only on one side.
+        let mut other = updates.clone();
+        let storage_path = StoredNibblesSubKey(path.clone());
+        other.extend([(
+            TrieKey::StorageNode(hashed_address, storage_path),
+            TrieOp::Update(node(3)),
+        )]);
+        assert_eq!(
+            trie_updates_differences(&other, &updates),
+            vec![format!("storage node {path:?} of account {hashed_address} is only on the left")]
+        );
+    }
+}
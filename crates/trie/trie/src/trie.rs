@@ -1,31 +1,51 @@
 use crate::{
-    hashed_cursor::{HashedCursorFactory, HashedStorageCursor},
+    audit::AuditTrail,
+    codec::{EthereumValueCodec, ValueCodec},
+    hashed_cursor::{
+        HashedCursor, HashedCursorFactory, HashedStorageCursor, SortedAccountsCursorFactory,
+    },
     node_iter::{TrieElement, TrieNodeIter},
-    prefix_set::{PrefixSet, PrefixSetLoader, TriePrefixSets},
-    progress::{IntermediateStateRootState, StateRootProgress},
-    stats::TrieTracker,
+    prefetch::PrefetchSource,
+    prefix_set::{PrefixSet, PrefixSetLoader, PrefixSetMut, TriePrefixSets},
+    progress::{IntermediateStateRootState, ProgressReport, ProgressReporter, StateRootProgress},
+    stats::{StateSummary, TrieStats, TrieTracker},
     trie_cursor::TrieCursorFactory,
     updates::{TrieKey, TrieOp, TrieUpdates},
     walker::TrieWalker,
 };
-use alloy_rlp::{BufMut, Encodable};
-use reth_db::transaction::DbTx;
+use alloy_rlp::EMPTY_STRING_CODE;
+use reth_db::{
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+    DatabaseError,
+};
 use reth_execution_errors::{StateRootError, StorageRootError};
 use reth_primitives::{
     constants::EMPTY_ROOT_HASH,
     keccak256,
-    trie::{HashBuilder, Nibbles, TrieAccount},
-    Address, BlockNumber, B256,
+    trie::{proof::ProofRetainer, HashBuilder, Nibbles},
+    Account, Address, BlockNumber, Bytes, GotExpected, B256, U256,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, SyncSender},
+        Arc,
+    },
+    time::Instant,
 };
-use std::ops::RangeInclusive;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 #[cfg(feature = "metrics")]
 use crate::metrics::{StateRootMetrics, TrieRootMetrics, TrieType};
 
 /// `StateRoot` is used to compute the root node of a state trie.
 #[derive(Debug)]
-pub struct StateRoot<T, H> {
+pub struct StateRoot<T, H, C = EthereumValueCodec, F = fn(TrieUpdates) -> Result<(), DatabaseError>>
+{
     /// The factory for trie cursors.
     pub trie_cursor_factory: T,
     /// The factory for hashed cursors.
@@ -36,12 +56,74 @@ pub struct StateRoot<T, H> {
     previous_state: Option<IntermediateStateRootState>,
     /// The number of updates after which the intermediate progress should be returned.
     threshold: u64,
+    /// The number of accounts ahead of the walk whose storage tries are prefetched, along with
+    /// the database the prefetcher reads from.
+    prefetch: Option<(usize, Arc<dyn PrefetchSource>)>,
+    /// The encoding of the leaf values.
+    codec: C,
+    /// The hashed addresses of the accounts left out of the state root.
+    excluded_accounts: HashSet<B256>,
+    /// The storage roots used instead of the computed ones, keyed by hashed address.
+    storage_root_overrides: HashMap<B256, B256>,
+    /// The writer of the trie updates of the subtrees passed by the walk.
+    intermediate_flush: Option<F>,
+    /// The flag requesting the walk to stop and return its intermediate progress.
+    cancellation: Option<Arc<AtomicBool>>,
+    /// The observer of the progress of the calculation.
+    progress_reporter: Option<Arc<dyn ProgressReporter>>,
     #[cfg(feature = "metrics")]
     /// State root metrics.
     metrics: StateRootMetrics,
 }
 
 impl<T, H> StateRoot<T, H> {
+    /// Create a new [`StateRoot`] instance.
+    pub fn new(trie_cursor_factory: T, hashed_cursor_factory: H) -> Self {
+        Self {
+            trie_cursor_factory,
+            hashed_cursor_factory,
+            prefix_sets: TriePrefixSets::default(),
+            previous_state: None,
+            threshold: 100_000,
+            prefetch: None,
+            codec: EthereumValueCodec,
+            excluded_accounts: HashSet::new(),
+            storage_root_overrides: HashMap::new(),
+            intermediate_flush: None,
+            cancellation: None,
+            progress_reporter: None,
+            #[cfg(feature = "metrics")]
+            metrics: StateRootMetrics::default(),
+        }
+    }
+}
+
+impl<T, H> StateRoot<T, SortedAccountsCursorFactory<H>> {
+    /// Create a new [`StateRoot`] instance over the in-memory accounts sorted by their hashed
+    /// address, with the storage read from the given factories.
+    ///
+    /// The accounts are the complete account set, the hashed accounts of the factory are never
+    /// read. Since the stored account trie does not reflect the in-memory accounts, all keys are
+    /// marked as changed and the root is computed from scratch, see
+    /// [`TriePrefixSets::all_changed`].
+    ///
+    /// # Panics
+    ///
+    /// If the hashed addresses of the accounts are not strictly ascending.
+    pub fn from_sorted_accounts(
+        trie_cursor_factory: T,
+        hashed_cursor_factory: H,
+        accounts: impl IntoIterator<Item = (B256, Account)>,
+    ) -> Self {
+        Self::new(
+            trie_cursor_factory,
+            SortedAccountsCursorFactory::new(hashed_cursor_factory, accounts),
+        )
+        .with_prefix_sets(TriePrefixSets::all_changed())
+    }
+}
+
+impl<T, H, C, F> StateRoot<T, H, C, F> {
     /// Set the prefix sets.
     pub fn with_prefix_sets(mut self, prefix_sets: TriePrefixSets) -> Self {
         self.prefix_sets = prefix_sets;
@@ -60,33 +142,160 @@ impl<T, H> StateRoot<T, H> {
         self
     }
 
+    /// Enable prefetching of the storage tries for up to `depth` accounts ahead of the account
+    /// walk on a background thread. Setting the depth to zero disables the prefetching.
+    ///
+    /// The prefetcher reads the given database through its own read-only transaction, since the
+    /// transaction of the walk, e.g. the read-write transaction of the merkle stage, cannot be
+    /// read from another thread. This warms the page cache of cold databases and does not affect
+    /// the computed root.
+    pub fn with_prefetch<DB: Database + 'static>(mut self, depth: usize, db: DB) -> Self {
+        self.prefetch = (depth > 0).then(|| (depth, Arc::new(db) as Arc<dyn PrefetchSource>));
+        self
+    }
+
+    /// Set the flag that cancels the calculation once it is set.
+    ///
+    /// The flag is checked after every account, so a cancelled [`Self::root_with_progress`]
+    /// returns the intermediate progress up to the last processed account as if the threshold was
+    /// reached, including the trie updates collected so far. Passing the intermediate state to
+    /// [`Self::with_intermediate_state`] resumes the calculation where it stopped, so a driver can
+    /// pause the calculation and commit its progress, see [`StateRootProgress::Progress`].
+    ///
+    /// The storage root of a single account is never interrupted. The flag is ignored by the
+    /// calculations that do not return intermediate progress and by the ones writing their
+    /// updates with [`Self::with_intermediate_flush`].
+    pub fn with_cancellation(mut self, cancellation: Arc<AtomicBool>) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Set the reporter observing the progress of the calculation while it runs, e.g. to drive a
+    /// progress bar or emit metrics during a long calculation.
+    ///
+    /// The reporter is called after the processed accounts at most once per its
+    /// [interval](ProgressReporter::interval), and once more when the calculation completes or
+    /// returns its intermediate progress. The returned progress is not affected.
+    pub fn with_progress_reporter(mut self, reporter: Arc<dyn ProgressReporter>) -> Self {
+        self.progress_reporter = Some(reporter);
+        self
+    }
+
     /// Set the previously recorded intermediate state.
     pub fn with_intermediate_state(mut self, state: Option<IntermediateStateRootState>) -> Self {
         self.previous_state = state;
         self
     }
 
+    /// Leave the accounts with the given hashed addresses out of the state root, as if they did
+    /// not exist.
+    ///
+    /// The resulting root is not the canonical state root and the resulting trie updates must not
+    /// be written to the trie tables. The stored nodes on the paths to the excluded accounts are
+    /// recomputed, so the root does not depend on whether the trie tables are populated.
+    pub fn without_accounts(mut self, accounts: HashSet<B256>) -> Self {
+        self.excluded_accounts = accounts;
+        self
+    }
+
+    /// Use the given storage roots, keyed by hashed address, for the accounts instead of
+    /// computing them, e.g. to model proposed storage changes or to plug in the storage roots
+    /// computed elsewhere.
+    ///
+    /// The overrides take precedence over both the computed and the stored storage roots, and the
+    /// overridden storage tries are not walked at all, so their trie updates are not returned.
+    /// The overrides of the accounts that do not exist are ignored. The resulting root is the
+    /// canonical state root only if every override is the actual storage root of its account.
+    pub fn with_storage_root_overrides(mut self, overrides: HashMap<B256, B256>) -> Self {
+        self.storage_root_overrides = overrides;
+        self
+    }
+
     /// Set the hashed cursor factory.
-    pub fn with_hashed_cursor_factory<HF>(self, hashed_cursor_factory: HF) -> StateRoot<T, HF> {
-        StateRoot {
-            trie_cursor_factory: self.trie_cursor_factory,
-            hashed_cursor_factory,
-            prefix_sets: self.prefix_sets,
-            threshold: self.threshold,
-            previous_state: self.previous_state,
-            #[cfg(feature = "metrics")]
-            metrics: self.metrics,
-        }
+    pub fn with_hashed_cursor_factory<HF>(
+        self,
+        hashed_cursor_factory: HF,
+    ) -> StateRoot<T, HF, C, F> {
+        self.map_parts(|trie, _, codec, flush| (trie, hashed_cursor_factory, codec, flush))
     }
 
     /// Set the trie cursor factory.
-    pub fn with_trie_cursor_factory<TF>(self, trie_cursor_factory: TF) -> StateRoot<TF, H> {
+    pub fn with_trie_cursor_factory<TF>(self, trie_cursor_factory: TF) -> StateRoot<TF, H, C, F> {
+        self.map_parts(|_, hashed, codec, flush| (trie_cursor_factory, hashed, codec, flush))
+    }
+
+    /// Set the encoding of the leaf values of the account and storage tries.
+    ///
+    /// Roots computed with any codec other than [`EthereumValueCodec`] are not Ethereum state
+    /// roots and the resulting trie updates must not be written to the Ethereum trie tables.
+    ///
+    /// The stored root is never returned as is for the other codecs, but the walk still reuses the
+    /// hashes of the unchanged subtrees stored in the trie tables, which are the hashes of the
+    /// Ethereum encoding. Unless the trie cursor factory holds the nodes of the same codec, all
+    /// keys have to be marked as changed with [`TriePrefixSets::all_changed`].
+    pub fn with_value_codec<VC>(self, codec: VC) -> StateRoot<T, H, VC, F> {
+        self.map_parts(|trie, hashed, _, flush| (trie, hashed, codec, flush))
+    }
+
+    /// Write the trie updates to the given transaction as the walk passes the subtrees they belong
+    /// to, instead of accumulating all of them until the calculation completes.
+    ///
+    /// The updates are written every time their number reaches the threshold, see
+    /// [`Self::with_threshold`], and once the calculation completes. No intermediate progress is
+    /// returned and the returned trie updates are always empty. The walk never revisits the
+    /// subtrees it has passed, so the written nodes are final and the trie tables end up the same
+    /// as if all updates were written at the end. This bounds the memory of rebuilding the trie of
+    /// the entire state.
+    ///
+    /// The trie cursors must not observe the written nodes, e.g. by reading the trie tables of the
+    /// same transaction with the unchanged nodes of a rebuilt trie still in place.
+    pub fn with_intermediate_flush<'tx, TX>(
+        self,
+        tx: &'tx TX,
+    ) -> StateRoot<T, H, C, impl FnMut(TrieUpdates) -> Result<(), DatabaseError> + 'tx>
+    where
+        TX: DbTx + DbTxMut,
+    {
+        self.map_parts(|trie, hashed, codec, _| {
+            (trie, hashed, codec, Some(move |updates: TrieUpdates| updates.flush(tx)))
+        })
+    }
+
+    /// Set the metrics to record the state and storage root calculations into.
+    ///
+    /// The metrics share their totals with their clones, so a clone kept by the caller can be
+    /// used to [snapshot](TrieRootMetrics::snapshot) them after the calculation.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: StateRootMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Replaces the fields the type of the calculator depends on, i.e. the cursor factories, the
+    /// value codec and the intermediate flush, keeping the rest of the configuration.
+    fn map_parts<TF, HF, VC, FF>(
+        self,
+        map: impl FnOnce(T, H, C, Option<F>) -> (TF, HF, VC, Option<FF>),
+    ) -> StateRoot<TF, HF, VC, FF> {
+        let (trie_cursor_factory, hashed_cursor_factory, codec, intermediate_flush) = map(
+            self.trie_cursor_factory,
+            self.hashed_cursor_factory,
+            self.codec,
+            self.intermediate_flush,
+        );
         StateRoot {
             trie_cursor_factory,
-            hashed_cursor_factory: self.hashed_cursor_factory,
+            hashed_cursor_factory,
             prefix_sets: self.prefix_sets,
             threshold: self.threshold,
+            prefetch: self.prefetch,
             previous_state: self.previous_state,
+            excluded_accounts: self.excluded_accounts,
+            storage_root_overrides: self.storage_root_overrides,
+            codec,
+            intermediate_flush,
+            cancellation: self.cancellation,
+            progress_reporter: self.progress_reporter,
             #[cfg(feature = "metrics")]
             metrics: self.metrics,
         }
@@ -95,16 +304,28 @@ impl<T, H> StateRoot<T, H> {
 
 impl<'a, TX: DbTx> StateRoot<&'a TX, &'a TX> {
     /// Create a new [`StateRoot`] instance.
+    ///
+    /// The hashed state and the trie tables are never moved to static files, which only hold the
+    /// headers, transactions and receipts. The transaction of a provider created by a
+    /// `ProviderFactory` with static files therefore has all the data the root is computed from,
+    /// regardless of the static file layout or the pruning configuration. The state root of the
+    /// header to check the root against is read by `DatabaseProvider::verify_latest_state_root`,
+    /// which looks the header up in the static files.
     pub fn from_tx(tx: &'a TX) -> Self {
-        Self {
-            trie_cursor_factory: tx,
-            hashed_cursor_factory: tx,
-            prefix_sets: TriePrefixSets::default(),
-            previous_state: None,
-            threshold: 100_000,
-            #[cfg(feature = "metrics")]
-            metrics: StateRootMetrics::default(),
+        Self::new(tx, tx)
+    }
+
+    /// Hint the operating system that the tables are about to be walked, so that it prefetches
+    /// the database pages ahead of the reads, see [`DbTx::advise_sequential_reads`].
+    ///
+    /// This speeds up the full recomputation of the root with a cold page cache, e.g. after a
+    /// restart, especially on spinning disks. The hint is best-effort and does not affect the
+    /// computed root, a failure to apply it is only logged.
+    pub fn with_sequential_hint(self) -> Self {
+        if let Err(error) = self.hashed_cursor_factory.advise_sequential_reads() {
+            debug!(target: "trie::state_root", %error, "Failed to hint sequential reads");
         }
+        self
     }
 
     /// Given a block number range, identifies all the accounts and storage keys that
@@ -166,21 +387,26 @@ impl<'a, TX: DbTx> StateRoot<&'a TX, &'a TX> {
     }
 }
 
-impl<T, H> StateRoot<T, H>
+impl<T, H, C, F> StateRoot<T, H, C, F>
 where
-    T: TrieCursorFactory + Clone,
-    H: HashedCursorFactory + Clone,
+    T: TrieCursorFactory + Clone + Send,
+    H: HashedCursorFactory + Clone + Send,
+    C: ValueCodec + Clone,
+    F: FnMut(TrieUpdates) -> Result<(), DatabaseError>,
 {
     /// Walks the intermediate nodes of existing state trie (if any) and hashed entries. Feeds the
     /// nodes into the hash builder. Collects the updates in the process.
     ///
-    /// Ignores the threshold.
+    /// Ignores the threshold and the cancellation.
     ///
     /// # Returns
     ///
     /// The intermediate progress of state root computation and the trie updates.
     pub fn root_with_updates(self) -> Result<(B256, TrieUpdates), StateRootError> {
-        match self.with_no_threshold().calculate(true)? {
+        let calculator = Self { cancellation: None, ..self }.with_no_threshold();
+        match calculator
+            .calculate(CalculationSinks { retain_updates: true, ..Default::default() })?
+        {
             StateRootProgress::Complete(root, _, updates) => Ok((root, updates)),
             StateRootProgress::Progress(..) => unreachable!(), // unreachable threshold
         }
@@ -189,27 +415,281 @@ where
     /// Walks the intermediate nodes of existing state trie (if any) and hashed entries. Feeds the
     /// nodes into the hash builder.
     ///
+    /// If the prefix sets are empty, i.e. nothing has changed, the hash of the stored root node is
+    /// returned without walking the trie. The lookup reads only the top node of the account trie
+    /// and none of the hashed entries.
+    ///
+    /// The root nodes are written by [`TrieUpdates::flush`] since the trie tables store them. The
+    /// tries written before have no root node, so the trie is walked even if nothing has changed,
+    /// until the first calculation with changes writes the root node.
+    ///
     /// # Returns
     ///
     /// The state root hash.
     pub fn root(self) -> Result<B256, StateRootError> {
-        match self.calculate(false)? {
+        match self.calculate(CalculationSinks::default())? {
             StateRootProgress::Complete(root, _, _) => Ok(root),
             StateRootProgress::Progress(..) => unreachable!(), // update retenion is disabled
         }
     }
 
+    /// Walks all hashed entries, ignoring the prefix sets, the intermediate state and the
+    /// existing state trie, and aggregates the summary of the accounts along the way.
+    ///
+    /// Every account has to be visited for the summary to be complete, so the root is computed
+    /// from scratch as with [`TriePrefixSets::all_changed`], including the storage roots.
+    ///
+    /// # Returns
+    ///
+    /// The state root hash and the summary of the accounts.
+    pub fn root_with_summary(self) -> Result<(B256, StateSummary), StateRootError> {
+        let mut summary = StateSummary::default();
+        let progress = self
+            .with_prefix_sets(TriePrefixSets::all_changed())
+            .with_intermediate_state(None)
+            .calculate(CalculationSinks { summary: Some(&mut summary), ..Default::default() })?;
+        match progress {
+            StateRootProgress::Complete(root, _, _) => Ok((root, summary)),
+            StateRootProgress::Progress(..) => unreachable!(), // update retenion is disabled
+        }
+    }
+
+    /// Walks all hashed entries, ignoring the prefix sets, the intermediate state and the
+    /// existing state trie, and checks the storage root of every account computed along the way
+    /// against the root hash recorded in the root node of its stored storage trie.
+    ///
+    /// The hashed accounts do not record the storage roots of the account leaves, so the root node
+    /// of the stored storage trie is the only recorded root. The check piggybacks on the
+    /// computation of the state root, so the state is walked only once. A mismatch means that the
+    /// stored storage trie is stale or corrupted and has to be recovered. The non-empty storage
+    /// tries whose root node is not stored, e.g. the single-slot ones or the ones written before
+    /// the root nodes were stored, have no recorded root and are reported as unverified.
+    ///
+    /// # Returns
+    ///
+    /// The state root hash and the outcome of the verification.
+    pub fn root_with_storage_root_verification(
+        self,
+    ) -> Result<(B256, StorageRootVerification), StateRootError> {
+        let mut verification = StorageRootVerification::default();
+        let progress = self
+            .with_prefix_sets(TriePrefixSets::all_changed())
+            .with_intermediate_state(None)
+            .calculate(CalculationSinks {
+                storage_root_verification: Some(&mut verification),
+                ..Default::default()
+            })?;
+        match progress {
+            StateRootProgress::Complete(root, _, _) => Ok((root, verification)),
+            StateRootProgress::Progress(..) => unreachable!(), // update retenion is disabled
+        }
+    }
+
     /// Walks the intermediate nodes of existing state trie (if any) and hashed entries. Feeds the
     /// nodes into the hash builder. Collects the updates in the process.
     ///
+    /// Stops once the threshold is reached or the calculation is cancelled, see
+    /// [`Self::with_cancellation`].
+    ///
     /// # Returns
     ///
     /// The intermediate progress of state root computation.
     pub fn root_with_progress(self) -> Result<StateRootProgress, StateRootError> {
-        self.calculate(true)
+        self.calculate(CalculationSinks { retain_updates: true, ..Default::default() })
+    }
+
+    /// Walks the intermediate nodes of existing state trie (if any) and hashed entries and keeps
+    /// the RLP encoded top-level node of the account trie.
+    ///
+    /// The top-level node is the node whose hash is the state root:
+    /// - the empty string for the empty trie, hashing to [`EMPTY_ROOT_HASH`],
+    /// - the leaf of the only account for a single-account trie,
+    /// - the root branch, or the extension above it if all accounts share the first nibbles, for
+    ///   the larger tries.
+    ///
+    /// The root node is rebuilt from its children rather than read from the database, so the
+    /// walk descends into the root node even if nothing has changed. Ignores the threshold and
+    /// the cancellation.
+    ///
+    /// # Returns
+    ///
+    /// The state root hash and the RLP encoded top-level node.
+    pub fn root_with_top_node(self) -> Result<(B256, Bytes), StateRootError> {
+        let calculator = self.with_root_descended();
+        let mut top_node = Bytes::new();
+        match calculator
+            .calculate(CalculationSinks { top_node: Some(&mut top_node), ..Default::default() })?
+        {
+            StateRootProgress::Complete(root, _, _) => Ok((root, top_node)),
+            StateRootProgress::Progress(..) => unreachable!(), // unreachable threshold
+        }
+    }
+
+    /// Computes the account-only digest of the state, which covers the nonces, balances and
+    /// bytecode hashes of the accounts but not their storage.
+    ///
+    /// The digest is the root of the account trie built with [`EMPTY_ROOT_HASH`] in place of the
+    /// storage root of every account, so changes to the storage never affect it. No storage is
+    /// read, which makes the digest much cheaper than the state root.
+    ///
+    /// **The digest is not canonical.** It differs from the state root as soon as any account
+    /// has storage and must never be compared against one. It is only meant for detecting the
+    /// drift of the account records between two states computed the same way.
+    ///
+    /// The stored account trie is built with the actual storage roots, so it cannot be reused and
+    /// all hashed accounts are walked. Honors the excluded accounts and the value codec, ignores
+    /// the prefix sets, the intermediate state, the threshold and the cancellation.
+    pub fn account_only_digest(self) -> Result<B256, StateRootError> {
+        let mut hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let mut hash_builder = HashBuilder::default();
+        let mut account_rlp = Vec::with_capacity(128);
+        let mut entry = hashed_account_cursor.seek(B256::ZERO)?;
+        while let Some((hashed_address, account)) = entry {
+            if !self.excluded_accounts.contains(&hashed_address) {
+                account_rlp.clear();
+                self.codec.encode_account(account, EMPTY_ROOT_HASH, &mut account_rlp);
+                hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp);
+            }
+            entry = hashed_account_cursor.next()?;
+        }
+        Ok(hash_builder.root())
+    }
+
+    /// Walks the intermediate nodes of existing state trie (if any) and hashed entries and records
+    /// the branch hashes and the leaves combined into the root of the account trie, in the order
+    /// they are fed to the hash builder.
+    ///
+    /// The trail is heavier than the calculation itself, as every leaf is copied into it, so it is
+    /// only meant for the audits that need to re-derive the root without the database, see
+    /// [`AuditTrail::replay`]. The root node is rebuilt from its children as in
+    /// [`Self::root_with_top_node`], so the trail of an unchanged trie holds the hashes of the
+    /// children of the root node. Ignores the threshold and the cancellation.
+    ///
+    /// # Returns
+    ///
+    /// The state root hash and the audit trail.
+    pub fn root_with_audit_trail(self) -> Result<(B256, AuditTrail), StateRootError> {
+        let calculator = self.with_root_descended();
+        let mut audit_trail = AuditTrail::default();
+        match calculator.calculate(CalculationSinks {
+            audit_trail: Some(&mut audit_trail),
+            ..Default::default()
+        })? {
+            StateRootProgress::Complete(root, _, _) => Ok((root, audit_trail)),
+            StateRootProgress::Progress(..) => unreachable!(), // unreachable threshold
+        }
+    }
+
+    /// Disables the threshold and the cancellation and marks the root node as changed, so that the
+    /// walk descends into it rather than returning its stored hash.
+    fn with_root_descended(self) -> Self {
+        let mut calculator = Self { cancellation: None, ..self }.with_no_threshold();
+        let account_prefix_set = &calculator.prefix_sets.account_prefix_set;
+        if !account_prefix_set.all() {
+            let mut extended = PrefixSetMut::from(account_prefix_set.iter().cloned());
+            extended.insert(Nibbles::default());
+            calculator.prefix_sets.account_prefix_set = extended.freeze();
+        }
+        calculator
+    }
+
+    fn calculate(
+        mut self,
+        mut sinks: CalculationSinks<'_>,
+    ) -> Result<StateRootProgress, StateRootError> {
+        if let Some(root) = self.unchanged_root()? {
+            if let Some(reporter) = &self.progress_reporter {
+                reporter.report(&ProgressReport { complete: true, ..Default::default() });
+            }
+            return Ok(StateRootProgress::Complete(root, 0, TrieUpdates::default()))
+        }
+
+        sinks.retain_updates |= self.intermediate_flush.is_some();
+        let Some((depth, source)) = self.prefetch.take() else { return self.walk(None, sinks) };
+
+        // The channel holds a single position, newer positions are dropped while the prefetcher
+        // is busy so that the walk is never blocked on it.
+        let (sender, receiver) = mpsc::sync_channel(1);
+        std::thread::scope(|scope| {
+            scope.spawn(move || match source.prefetch(depth, receiver) {
+                Ok(prefetched) => {
+                    debug!(target: "trie::prefetch", prefetched, "Storage trie prefetcher finished")
+                }
+                Err(error) => {
+                    warn!(target: "trie::prefetch", %error, "Storage trie prefetcher failed")
+                }
+            });
+            // The sender is dropped once the walk returns, which stops the prefetcher.
+            self.walk(Some(sender), sinks)
+        })
+    }
+
+    /// Returns the hash of the stored root node if nothing has changed since it was written.
+    ///
+    /// Returns `None` if there are changes, the calculation is resumed from an intermediate state,
+    /// some accounts are excluded, some storage roots are overridden, the leaf values are not
+    /// encoded with the Ethereum codec or the root node is not stored, in which case the trie has
+    /// to be walked.
+    fn unchanged_root(&self) -> Result<Option<B256>, DatabaseError> {
+        if !C::IS_ETHEREUM ||
+            self.previous_state.is_some() ||
+            !self.excluded_accounts.is_empty() ||
+            !self.storage_root_overrides.is_empty() ||
+            !self.prefix_sets.is_empty()
+        {
+            return Ok(None)
+        }
+
+        let root_node =
+            self.trie_cursor_factory.account_trie_cursor()?.seek_exact(Nibbles::default())?;
+        Ok(root_node.and_then(|(_, node)| node.root_hash))
+    }
+
+    /// Returns the hashed address of the first account that is not excluded from the state root.
+    fn first_account(&self) -> Result<Option<B256>, DatabaseError> {
+        let mut cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let mut entry = cursor.seek(B256::ZERO)?;
+        while let Some((hashed_address, _)) = entry {
+            if !self.excluded_accounts.contains(&hashed_address) {
+                return Ok(Some(hashed_address))
+            }
+            entry = cursor.next()?;
+        }
+        Ok(None)
+    }
+
+    /// Returns the account prefix set extended with the excluded accounts and the accounts with
+    /// overridden storage roots, so that the walker descends to them instead of reusing the stored
+    /// nodes above them.
+    fn account_prefix_set(&self) -> PrefixSet {
+        let prefix_set = &self.prefix_sets.account_prefix_set;
+        if (self.excluded_accounts.is_empty() && self.storage_root_overrides.is_empty()) ||
+            prefix_set.all()
+        {
+            return prefix_set.clone()
+        }
+
+        let mut extended = PrefixSetMut::from(prefix_set.iter().cloned());
+        for hashed_address in
+            self.excluded_accounts.iter().chain(self.storage_root_overrides.keys())
+        {
+            extended.insert(Nibbles::unpack(hashed_address));
+        }
+        extended.freeze()
     }
 
-    fn calculate(self, retain_updates: bool) -> Result<StateRootProgress, StateRootError> {
+    fn walk(
+        mut self,
+        prefetch: Option<SyncSender<B256>>,
+        sinks: CalculationSinks<'_>,
+    ) -> Result<StateRootProgress, StateRootError> {
+        let CalculationSinks {
+            retain_updates,
+            mut summary,
+            mut storage_root_verification,
+            top_node,
+            mut audit_trail,
+        } = sinks;
         trace!(target: "trie::state_root", "calculating state root");
         let mut tracker = TrieTracker::default();
         let mut trie_updates = TrieUpdates::default();
@@ -217,40 +697,65 @@ where
         let trie_cursor = self.trie_cursor_factory.account_trie_cursor()?;
 
         let hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let account_prefix_set = self.account_prefix_set();
         let (mut hash_builder, mut account_node_iter) = match self.previous_state {
             Some(state) => {
                 let hash_builder = state.hash_builder.with_updates(retain_updates);
-                let walker = TrieWalker::from_stack(
-                    trie_cursor,
-                    state.walker_stack,
-                    self.prefix_sets.account_prefix_set,
-                )
-                .with_updates(retain_updates);
+                let walker =
+                    TrieWalker::from_stack(trie_cursor, state.walker_stack, account_prefix_set)
+                        .with_updates(retain_updates);
                 let node_iter = TrieNodeIter::new(walker, hashed_account_cursor)
                     .with_last_hashed_key(state.last_account_key);
                 (hash_builder, node_iter)
             }
             None => {
                 let hash_builder = HashBuilder::default().with_updates(retain_updates);
-                let walker = TrieWalker::new(trie_cursor, self.prefix_sets.account_prefix_set)
-                    .with_updates(retain_updates);
+                let walker =
+                    TrieWalker::new(trie_cursor, account_prefix_set).with_updates(retain_updates);
                 let node_iter = TrieNodeIter::new(walker, hashed_account_cursor);
                 (hash_builder, node_iter)
             }
         };
+        if top_node.is_some() {
+            // Every node on the path to the first account is retained, including the top-level
+            // node, which is the leaf of the first account if it is the only one.
+            let target = self.first_account()?.unwrap_or_default();
+            hash_builder = hash_builder
+                .with_proof_retainer(ProofRetainer::from_iter([Nibbles::unpack(target)]));
+        }
 
         let mut account_rlp = Vec::with_capacity(128);
         let mut hashed_entries_walked = 0;
+        let mut report = ProgressReport::default();
+        let mut last_report = Instant::now();
         while let Some(node) = account_node_iter.try_next()? {
             match node {
                 TrieElement::Branch(node) => {
                     tracker.inc_branch();
+                    if let Some(audit_trail) = audit_trail.as_deref_mut() {
+                        audit_trail.record_branch(
+                            node.key.clone(),
+                            node.value,
+                            node.children_are_in_trie,
+                        );
+                    }
                     hash_builder.add_branch(node.key, node.value, node.children_are_in_trie);
                 }
                 TrieElement::Leaf(hashed_address, account) => {
+                    if self.excluded_accounts.contains(&hashed_address) {
+                        continue
+                    }
+
                     tracker.inc_leaf();
+                    if let Some(summary) = summary.as_deref_mut() {
+                        summary.record(&account);
+                    }
                     hashed_entries_walked += 1;
 
+                    if let Some(prefetch) = &prefetch {
+                        let _ = prefetch.try_send(hashed_address);
+                    }
+
                     // We assume we can always calculate a storage root without
                     // OOMing. This opens us up to a potential DOS vector if
                     // a contract had too many storage entries and they were
@@ -258,41 +763,102 @@ where
                     // progress.
                     // TODO: We can consider introducing the TrieProgress::Progress/Complete
                     // abstraction inside StorageRoot, but let's give it a try as-is for now.
-                    let storage_root_calculator = StorageRoot::new_hashed(
-                        self.trie_cursor_factory.clone(),
-                        self.hashed_cursor_factory.clone(),
-                        hashed_address,
-                        #[cfg(feature = "metrics")]
-                        self.metrics.storage_trie.clone(),
-                    )
-                    .with_prefix_set(
-                        self.prefix_sets
-                            .storage_prefix_sets
-                            .get(&hashed_address)
-                            .cloned()
-                            .unwrap_or_default(),
-                    );
-
-                    let storage_root = if retain_updates {
-                        let (root, storage_slots_walked, updates) =
-                            storage_root_calculator.root_with_updates()?;
-                        hashed_entries_walked += storage_slots_walked;
-                        trie_updates.extend(updates);
-                        root
+                    let storage_root = if let Some(storage_root) =
+                        self.storage_root_overrides.get(&hashed_address)
+                    {
+                        *storage_root
                     } else {
-                        storage_root_calculator.root()?
+                        let storage_root_calculator = StorageRoot::new_hashed(
+                            self.trie_cursor_factory.clone(),
+                            self.hashed_cursor_factory.clone(),
+                            hashed_address,
+                            #[cfg(feature = "metrics")]
+                            self.metrics.storage_trie.clone(),
+                        )
+                        .with_prefix_set(self.prefix_sets.storage_prefix_set(&hashed_address))
+                        .with_value_codec(self.codec.clone());
+
+                        if retain_updates {
+                            let (root, storage_slots_walked, updates) =
+                                storage_root_calculator.root_with_updates()?;
+                            hashed_entries_walked += storage_slots_walked;
+                            trie_updates.extend(updates);
+                            root
+                        } else {
+                            storage_root_calculator.root()?
+                        }
                     };
 
+                    if let Some(verification) = storage_root_verification.as_deref_mut() {
+                        let recorded = self
+                            .trie_cursor_factory
+                            .storage_tries_cursor(hashed_address)?
+                            .seek_exact(Nibbles::default())?
+                            .and_then(|(_, node)| node.root_hash);
+                        match recorded {
+                            Some(recorded) if recorded != storage_root => {
+                                verification.mismatches.push(StorageRootMismatch {
+                                    hashed_address,
+                                    root: GotExpected::new(storage_root, recorded),
+                                })
+                            }
+                            None if storage_root != EMPTY_ROOT_HASH => {
+                                verification.unverified.push(hashed_address)
+                            }
+                            _ => {}
+                        }
+                    }
+
                     account_rlp.clear();
-                    let account = TrieAccount::from((account, storage_root));
-                    account.encode(&mut account_rlp as &mut dyn BufMut);
+                    self.codec.encode_account(account, storage_root, &mut account_rlp);
+                    if let Some(audit_trail) = audit_trail.as_deref_mut() {
+                        audit_trail.record_leaf(Nibbles::unpack(hashed_address), &account_rlp);
+                    }
                     hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp);
 
+                    report.accounts_processed += 1;
+                    report.hashed_entries_walked = hashed_entries_walked;
+                    report.last_account_key = Some(hashed_address);
+                    if let Some(reporter) = &self.progress_reporter {
+                        if last_report.elapsed() >= reporter.interval() {
+                            reporter.report(&report);
+                            last_report = Instant::now();
+                        }
+                    }
+
                     // Decide if we need to return intermediate progress.
                     let total_updates_len = trie_updates.len() +
                         account_node_iter.walker.updates_len() +
                         hash_builder.updates_len();
-                    if retain_updates && total_updates_len as u64 >= self.threshold {
+                    let threshold_reached = total_updates_len as u64 >= self.threshold;
+                    if retain_updates && threshold_reached {
+                        if let Some(flush) = &mut self.intermediate_flush {
+                            // The nodes of the passed subtrees are final, write them and continue.
+                            let (builder, hash_builder_updates) = hash_builder.split();
+                            hash_builder = builder.with_updates(true);
+                            trie_updates.extend(account_node_iter.walker.take_updates());
+                            trie_updates.extend_with_account_updates(hash_builder_updates);
+                            flush(std::mem::take(&mut trie_updates))?;
+                            continue
+                        }
+                    }
+
+                    let cancelled = self
+                        .cancellation
+                        .as_ref()
+                        .is_some_and(|cancellation| cancellation.load(Ordering::Relaxed));
+                    if retain_updates &&
+                        self.intermediate_flush.is_none() &&
+                        (threshold_reached || cancelled)
+                    {
+                        if cancelled {
+                            debug!(
+                                target: "trie::state_root",
+                                last_account_key = %hashed_address,
+                                "state root calculation cancelled"
+                            );
+                        }
+
                         let (walker_stack, walker_updates) = account_node_iter.walker.split();
                         let (hash_builder, hash_builder_updates) = hash_builder.split();
 
@@ -305,6 +871,9 @@ where
                         trie_updates.extend(walker_updates);
                         trie_updates.extend_with_account_updates(hash_builder_updates);
 
+                        if let Some(reporter) = &self.progress_reporter {
+                            reporter.report(&report);
+                        }
                         return Ok(StateRootProgress::Progress(
                             Box::new(state),
                             hashed_entries_walked,
@@ -316,12 +885,22 @@ where
         }
 
         let root = hash_builder.root();
+        if let Some(top_node) = top_node {
+            *top_node = hash_builder
+                .take_proofs()
+                .into_values()
+                .next()
+                .unwrap_or_else(|| Bytes::from_static(&[EMPTY_STRING_CODE]));
+        }
 
         trie_updates.finalize_state_updates(
             account_node_iter.walker,
             hash_builder,
             self.prefix_sets.destroyed_accounts,
         );
+        if let Some(flush) = &mut self.intermediate_flush {
+            flush(std::mem::take(&mut trie_updates))?;
+        }
 
         let stats = tracker.finish();
 
@@ -337,13 +916,83 @@ where
             "calculated state root"
         );
 
+        if let Some(reporter) = &self.progress_reporter {
+            reporter.report(&ProgressReport { complete: true, hashed_entries_walked, ..report });
+        }
         Ok(StateRootProgress::Complete(root, hashed_entries_walked, trie_updates))
     }
 }
 
+/// The outputs of [`StateRoot::calculate`] collected along with the root, each one only if
+/// requested.
+#[derive(Default, Debug)]
+struct CalculationSinks<'a> {
+    /// Whether to retain the trie updates.
+    retain_updates: bool,
+    /// The summary of the walked accounts.
+    summary: Option<&'a mut StateSummary>,
+    /// The outcome of the verification of the storage roots against the stored storage tries.
+    storage_root_verification: Option<&'a mut StorageRootVerification>,
+    /// The RLP encoded top-level node of the account trie.
+    top_node: Option<&'a mut Bytes>,
+    /// The branch hashes and the leaves fed to the hash builder.
+    audit_trail: Option<&'a mut AuditTrail>,
+}
+
+/// The outcome of the verification of the storage roots, see
+/// [`StateRoot::root_with_storage_root_verification`].
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct StorageRootVerification {
+    /// The storage roots differing from the recorded ones, in the order of the hashed addresses.
+    pub mismatches: Vec<StorageRootMismatch>,
+    /// The hashed addresses of the accounts with storage whose storage trie records no root, in
+    /// order.
+    pub unverified: Vec<B256>,
+}
+
+/// The storage root of an account that differs from the root hash recorded in its stored storage
+/// trie, see [`StateRoot::root_with_storage_root_verification`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct StorageRootMismatch {
+    /// The hashed address of the account.
+    pub hashed_address: B256,
+    /// The storage root computed from the hashed storage and the recorded root hash.
+    pub root: GotExpected<B256>,
+}
+
+/// The key of the account whose storage trie is computed by [`StorageRoot`].
+///
+/// Distinguishes the raw addresses from the hashed ones, so that an unhashed address is never
+/// used as a hashed one by mistake.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum StorageKey {
+    /// The raw address of the account, hashed before the calculation.
+    Raw(Address),
+    /// The hashed address of the account.
+    Hashed(B256),
+}
+
+impl StorageKey {
+    /// Returns the hashed address of the account.
+    pub fn hashed_address(&self) -> B256 {
+        match self {
+            Self::Raw(address) => keccak256(address),
+            Self::Hashed(hashed_address) => *hashed_address,
+        }
+    }
+}
+
+/// Returns `true` if the account with the given raw address is present in the hashed state.
+///
+/// The storage root of an absent account is the empty root, same as of an account without storage,
+/// see [`StorageRoot::root_if_exists`] to tell the two apart.
+pub fn account_exists<TX: DbTx>(tx: &TX, address: Address) -> Result<bool, DatabaseError> {
+    Ok(tx.get::<tables::HashedAccounts>(keccak256(address))?.is_some())
+}
+
 /// `StorageRoot` is used to compute the root node of an account storage trie.
 #[derive(Debug)]
-pub struct StorageRoot<T, H> {
+pub struct StorageRoot<T, H, C = EthereumValueCodec, F = fn(B256, U256)> {
     /// A reference to the database transaction.
     pub trie_cursor_factory: T,
     /// The factory for hashed cursors.
@@ -352,6 +1001,16 @@ pub struct StorageRoot<T, H> {
     pub hashed_address: B256,
     /// The set of storage slot prefixes that have changed.
     pub prefix_set: PrefixSet,
+    /// Flag indicating whether the account is known to have no storage.
+    known_empty: bool,
+    /// The encoding of the leaf values.
+    codec: C,
+    /// The callback invoked with every walked storage leaf.
+    on_slot: Option<F>,
+    /// Flag indicating whether the nodes hashed during the calculation are accounted.
+    hash_accounting: bool,
+    /// Flag indicating whether the slots with zero values are included as leaves.
+    include_zero_slots: bool,
     /// Storage root metrics.
     #[cfg(feature = "metrics")]
     metrics: TrieRootMetrics,
@@ -364,11 +1023,27 @@ impl<T, H> StorageRoot<T, H> {
         hashed_cursor_factory: H,
         address: Address,
         #[cfg(feature = "metrics")] metrics: TrieRootMetrics,
+    ) -> Self {
+        Self::new_with_key(
+            trie_cursor_factory,
+            hashed_cursor_factory,
+            StorageKey::Raw(address),
+            #[cfg(feature = "metrics")]
+            metrics,
+        )
+    }
+
+    /// Creates a new storage root calculator given either a raw or a hashed address.
+    pub fn new_with_key(
+        trie_cursor_factory: T,
+        hashed_cursor_factory: H,
+        key: StorageKey,
+        #[cfg(feature = "metrics")] metrics: TrieRootMetrics,
     ) -> Self {
         Self::new_hashed(
             trie_cursor_factory,
             hashed_cursor_factory,
-            keccak256(address),
+            key.hashed_address(),
             #[cfg(feature = "metrics")]
             metrics,
         )
@@ -386,36 +1061,109 @@ impl<T, H> StorageRoot<T, H> {
             hashed_cursor_factory,
             hashed_address,
             prefix_set: PrefixSet::default(),
+            known_empty: false,
+            codec: EthereumValueCodec,
+            on_slot: None,
+            hash_accounting: false,
+            include_zero_slots: false,
             #[cfg(feature = "metrics")]
             metrics,
         }
     }
+}
 
+impl<T, H, C, F> StorageRoot<T, H, C, F> {
     /// Set the changed prefixes.
     pub fn with_prefix_set(mut self, prefix_set: PrefixSet) -> Self {
         self.prefix_set = prefix_set;
         self
     }
 
+    /// Set the hint that the account is known to have no storage, e.g. from execution metadata.
+    ///
+    /// If set, the calculation short-circuits to the empty root without creating any cursors.
+    /// Setting the hint for an account that does have storage results in an incorrect root.
+    pub const fn with_known_empty(mut self, known_empty: bool) -> Self {
+        self.known_empty = known_empty;
+        self
+    }
+
+    /// Enable the accounting of the nodes hashed during the calculation, reported by
+    /// [`TrieStats::nodes_hashed`] and [`TrieStats::bytes_hashed`] of [`Self::root_with_stats`],
+    /// e.g. to check the sizes of the encoded nodes in tests.
+    ///
+    /// The nodes are hashed inside of the [`HashBuilder`], so the accounting retains all nodes it
+    /// builds and measures their encodings. This requires reading the storage of the account
+    /// upfront, so the accounting is meant for testing only.
+    pub const fn with_hash_accounting(mut self, enabled: bool) -> Self {
+        self.hash_accounting = enabled;
+        self
+    }
+
+    /// Set whether the slots with zero values are included in the trie, for debugging only.
+    ///
+    /// The consensus storage trie has no leaves for the slots with zero values, which are deleted
+    /// rather than stored, so by default the zero-valued entries of the hashed storage are skipped.
+    /// Including them computes a non-canonical root that differs from the consensus one whenever
+    /// such entries exist, e.g. to tell whether the zero values written to the hashed storage by
+    /// mistake explain an unexpected root. The slots zeroed by the hashed post state are deleted
+    /// either way. The storage proofs of [`crate::proof::Proof`] skip the zero-valued entries like
+    /// the default.
+    pub const fn include_zero_slots(mut self, include: bool) -> Self {
+        self.include_zero_slots = include;
+        self
+    }
+
     /// Set the hashed cursor factory.
-    pub fn with_hashed_cursor_factory<HF>(self, hashed_cursor_factory: HF) -> StorageRoot<T, HF> {
-        StorageRoot {
-            trie_cursor_factory: self.trie_cursor_factory,
-            hashed_cursor_factory,
-            hashed_address: self.hashed_address,
-            prefix_set: self.prefix_set,
-            #[cfg(feature = "metrics")]
-            metrics: self.metrics,
-        }
+    pub fn with_hashed_cursor_factory<HF>(
+        self,
+        hashed_cursor_factory: HF,
+    ) -> StorageRoot<T, HF, C, F> {
+        self.map_parts(|trie, _, codec, on_slot| (trie, hashed_cursor_factory, codec, on_slot))
     }
 
     /// Set the trie cursor factory.
-    pub fn with_trie_cursor_factory<TF>(self, trie_cursor_factory: TF) -> StorageRoot<TF, H> {
+    pub fn with_trie_cursor_factory<TF>(self, trie_cursor_factory: TF) -> StorageRoot<TF, H, C, F> {
+        self.map_parts(|_, hashed, codec, on_slot| (trie_cursor_factory, hashed, codec, on_slot))
+    }
+
+    /// Set the encoding of the storage leaf values.
+    pub fn with_value_codec<VC>(self, codec: VC) -> StorageRoot<T, H, VC, F> {
+        self.map_parts(|trie, hashed, _, on_slot| (trie, hashed, codec, on_slot))
+    }
+
+    /// Set the callback invoked with the hashed slot and the value of every storage leaf walked
+    /// during the calculation, in ascending order of the hashed slots.
+    ///
+    /// The subtries that are unchanged according to the prefix set are not walked, their leaves
+    /// are skipped. To stream all slots of the account, mark all keys as changed with
+    /// [`PrefixSetMut::all`](crate::prefix_set::PrefixSetMut::all). The callback is not invoked
+    /// for an account without storage.
+    pub fn on_slot<G>(self, on_slot: G) -> StorageRoot<T, H, C, G>
+    where
+        G: FnMut(B256, U256),
+    {
+        self.map_parts(|trie, hashed, codec, _| (trie, hashed, codec, Some(on_slot)))
+    }
+
+    /// Replaces the fields the type of the calculator depends on, i.e. the cursor factories, the
+    /// value codec and the slot callback, keeping the rest of the configuration.
+    fn map_parts<TF, HF, VC, G>(
+        self,
+        map: impl FnOnce(T, H, C, Option<F>) -> (TF, HF, VC, Option<G>),
+    ) -> StorageRoot<TF, HF, VC, G> {
+        let (trie_cursor_factory, hashed_cursor_factory, codec, on_slot) =
+            map(self.trie_cursor_factory, self.hashed_cursor_factory, self.codec, self.on_slot);
         StorageRoot {
             trie_cursor_factory,
-            hashed_cursor_factory: self.hashed_cursor_factory,
+            hashed_cursor_factory,
             hashed_address: self.hashed_address,
             prefix_set: self.prefix_set,
+            known_empty: self.known_empty,
+            codec,
+            on_slot,
+            hash_accounting: self.hash_accounting,
+            include_zero_slots: self.include_zero_slots,
             #[cfg(feature = "metrics")]
             metrics: self.metrics,
         }
@@ -425,31 +1173,33 @@ impl<T, H> StorageRoot<T, H> {
 impl<'a, TX: DbTx> StorageRoot<&'a TX, &'a TX> {
     /// Create a new storage root calculator from database transaction and raw address.
     pub fn from_tx(tx: &'a TX, address: Address) -> Self {
-        Self::new(
-            tx,
-            tx,
-            address,
-            #[cfg(feature = "metrics")]
-            TrieRootMetrics::new(TrieType::Storage),
-        )
+        Self::from_tx_key(tx, StorageKey::Raw(address))
     }
 
     /// Create a new storage root calculator from database transaction and hashed address.
     pub fn from_tx_hashed(tx: &'a TX, hashed_address: B256) -> Self {
-        Self::new_hashed(
+        Self::from_tx_key(tx, StorageKey::Hashed(hashed_address))
+    }
+
+    /// Create a new storage root calculator from database transaction and either a raw or a
+    /// hashed address.
+    pub fn from_tx_key(tx: &'a TX, key: StorageKey) -> Self {
+        Self::new_with_key(
             tx,
             tx,
-            hashed_address,
+            key,
             #[cfg(feature = "metrics")]
             TrieRootMetrics::new(TrieType::Storage),
         )
     }
 }
 
-impl<T, H> StorageRoot<T, H>
+impl<T, H, C, F> StorageRoot<T, H, C, F>
 where
     T: TrieCursorFactory,
     H: HashedCursorFactory,
+    C: ValueCodec,
+    F: FnMut(B256, U256),
 {
     /// Walks the hashed storage table entries for a given address and calculates the storage root.
     ///
@@ -470,6 +1220,34 @@ where
         Ok(root)
     }
 
+    /// Walks the hashed storage table entries for a given address and calculates the storage root.
+    ///
+    /// # Returns
+    ///
+    /// The storage root and the stats of the calculation.
+    pub fn root_with_stats(self) -> Result<(B256, TrieStats), StorageRootError> {
+        let (root, _, _, _, stats) = self.calculate_with_proofs(false, None)?;
+        Ok((root, stats))
+    }
+
+    /// Calculates the storage root if the account exists.
+    ///
+    /// # Returns
+    ///
+    /// The storage root or `None` if there is no account with the given address, whereas
+    /// [`Self::root`] returns the empty root for both an absent account and an account without
+    /// storage.
+    pub fn root_if_exists(self) -> Result<Option<B256>, StorageRootError> {
+        let mut hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let exists = hashed_account_cursor
+            .seek(self.hashed_address)?
+            .is_some_and(|(hashed_address, _)| hashed_address == self.hashed_address);
+        if !exists {
+            return Ok(None)
+        }
+        self.root().map(Some)
+    }
+
     /// Walks the hashed storage table entries for a given address and calculates the storage root.
     ///
     /// # Returns
@@ -480,18 +1258,46 @@ where
         self,
         retain_updates: bool,
     ) -> Result<(B256, usize, TrieUpdates), StorageRootError> {
+        let (root, storage_slots_walked, trie_updates, _, _) =
+            self.calculate_with_proofs(retain_updates, None)?;
+        Ok((root, storage_slots_walked, trie_updates))
+    }
+
+    /// Walks the hashed storage table entries for a given address and calculates the storage root,
+    /// retaining the nodes along the paths of the given hashed slots.
+    ///
+    /// # Returns
+    ///
+    /// The storage root, the trie updates and the retained nodes keyed by their path.
+    pub(crate) fn root_with_updates_and_proofs(
+        self,
+        targets: impl IntoIterator<Item = Nibbles>,
+    ) -> Result<(B256, TrieUpdates, BTreeMap<Nibbles, Bytes>), StorageRootError> {
+        let targets = targets.into_iter().collect();
+        let (root, _, trie_updates, proofs, _) = self.calculate_with_proofs(true, Some(targets))?;
+        Ok((root, trie_updates, proofs))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn calculate_with_proofs(
+        mut self,
+        retain_updates: bool,
+        targets: Option<Vec<Nibbles>>,
+    ) -> Result<(B256, usize, TrieUpdates, BTreeMap<Nibbles, Bytes>, TrieStats), StorageRootError>
+    {
         trace!(target: "trie::storage_root", hashed_address = ?self.hashed_address, "calculating storage root");
 
+        // short circuit on storage known to be empty
+        if self.known_empty {
+            return Ok(Self::empty_storage_result(self.hashed_address))
+        }
+
         let mut hashed_storage_cursor =
             self.hashed_cursor_factory.hashed_storage_cursor(self.hashed_address)?;
 
         // short circuit on empty storage
         if hashed_storage_cursor.is_storage_empty()? {
-            return Ok((
-                EMPTY_ROOT_HASH,
-                0,
-                TrieUpdates::from([(TrieKey::StorageTrie(self.hashed_address), TrieOp::Delete)]),
-            ))
+            return Ok(Self::empty_storage_result(self.hashed_address))
         }
 
         let mut tracker = TrieTracker::default();
@@ -499,7 +1305,24 @@ where
         let walker = TrieWalker::new(trie_cursor, self.prefix_set).with_updates(retain_updates);
 
         let mut hash_builder = HashBuilder::default().with_updates(retain_updates);
+        let mut accounting_targets = Vec::new();
+        if self.hash_accounting {
+            let mut hashed_storage_cursor =
+                self.hashed_cursor_factory.hashed_storage_cursor(self.hashed_address)?;
+            let mut entry = hashed_storage_cursor.seek(B256::ZERO)?;
+            while let Some((hashed_slot, _)) = entry {
+                accounting_targets.push(Nibbles::unpack(hashed_slot));
+                entry = hashed_storage_cursor.next()?;
+            }
+        }
+        if targets.is_some() || self.hash_accounting {
+            let retainer = ProofRetainer::from_iter(
+                targets.iter().flatten().cloned().chain(accounting_targets),
+            );
+            hash_builder = hash_builder.with_proof_retainer(retainer);
+        }
 
+        let mut value_rlp = Vec::with_capacity(33);
         let mut storage_node_iter = TrieNodeIter::new(walker, hashed_storage_cursor);
         while let Some(node) = storage_node_iter.try_next()? {
             match node {
@@ -507,17 +1330,28 @@ where
                     tracker.inc_branch();
                     hash_builder.add_branch(node.key, node.value, node.children_are_in_trie);
                 }
+                TrieElement::Leaf(_, value) if value.is_zero() && !self.include_zero_slots => {}
                 TrieElement::Leaf(hashed_slot, value) => {
                     tracker.inc_leaf();
-                    hash_builder.add_leaf(
-                        Nibbles::unpack(hashed_slot),
-                        alloy_rlp::encode_fixed_size(&value).as_ref(),
-                    );
+                    if let Some(on_slot) = &mut self.on_slot {
+                        on_slot(hashed_slot, value);
+                    }
+                    value_rlp.clear();
+                    self.codec.encode_storage(value, &mut value_rlp);
+                    hash_builder.add_leaf(Nibbles::unpack(hashed_slot), &value_rlp);
                 }
             }
         }
 
         let root = hash_builder.root();
+        let mut proofs = hash_builder.take_proofs();
+        tracker.set_subtrees_descended(storage_node_iter.walker.subtrees_descended());
+        if self.hash_accounting {
+            tracker.record_hashed_nodes(&proofs);
+            // Leave out the nodes retained for the accounting only.
+            let targets = targets.unwrap_or_default();
+            proofs.retain(|path, _| targets.iter().any(|target| target.starts_with(path)));
+        }
 
         let mut trie_updates = TrieUpdates::default();
         trie_updates.finalize_storage_updates(
@@ -538,21 +1372,84 @@ where
             duration = ?stats.duration(),
             branches_added = stats.branches_added(),
             leaves_added = stats.leaves_added(),
+            subtrees_descended = stats.subtrees_descended(),
+            bytes_hashed = stats.bytes_hashed(),
             "calculated storage root"
         );
 
         let storage_slots_walked = stats.leaves_added() as usize;
-        Ok((root, storage_slots_walked, trie_updates))
+        Ok((root, storage_slots_walked, trie_updates, proofs, stats))
+    }
+
+    /// The result of the calculation for an account without storage.
+    fn empty_storage_result(
+        hashed_address: B256,
+    ) -> (B256, usize, TrieUpdates, BTreeMap<Nibbles, Bytes>, TrieStats) {
+        (
+            EMPTY_ROOT_HASH,
+            0,
+            TrieUpdates::from([(TrieKey::StorageTrie(hashed_address), TrieOp::Delete)]),
+            BTreeMap::new(),
+            TrieTracker::default().finish(),
+        )
     }
 }
 
+/// Collects the hashes referenced by the subtree of the account trie rooted at the branch node
+/// stored at the given path.
+///
+/// The subtree is walked through the branch nodes stored in the trie, up to `max_depth` levels of
+/// branch nodes with the root of the subtree being the first level, collecting the child hashes of
+/// every visited node. The children that are stored in the trie without a hash, i.e. the nodes
+/// inlined into their parent, are descended into without adding a hash. Pass [`usize::MAX`] to
+/// walk the subtree down to the leaves.
+///
+/// Returns an empty set if no branch node is stored at the given path.
+pub fn referenced_hashes<T: TrieCursorFactory>(
+    trie_cursor_factory: &T,
+    root_path: Nibbles,
+    max_depth: usize,
+) -> Result<HashSet<B256>, DatabaseError> {
+    let mut cursor = trie_cursor_factory.account_trie_cursor()?;
+    let mut hashes = HashSet::new();
+    let mut stack = Vec::new();
+    if max_depth > 0 {
+        stack.extend(cursor.seek_exact(root_path)?.map(|(path, node)| (path, node, 1)));
+    }
+
+    while let Some((path, node, depth)) = stack.pop() {
+        for nibble in 0..16u8 {
+            if node.hash_mask.is_bit_set(nibble) {
+                hashes.insert(node.hash_for_nibble(nibble));
+            }
+
+            // The stored child may be placed deeper if the child is an extension node.
+            if depth < max_depth && node.tree_mask.is_bit_set(nibble) {
+                let mut child_path = path.clone();
+                child_path.push(nibble);
+                if let Some((key, child)) = cursor.seek(child_path.clone())? {
+                    if key.has_prefix(&child_path) {
+                        stack.push((key, child, depth + 1));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(hashes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        hashed_cursor::HashedCursor,
         prefix_set::PrefixSetMut,
+        proof::Proof,
         test_utils::{state_root, state_root_prehashed, storage_root, storage_root_prehashed},
+        trie_cursor::noop::NoopTrieCursorFactory,
     };
+    use alloy_rlp::Encodable;
     use proptest::{prelude::ProptestConfig, proptest};
     use reth_db::{
         cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO},
@@ -564,15 +1461,21 @@ mod tests {
     use reth_primitives::{
         hex_literal::hex,
         proofs::triehash::KeccakHasher,
-        trie::{BranchNodeCompact, TrieMask},
-        Account, StorageEntry, U256,
+        trie::{
+            BranchNodeCompact, StorageTrieEntry, StoredBranchNode, StoredNibbles,
+            StoredNibblesSubKey, TrieAccount, TrieMask,
+        },
+        StorageEntry,
     };
     use reth_provider::{test_utils::create_test_provider_factory, DatabaseProviderRW};
     use std::{
-        collections::{BTreeMap, HashMap},
+        collections::{BTreeMap, HashMap, HashSet},
         ops::Mul,
         str::FromStr,
-        sync::Arc,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
     };
 
     fn insert_account(
@@ -717,105 +1620,747 @@ mod tests {
     }
 
     #[test]
-    // This ensures we return an empty root when there are no storage entries
-    fn test_empty_storage_root() {
+    // This ensures we return an empty root when there are no storage entries
+    fn test_empty_storage_root() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let address = Address::random();
+        let code = "el buen fla";
+        let account = Account {
+            nonce: 155,
+            balance: U256::from(414241124u32),
+            bytecode_hash: Some(keccak256(code)),
+        };
+        insert_account(tx.tx_ref(), address, account, &Default::default());
+        tx.commit().unwrap();
+
+        let tx = factory.provider_rw().unwrap();
+        let got = StorageRoot::from_tx(tx.tx_ref(), address).root().unwrap();
+        assert_eq!(got, EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn known_empty_storage_root() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+        let hashed_address = B256::random();
+
+        let expected =
+            StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address).calculate(true).unwrap();
+        let got = StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address)
+            .with_known_empty(true)
+            .calculate(true)
+            .unwrap();
+        assert_eq!(got, expected);
+        assert_eq!(got.0, EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn storage_root_on_slot() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        // No slots are streamed for an account without storage.
+        let mut slots = Vec::new();
+        let root = StorageRoot::from_tx_hashed(tx.tx_ref(), B256::with_last_byte(1))
+            .on_slot(|slot, value| slots.push((slot, value)))
+            .root()
+            .unwrap();
+        assert_eq!(root, EMPTY_ROOT_HASH);
+        assert!(slots.is_empty());
+
+        let hashed_address = B256::with_last_byte(2);
+        let storage = (1..=30u64)
+            .map(|i| (keccak256(B256::from(U256::from(i))), U256::from(i)))
+            .collect::<BTreeMap<_, _>>();
+        for (hashed_slot, value) in &storage {
+            tx.tx_ref()
+                .put::<tables::HashedStorages>(
+                    hashed_address,
+                    StorageEntry { key: *hashed_slot, value: *value },
+                )
+                .unwrap();
+        }
+
+        // All slots are streamed in ascending order of the hashed slots.
+        let mut slots = Vec::new();
+        let (root, _, updates) = StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address)
+            .on_slot(|slot, value| slots.push((slot, value)))
+            .root_with_updates()
+            .unwrap();
+        assert_eq!(root, storage_root_prehashed(storage.clone()));
+        assert_eq!(slots, storage.clone().into_iter().collect::<Vec<_>>());
+        updates.flush(tx.tx_ref()).unwrap();
+
+        // With the stored nodes, only the leaves of the changed subtries are walked unless all
+        // keys are marked as changed.
+        let changed = *storage.keys().next().unwrap();
+        let mut prefix_set = PrefixSetMut::default();
+        prefix_set.insert(Nibbles::unpack(changed));
+        let mut slots = Vec::new();
+        let root = StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address)
+            .with_prefix_set(prefix_set.freeze())
+            .on_slot(|slot, value| slots.push((slot, value)))
+            .root()
+            .unwrap();
+        assert_eq!(root, storage_root_prehashed(storage.clone()));
+        assert!(slots.contains(&(changed, storage[&changed])));
+        assert!(slots.len() < storage.len());
+        assert!(slots.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        let mut slots = Vec::new();
+        StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address)
+            .with_prefix_set(PrefixSetMut::all().freeze())
+            .on_slot(|slot, value| slots.push((slot, value)))
+            .root()
+            .unwrap();
+        assert_eq!(slots, storage.into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    // This ensures that the walker goes over all the storage slots
+    fn test_storage_root() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let address = Address::random();
+        let storage =
+            BTreeMap::from([(B256::ZERO, U256::from(3)), (B256::with_last_byte(2), U256::from(1))]);
+
+        let code = "el buen fla";
+        let account = Account {
+            nonce: 155,
+            balance: U256::from(414241124u32),
+            bytecode_hash: Some(keccak256(code)),
+        };
+
+        insert_account(tx.tx_ref(), address, account, &storage);
+        tx.commit().unwrap();
+
+        let tx = factory.provider_rw().unwrap();
+        let got = StorageRoot::from_tx(tx.tx_ref(), address).root().unwrap();
+
+        assert_eq!(storage_root(storage.into_iter()), got);
+    }
+
+    #[test]
+    fn storage_root_from_key() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let address = Address::with_last_byte(1);
+        let storage =
+            BTreeMap::from([(B256::ZERO, U256::from(3)), (B256::with_last_byte(2), U256::from(1))]);
+        insert_account(tx.tx_ref(), address, Account::default(), &storage);
+        let expected = storage_root(storage.into_iter());
+
+        let raw = StorageKey::Raw(address);
+        let hashed = StorageKey::Hashed(keccak256(address));
+        assert_eq!(raw.hashed_address(), hashed.hashed_address());
+        assert_eq!(StorageRoot::from_tx_key(tx.tx_ref(), raw).root().unwrap(), expected);
+        assert_eq!(StorageRoot::from_tx_key(tx.tx_ref(), hashed).root().unwrap(), expected);
+
+        // The raw address passed as a hashed one addresses a different, empty, storage trie.
+        let misused = StorageKey::Hashed(address.into_word());
+        assert_eq!(StorageRoot::from_tx_key(tx.tx_ref(), misused).root().unwrap(), EMPTY_ROOT_HASH);
+    }
+
+    type State = BTreeMap<Address, (Account, BTreeMap<B256, U256>)>;
+
+    #[test]
+    fn arbitrary_state_root() {
+        proptest!(
+            ProptestConfig::with_cases(10), | (state: State) | {
+                test_state_root_with_state(state);
+            }
+        );
+    }
+
+    #[test]
+    fn arbitrary_state_root_with_progress() {
+        proptest!(
+            ProptestConfig::with_cases(10), | (state: State) | {
+                let hashed_entries_total = state.len() +
+                    state.values().map(|(_, slots)| slots.len()).sum::<usize>();
+
+                let factory = create_test_provider_factory();
+                let tx = factory.provider_rw().unwrap();
+
+                for (address, (account, storage)) in &state {
+                    insert_account(tx.tx_ref(), *address, *account, storage)
+                }
+                tx.commit().unwrap();
+                let tx =  factory.provider_rw().unwrap();
+
+                let expected = state_root(state);
+
+                let threshold = 10;
+                let mut got = None;
+                let mut hashed_entries_walked = 0;
+
+                let mut intermediate_state: Option<Box<IntermediateStateRootState>> = None;
+                while got.is_none() {
+                    let calculator = StateRoot::from_tx(tx.tx_ref())
+                        .with_threshold(threshold)
+                        .with_intermediate_state(intermediate_state.take().map(|state| *state));
+                    match calculator.root_with_progress().unwrap() {
+                        StateRootProgress::Progress(state, walked, _) => {
+                            intermediate_state = Some(state);
+                            hashed_entries_walked += walked;
+                        },
+                        StateRootProgress::Complete(root, walked, _) => {
+                            got = Some(root);
+                            hashed_entries_walked += walked;
+                        },
+                    };
+                }
+                assert_eq!(expected, got.unwrap());
+                assert_eq!(hashed_entries_total, hashed_entries_walked)
+            }
+        );
+    }
+
+    #[test]
+    fn arbitrary_state_root_with_prefetch() {
+        proptest!(
+            ProptestConfig::with_cases(10), | (state: State) | {
+                let factory = create_test_provider_factory();
+                let tx = factory.provider_rw().unwrap();
+
+                for (address, (account, storage)) in &state {
+                    insert_account(tx.tx_ref(), *address, *account, storage)
+                }
+                tx.commit().unwrap();
+                let expected = state_root(state);
+
+                // The prefetcher reads alongside the read-write transaction of the walk.
+                let tx = factory.provider_rw().unwrap();
+                for depth in [1, 16] {
+                    let (got, updates) = StateRoot::from_tx(tx.tx_ref())
+                        .with_prefetch(depth, factory.db_ref().clone())
+                        .root_with_updates()
+                        .unwrap();
+                    let (_, expected_updates) =
+                        StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+                    assert_eq!(expected, got);
+                    assert_eq!(expected_updates, updates);
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn all_changed_prefix_sets_ignore_stored_nodes() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let mut state = (1..=20u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage = (0..(i as u64 * 2))
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + 1)))
+                    .collect::<BTreeMap<_, _>>();
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+
+        // Modify the hashed state without updating the stored trie nodes.
+        let address = Address::with_last_byte(10);
+        let (account, storage) = state.get_mut(&address).unwrap();
+        account.nonce += 1;
+        let new_slot = BTreeMap::from([(B256::from(U256::from(1_000)), U256::from(1))]);
+        storage.extend(new_slot.clone());
+        insert_account(tx.tx_ref(), address, *account, &new_slot);
+        let expected = state_root(state);
+
+        // The stale nodes are reused unless all keys are marked as changed.
+        assert_ne!(StateRoot::from_tx(tx.tx_ref()).root().unwrap(), expected);
+        let got = StateRoot::from_tx(tx.tx_ref())
+            .with_prefix_sets(TriePrefixSets::all_changed())
+            .root()
+            .unwrap();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn state_root_from_sorted_accounts() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let mut state = (1..=20u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage = (0..(i as u64 % 4))
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + 1)))
+                    .collect::<BTreeMap<_, _>>();
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+
+        // Change the accounts in memory only, leaving the hashed accounts and the stored account
+        // trie nodes stale.
+        state.get_mut(&Address::with_last_byte(3)).unwrap().0.balance = U256::from(1);
+        state.remove(&Address::with_last_byte(7));
+        state.insert(Address::with_last_byte(42), (Account::default(), BTreeMap::default()));
+        let accounts = state
+            .iter()
+            .map(|(address, (account, _))| (keccak256(address), *account))
+            .collect::<BTreeMap<_, _>>();
+
+        let got = StateRoot::from_sorted_accounts(tx.tx_ref(), tx.tx_ref(), accounts.clone())
+            .root()
+            .unwrap();
+        assert_eq!(got, state_root(state.clone()));
+
+        // The root matches the database computation over the equivalent hashed state.
+        let removed = keccak256(Address::with_last_byte(7));
+        tx.tx_ref().delete::<tables::HashedAccounts>(removed, None).unwrap();
+        for (hashed_address, account) in accounts {
+            tx.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+        }
+        let expected = StateRoot::from_tx(tx.tx_ref())
+            .with_prefix_sets(TriePrefixSets::all_changed())
+            .root()
+            .unwrap();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn state_root_with_summary() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let state = (1..=10u8)
+            .map(|i| {
+                let account = Account { balance: U256::from(i), ..Default::default() };
+                let storage = BTreeMap::from([(B256::with_last_byte(i), U256::from(i))]);
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<State>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+
+        // The stored nodes do not make the walk skip any accounts.
+        let (root, summary) = StateRoot::from_tx(tx.tx_ref()).root_with_summary().unwrap();
+        assert_eq!(root, state_root(state));
+        assert_eq!(summary.accounts(), 10);
+        assert_eq!(summary.total_balance(), Some(U256::from(55)));
+
+        // The sum of the balances overflows.
+        let address = Address::with_last_byte(11);
+        let account = Account { balance: U256::MAX, ..Default::default() };
+        insert_account(tx.tx_ref(), address, account, &BTreeMap::default());
+        let (_, summary) = StateRoot::from_tx(tx.tx_ref()).root_with_summary().unwrap();
+        assert_eq!(summary.accounts(), 11);
+        assert_eq!(summary.total_balance(), None);
+    }
+
+    #[test]
+    fn state_root_with_storage_root_verification() {
         let factory = create_test_provider_factory();
         let tx = factory.provider_rw().unwrap();
 
-        let address = Address::random();
-        let code = "el buen fla";
-        let account = Account {
-            nonce: 155,
-            balance: U256::from(414241124u32),
-            bytecode_hash: Some(keccak256(code)),
-        };
-        insert_account(tx.tx_ref(), address, account, &Default::default());
-        tx.commit().unwrap();
+        let mut state = (1..=10u8)
+            .map(|i| {
+                let storage = (0..100 * i as u64)
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + 1)))
+                    .collect::<BTreeMap<_, _>>();
+                (Address::with_last_byte(i), (Account::default(), storage))
+            })
+            .collect::<State>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
 
-        let tx = factory.provider_rw().unwrap();
-        let got = StorageRoot::from_tx(tx.tx_ref(), address).root().unwrap();
-        assert_eq!(got, EMPTY_ROOT_HASH);
+        let (root, verification) =
+            StateRoot::from_tx(tx.tx_ref()).root_with_storage_root_verification().unwrap();
+        assert_eq!(root, state_root(state.clone()));
+        assert_eq!(verification, StorageRootVerification::default());
+
+        // Change a slot without updating the storage trie, leaving the recorded root stale.
+        let address = Address::with_last_byte(5);
+        let hashed_address = keccak256(address);
+        let storage = &mut state.get_mut(&address).unwrap().1;
+        let recorded = storage_root(storage.clone());
+        let slot = B256::ZERO;
+        let entry = StorageEntry { key: keccak256(slot), value: storage[&slot] };
+        tx.tx_ref().delete::<tables::HashedStorages>(hashed_address, Some(entry)).unwrap();
+        storage.insert(slot, U256::MAX);
+        let computed = storage_root(storage.clone());
+        insert_storage(tx.tx_ref(), hashed_address, &BTreeMap::from([(slot, U256::MAX)]));
+
+        // The stale storage trie is not visible to the incremental root.
+        assert_ne!(StateRoot::from_tx(tx.tx_ref()).root().unwrap(), state_root(state.clone()));
+
+        let (root, verification) =
+            StateRoot::from_tx(tx.tx_ref()).root_with_storage_root_verification().unwrap();
+        assert_eq!(root, state_root(state.clone()));
+        assert_eq!(
+            verification.mismatches,
+            vec![StorageRootMismatch {
+                hashed_address,
+                root: GotExpected::new(computed, recorded)
+            }]
+        );
+        assert_eq!(verification.unverified, vec![]);
+
+        // A storage trie without the root node records nothing to compare with.
+        let mut storage_trie_cursor =
+            tx.tx_ref().cursor_dup_write::<tables::StoragesTrie>().unwrap();
+        storage_trie_cursor
+            .seek_by_key_subkey(hashed_address, StoredNibblesSubKey(Nibbles::default()))
+            .unwrap()
+            .unwrap();
+        storage_trie_cursor.delete_current().unwrap();
+        let (root, verification) =
+            StateRoot::from_tx(tx.tx_ref()).root_with_storage_root_verification().unwrap();
+        assert_eq!(root, state_root(state));
+        assert_eq!(verification.mismatches, vec![]);
+        assert_eq!(verification.unverified, vec![hashed_address]);
+    }
+
+    fn trie_tables(
+        tx: &impl DbTx,
+    ) -> (Vec<(StoredNibbles, StoredBranchNode)>, Vec<(B256, StorageTrieEntry)>) {
+        let mut accounts_trie = tx.cursor_read::<tables::AccountsTrie>().unwrap();
+        let mut storages_trie = tx.cursor_dup_read::<tables::StoragesTrie>().unwrap();
+        (
+            accounts_trie.walk(None).unwrap().collect::<Result<_, _>>().unwrap(),
+            storages_trie.walk(None).unwrap().collect::<Result<_, _>>().unwrap(),
+        )
+    }
+
+    /// The hashed cursor factory counting the entries read by its cursors.
+    #[derive(Clone, Debug)]
+    struct RecordingHashedCursorFactory<F> {
+        inner: F,
+        reads: Arc<AtomicUsize>,
+    }
+
+    #[derive(Debug)]
+    struct RecordingHashedCursor<C> {
+        inner: C,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl<F: HashedCursorFactory> HashedCursorFactory for RecordingHashedCursorFactory<F> {
+        type AccountCursor = RecordingHashedCursor<F::AccountCursor>;
+        type StorageCursor = RecordingHashedCursor<F::StorageCursor>;
+
+        fn hashed_account_cursor(&self) -> Result<Self::AccountCursor, DatabaseError> {
+            let inner = self.inner.hashed_account_cursor()?;
+            Ok(RecordingHashedCursor { inner, reads: self.reads.clone() })
+        }
+
+        fn hashed_storage_cursor(
+            &self,
+            hashed_address: B256,
+        ) -> Result<Self::StorageCursor, DatabaseError> {
+            let inner = self.inner.hashed_storage_cursor(hashed_address)?;
+            Ok(RecordingHashedCursor { inner, reads: self.reads.clone() })
+        }
+    }
+
+    impl<C: HashedCursor> HashedCursor for RecordingHashedCursor<C> {
+        type Value = C::Value;
+
+        fn seek(&mut self, key: B256) -> Result<Option<(B256, Self::Value)>, DatabaseError> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.inner.seek(key)
+        }
+
+        fn next(&mut self) -> Result<Option<(B256, Self::Value)>, DatabaseError> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.inner.next()
+        }
+    }
+
+    impl<C: HashedStorageCursor> HashedStorageCursor for RecordingHashedCursor<C> {
+        fn is_storage_empty(&mut self) -> Result<bool, DatabaseError> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.inner.is_storage_empty()
+        }
     }
 
     #[test]
-    // This ensures that the walker goes over all the storage slots
-    fn test_storage_root() {
+    fn unchanged_root_reads_no_leaves() {
         let factory = create_test_provider_factory();
         let tx = factory.provider_rw().unwrap();
+        let state = (0..100u8)
+            .map(|i| {
+                let storage =
+                    BTreeMap::from([(B256::with_last_byte(i), U256::from(i) + U256::from(1))]);
+                (
+                    Address::with_last_byte(i),
+                    (Account { nonce: i as u64, ..Default::default() }, storage),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let (root, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
 
-        let address = Address::random();
-        let storage =
-            BTreeMap::from([(B256::ZERO, U256::from(3)), (B256::with_last_byte(2), U256::from(1))]);
+        // No changesets in the range, so the stored root is returned without reading any leaves.
+        let reads = Arc::new(AtomicUsize::new(0));
+        let recording = RecordingHashedCursorFactory { inner: tx.tx_ref(), reads: reads.clone() };
+        let calculator = StateRoot::incremental_root_calculator(tx.tx_ref(), 1..=1).unwrap();
+        assert!(calculator.prefix_sets.is_empty());
+        assert_eq!(calculator.with_hashed_cursor_factory(recording.clone()).root(), Ok(root));
+        assert_eq!(reads.load(Ordering::Relaxed), 0);
 
-        let code = "el buen fla";
-        let account = Account {
-            nonce: 155,
-            balance: U256::from(414241124u32),
-            bytecode_hash: Some(keccak256(code)),
-        };
+        // Any change walks the trie.
+        let mut prefix_set = PrefixSetMut::default();
+        prefix_set.insert(Nibbles::unpack(keccak256(Address::with_last_byte(1))));
+        let root_with_change = StateRoot::new(tx.tx_ref(), recording)
+            .with_prefix_sets(TriePrefixSets {
+                account_prefix_set: prefix_set.freeze(),
+                ..Default::default()
+            })
+            .root();
+        assert_eq!(root_with_change, Ok(root));
+        assert!(reads.load(Ordering::Relaxed) > 0);
+    }
 
-        insert_account(tx.tx_ref(), address, account, &storage);
-        tx.commit().unwrap();
+    #[test]
+    fn state_root_with_intermediate_flush() {
+        let flushed = create_test_provider_factory();
+        let flushed = flushed.provider_rw().unwrap();
+        let committed = create_test_provider_factory();
+        let committed = committed.provider_rw().unwrap();
+        for provider in [&flushed, &committed] {
+            for i in 0..200u64 {
+                let storage = (0..i % 5)
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(i + 1)))
+                    .collect();
+                let account = Account { nonce: i, ..Default::default() };
+                let address = Address::from_word(B256::from(U256::from(i)));
+                insert_account(provider.tx_ref(), address, account, &storage);
+            }
+        }
 
-        let tx = factory.provider_rw().unwrap();
-        let got = StorageRoot::from_tx(tx.tx_ref(), address).root().unwrap();
+        let (expected, updates) =
+            StateRoot::from_tx(committed.tx_ref()).root_with_updates().unwrap();
+        updates.flush(committed.tx_ref()).unwrap();
 
-        assert_eq!(storage_root(storage.into_iter()), got);
+        // The low threshold writes the updates many times during the walk.
+        let progress = StateRoot::new(NoopTrieCursorFactory, flushed.tx_ref())
+            .with_threshold(10)
+            .with_intermediate_flush(flushed.tx_ref())
+            .root_with_progress()
+            .unwrap();
+        let StateRootProgress::Complete(root, _, updates) = progress else {
+            panic!("no intermediate progress is returned")
+        };
+        assert_eq!(root, expected);
+        assert!(updates.is_empty());
+        assert_eq!(trie_tables(flushed.tx_ref()), trie_tables(committed.tx_ref()));
     }
 
-    type State = BTreeMap<Address, (Account, BTreeMap<B256, U256>)>;
-
     #[test]
-    fn arbitrary_state_root() {
-        proptest!(
-            ProptestConfig::with_cases(10), | (state: State) | {
-                test_state_root_with_state(state);
+    fn cancelled_state_root_resumes() {
+        let cancelled = create_test_provider_factory();
+        let cancelled = cancelled.provider_rw().unwrap();
+        let committed = create_test_provider_factory();
+        let committed = committed.provider_rw().unwrap();
+        for provider in [&cancelled, &committed] {
+            for i in 0..200u64 {
+                let storage = (0..i % 5)
+                    .map(|slot| (B256::from(U256::from(slot)), U256::from(i + 1)))
+                    .collect();
+                let account = Account { nonce: i, ..Default::default() };
+                let address = Address::from_word(B256::from(U256::from(i)));
+                insert_account(provider.tx_ref(), address, account, &storage);
             }
-        );
+        }
+
+        let (expected, updates) =
+            StateRoot::from_tx(committed.tx_ref()).root_with_updates().unwrap();
+        updates.flush(committed.tx_ref()).unwrap();
+
+        // The cancellation is ignored when no intermediate progress can be returned.
+        let cancellation = Arc::new(AtomicBool::new(true));
+        let calculator =
+            StateRoot::from_tx(cancelled.tx_ref()).with_cancellation(cancellation.clone());
+        assert_eq!(calculator.root_with_updates().map(|(root, _)| root), Ok(expected));
+
+        // The walk stops right after the first account despite the high threshold.
+        let first = (0..200u64)
+            .map(|i| keccak256(Address::from_word(B256::from(U256::from(i)))))
+            .min()
+            .unwrap();
+        let progress = StateRoot::from_tx(cancelled.tx_ref())
+            .with_cancellation(cancellation.clone())
+            .root_with_progress()
+            .unwrap();
+        let StateRootProgress::Progress(state, _, updates) = progress else {
+            panic!("the cancelled calculation returns its progress")
+        };
+        assert_eq!(state.last_account_key, first);
+        updates.flush(cancelled.tx_ref()).unwrap();
+
+        // Resuming from the checkpoint completes the root and the stored trie.
+        cancellation.store(false, Ordering::Relaxed);
+        let progress = StateRoot::from_tx(cancelled.tx_ref())
+            .with_cancellation(cancellation)
+            .with_intermediate_state(Some(*state))
+            .root_with_progress()
+            .unwrap();
+        let StateRootProgress::Complete(root, _, updates) = progress else {
+            panic!("the resumed calculation completes")
+        };
+        assert_eq!(root, expected);
+        updates.flush(cancelled.tx_ref()).unwrap();
+        assert_eq!(trie_tables(cancelled.tx_ref()), trie_tables(committed.tx_ref()));
     }
 
     #[test]
-    fn arbitrary_state_root_with_progress() {
-        proptest!(
-            ProptestConfig::with_cases(10), | (state: State) | {
-                let hashed_entries_total = state.len() +
-                    state.values().map(|(_, slots)| slots.len()).sum::<usize>();
+    fn state_root_without_accounts() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
 
-                let factory = create_test_provider_factory();
-                let tx = factory.provider_rw().unwrap();
+        let state = (1..=20u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage = BTreeMap::from([(B256::with_last_byte(i), U256::from(i))]);
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<State>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let (root, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        assert_eq!(root, state_root(state.clone()));
 
-                for (address, (account, storage)) in &state {
-                    insert_account(tx.tx_ref(), *address, *account, storage)
-                }
-                tx.commit().unwrap();
-                let tx =  factory.provider_rw().unwrap();
+        let excluded_addresses = [3u8, 7, 15].map(Address::with_last_byte);
+        let excluded = excluded_addresses.iter().map(keccak256).collect::<HashSet<_>>();
+        let remaining = state
+            .clone()
+            .into_iter()
+            .filter(|(address, _)| !excluded_addresses.contains(address))
+            .collect::<State>();
+        let expected = state_root(remaining);
+
+        // The root is the same with and without the stored nodes and across the calculations.
+        let without_nodes =
+            StateRoot::from_tx(tx.tx_ref()).without_accounts(excluded.clone()).root().unwrap();
+        assert_eq!(without_nodes, expected);
+        updates.flush(tx.tx_ref()).unwrap();
+        for _ in 0..3 {
+            let with_nodes =
+                StateRoot::from_tx(tx.tx_ref()).without_accounts(excluded.clone()).root().unwrap();
+            assert_eq!(with_nodes, expected);
+        }
 
-                let expected = state_root(state);
+        // Excluding no accounts yields the canonical root.
+        let canonical =
+            StateRoot::from_tx(tx.tx_ref()).without_accounts(HashSet::new()).root().unwrap();
+        assert_eq!(canonical, root);
+    }
 
-                let threshold = 10;
-                let mut got = None;
-                let mut hashed_entries_walked = 0;
+    #[test]
+    fn state_root_with_storage_root_overrides() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
 
-                let mut intermediate_state: Option<Box<IntermediateStateRootState>> = None;
-                while got.is_none() {
-                    let calculator = StateRoot::from_tx(tx.tx_ref())
-                        .with_threshold(threshold)
-                        .with_intermediate_state(intermediate_state.take().map(|state| *state));
-                    match calculator.root_with_progress().unwrap() {
-                        StateRootProgress::Progress(state, walked, _) => {
-                            intermediate_state = Some(state);
-                            hashed_entries_walked += walked;
-                        },
-                        StateRootProgress::Complete(root, walked, _) => {
-                            got = Some(root);
-                            hashed_entries_walked += walked;
-                        },
-                    };
-                }
-                assert_eq!(expected, got.unwrap());
-                assert_eq!(hashed_entries_total, hashed_entries_walked)
-            }
-        );
+        let state = (1..=20u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage =
+                    (0..i).map(|slot| (B256::with_last_byte(slot), U256::from(i))).collect();
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<State>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let (root, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+
+        // The overrides matching the actual storage roots yield the canonical root.
+        let actual = [3u8, 7, 15]
+            .map(|i| keccak256(Address::with_last_byte(i)))
+            .into_iter()
+            .map(|hashed_address| {
+                let storage_root =
+                    StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address).root().unwrap();
+                (hashed_address, storage_root)
+            })
+            .collect::<HashMap<_, _>>();
+        let overridden =
+            StateRoot::from_tx(tx.tx_ref()).with_storage_root_overrides(actual.clone()).root();
+        assert_eq!(overridden.unwrap(), root);
+
+        // The overrides take precedence over the stored storage roots.
+        let proposed = BTreeMap::from([(B256::with_last_byte(0xff), U256::from(1))]);
+        let mut overrides = actual;
+        overrides.insert(keccak256(Address::with_last_byte(7)), storage_root(proposed.clone()));
+        // The override of an account that does not exist is ignored.
+        overrides.insert(keccak256(Address::with_last_byte(100)), B256::with_last_byte(1));
+        let mut modified = state;
+        modified.get_mut(&Address::with_last_byte(7)).unwrap().1 = proposed;
+        let expected = state_root(modified);
+
+        let without_nodes = StateRoot::from_tx(tx.tx_ref())
+            .with_storage_root_overrides(overrides.clone())
+            .root()
+            .unwrap();
+        assert_eq!(without_nodes, expected);
+        updates.flush(tx.tx_ref()).unwrap();
+        let with_nodes =
+            StateRoot::from_tx(tx.tx_ref()).with_storage_root_overrides(overrides).root().unwrap();
+        assert_eq!(with_nodes, expected);
+        assert_eq!(StateRoot::from_tx(tx.tx_ref()).root().unwrap(), root);
+    }
+
+    #[test]
+    fn state_root_with_sequential_hint() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let state = (1..=50u8)
+            .map(|i| {
+                let account = Account { nonce: i as u64, ..Default::default() };
+                let storage = BTreeMap::from([(B256::with_last_byte(i), U256::from(i))]);
+                (Address::with_last_byte(i), (account, storage))
+            })
+            .collect::<State>();
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage);
+        }
+        let expected = state_root(state);
+
+        let root = StateRoot::from_tx(tx.tx_ref()).with_sequential_hint().root().unwrap();
+        assert_eq!(root, expected);
+        let (root, updates) =
+            StateRoot::from_tx(tx.tx_ref()).with_sequential_hint().root_with_updates().unwrap();
+        assert_eq!(root, expected);
+        updates.flush(tx.tx_ref()).unwrap();
+        tx.commit().unwrap();
+
+        let provider = factory.provider().unwrap();
+        let root = StateRoot::from_tx(provider.tx_ref())
+            .with_prefix_sets(TriePrefixSets::all_changed())
+            .with_sequential_hint()
+            .root()
+            .unwrap();
+        assert_eq!(root, expected);
     }
 
     fn test_state_root_with_state(state: State) {
@@ -833,6 +2378,83 @@ mod tests {
         assert_eq!(expected, got);
     }
 
+    /// The codec encoding the storage values as fixed size big endian words.
+    #[derive(Clone, Debug)]
+    struct FixedSizeStorageCodec;
+
+    impl ValueCodec for FixedSizeStorageCodec {
+        fn encode_account(&self, account: Account, storage_root: B256, buf: &mut Vec<u8>) {
+            EthereumValueCodec.encode_account(account, storage_root, buf);
+        }
+
+        fn encode_storage(&self, value: U256, buf: &mut Vec<u8>) {
+            buf.extend_from_slice(&value.to_be_bytes::<32>());
+        }
+    }
+
+    #[test]
+    fn state_root_with_value_codec() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+        let state = State::from([
+            (
+                Address::with_last_byte(1),
+                (Account { nonce: 1, ..Default::default() }, BTreeMap::new()),
+            ),
+            (
+                Address::with_last_byte(2),
+                (
+                    Account { balance: U256::from(10), ..Default::default() },
+                    BTreeMap::from([
+                        (B256::ZERO, U256::from(3)),
+                        (B256::with_last_byte(2), U256::from(1)),
+                    ]),
+                ),
+            ),
+        ]);
+        for (address, (account, storage)) in &state {
+            insert_account(tx.tx_ref(), *address, *account, storage)
+        }
+
+        // The default codec reproduces the canonical roots.
+        let got =
+            StateRoot::from_tx(tx.tx_ref()).with_value_codec(EthereumValueCodec).root().unwrap();
+        assert_eq!(got, state_root(state.clone()));
+        let (address, (_, storage)) = state.last_key_value().unwrap();
+        let got = StorageRoot::from_tx(tx.tx_ref(), *address)
+            .with_value_codec(EthereumValueCodec)
+            .root()
+            .unwrap();
+        assert_eq!(got, storage_root(storage.clone()));
+
+        // A custom codec changes the roots of the tries with storage only.
+        let custom_storage_root = StorageRoot::from_tx(tx.tx_ref(), *address)
+            .with_value_codec(FixedSizeStorageCodec)
+            .root()
+            .unwrap();
+        assert_ne!(custom_storage_root, storage_root(storage.clone()));
+        let custom_state_root =
+            StateRoot::from_tx(tx.tx_ref()).with_value_codec(FixedSizeStorageCodec).root().unwrap();
+        let expected = triehash::sec_trie_root::<KeccakHasher, _, _, _>(state.into_iter().map(
+            |(address, (account, storage))| {
+                let storage_root = (!storage.is_empty()).then_some(custom_storage_root);
+                (address, encode_account(account, storage_root))
+            },
+        ));
+        assert_eq!(custom_state_root, expected);
+
+        // The stored root of the Ethereum encoding is not returned for a custom codec.
+        let (root, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+        assert_eq!(StateRoot::from_tx(tx.tx_ref()).root(), Ok(root));
+        let got = StateRoot::from_tx(tx.tx_ref())
+            .with_value_codec(FixedSizeStorageCodec)
+            .with_prefix_sets(TriePrefixSets::all_changed())
+            .root()
+            .unwrap();
+        assert_eq!(got, custom_state_root);
+    }
+
     fn encode_account(account: Account, storage_root: Option<B256>) -> Vec<u8> {
         let account = TrieAccount::from((account, storage_root.unwrap_or(EMPTY_ROOT_HASH)));
         let mut account_rlp = Vec::with_capacity(account.length());
@@ -840,6 +2462,87 @@ mod tests {
         account_rlp
     }
 
+    #[test]
+    fn state_root_with_top_node() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        // The empty trie.
+        assert_eq!(
+            StateRoot::from_tx(tx.tx_ref()).root_with_top_node(),
+            Ok((EMPTY_ROOT_HASH, Bytes::from_static(&[EMPTY_STRING_CODE])))
+        );
+        assert_eq!(keccak256([EMPTY_STRING_CODE]), EMPTY_ROOT_HASH);
+
+        // The leaf of the only account.
+        let account = Account { nonce: 1, ..Default::default() };
+        insert_account(tx.tx_ref(), Address::with_last_byte(0), account, &BTreeMap::new());
+        let (root, top_node) = StateRoot::from_tx(tx.tx_ref()).root_with_top_node().unwrap();
+        assert_eq!(root, StateRoot::from_tx(tx.tx_ref()).root().unwrap());
+        assert_eq!(keccak256(&top_node), root);
+        assert!(top_node.ends_with(&encode_account(account, None)));
+
+        // The root branch, rebuilt from the stored children once nothing has changed.
+        for i in 1..100u8 {
+            let storage = BTreeMap::from([(B256::with_last_byte(i), U256::from(i))]);
+            let account = Account { nonce: i as u64, ..Default::default() };
+            insert_account(tx.tx_ref(), Address::with_last_byte(i), account, &storage);
+        }
+        let (root, top_node) = StateRoot::from_tx(tx.tx_ref()).root_with_top_node().unwrap();
+        assert_eq!(keccak256(&top_node), root);
+        let (expected_root, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        assert_eq!(root, expected_root);
+        updates.flush(tx.tx_ref()).unwrap();
+        assert_eq!(StateRoot::from_tx(tx.tx_ref()).root_with_top_node(), Ok((root, top_node)));
+
+        // The extension above the root branch.
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+        let root = extension_node_trie(&tx);
+        let (got, top_node) = StateRoot::from_tx(tx.tx_ref()).root_with_top_node().unwrap();
+        assert_eq!(got, root);
+        assert_eq!(keccak256(&top_node), root);
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+        assert_eq!(StateRoot::from_tx(tx.tx_ref()).root_with_top_node(), Ok((root, top_node)));
+    }
+
+    #[test]
+    fn account_only_digest_ignores_storage() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        let mut accounts = BTreeMap::new();
+        for i in 0..50u8 {
+            let account = Account { nonce: i as u64, ..Default::default() };
+            let storage =
+                BTreeMap::from([(B256::with_last_byte(i), U256::from(i) + U256::from(1))]);
+            insert_account(tx.tx_ref(), Address::with_last_byte(i), account, &storage);
+            accounts.insert(Address::with_last_byte(i), (account, BTreeMap::<B256, U256>::new()));
+        }
+        let digest = StateRoot::from_tx(tx.tx_ref()).account_only_digest().unwrap();
+        let root = StateRoot::from_tx(tx.tx_ref()).root().unwrap();
+        assert_ne!(digest, root);
+        // The digest is the state root of the same accounts without storage.
+        assert_eq!(digest, state_root(accounts.clone()));
+
+        // Changing only a slot changes the state root but not the digest.
+        let hashed_address = keccak256(Address::with_last_byte(7));
+        tx.tx_ref().delete::<tables::HashedStorages>(hashed_address, None).unwrap();
+        let storage = BTreeMap::from([(B256::with_last_byte(7), U256::from(1_000))]);
+        insert_storage(tx.tx_ref(), hashed_address, &storage);
+        assert_ne!(StateRoot::from_tx(tx.tx_ref()).root().unwrap(), root);
+        assert_eq!(StateRoot::from_tx(tx.tx_ref()).account_only_digest(), Ok(digest));
+
+        // Changing a balance changes the digest.
+        let account = Account { nonce: 7, balance: U256::from(1), ..Default::default() };
+        tx.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+        accounts.insert(Address::with_last_byte(7), (account, BTreeMap::new()));
+        let changed = StateRoot::from_tx(tx.tx_ref()).account_only_digest().unwrap();
+        assert_ne!(changed, digest);
+        assert_eq!(changed, state_root(accounts));
+    }
+
     #[test]
     fn storage_root_regression() {
         let factory = create_test_provider_factory();
@@ -1246,6 +2949,37 @@ mod tests {
         assert_trie_updates(&account_updates);
     }
 
+    #[test]
+    fn referenced_hashes_around_extension_node() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        extension_node_trie(&tx);
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+        let node = tx
+            .tx_ref()
+            .get::<tables::AccountsTrie>(StoredNibbles(Nibbles::from_nibbles([0x3, 0x0, 0xA, 0xF])))
+            .unwrap()
+            .unwrap()
+            .0;
+
+        // The node at 0x3 references the extension node at 0x30 without a hash, the hash is
+        // referenced by the branch node at 0x30af below it.
+        let root_path = Nibbles::from_nibbles([0x3]);
+        assert_eq!(
+            referenced_hashes(&tx.tx_ref(), root_path.clone(), usize::MAX).unwrap(),
+            HashSet::from([node.hashes[0]])
+        );
+        assert!(referenced_hashes(&tx.tx_ref(), root_path.clone(), 1).unwrap().is_empty());
+        assert!(referenced_hashes(&tx.tx_ref(), root_path, 0).unwrap().is_empty());
+
+        // No branch node is stored at the root.
+        assert!(referenced_hashes(&tx.tx_ref(), Nibbles::default(), usize::MAX)
+            .unwrap()
+            .is_empty());
+    }
+
     proptest! {
         #![proptest_config(ProptestConfig {
             cases: 128, ..ProptestConfig::default()
@@ -1310,6 +3044,118 @@ mod tests {
         assert_trie_updates(&storage_updates);
     }
 
+    #[test]
+    fn storage_root_zero_slots() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+
+        // The hashed storage with zero values written by mistake.
+        let hashed_address = B256::with_last_byte(1);
+        let storage = (1..=30u64)
+            .map(|i| {
+                let value = if i % 6 == 0 { U256::ZERO } else { U256::from(i) };
+                (keccak256(B256::from(U256::from(i))), value)
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (hashed_slot, value) in &storage {
+            tx.tx_ref()
+                .put::<tables::HashedStorages>(
+                    hashed_address,
+                    StorageEntry { key: *hashed_slot, value: *value },
+                )
+                .unwrap();
+        }
+        let non_zero = storage.iter().filter(|(_, value)| !value.is_zero());
+
+        // The zero slots are excluded by default, matching the consensus root.
+        let root = StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address).root().unwrap();
+        assert_eq!(root, storage_root_prehashed(non_zero.map(|(slot, value)| (*slot, *value))));
+        assert_eq!(
+            StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address)
+                .include_zero_slots(false)
+                .root()
+                .unwrap(),
+            root
+        );
+
+        // Including them changes the root.
+        let with_zeros = StorageRoot::from_tx_hashed(tx.tx_ref(), hashed_address)
+            .include_zero_slots(true)
+            .root()
+            .unwrap();
+        assert_ne!(with_zeros, root);
+        assert_eq!(with_zeros, storage_root_prehashed(storage));
+    }
+
+    #[test]
+    fn storage_root_hash_accounting() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // The root branch node has two leaf children under the nibbles 0x1 and 0x2.
+        let hashed_address = B256::with_last_byte(1);
+        let slot = |first_byte: u8| {
+            let mut slot = B256::ZERO;
+            slot[0] = first_byte;
+            slot
+        };
+        let slots = [slot(0x10), slot(0x20)];
+        for hashed_slot in slots {
+            let entry = StorageEntry { key: hashed_slot, value: U256::from(1) };
+            tx.put::<tables::HashedStorages>(hashed_address, entry).unwrap();
+        }
+        let encoded_length = |payload_length| {
+            alloy_rlp::Header { list: true, payload_length }.length() + payload_length
+        };
+        // The leaves list the remaining 63 nibbles of the path packed into 32 bytes and the
+        // encoded value, the branch node lists the hashes of both leaves and 15 empty strings.
+        let value = alloy_rlp::encode(U256::from(1));
+        let leaf_length = encoded_length([0u8; 32][..].length() + value[..].length());
+        let branch_length = encoded_length(2 * 33 + 15);
+
+        let (root, stats) = StorageRoot::from_tx_hashed(tx, hashed_address)
+            .with_hash_accounting(true)
+            .root_with_stats()
+            .unwrap();
+        assert_eq!(
+            root,
+            storage_root_prehashed(slots.map(|hashed_slot| (hashed_slot, U256::from(1))))
+        );
+        assert_eq!(stats.nodes_hashed(), 3);
+        assert_eq!(stats.bytes_hashed() as usize, branch_length + 2 * leaf_length);
+
+        // The accounting is disabled by default.
+        let (_, stats) = StorageRoot::from_tx_hashed(tx, hashed_address).root_with_stats().unwrap();
+        assert_eq!((stats.nodes_hashed(), stats.bytes_hashed()), (0, 0));
+
+        // The hashed nodes are the distinct nodes of the proofs of all slots.
+        let address = Address::with_last_byte(2);
+        let storage = (0..100u64)
+            .map(|slot| (B256::from(U256::from(slot)), U256::from(slot + 1)))
+            .collect::<BTreeMap<_, _>>();
+        tx.put::<tables::HashedAccounts>(keccak256(address), Account::default()).unwrap();
+        for (slot, value) in &storage {
+            let entry = StorageEntry { key: keccak256(slot), value: *value };
+            tx.put::<tables::HashedStorages>(keccak256(address), entry).unwrap();
+        }
+        let (root, stats) =
+            StorageRoot::from_tx(tx, address).with_hash_accounting(true).root_with_stats().unwrap();
+        assert_eq!(root, storage_root(storage.clone()));
+
+        let slots = storage.keys().copied().collect::<Vec<_>>();
+        let proof = Proof::new(tx).account_proof(address, &slots).unwrap();
+        let root_node = proof.storage_proofs[0].proof[0].clone();
+        let hashed = proof
+            .storage_proofs
+            .into_iter()
+            .flat_map(|proof| proof.proof)
+            .filter(|node| node.len() >= 32 || *node == root_node)
+            .collect::<HashSet<_>>();
+        assert_eq!(stats.nodes_hashed(), hashed.len() as u64);
+        assert_eq!(stats.bytes_hashed(), hashed.iter().map(|node| node.len() as u64).sum::<u64>());
+    }
+
     fn extension_node_storage_trie(
         tx: &DatabaseProviderRW<Arc<TempDatabase<DatabaseEnv>>>,
         hashed_address: B256,
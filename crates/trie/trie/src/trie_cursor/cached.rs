@@ -0,0 +1,395 @@
+use super::{TrieCursor, TrieCursorFactory};
+use crate::{prefix_set::TriePrefixSets, updates::TrieKey};
+use reth_db::DatabaseError;
+use reth_primitives::{
+    trie::{BranchNodeCompact, Nibbles, StoredNibbles, StoredNibblesSubKey},
+    B256,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// The default maximum number of seeks cached by the [`BranchNodeCache`].
+pub const DEFAULT_MAX_CACHED_SEEKS: usize = 1 << 20;
+
+/// The results of the seeks of a single trie by the sought key.
+type CachedSeeks = BTreeMap<Nibbles, Option<(Nibbles, BranchNodeCompact)>>;
+
+/// The branch nodes read by the trie cursors, carried over from one state root computation to the
+/// next one.
+///
+/// Between consecutive blocks, most of the upper branch nodes of the account trie and of the busy
+/// storage tries stay the same. The cache keeps the results of the seeks of the trie cursors
+/// created by the [`CachedTrieCursorFactory`], so that the next computation reads only the nodes
+/// that were not read before or have changed since.
+///
+/// The trie updates of a computation change only the nodes along the paths of its changed keys,
+/// so once they are written, the cache has to be invalidated with the prefix sets of that
+/// computation, see [`Self::invalidate`]. The cache is cleared once it holds more than the given
+/// number of seeks.
+///
+/// The cache is cheap to clone, the clones share the cached nodes.
+#[derive(Clone, Debug)]
+pub struct BranchNodeCache {
+    /// The cached seeks shared by the clones.
+    inner: Arc<Mutex<CachedNodes>>,
+    /// The maximum number of cached seeks.
+    max_seeks: usize,
+}
+
+/// The cached seeks of the account trie and the storage tries.
+#[derive(Default, Debug)]
+struct CachedNodes {
+    /// The seeks by the trie, `None` for the account trie or the hashed address of the account for
+    /// its storage trie.
+    tries: HashMap<Option<B256>, CachedSeeks>,
+    /// The total number of cached seeks.
+    len: usize,
+    /// The number of seeks served from the cache.
+    hits: u64,
+    /// The number of seeks read through the underlying cursors.
+    misses: u64,
+}
+
+impl Default for BranchNodeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CACHED_SEEKS)
+    }
+}
+
+impl BranchNodeCache {
+    /// Create a new empty cache holding up to the given number of seeks.
+    pub fn new(max_seeks: usize) -> Self {
+        Self { inner: Arc::default(), max_seeks }
+    }
+
+    /// Returns the number of cached seeks.
+    pub fn len(&self) -> usize {
+        self.lock().len
+    }
+
+    /// Returns `true` if no seeks are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of seeks served from the cache and the number of seeks read through the
+    /// underlying cursors since the cache was created.
+    pub fn hits_and_misses(&self) -> (u64, u64) {
+        let inner = self.lock();
+        (inner.hits, inner.misses)
+    }
+
+    /// Removes the cached seeks that might have been affected by the trie updates of the
+    /// computation with the given prefix sets.
+    ///
+    /// A branch node can only be updated, added or removed at a path that is a prefix of a changed
+    /// key, so the seeks of the trie starting at or before such a path and ending at or after it
+    /// are removed. The storage tries of the destroyed accounts and the tries with all keys marked
+    /// as changed are removed entirely.
+    pub fn invalidate(&self, prefix_sets: &TriePrefixSets) {
+        let mut inner = self.lock();
+        if prefix_sets.account_prefix_set.all() {
+            inner.clear();
+            return
+        }
+
+        inner.invalidate_trie(None, prefix_sets.account_prefix_set.iter());
+        for (hashed_address, prefix_set) in &prefix_sets.storage_prefix_sets {
+            if prefix_set.all() {
+                inner.remove_trie(Some(*hashed_address));
+            } else {
+                inner.invalidate_trie(Some(*hashed_address), prefix_set.iter());
+            }
+        }
+        for hashed_address in &prefix_sets.destroyed_accounts {
+            inner.remove_trie(Some(*hashed_address));
+        }
+    }
+
+    /// Removes all cached seeks.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Looks up the result of the seek of the key in the trie.
+    fn get(
+        &self,
+        trie: Option<B256>,
+        key: &Nibbles,
+    ) -> Option<Option<(Nibbles, BranchNodeCompact)>> {
+        let mut inner = self.lock();
+        let entry = inner.tries.get(&trie).and_then(|seeks| seeks.get(key)).cloned();
+        if entry.is_some() {
+            inner.hits += 1;
+        } else {
+            inner.misses += 1;
+        }
+        entry
+    }
+
+    /// Caches the result of the seek of the key in the trie.
+    fn insert(
+        &self,
+        trie: Option<B256>,
+        key: Nibbles,
+        entry: Option<(Nibbles, BranchNodeCompact)>,
+    ) {
+        let mut inner = self.lock();
+        if inner.len >= self.max_seeks {
+            inner.clear();
+        }
+        if inner.tries.entry(trie).or_default().insert(key, entry).is_none() {
+            inner.len += 1;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CachedNodes> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl CachedNodes {
+    /// Removes the seeks of the trie that might have been affected by the changes of the keys.
+    fn invalidate_trie<'a>(
+        &mut self,
+        trie: Option<B256>,
+        changed_keys: impl Iterator<Item = &'a Nibbles>,
+    ) {
+        let Some(seeks) = self.tries.get_mut(&trie) else { return };
+
+        let paths = changed_keys
+            .flat_map(|key| (0..=key.len()).map(|len| Nibbles::from_nibbles_unchecked(&key[..len])))
+            .collect::<BTreeSet<_>>();
+        for path in paths {
+            // The results of the seeks are ordered as the sought keys, so the seeks ending before
+            // the path all precede the last one that does.
+            let stale = seeks
+                .range(..=path.clone())
+                .rev()
+                .take_while(|(_, entry)| entry.as_ref().map_or(true, |(found, _)| *found >= path))
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            for key in stale {
+                seeks.remove(&key);
+                self.len -= 1;
+            }
+        }
+    }
+
+    /// Removes all seeks of the trie.
+    fn remove_trie(&mut self, trie: Option<B256>) {
+        if let Some(seeks) = self.tries.remove(&trie) {
+            self.len -= seeks.len();
+        }
+    }
+
+    /// Removes all seeks.
+    fn clear(&mut self) {
+        self.tries.clear();
+        self.len = 0;
+    }
+}
+
+/// The trie cursor factory wrapping the cursors of the underlying factory in
+/// [`CachedTrieCursor`]s sharing the [`BranchNodeCache`].
+#[derive(Debug, Clone)]
+pub struct CachedTrieCursorFactory<F> {
+    /// The underlying factory.
+    factory: F,
+    /// The cache of the seeks.
+    cache: BranchNodeCache,
+}
+
+impl<F> CachedTrieCursorFactory<F> {
+    /// Create a new cached trie cursor factory.
+    pub const fn new(factory: F, cache: BranchNodeCache) -> Self {
+        Self { factory, cache }
+    }
+}
+
+impl<F: TrieCursorFactory> TrieCursorFactory for CachedTrieCursorFactory<F> {
+    fn account_trie_cursor(&self) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.factory.account_trie_cursor()?;
+        Ok(Box::new(CachedTrieCursor::new(cursor, None, self.cache.clone())))
+    }
+
+    fn storage_tries_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.factory.storage_tries_cursor(hashed_address)?;
+        Ok(Box::new(CachedTrieCursor::new(cursor, Some(hashed_address), self.cache.clone())))
+    }
+}
+
+/// The trie cursor serving the seeks from the [`BranchNodeCache`] and caching the seeks read
+/// through the underlying cursor.
+///
+/// The exact seeks are served from the cached seeks of the same key, so the underlying cursor is
+/// only ever sought with [`TrieCursor::seek`].
+#[derive(Debug)]
+pub struct CachedTrieCursor<C> {
+    /// The underlying cursor.
+    cursor: C,
+    /// The hashed address of the account for a storage trie cursor, `None` for the account trie.
+    hashed_address: Option<B256>,
+    /// The cache of the seeks.
+    cache: BranchNodeCache,
+    /// The key of the last returned node.
+    last_key: Option<Nibbles>,
+}
+
+impl<C> CachedTrieCursor<C> {
+    /// Create a new cached trie cursor.
+    pub const fn new(cursor: C, hashed_address: Option<B256>, cache: BranchNodeCache) -> Self {
+        Self { cursor, hashed_address, cache, last_key: None }
+    }
+}
+
+impl<C: TrieCursor> TrieCursor for CachedTrieCursor<C> {
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = self.seek(key.clone())?.filter(|(found, _)| *found == key);
+        if entry.is_none() {
+            self.last_key = None;
+        }
+        Ok(entry)
+    }
+
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = match self.cache.get(self.hashed_address, &key) {
+            Some(entry) => entry,
+            None => {
+                let entry = self.cursor.seek(key.clone())?;
+                self.cache.insert(self.hashed_address, key, entry.clone());
+                entry
+            }
+        };
+        self.last_key = entry.as_ref().map(|(found, _)| found.clone());
+        Ok(entry)
+    }
+
+    fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+        Ok(self.last_key.clone().map(|key| match self.hashed_address {
+            Some(hashed_address) => TrieKey::StorageNode(hashed_address, StoredNibblesSubKey(key)),
+            None => TrieKey::AccountNode(StoredNibbles(key)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix_set::PrefixSetMut, StateRoot};
+    use reth_db::{
+        cursor::DbDupCursorRO,
+        tables,
+        transaction::{DbTx, DbTxMut},
+    };
+    use reth_primitives::{keccak256, Account, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    #[test]
+    fn cached_roots_of_consecutive_blocks() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed_addresses =
+            (0..1_000u64).map(|i| keccak256(B256::from(U256::from(i)))).collect::<Vec<_>>();
+        for (i, hashed_address) in hashed_addresses.iter().enumerate() {
+            let account = Account { nonce: i as u64, ..Default::default() };
+            tx.put::<tables::HashedAccounts>(*hashed_address, account).unwrap();
+            for slot in 0..(i % 50) as u64 {
+                let entry = StorageEntry {
+                    key: keccak256(B256::from(U256::from(slot))),
+                    value: U256::from(slot + 1),
+                };
+                tx.put::<tables::HashedStorages>(*hashed_address, entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        let cache = BranchNodeCache::default();
+        for block in 1..=10u64 {
+            // Change scattered accounts and slots, destroying some of the accounts.
+            let mut account_prefix_set = PrefixSetMut::default();
+            let mut storage_prefix_sets = HashMap::<B256, PrefixSetMut>::new();
+            let mut destroyed_accounts = std::collections::HashSet::new();
+            for i in 0..20u64 {
+                let hashed_address = &hashed_addresses[((block * 37 + i * 53) % 1_000) as usize];
+                account_prefix_set.insert(Nibbles::unpack(hashed_address));
+                if (block + i) % 10 == 0 {
+                    tx.delete::<tables::HashedAccounts>(*hashed_address, None).unwrap();
+                    tx.delete::<tables::HashedStorages>(*hashed_address, None).unwrap();
+                    destroyed_accounts.insert(*hashed_address);
+                    continue
+                }
+                let account = Account { nonce: block, ..Default::default() };
+                tx.put::<tables::HashedAccounts>(*hashed_address, account).unwrap();
+                for slot in (block + i..60).step_by(15) {
+                    let hashed_slot = keccak256(B256::from(U256::from(slot)));
+                    let entry = StorageEntry { key: hashed_slot, value: U256::from(block) };
+                    let existing = tx
+                        .cursor_dup_read::<tables::HashedStorages>()
+                        .unwrap()
+                        .seek_by_key_subkey(*hashed_address, hashed_slot)
+                        .unwrap()
+                        .filter(|existing| existing.key == hashed_slot);
+                    tx.delete::<tables::HashedStorages>(*hashed_address, existing).unwrap();
+                    tx.put::<tables::HashedStorages>(*hashed_address, entry).unwrap();
+                    storage_prefix_sets
+                        .entry(*hashed_address)
+                        .or_default()
+                        .insert(Nibbles::unpack(hashed_slot));
+                }
+            }
+            let prefix_sets = TriePrefixSets {
+                account_prefix_set: account_prefix_set.freeze(),
+                storage_prefix_sets: storage_prefix_sets
+                    .into_iter()
+                    .map(|(hashed_address, prefix_set)| (hashed_address, prefix_set.freeze()))
+                    .collect(),
+                destroyed_accounts,
+            };
+
+            let (root, updates) = StateRoot::from_tx(tx)
+                .with_trie_cursor_factory(CachedTrieCursorFactory::new(tx, cache.clone()))
+                .with_prefix_sets(prefix_sets.clone())
+                .root_with_updates()
+                .unwrap();
+            let (expected_root, expected_updates) = StateRoot::from_tx(tx)
+                .with_prefix_sets(prefix_sets.clone())
+                .root_with_updates()
+                .unwrap();
+            assert_eq!(root, expected_root, "block {block}");
+            assert_eq!(updates, expected_updates, "block {block}");
+
+            updates.flush(tx).unwrap();
+            cache.invalidate(&prefix_sets);
+
+            // The remaining cached seeks match the updated trie.
+            let inner = cache.lock();
+            for (trie, seeks) in &inner.tries {
+                let mut cursor = match trie {
+                    Some(hashed_address) => tx.storage_tries_cursor(*hashed_address).unwrap(),
+                    None => tx.account_trie_cursor().unwrap(),
+                };
+                for (key, entry) in seeks {
+                    assert_eq!(&cursor.seek(key.clone()).unwrap(), entry, "block {block}");
+                }
+            }
+        }
+
+        let (hits, misses) = cache.hits_and_misses();
+        assert!(hits > 0 && misses > 0, "hits {hits}, misses {misses}");
+    }
+}
@@ -0,0 +1,213 @@
+use super::{TrieCursor, TrieCursorFactory};
+use crate::updates::TrieKey;
+use reth_db::DatabaseError;
+use reth_primitives::{
+    trie::{BranchNodeCompact, Nibbles},
+    B256,
+};
+
+/// The trie cursor factory that reads through the primary factory and falls back to the secondary
+/// one.
+///
+/// Unlike [`InMemoryTrieCursorFactory`](super::InMemoryTrieCursorFactory), the primary factory
+/// cannot delete the nodes of the fallback factory, the nodes of both factories are merged with
+/// the nodes of the primary factory taking precedence. This is useful for layering a partial trie
+/// over the database.
+#[derive(Debug, Clone)]
+pub struct ChainedTrieCursorFactory<P, F> {
+    /// The factory that is consulted first.
+    primary: P,
+    /// The factory that is consulted on a miss in the primary factory.
+    fallback: F,
+}
+
+impl<P, F> ChainedTrieCursorFactory<P, F> {
+    /// Create a new chained trie cursor factory.
+    pub const fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+impl<P: TrieCursorFactory, F: TrieCursorFactory> TrieCursorFactory
+    for ChainedTrieCursorFactory<P, F>
+{
+    fn account_trie_cursor(&self) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        Ok(Box::new(ChainedTrieCursor::new(
+            self.primary.account_trie_cursor()?,
+            self.fallback.account_trie_cursor()?,
+        )))
+    }
+
+    fn storage_tries_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        Ok(Box::new(ChainedTrieCursor::new(
+            self.primary.storage_tries_cursor(hashed_address)?,
+            self.fallback.storage_tries_cursor(hashed_address)?,
+        )))
+    }
+}
+
+/// The trie cursor that reads through the primary cursor and falls back to the secondary one.
+#[derive(Debug)]
+pub struct ChainedTrieCursor<P, F> {
+    /// The cursor that is consulted first.
+    primary: P,
+    /// The cursor that is consulted on a miss in the primary cursor.
+    fallback: F,
+    /// Flag indicating whether the last returned entry came from the fallback cursor.
+    from_fallback: bool,
+}
+
+impl<P, F> ChainedTrieCursor<P, F> {
+    /// Create a new chained trie cursor.
+    pub const fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback, from_fallback: false }
+    }
+}
+
+impl<P: TrieCursor, F: TrieCursor> TrieCursor for ChainedTrieCursor<P, F> {
+    /// Seeks an exact match in the primary cursor and, on a miss, in the fallback cursor.
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        if let Some(entry) = self.primary.seek_exact(key.clone())? {
+            self.from_fallback = false;
+            return Ok(Some(entry))
+        }
+
+        let entry = self.fallback.seek_exact(key)?;
+        self.from_fallback = entry.is_some();
+        Ok(entry)
+    }
+
+    /// Seeks the key in both cursors and returns the lesser of the two candidates.
+    /// If both cursors return the same key, the entry of the primary cursor is returned.
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let primary = self.primary.seek(key.clone())?;
+        let fallback = self.fallback.seek(key)?;
+        let (entry, from_fallback) = match (primary, fallback) {
+            (Some(primary), Some(fallback)) if fallback.0 < primary.0 => (Some(fallback), true),
+            (Some(primary), _) => (Some(primary), false),
+            (None, fallback) => (fallback, true),
+        };
+        self.from_fallback = from_fallback && entry.is_some();
+        Ok(entry)
+    }
+
+    /// Retrieves the current key of the cursor that returned the last entry.
+    fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+        if self.from_fallback {
+            self.fallback.current()
+        } else {
+            self.primary.current()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        trie_cursor::{noop::NoopTrieCursorFactory, InMemoryTrieCursorFactory},
+        updates::{TrieOp, TrieUpdates},
+        StateRoot,
+    };
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{
+        keccak256,
+        trie::{StoredBranchNode, StoredNibbles},
+        Account, U256,
+    };
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    fn node(hash_byte: u8) -> BranchNodeCompact {
+        BranchNodeCompact::new(0b11, 0, 0b11, vec![B256::with_last_byte(hash_byte); 2], None)
+    }
+
+    #[test]
+    fn seek_returns_lesser_candidate() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        for (key, hash_byte) in [(vec![0x1, 0x2], 1), (vec![0x2], 2), (vec![0x3], 3)] {
+            provider
+                .tx_ref()
+                .put::<tables::AccountsTrie>(key.into(), StoredBranchNode(node(hash_byte)))
+                .unwrap();
+        }
+        let upper_nodes = TrieUpdates::from([
+            (TrieKey::AccountNode(vec![0x1].into()), TrieOp::Update(node(4))),
+            (TrieKey::AccountNode(vec![0x3].into()), TrieOp::Update(node(5))),
+        ])
+        .into_sorted();
+
+        let factory = ChainedTrieCursorFactory::new(
+            InMemoryTrieCursorFactory::new(NoopTrieCursorFactory, &upper_nodes),
+            provider.tx_ref(),
+        );
+        let mut cursor = factory.account_trie_cursor().unwrap();
+
+        let key = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+        assert_eq!(cursor.seek(key(&[])).unwrap(), Some((key(&[0x1]), node(4))));
+        assert_eq!(cursor.seek(key(&[0x1, 0x0])).unwrap(), Some((key(&[0x1, 0x2]), node(1))));
+        assert_eq!(
+            cursor.current().unwrap(),
+            Some(TrieKey::AccountNode(StoredNibbles(key(&[0x1, 0x2]))))
+        );
+        assert_eq!(cursor.seek(key(&[0x1, 0x3])).unwrap(), Some((key(&[0x2]), node(2))));
+        // The primary cursor takes precedence on equal keys.
+        assert_eq!(cursor.seek(key(&[0x3])).unwrap(), Some((key(&[0x3]), node(5))));
+        assert_eq!(
+            cursor.current().unwrap(),
+            Some(TrieKey::AccountNode(StoredNibbles(key(&[0x3]))))
+        );
+        assert_eq!(cursor.seek(key(&[0x4])).unwrap(), None);
+
+        assert_eq!(cursor.seek_exact(key(&[0x2])).unwrap(), Some((key(&[0x2]), node(2))));
+        assert_eq!(cursor.seek_exact(key(&[0x3])).unwrap(), Some((key(&[0x3]), node(5))));
+        assert_eq!(cursor.seek_exact(key(&[0x4])).unwrap(), None);
+    }
+
+    #[test]
+    fn upper_trie_over_database() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        for i in 0..1_000u64 {
+            provider
+                .tx_ref()
+                .put::<tables::HashedAccounts>(
+                    keccak256(B256::from(U256::from(i))),
+                    Account { nonce: i, ..Default::default() },
+                )
+                .unwrap();
+        }
+        let (expected, updates) =
+            StateRoot::from_tx(provider.tx_ref()).root_with_updates().unwrap();
+
+        // Keep the upper nodes in memory and write the rest to the database.
+        let (upper, lower): (Vec<_>, Vec<_>) = updates.into_iter().partition(
+            |(key, _)| matches!(key, TrieKey::AccountNode(nibbles) if nibbles.0.len() <= 1),
+        );
+        assert!(upper
+            .iter()
+            .any(|(key, _)| matches!(key, TrieKey::AccountNode(nibbles) if nibbles.0.len() == 1)));
+        assert!(!lower.is_empty());
+        let mut lower_updates = TrieUpdates::default();
+        lower_updates.extend(lower);
+        lower_updates.flush(provider.tx_ref()).unwrap();
+        let mut upper_updates = TrieUpdates::default();
+        upper_updates.extend(upper);
+        let upper_nodes = upper_updates.into_sorted();
+
+        let chained = ChainedTrieCursorFactory::new(
+            InMemoryTrieCursorFactory::new(NoopTrieCursorFactory, &upper_nodes),
+            provider.tx_ref(),
+        );
+        assert_eq!(StateRoot::new(chained, provider.tx_ref()).root().unwrap(), expected);
+    }
+}
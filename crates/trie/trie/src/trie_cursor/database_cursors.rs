@@ -66,6 +66,12 @@ where
 }
 
 /// A cursor over the storage tries stored in the database.
+///
+/// The cursor is scoped to the storage trie of a single account: seeks never return and
+/// [`TrieCursor::current`] never reports the nodes of other accounts, even though all storage
+/// tries share the same dup table. Each cursor owns its own position, so any number of cursors
+/// for different accounts can be used side by side within the same transaction and their
+/// seeks can be interleaved freely.
 #[derive(Debug)]
 pub struct DatabaseStorageTrieCursor<C> {
     /// The underlying cursor.
@@ -81,6 +87,26 @@ impl<C> DatabaseStorageTrieCursor<C> {
     }
 }
 
+impl<C> DatabaseStorageTrieCursor<C>
+where
+    C: DbDupCursorRO<tables::StoragesTrie> + DbCursorRO<tables::StoragesTrie>,
+{
+    /// Returns the number of nodes stored in the storage trie of the account.
+    ///
+    /// The nodes are counted by stepping over the duplicate entries of the account in the dup
+    /// table, which is linear in the size of the stored trie but involves no hashing or root
+    /// computation. The cursor is left positioned past the last node of the account.
+    pub fn count_nodes(&mut self) -> Result<usize, DatabaseError> {
+        let mut count = 0;
+        let mut entry = self.cursor.seek_exact(self.hashed_address)?;
+        while entry.is_some() {
+            count += 1;
+            entry = self.cursor.next_dup()?;
+        }
+        Ok(count)
+    }
+}
+
 impl<C> TrieCursor for DatabaseStorageTrieCursor<C>
 where
     C: DbDupCursorRO<tables::StoragesTrie> + DbCursorRO<tables::StoragesTrie> + Send + Sync,
@@ -98,6 +124,11 @@ where
     }
 
     /// Seeks the given key in the storage trie.
+    ///
+    /// Returns the node at the given path if it exists, otherwise the node at the next path of the
+    /// storage trie of the account. The seek is scoped to the account by
+    /// [`DbDupCursorRO::seek_by_key_subkey`], so it returns `None` past the last node of the
+    /// account rather than the first node of the next account in the table.
     fn seek(
         &mut self,
         key: Nibbles,
@@ -109,8 +140,15 @@ where
     }
 
     /// Retrieves the current value in the storage trie cursor.
+    ///
+    /// Returns `None` if the underlying cursor is positioned outside of the storage trie of the
+    /// account, e.g. after an unsuccessful seek.
     fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
-        Ok(self.cursor.current()?.map(|(k, v)| TrieKey::StorageNode(k, v.nibbles)))
+        Ok(self
+            .cursor
+            .current()?
+            .filter(|(hashed_address, _)| *hashed_address == self.hashed_address)
+            .map(|(k, v)| TrieKey::StorageNode(k, v.nibbles)))
     }
 }
 
@@ -164,6 +202,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn count_storage_trie_nodes() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let mut cursor = provider.tx_ref().cursor_dup_write::<tables::StoragesTrie>().unwrap();
+
+        let node = BranchNodeCompact::new(1, 1, 1, vec![B256::random()], None);
+        let addresses = [B256::with_last_byte(1), B256::with_last_byte(2), B256::with_last_byte(3)];
+        for (hashed_address, nodes) in addresses.into_iter().zip([3u8, 17, 1]) {
+            for i in 0..nodes {
+                let nibbles = StoredNibblesSubKey::from(vec![i >> 4, i & 0xf]);
+                cursor
+                    .upsert(hashed_address, StorageTrieEntry { nibbles, node: node.clone() })
+                    .unwrap();
+            }
+        }
+
+        let count = |hashed_address| {
+            let cursor = provider.tx_ref().cursor_dup_read::<tables::StoragesTrie>().unwrap();
+            DatabaseStorageTrieCursor::new(cursor, hashed_address).count_nodes().unwrap()
+        };
+        assert_eq!(count(addresses[0]), 3);
+        assert_eq!(count(addresses[1]), 17);
+        assert_eq!(count(addresses[2]), 1);
+        assert_eq!(count(B256::ZERO), 0);
+        assert_eq!(count(B256::with_last_byte(4)), 0);
+    }
+
     // tests that upsert and seek match on the storage trie cursor
     #[test]
     fn test_storage_cursor_abstraction() {
@@ -182,4 +248,168 @@ mod tests {
         let mut cursor = DatabaseStorageTrieCursor::new(cursor, hashed_address);
         assert_eq!(cursor.seek(key.into()).unwrap().unwrap().1, value);
     }
+
+    #[test]
+    fn seek_exact_or_next() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed_address = B256::with_last_byte(1);
+        let node = BranchNodeCompact::new(1, 1, 1, vec![B256::random()], None);
+        for key in [vec![0x1], vec![0x3]] {
+            tx.put::<tables::AccountsTrie>(key.clone().into(), StoredBranchNode(node.clone()))
+                .unwrap();
+            tx.put::<tables::StoragesTrie>(
+                hashed_address,
+                StorageTrieEntry { nibbles: key.into(), node: node.clone() },
+            )
+            .unwrap();
+        }
+        // The storage trie entries of other accounts are out of scope of the storage cursor.
+        tx.put::<tables::StoragesTrie>(
+            B256::with_last_byte(2),
+            StorageTrieEntry { nibbles: vec![0x5].into(), node: node.clone() },
+        )
+        .unwrap();
+
+        let mut account_cursor =
+            DatabaseAccountTrieCursor::new(tx.cursor_read::<tables::AccountsTrie>().unwrap());
+        let mut storage_cursor = DatabaseStorageTrieCursor::new(
+            tx.cursor_dup_read::<tables::StoragesTrie>().unwrap(),
+            hashed_address,
+        );
+        let key = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+        for cursor in [&mut account_cursor as &mut dyn TrieCursor, &mut storage_cursor] {
+            assert_eq!(
+                cursor.seek_exact_or_next(key(&[0x1])).unwrap(),
+                (true, Some((key(&[0x1]), node.clone())))
+            );
+            assert_eq!(
+                cursor.seek_exact_or_next(key(&[0x2])).unwrap(),
+                (false, Some((key(&[0x3]), node.clone())))
+            );
+            assert_eq!(cursor.seek_exact_or_next(key(&[0x4])).unwrap(), (false, None));
+        }
+    }
+
+    #[test]
+    fn storage_cursors_are_isolated() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // Every account has nodes at the same paths, distinguishable by their root hash.
+        let addresses = [1, 2, 3].map(B256::with_last_byte);
+        let paths = [vec![0x1], vec![0x1, 0x2], vec![0x3]];
+        let node =
+            |address: B256| BranchNodeCompact::new(1, 1, 1, vec![B256::random()], Some(address));
+        for address in addresses {
+            for path in &paths {
+                tx.put::<tables::StoragesTrie>(
+                    address,
+                    StorageTrieEntry { nibbles: path.clone().into(), node: node(address) },
+                )
+                .unwrap();
+            }
+        }
+
+        let mut cursors = addresses.map(|address| {
+            DatabaseStorageTrieCursor::new(
+                tx.cursor_dup_read::<tables::StoragesTrie>().unwrap(),
+                address,
+            )
+        });
+        let key = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+
+        // Interleave the seeks of all cursors, twice to check that repeated seeks are idempotent.
+        for _ in 0..2 {
+            for (path, next) in [
+                (&[0x0][..], &[0x1][..]),
+                (&[0x1, 0x1][..], &[0x1, 0x2][..]),
+                (&[0x2][..], &[0x3][..]),
+            ] {
+                for (address, cursor) in addresses.iter().zip(cursors.iter_mut()) {
+                    let (found, node) = cursor.seek(key(path)).unwrap().unwrap();
+                    assert_eq!(found, key(next));
+                    assert_eq!(node.root_hash, Some(*address));
+                    assert_eq!(
+                        cursor.current().unwrap(),
+                        Some(TrieKey::StorageNode(*address, next.to_vec().into()))
+                    );
+                }
+            }
+
+            for (address, cursor) in addresses.iter().zip(cursors.iter_mut()) {
+                let (found, node) = cursor.seek_exact(key(&[0x1, 0x2])).unwrap().unwrap();
+                assert_eq!(found, key(&[0x1, 0x2]));
+                assert_eq!(node.root_hash, Some(*address));
+            }
+
+            // Seeking past the last node of an account must not move on to the next account.
+            for (address, cursor) in addresses.iter().zip(cursors.iter_mut()) {
+                assert_eq!(cursor.seek(key(&[0x4])).unwrap(), None);
+                assert!(cursor.current().unwrap().map_or(
+                    true,
+                    |current| matches!(current, TrieKey::StorageNode(a, _) if a == *address)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn storage_cursor_seek_at_address_boundary() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        // Adjacent addresses, the nodes of the next one sorting both before and after the paths of
+        // the previous one. The address in between has no nodes.
+        let [first, empty, last] = [1, 2, 3].map(B256::with_last_byte);
+        let node =
+            |address: B256| BranchNodeCompact::new(1, 1, 1, vec![B256::random()], Some(address));
+        for (address, path) in
+            [(first, vec![0x1]), (first, vec![0x3]), (last, vec![0x0]), (last, vec![0x4])]
+        {
+            tx.put::<tables::StoragesTrie>(
+                address,
+                StorageTrieEntry { nibbles: path.into(), node: node(address) },
+            )
+            .unwrap();
+        }
+        let cursor = |address| {
+            DatabaseStorageTrieCursor::new(
+                tx.cursor_dup_read::<tables::StoragesTrie>().unwrap(),
+                address,
+            )
+        };
+        let key = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+
+        // The absent path is followed by the next path of the same account.
+        let mut first_cursor = cursor(first);
+        let (found, found_node) = first_cursor.seek(key(&[0x2])).unwrap().unwrap();
+        assert_eq!((found, found_node.root_hash), (key(&[0x3]), Some(first)));
+
+        // Past the last node of the account, the nodes of the next account are not returned.
+        assert_eq!(first_cursor.seek(key(&[0x4])).unwrap(), None);
+        assert_eq!(first_cursor.seek_exact(key(&[0x4])).unwrap(), None);
+        assert!(first_cursor.current().unwrap().map_or(
+            true,
+            |current| matches!(current, TrieKey::StorageNode(address, _) if address == first)
+        ));
+
+        // The account without nodes sees neither of its neighbours.
+        let mut empty_cursor = cursor(empty);
+        assert_eq!(empty_cursor.seek(Nibbles::default()).unwrap(), None);
+        assert_eq!(empty_cursor.seek_exact(key(&[0x0])).unwrap(), None);
+        assert_eq!(empty_cursor.current().unwrap(), None);
+
+        // The first node of the next account is before the first node of the previous account.
+        let mut last_cursor = cursor(last);
+        let (found, found_node) = last_cursor.seek(Nibbles::default()).unwrap().unwrap();
+        assert_eq!((found, found_node.root_hash), (key(&[0x0]), Some(last)));
+        let (found, found_node) = last_cursor.seek(key(&[0x1])).unwrap().unwrap();
+        assert_eq!((found, found_node.root_hash), (key(&[0x4]), Some(last)));
+        assert_eq!(last_cursor.seek(key(&[0x5])).unwrap(), None);
+    }
 }
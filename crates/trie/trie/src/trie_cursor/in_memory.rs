@@ -0,0 +1,248 @@
+use super::{TrieCursor, TrieCursorFactory};
+use crate::updates::{TrieKey, TrieOp, TrieUpdatesSorted};
+use reth_db::DatabaseError;
+use reth_primitives::{
+    trie::{BranchNodeCompact, Nibbles, StoredNibbles, StoredNibblesSubKey},
+    B256,
+};
+
+/// The trie cursor factory for the trie updates.
+#[derive(Debug, Clone)]
+pub struct InMemoryTrieCursorFactory<'a, CF> {
+    /// Underlying trie cursor factory.
+    cursor_factory: CF,
+    /// Reference to sorted trie updates.
+    trie_updates: &'a TrieUpdatesSorted,
+}
+
+impl<'a, CF> InMemoryTrieCursorFactory<'a, CF> {
+    /// Create a new trie cursor factory.
+    pub const fn new(cursor_factory: CF, trie_updates: &'a TrieUpdatesSorted) -> Self {
+        Self { cursor_factory, trie_updates }
+    }
+}
+
+impl<'a, CF: TrieCursorFactory> TrieCursorFactory for InMemoryTrieCursorFactory<'a, CF> {
+    fn account_trie_cursor(&self) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.cursor_factory.account_trie_cursor()?;
+        Ok(Box::new(InMemoryAccountTrieCursor::new(cursor, self.trie_updates)))
+    }
+
+    fn storage_tries_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.cursor_factory.storage_tries_cursor(hashed_address)?;
+        Ok(Box::new(InMemoryStorageTrieCursor::new(cursor, self.trie_updates, hashed_address)))
+    }
+}
+
+/// The cursor to iterate over account trie updates and corresponding database entries.
+/// It will always give precedence to the data from the trie updates.
+#[derive(Debug)]
+pub struct InMemoryAccountTrieCursor<'a, C> {
+    /// The database cursor.
+    cursor: C,
+    /// The reference to the sorted account node operations.
+    nodes: &'a [(Nibbles, TrieOp)],
+    /// The last key that was returned by the cursor.
+    last_key: Option<Nibbles>,
+}
+
+impl<'a, C> InMemoryAccountTrieCursor<'a, C> {
+    /// Create new account trie cursor over the given trie updates.
+    pub fn new(cursor: C, trie_updates: &'a TrieUpdatesSorted) -> Self {
+        Self { cursor, nodes: &trie_updates.account_nodes, last_key: None }
+    }
+}
+
+impl<'a, C: TrieCursor> TrieCursor for InMemoryAccountTrieCursor<'a, C> {
+    /// Seeks an exact match for the provided key in the trie updates and the database.
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = seek_exact(&mut self.cursor, self.nodes, false, key)?;
+        self.last_key = entry.as_ref().map(|(nibbles, _)| nibbles.clone());
+        Ok(entry)
+    }
+
+    /// Seeks the first key in the trie updates and the database that is greater than or equal
+    /// to the provided key.
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = seek(&mut self.cursor, self.nodes, false, key)?;
+        self.last_key = entry.as_ref().map(|(nibbles, _)| nibbles.clone());
+        Ok(entry)
+    }
+
+    /// Retrieves the key of the last returned entry.
+    fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+        Ok(self.last_key.clone().map(|key| TrieKey::AccountNode(StoredNibbles(key))))
+    }
+}
+
+/// The cursor to iterate over storage trie updates and corresponding database entries.
+/// It will always give precedence to the data from the trie updates.
+#[derive(Debug)]
+pub struct InMemoryStorageTrieCursor<'a, C> {
+    /// The database cursor.
+    cursor: C,
+    /// The reference to the sorted storage node operations of the account.
+    nodes: &'a [(Nibbles, TrieOp)],
+    /// Flag indicating whether the storage trie of the account was wiped.
+    wiped: bool,
+    /// The hashed address of the account.
+    hashed_address: B256,
+    /// The last key that was returned by the cursor.
+    last_key: Option<Nibbles>,
+}
+
+impl<'a, C> InMemoryStorageTrieCursor<'a, C> {
+    /// Create new storage trie cursor over the given trie updates for the given hashed address.
+    pub fn new(cursor: C, trie_updates: &'a TrieUpdatesSorted, hashed_address: B256) -> Self {
+        let (nodes, wiped) = trie_updates
+            .storage_tries
+            .get(&hashed_address)
+            .map(|storage| (storage.storage_nodes.as_slice(), storage.wiped))
+            .unwrap_or_default();
+        Self { cursor, nodes, wiped, hashed_address, last_key: None }
+    }
+}
+
+impl<'a, C: TrieCursor> TrieCursor for InMemoryStorageTrieCursor<'a, C> {
+    /// Seeks an exact match for the provided key in the trie updates and the database.
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = seek_exact(&mut self.cursor, self.nodes, self.wiped, key)?;
+        self.last_key = entry.as_ref().map(|(nibbles, _)| nibbles.clone());
+        Ok(entry)
+    }
+
+    /// Seeks the first key in the trie updates and the database that is greater than or equal
+    /// to the provided key.
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = seek(&mut self.cursor, self.nodes, self.wiped, key)?;
+        self.last_key = entry.as_ref().map(|(nibbles, _)| nibbles.clone());
+        Ok(entry)
+    }
+
+    /// Retrieves the key of the last returned entry.
+    fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+        Ok(self
+            .last_key
+            .clone()
+            .map(|key| TrieKey::StorageNode(self.hashed_address, StoredNibblesSubKey(key))))
+    }
+}
+
+/// Looks up the exact key in the in-memory nodes, falling back to the database cursor unless the
+/// database entries were wiped.
+fn seek_exact(
+    cursor: &mut impl TrieCursor,
+    nodes: &[(Nibbles, TrieOp)],
+    wiped: bool,
+    key: Nibbles,
+) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+    if let Ok(index) = nodes.binary_search_by(|(nibbles, _)| nibbles.cmp(&key)) {
+        return Ok(match &nodes[index].1 {
+            TrieOp::Update(node) => Some((key, node.clone())),
+            TrieOp::Delete => None,
+        })
+    }
+
+    if wiped {
+        return Ok(None)
+    }
+    cursor.seek_exact(key)
+}
+
+/// Returns the lowest entry with the key greater than or equal to the given key from the
+/// in-memory nodes and the database cursor. Database entries deleted in memory are skipped and
+/// in-memory entries are given precedence if the keys are equal.
+fn seek(
+    cursor: &mut impl TrieCursor,
+    nodes: &[(Nibbles, TrieOp)],
+    wiped: bool,
+    key: Nibbles,
+) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+    let start = nodes.partition_point(|(nibbles, _)| nibbles < &key);
+    let in_memory_entry = nodes[start..].iter().find_map(|(nibbles, op)| match op {
+        TrieOp::Update(node) => Some((nibbles, node)),
+        TrieOp::Delete => None,
+    });
+
+    let mut db_entry = if wiped { None } else { cursor.seek(key)? };
+    while let Some((nibbles, _)) = &db_entry {
+        if nodes.binary_search_by(|(key, _)| key.cmp(nibbles)).is_err() {
+            break
+        }
+        // The entry is overwritten in memory, move to the key immediately following it.
+        let mut next = nibbles.to_vec();
+        next.push(0);
+        db_entry = cursor.seek(Nibbles::from_nibbles_unchecked(next))?;
+    }
+
+    Ok(match (in_memory_entry, db_entry) {
+        (Some((in_memory_key, node)), Some((db_key, _))) if in_memory_key <= &db_key => {
+            Some((in_memory_key.clone(), node.clone()))
+        }
+        (Some((in_memory_key, node)), None) => Some((in_memory_key.clone(), node.clone())),
+        (_, db_entry) => db_entry,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::updates::TrieUpdates;
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::trie::StoredBranchNode;
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    fn node(hash_byte: u8) -> BranchNodeCompact {
+        BranchNodeCompact::new(0b11, 0, 0b11, vec![B256::with_last_byte(hash_byte); 2], None)
+    }
+
+    #[test]
+    fn account_trie_cursor_overlay() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        for (key, hash_byte) in [(vec![0x1], 1), (vec![0x1, 0x2], 2), (vec![0x3], 3)] {
+            provider
+                .tx_ref()
+                .put::<tables::AccountsTrie>(key.into(), StoredBranchNode(node(hash_byte)))
+                .unwrap();
+        }
+
+        let trie_updates = TrieUpdates::from([
+            (TrieKey::AccountNode(vec![0x1, 0x2].into()), TrieOp::Delete),
+            (TrieKey::AccountNode(vec![0x2].into()), TrieOp::Update(node(4))),
+            (TrieKey::AccountNode(vec![0x3].into()), TrieOp::Update(node(5))),
+        ])
+        .into_sorted();
+        let factory = InMemoryTrieCursorFactory::new(provider.tx_ref(), &trie_updates);
+        let mut cursor = factory.account_trie_cursor().unwrap();
+
+        // Deleted entries are skipped and updated entries take precedence.
+        let key = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+        assert_eq!(cursor.seek(key(&[0x1])).unwrap(), Some((key(&[0x1]), node(1))));
+        assert_eq!(cursor.seek(key(&[0x1, 0x0])).unwrap(), Some((key(&[0x2]), node(4))));
+        assert_eq!(cursor.seek(key(&[0x2, 0x0])).unwrap(), Some((key(&[0x3]), node(5))));
+        assert_eq!(
+            cursor.current().unwrap(),
+            Some(TrieKey::AccountNode(StoredNibbles(key(&[0x3]))))
+        );
+        assert_eq!(cursor.seek(key(&[0x4])).unwrap(), None);
+
+        assert_eq!(cursor.seek_exact(key(&[0x1, 0x2])).unwrap(), None);
+        assert_eq!(cursor.seek_exact(key(&[0x3])).unwrap(), Some((key(&[0x3]), node(5))));
+    }
+}
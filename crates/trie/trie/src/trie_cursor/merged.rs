@@ -0,0 +1,228 @@
+use super::{TrieCursor, TrieCursorFactory};
+use crate::updates::TrieKey;
+use reth_db::DatabaseError;
+use reth_primitives::{
+    trie::{BranchNodeCompact, Nibbles},
+    B256,
+};
+
+/// The trie cursor factory presenting several trie cursor factories as a single view.
+///
+/// The factories are expected to hold disjoint parts of the trie, e.g. the account ranges of
+/// separate database environments. If several factories hold a node at the same key, the node of
+/// the factory that comes first in the precedence order takes precedence and the others are
+/// ignored.
+#[derive(Debug, Clone)]
+pub struct MergedTrieCursorFactory<F> {
+    /// The factories in the precedence order.
+    factories: Vec<F>,
+}
+
+impl<F> MergedTrieCursorFactory<F> {
+    /// Create a new merged trie cursor factory from the factories in the precedence order.
+    pub fn new(factories: impl IntoIterator<Item = F>) -> Self {
+        Self { factories: factories.into_iter().collect() }
+    }
+}
+
+impl<F: TrieCursorFactory> TrieCursorFactory for MergedTrieCursorFactory<F> {
+    fn account_trie_cursor(&self) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursors = self
+            .factories
+            .iter()
+            .map(|factory| factory.account_trie_cursor())
+            .collect::<Result<_, _>>()?;
+        Ok(Box::new(MergedTrieCursor::new(cursors)))
+    }
+
+    fn storage_tries_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursors = self
+            .factories
+            .iter()
+            .map(|factory| factory.storage_tries_cursor(hashed_address))
+            .collect::<Result<_, _>>()?;
+        Ok(Box::new(MergedTrieCursor::new(cursors)))
+    }
+}
+
+/// The trie cursor merging the entries of several cursors in the precedence order.
+#[derive(Debug)]
+pub struct MergedTrieCursor<C> {
+    /// The cursors in the precedence order.
+    cursors: Vec<C>,
+    /// The index of the cursor that returned the last entry.
+    current: Option<usize>,
+}
+
+impl<C> MergedTrieCursor<C> {
+    /// Create a new merged trie cursor from the cursors in the precedence order.
+    pub const fn new(cursors: Vec<C>) -> Self {
+        Self { cursors, current: None }
+    }
+}
+
+impl<C: TrieCursor> TrieCursor for MergedTrieCursor<C> {
+    /// Seeks an exact match in the cursors in the precedence order.
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        self.current = None;
+        for (idx, cursor) in self.cursors.iter_mut().enumerate() {
+            if let Some(entry) = cursor.seek_exact(key.clone())? {
+                self.current = Some(idx);
+                return Ok(Some(entry))
+            }
+        }
+        Ok(None)
+    }
+
+    /// Seeks the key in all cursors and returns the least of the candidates.
+    /// If several cursors return the same key, the entry of the first one is returned.
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let mut least: Option<(usize, (Nibbles, BranchNodeCompact))> = None;
+        for (idx, cursor) in self.cursors.iter_mut().enumerate() {
+            if let Some(entry) = cursor.seek(key.clone())? {
+                if least.as_ref().map_or(true, |(_, least)| entry.0 < least.0) {
+                    least = Some((idx, entry));
+                }
+            }
+        }
+        self.current = least.as_ref().map(|(idx, _)| *idx);
+        Ok(least.map(|(_, entry)| entry))
+    }
+
+    /// Retrieves the current key of the cursor that returned the last entry.
+    fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+        match self.current {
+            Some(idx) => self.cursors[idx].current(),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prefix_set::{PrefixSetMut, TriePrefixSets},
+        updates::TrieUpdates,
+        StateRoot,
+    };
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{
+        keccak256,
+        trie::{StoredBranchNode, StoredNibbles},
+        Account, U256,
+    };
+    use reth_provider::test_utils::create_test_provider_factory;
+
+    fn node(hash_byte: u8) -> BranchNodeCompact {
+        BranchNodeCompact::new(0b11, 0, 0b11, vec![B256::with_last_byte(hash_byte); 2], None)
+    }
+
+    #[test]
+    fn overlapping_keys_precedence() {
+        let first = create_test_provider_factory();
+        let first = first.provider_rw().unwrap();
+        let second = create_test_provider_factory();
+        let second = second.provider_rw().unwrap();
+        for (provider, nodes) in [
+            (&first, [(vec![0x1], 1), (vec![0x3], 3)]),
+            (&second, [(vec![0x1], 4), (vec![0x2], 2)]),
+        ] {
+            for (key, hash_byte) in nodes {
+                provider
+                    .tx_ref()
+                    .put::<tables::AccountsTrie>(key.into(), StoredBranchNode(node(hash_byte)))
+                    .unwrap();
+            }
+        }
+
+        let factory = MergedTrieCursorFactory::new([first.tx_ref(), second.tx_ref()]);
+        let mut cursor = factory.account_trie_cursor().unwrap();
+
+        let key = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+        // The first factory takes precedence on equal keys.
+        assert_eq!(cursor.seek(key(&[])).unwrap(), Some((key(&[0x1]), node(1))));
+        assert_eq!(cursor.seek_exact(key(&[0x1])).unwrap(), Some((key(&[0x1]), node(1))));
+        assert_eq!(cursor.seek(key(&[0x1, 0x0])).unwrap(), Some((key(&[0x2]), node(2))));
+        assert_eq!(
+            cursor.current().unwrap(),
+            Some(TrieKey::AccountNode(StoredNibbles(key(&[0x2]))))
+        );
+        assert_eq!(cursor.seek_exact(key(&[0x3])).unwrap(), Some((key(&[0x3]), node(3))));
+        assert_eq!(cursor.seek(key(&[0x4])).unwrap(), None);
+        assert_eq!(cursor.current().unwrap(), None);
+    }
+
+    #[test]
+    fn disjoint_account_ranges_root() {
+        let state = create_test_provider_factory();
+        let state = state.provider_rw().unwrap();
+        let hashed_addresses = (0..1_000u64)
+            .map(|i| {
+                let hashed_address = keccak256(B256::from(U256::from(i)));
+                let account = Account { nonce: i, ..Default::default() };
+                state.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+                hashed_address
+            })
+            .collect::<Vec<_>>();
+        let (_, updates) = StateRoot::from_tx(state.tx_ref()).root_with_updates().unwrap();
+
+        // Split the account trie between two databases by the first nibble of the node key.
+        let lower = create_test_provider_factory();
+        let lower = lower.provider_rw().unwrap();
+        let upper = create_test_provider_factory();
+        let upper = upper.provider_rw().unwrap();
+        let (lower_nodes, upper_nodes): (Vec<_>, Vec<_>) =
+            updates.clone().into_iter().partition(|(key, _)| {
+                matches!(key, TrieKey::AccountNode(nibbles) if nibbles.0.first() < Some(&0x8))
+            });
+        for (provider, nodes) in [(&lower, lower_nodes), (&upper, upper_nodes)] {
+            let mut updates = TrieUpdates::default();
+            updates.extend(nodes);
+            updates.flush(provider.tx_ref()).unwrap();
+        }
+        updates.flush(state.tx_ref()).unwrap();
+
+        // Change the accounts in both ranges.
+        let mut account_prefix_set = PrefixSetMut::default();
+        for hashed_address in hashed_addresses.iter().step_by(100) {
+            let account = Account { nonce: u64::MAX, ..Default::default() };
+            state.tx_ref().put::<tables::HashedAccounts>(*hashed_address, account).unwrap();
+            account_prefix_set.insert(Nibbles::unpack(hashed_address));
+        }
+        assert!(hashed_addresses.iter().step_by(100).any(|address| address[0] < 0x80));
+        assert!(hashed_addresses.iter().step_by(100).any(|address| address[0] >= 0x80));
+        let prefix_sets = || TriePrefixSets {
+            account_prefix_set: account_prefix_set.clone().freeze(),
+            ..Default::default()
+        };
+
+        // The merged view yields the same root and updates as the single database.
+        let expected = StateRoot::from_tx(state.tx_ref())
+            .with_prefix_sets(prefix_sets())
+            .root_with_updates()
+            .unwrap();
+        let merged = MergedTrieCursorFactory::new([lower.tx_ref(), upper.tx_ref()]);
+        let merged = StateRoot::new(merged, state.tx_ref())
+            .with_prefix_sets(prefix_sets())
+            .root_with_updates()
+            .unwrap();
+        assert_eq!(merged, expected);
+        assert_eq!(
+            merged.0,
+            StateRoot::from_tx(state.tx_ref())
+                .with_prefix_sets(TriePrefixSets::all_changed())
+                .root()
+                .unwrap()
+        );
+    }
+}
@@ -5,14 +5,26 @@ use reth_primitives::{
     B256,
 };
 
+mod cached;
+mod chained;
 mod database_cursors;
+mod in_memory;
+mod merged;
+mod retrying;
 mod subnode;
 
 /// Noop trie cursor implementations.
 pub mod noop;
 
 pub use self::{
+    cached::{
+        BranchNodeCache, CachedTrieCursor, CachedTrieCursorFactory, DEFAULT_MAX_CACHED_SEEKS,
+    },
+    chained::{ChainedTrieCursor, ChainedTrieCursorFactory},
     database_cursors::{DatabaseAccountTrieCursor, DatabaseStorageTrieCursor},
+    in_memory::{InMemoryAccountTrieCursor, InMemoryStorageTrieCursor, InMemoryTrieCursorFactory},
+    merged::{MergedTrieCursor, MergedTrieCursorFactory},
+    retrying::{RetryPolicy, RetryingTrieCursor, RetryingTrieCursorFactory},
     subnode::CursorSubNode,
 };
 
@@ -41,6 +53,19 @@ pub trait TrieCursor: Send + Sync {
     fn seek(&mut self, key: Nibbles)
         -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>;
 
+    /// Move the cursor to the key and return a value matching or greater than the key along with
+    /// the flag indicating whether the returned entry is an exact match.
+    ///
+    /// Unlike [`seek_exact`](Self::seek_exact) followed by [`seek`](Self::seek) on a miss, this
+    /// positions the cursor with a single seek.
+    fn seek_exact_or_next(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<(bool, Option<(Nibbles, BranchNodeCompact)>), DatabaseError> {
+        let entry = self.seek(key.clone())?;
+        Ok((entry.as_ref().is_some_and(|(found, _)| *found == key), entry))
+    }
+
     /// Get the current entry.
     fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError>;
 }
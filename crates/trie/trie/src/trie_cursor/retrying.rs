@@ -0,0 +1,212 @@
+use super::{TrieCursor, TrieCursorFactory};
+use crate::updates::TrieKey;
+use reth_db::DatabaseError;
+use reth_primitives::{
+    trie::{BranchNodeCompact, Nibbles},
+    B256,
+};
+use std::time::Duration;
+use tracing::debug;
+
+/// The policy for retrying the failed reads of a [`RetryingTrieCursor`].
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of retries of a single read.
+    pub max_retries: u32,
+    /// The delay before the first retry, doubled on every subsequent retry.
+    pub initial_backoff: Duration,
+    /// Returns `true` if the read failing with the error should be retried.
+    pub is_retryable: fn(&DatabaseError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    /// Retries the failed reads up to three times, starting with a 10ms backoff.
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(10),
+            is_retryable: |error| matches!(error, DatabaseError::Read(_)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs the read, retrying it with exponential backoff while it fails with a retryable error.
+    fn run<T>(
+        &self,
+        mut read: impl FnMut() -> Result<T, DatabaseError>,
+    ) -> Result<T, DatabaseError> {
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match read() {
+                Err(error) if retries < self.max_retries && (self.is_retryable)(&error) => {
+                    retries += 1;
+                    debug!(
+                        target: "trie::cursor",
+                        %error,
+                        retries,
+                        ?backoff,
+                        "retrying trie cursor read"
+                    );
+                    std::thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// The trie cursor factory wrapping the cursors of the underlying factory in
+/// [`RetryingTrieCursor`]s.
+#[derive(Debug, Clone)]
+pub struct RetryingTrieCursorFactory<F> {
+    /// The underlying factory.
+    factory: F,
+    /// The policy for retrying the failed reads.
+    policy: RetryPolicy,
+}
+
+impl<F> RetryingTrieCursorFactory<F> {
+    /// Create a new retrying trie cursor factory.
+    pub const fn new(factory: F, policy: RetryPolicy) -> Self {
+        Self { factory, policy }
+    }
+}
+
+impl<F: TrieCursorFactory> TrieCursorFactory for RetryingTrieCursorFactory<F> {
+    fn account_trie_cursor(&self) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.policy.run(|| self.factory.account_trie_cursor())?;
+        Ok(Box::new(RetryingTrieCursor::new(cursor, self.policy)))
+    }
+
+    fn storage_tries_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Box<dyn TrieCursor + '_>, DatabaseError> {
+        let cursor = self.policy.run(|| self.factory.storage_tries_cursor(hashed_address))?;
+        Ok(Box::new(RetryingTrieCursor::new(cursor, self.policy)))
+    }
+}
+
+/// The trie cursor retrying the failed seeks of the underlying cursor according to the
+/// [`RetryPolicy`].
+///
+/// The errors that are not retryable and the errors of the last retry are returned as is.
+#[derive(Debug)]
+pub struct RetryingTrieCursor<C> {
+    /// The underlying cursor.
+    cursor: C,
+    /// The policy for retrying the failed reads.
+    policy: RetryPolicy,
+}
+
+impl<C> RetryingTrieCursor<C> {
+    /// Create a new retrying trie cursor.
+    pub const fn new(cursor: C, policy: RetryPolicy) -> Self {
+        Self { cursor, policy }
+    }
+}
+
+impl<C: TrieCursor> TrieCursor for RetryingTrieCursor<C> {
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        self.policy.run(|| self.cursor.seek_exact(key.clone()))
+    }
+
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        self.policy.run(|| self.cursor.seek(key.clone()))
+    }
+
+    fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+        self.cursor.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_storage_errors::db::DatabaseErrorInfo;
+
+    /// The cursor failing the given number of seeks with the given error before succeeding.
+    #[derive(Debug)]
+    struct FlakyCursor {
+        failures: u32,
+        error: DatabaseError,
+        seeks: u32,
+    }
+
+    impl FlakyCursor {
+        fn new(failures: u32, error: DatabaseError) -> Self {
+            Self { failures, error, seeks: 0 }
+        }
+
+        fn read(&mut self) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+            self.seeks += 1;
+            if self.seeks <= self.failures {
+                return Err(self.error.clone())
+            }
+            Ok(Some((Nibbles::default(), BranchNodeCompact::new(1, 0, 0, Vec::new(), None))))
+        }
+    }
+
+    impl TrieCursor for FlakyCursor {
+        fn seek_exact(
+            &mut self,
+            _key: Nibbles,
+        ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+            self.read()
+        }
+
+        fn seek(
+            &mut self,
+            _key: Nibbles,
+        ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+            self.read()
+        }
+
+        fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+            Ok(None)
+        }
+    }
+
+    fn read_error() -> DatabaseError {
+        DatabaseError::Read(DatabaseErrorInfo { message: "transient".to_string(), code: -30000 })
+    }
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy { max_retries, initial_backoff: Duration::ZERO, ..Default::default() }
+    }
+
+    #[test]
+    fn retries_transient_errors() {
+        let mut cursor = RetryingTrieCursor::new(FlakyCursor::new(2, read_error()), policy(3));
+        assert!(cursor.seek(Nibbles::default()).unwrap().is_some());
+        assert_eq!(cursor.cursor.seeks, 3);
+
+        let mut cursor = RetryingTrieCursor::new(FlakyCursor::new(3, read_error()), policy(3));
+        assert!(cursor.seek_exact(Nibbles::default()).unwrap().is_some());
+        assert_eq!(cursor.cursor.seeks, 4);
+    }
+
+    #[test]
+    fn gives_up_after_max_retries() {
+        let mut cursor = RetryingTrieCursor::new(FlakyCursor::new(4, read_error()), policy(3));
+        assert_eq!(cursor.seek(Nibbles::default()), Err(read_error()));
+        assert_eq!(cursor.cursor.seeks, 4);
+    }
+
+    #[test]
+    fn propagates_non_retryable_errors() {
+        let mut cursor =
+            RetryingTrieCursor::new(FlakyCursor::new(1, DatabaseError::Decode), policy(3));
+        assert_eq!(cursor.seek(Nibbles::default()), Err(DatabaseError::Decode));
+        assert_eq!(cursor.cursor.seeks, 1);
+    }
+}
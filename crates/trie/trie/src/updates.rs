@@ -1,19 +1,33 @@
-use crate::walker::TrieWalker;
+use crate::{
+    hashed_cursor::HashedCursorFactory,
+    trie_cursor::{InMemoryTrieCursorFactory, TrieCursorFactory},
+    walker::TrieWalker,
+    StateRoot,
+};
 use derive_more::Deref;
 use reth_db::{
     cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW},
+    table::{Compress, Decompress},
     tables,
     transaction::{DbTx, DbTxMut},
 };
+use reth_execution_errors::StateRootError;
 use reth_primitives::{
+    keccak256,
     trie::{
         BranchNodeCompact, HashBuilder, Nibbles, StorageTrieEntry, StoredBranchNode, StoredNibbles,
         StoredNibblesSubKey,
     },
-    B256,
+    Bytes, GotExpected, B256,
 };
 use std::collections::{hash_map::IntoIter, HashMap, HashSet};
 
+/// The tag of the account trie nodes in the encoding of [`TrieUpdates::to_nodes`].
+const ACCOUNT_NODE_TAG: u8 = 0;
+
+/// The tag of the storage trie nodes in the encoding of [`TrieUpdates::to_nodes`].
+const STORAGE_NODE_TAG: u8 = 1;
+
 /// The key of a trie node.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TrieKey {
@@ -128,7 +142,167 @@ impl TrieUpdates {
         }));
     }
 
+    /// Converts trie updates into [`TrieUpdatesSorted`].
+    ///
+    /// The sorted updates reflect the state of the trie after the updates are flushed, i.e. the
+    /// nodes of wiped storage tries are discarded.
+    pub fn into_sorted(self) -> TrieUpdatesSorted {
+        let mut account_nodes = Vec::new();
+        let mut storage_tries = HashMap::<B256, StorageTrieUpdatesSorted>::default();
+        for (key, operation) in self.trie_operations {
+            match key {
+                TrieKey::AccountNode(nibbles) => account_nodes.push((nibbles.0, operation)),
+                TrieKey::StorageNode(hashed_address, nibbles) => {
+                    storage_tries
+                        .entry(hashed_address)
+                        .or_default()
+                        .storage_nodes
+                        .push((nibbles.0, operation));
+                }
+                TrieKey::StorageTrie(hashed_address) => {
+                    storage_tries.entry(hashed_address).or_default().wiped = true;
+                }
+            }
+        }
+
+        account_nodes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        for storage_trie in storage_tries.values_mut() {
+            if storage_trie.wiped {
+                storage_trie.storage_nodes.clear();
+            } else {
+                storage_trie.storage_nodes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            }
+        }
+
+        TrieUpdatesSorted { account_nodes, storage_tries }
+    }
+
+    /// Computes the state root that results from applying the updates on top of the base state
+    /// without writing them to the database.
+    ///
+    /// The trie nodes of the base factory are overlaid with the updates, and the hashed state of
+    /// the base factory is expected to already reflect the changes the updates were computed for.
+    /// This allows checking the updates against the expected root before they are committed.
+    pub fn resulting_root<F>(&self, base_factory: F) -> Result<B256, StateRootError>
+    where
+        F: TrieCursorFactory + HashedCursorFactory + Clone + Send,
+    {
+        let trie_updates = self.clone().into_sorted();
+        StateRoot::new(
+            InMemoryTrieCursorFactory::new(base_factory.clone(), &trie_updates),
+            base_factory,
+        )
+        .root()
+    }
+
+    /// Returns the distinct hashed addresses of the accounts whose storage tries are updated, i.e.
+    /// with updated or deleted storage nodes or a wiped storage trie, in no particular order.
+    pub fn touched_storage_tries(&self) -> impl Iterator<Item = B256> {
+        self.trie_operations
+            .keys()
+            .filter_map(|key| match key {
+                TrieKey::AccountNode(_) => None,
+                TrieKey::StorageNode(hashed_address, _) | TrieKey::StorageTrie(hashed_address) => {
+                    Some(*hashed_address)
+                }
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+    }
+
+    /// Encodes the updated nodes as a set of nodes keyed by their hash, so that the changes can be
+    /// shipped as a delta witness and verified by the receiver.
+    ///
+    /// Each node is encoded as the tag of its trie, the hashed address for the storage nodes, the
+    /// length-prefixed path and the stored encoding of the branch node, see [`StoredBranchNode`].
+    /// The deleted nodes and wiped storage tries are not part of the set, see [`Self::tombstones`].
+    /// The nodes are ordered by their key.
+    pub fn to_nodes(&self) -> Vec<(B256, Bytes)> {
+        let mut updated = self
+            .trie_operations
+            .iter()
+            .filter_map(|(key, operation)| match operation {
+                TrieOp::Update(node) => Some((key, node)),
+                TrieOp::Delete => None,
+            })
+            .collect::<Vec<_>>();
+        updated.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        updated
+            .into_iter()
+            .map(|(key, node)| {
+                let encoded = Bytes::from(encode_node(key, node));
+                (keccak256(&encoded), encoded)
+            })
+            .collect()
+    }
+
+    /// Returns the keys of the deleted nodes and wiped storage tries ordered by key, complementing
+    /// the updated nodes of [`Self::to_nodes`].
+    pub fn tombstones(&self) -> Vec<TrieKey> {
+        let mut deleted = self
+            .trie_operations
+            .iter()
+            .filter(|(_, operation)| !operation.is_update())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        deleted.sort_unstable();
+        deleted
+    }
+
+    /// Reconstructs the trie updates from the nodes returned by [`Self::to_nodes`] and the
+    /// tombstones returned by [`Self::tombstones`].
+    ///
+    /// The hash of every node is checked against its encoding before the node is decoded.
+    pub fn from_nodes(
+        nodes: impl IntoIterator<Item = (B256, Bytes)>,
+        tombstones: impl IntoIterator<Item = TrieKey>,
+    ) -> Result<Self, TrieNodesError> {
+        let mut updates = Self::default();
+        updates.extend(tombstones.into_iter().map(|key| (key, TrieOp::Delete)));
+        for (hash, encoded) in nodes {
+            let got = keccak256(&encoded);
+            if got != hash {
+                return Err(TrieNodesError::HashMismatch(GotExpected::new(got, hash)))
+            }
+            let (key, node) = decode_node(&encoded).ok_or(TrieNodesError::Malformed(hash))?;
+            updates.trie_operations.insert(key, TrieOp::Update(node));
+        }
+        Ok(updates)
+    }
+
+    /// Splits the updates into batches of at most `max_entries` operations each, so that a large
+    /// set of updates can be committed in several transactions of bounded size.
+    ///
+    /// The operations are split in the order they are written by [`Self::flush`], so flushing the
+    /// batches in order, each in its own transaction, results in the same tries as flushing all
+    /// updates at once. The wiped storage trie of an account is ordered after the storage nodes of
+    /// all accounts, so the wipe is applied after any updated nodes of the same trie regardless of
+    /// the batch boundaries, like in a single flush.
+    ///
+    /// # Panics
+    ///
+    /// If `max_entries` is zero.
+    pub fn into_batches(self, max_entries: usize) -> Vec<Self> {
+        assert!(max_entries > 0, "The batch size must be greater than zero");
+
+        let mut trie_operations = Vec::from_iter(self.trie_operations);
+        trie_operations.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let mut batches = Vec::with_capacity(trie_operations.len().div_ceil(max_entries));
+        let mut trie_operations = trie_operations.into_iter().peekable();
+        while trie_operations.peek().is_some() {
+            batches.push(Self {
+                trie_operations: trie_operations.by_ref().take(max_entries).collect(),
+            });
+        }
+        batches
+    }
+
     /// Flush updates all aggregated updates to the database.
+    ///
+    /// The root nodes of the tries are stored at the empty path along with the other nodes. The
+    /// tries written before the root nodes were stored lack them until a change of the trie writes
+    /// the root node, see [`StateRoot::root`] and
+    /// [`backfill_root_nodes`](crate::maintenance::backfill_root_nodes).
     pub fn flush(self, tx: &(impl DbTx + DbTxMut)) -> Result<(), reth_db::DatabaseError> {
         if self.trie_operations.is_empty() {
             return Ok(())
@@ -148,9 +322,7 @@ impl TrieUpdates {
                         }
                     }
                     TrieOp::Update(node) => {
-                        if !nibbles.0.is_empty() {
-                            account_trie_cursor.upsert(nibbles, StoredBranchNode(node))?;
-                        }
+                        account_trie_cursor.upsert(nibbles, StoredBranchNode(node))?;
                     }
                 },
                 TrieKey::StorageTrie(hashed_address) => match operation {
@@ -162,21 +334,19 @@ impl TrieUpdates {
                     TrieOp::Update(..) => unreachable!("Cannot update full storage trie."),
                 },
                 TrieKey::StorageNode(hashed_address, nibbles) => {
-                    if !nibbles.is_empty() {
-                        // Delete the old entry if it exists.
-                        if storage_trie_cursor
-                            .seek_by_key_subkey(hashed_address, nibbles.clone())?
-                            .filter(|e| e.nibbles == nibbles)
-                            .is_some()
-                        {
-                            storage_trie_cursor.delete_current()?;
-                        }
+                    // Delete the old entry if it exists.
+                    if storage_trie_cursor
+                        .seek_by_key_subkey(hashed_address, nibbles.clone())?
+                        .filter(|e| e.nibbles == nibbles)
+                        .is_some()
+                    {
+                        storage_trie_cursor.delete_current()?;
+                    }
 
-                        // The operation is an update, insert new entry.
-                        if let TrieOp::Update(node) = operation {
-                            storage_trie_cursor
-                                .upsert(hashed_address, StorageTrieEntry { nibbles, node })?;
-                        }
+                    // The operation is an update, insert new entry.
+                    if let TrieOp::Update(node) = operation {
+                        storage_trie_cursor
+                            .upsert(hashed_address, StorageTrieEntry { nibbles, node })?;
                     }
                 }
             };
@@ -185,3 +355,431 @@ impl TrieUpdates {
         Ok(())
     }
 }
+
+/// The error returned when the trie updates cannot be reconstructed from the encoded nodes.
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum TrieNodesError {
+    /// The hash of the node encoding does not match the hash it was shipped with.
+    #[error("trie node hash mismatch: {0}")]
+    HashMismatch(GotExpected<B256>),
+    /// The node with the given hash failed to decode.
+    #[error("malformed trie node {0}")]
+    Malformed(B256),
+}
+
+/// Encodes the trie node as described in [`TrieUpdates::to_nodes`].
+fn encode_node(key: &TrieKey, node: &BranchNodeCompact) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let nibbles = match key {
+        TrieKey::AccountNode(nibbles) => {
+            buf.push(ACCOUNT_NODE_TAG);
+            &nibbles.0
+        }
+        TrieKey::StorageNode(hashed_address, nibbles) => {
+            buf.push(STORAGE_NODE_TAG);
+            buf.extend_from_slice(hashed_address.as_slice());
+            &nibbles.0
+        }
+        TrieKey::StorageTrie(..) => unreachable!("Cannot update full storage trie."),
+    };
+    buf.push(nibbles.len() as u8);
+    buf.extend_from_slice(nibbles);
+    StoredBranchNode(node.clone()).compress_to_buf(&mut buf);
+    buf
+}
+
+/// Decodes the trie node encoded by [`encode_node`], returning `None` if the encoding is
+/// malformed.
+fn decode_node(buf: &[u8]) -> Option<(TrieKey, BranchNodeCompact)> {
+    let (&tag, buf) = buf.split_first()?;
+    let (hashed_address, buf) = match tag {
+        ACCOUNT_NODE_TAG => (None, buf),
+        STORAGE_NODE_TAG if buf.len() >= 32 => {
+            let (hashed_address, buf) = buf.split_at(32);
+            (Some(B256::from_slice(hashed_address)), buf)
+        }
+        _ => return None,
+    };
+    let (&len, buf) = buf.split_first()?;
+    if len > 64 || buf.len() < len as usize {
+        return None
+    }
+    let (nibbles, buf) = buf.split_at(len as usize);
+    if nibbles.iter().any(|nibble| *nibble > 0xf) {
+        return None
+    }
+    let nibbles = Nibbles::from_nibbles_unchecked(nibbles);
+
+    // Validate the masks and the number of hashes, the branch node constructor panics otherwise.
+    if buf.len() < 6 || (buf.len() - 6) % 32 != 0 {
+        return None
+    }
+    let mask = |offset: usize| u16::from_be_bytes([buf[offset], buf[offset + 1]]);
+    let (state_mask, tree_mask, hash_mask) = (mask(0), mask(2), mask(4));
+    let num_hashes = (buf.len() - 6) / 32;
+    let num_child_hashes = hash_mask.count_ones() as usize;
+    if tree_mask & !state_mask != 0 ||
+        hash_mask & !state_mask != 0 ||
+        (num_hashes != num_child_hashes && num_hashes != num_child_hashes + 1)
+    {
+        return None
+    }
+    let StoredBranchNode(node) = StoredBranchNode::decompress(buf).ok()?;
+
+    let key = match hashed_address {
+        Some(hashed_address) => TrieKey::StorageNode(hashed_address, nibbles.into()),
+        None => TrieKey::AccountNode(nibbles.into()),
+    };
+    Some((key, node))
+}
+
+/// Sorted trie updates used for lookups during state root calculation.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct TrieUpdatesSorted {
+    /// Sorted collection of account node paths and the operations on them.
+    pub(crate) account_nodes: Vec<(Nibbles, TrieOp)>,
+    /// Map of hashed addresses to the sorted storage trie updates.
+    pub(crate) storage_tries: HashMap<B256, StorageTrieUpdatesSorted>,
+}
+
+/// Sorted storage trie updates of a single account.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct StorageTrieUpdatesSorted {
+    /// Flag indicating whether the storage trie was wiped.
+    pub(crate) wiped: bool,
+    /// Sorted collection of storage node paths and the operations on them.
+    pub(crate) storage_nodes: Vec<(Nibbles, TrieOp)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hashed_cursor::HashedPostStateCursorFactory, prefix_set::TriePrefixSets,
+        trie_cursor::noop::NoopTrieCursorFactory, HashedPostState, HashedStorage, StorageRoot,
+    };
+    use reth_primitives::{Account, U256};
+    use reth_provider::{
+        bundle_state::HashedStateChanges, test_utils::create_test_provider_factory,
+    };
+
+    #[test]
+    fn touched_storage_tries() {
+        let node = BranchNodeCompact::new(0b11, 0, 0, vec![], None);
+        let storage_node = |hashed_address, nibble: u8| {
+            TrieKey::StorageNode(
+                hashed_address,
+                StoredNibblesSubKey(Nibbles::from_nibbles([nibble])),
+            )
+        };
+        let (updated, deleted, wiped) =
+            (B256::with_last_byte(1), B256::with_last_byte(2), B256::with_last_byte(3));
+        let updates = TrieUpdates::from([
+            (TrieKey::AccountNode(StoredNibbles(Nibbles::from_nibbles([1]))), TrieOp::Delete),
+            (storage_node(updated, 1), TrieOp::Update(node.clone())),
+            (storage_node(updated, 2), TrieOp::Update(node)),
+            (storage_node(deleted, 1), TrieOp::Delete),
+            (TrieKey::StorageTrie(wiped), TrieOp::Delete),
+        ]);
+
+        let touched = updates.touched_storage_tries().collect::<Vec<_>>();
+        assert_eq!(touched.len(), 3);
+        assert_eq!(
+            touched.into_iter().collect::<HashSet<_>>(),
+            HashSet::from([updated, deleted, wiped])
+        );
+        assert_eq!(TrieUpdates::default().touched_storage_tries().next(), None);
+    }
+
+    #[test]
+    fn staged_updates_resulting_root() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+
+        let initial_state = HashedPostState::default()
+            .with_accounts((0..20u8).map(|i| {
+                (B256::with_last_byte(i), Some(Account { nonce: i as u64, ..Default::default() }))
+            }))
+            .with_storages((0..5u8).map(|i| {
+                (
+                    B256::with_last_byte(i),
+                    HashedStorage::from_iter(
+                        false,
+                        (1..20u64).map(|slot| (B256::from(U256::from(slot)), U256::from(slot))),
+                    ),
+                )
+            }));
+        HashedStateChanges(initial_state).write_to_db(provider.tx_ref()).unwrap();
+        let (_, updates) = StateRoot::from_tx(provider.tx_ref()).root_with_updates().unwrap();
+        updates.flush(provider.tx_ref()).unwrap();
+
+        let changes = HashedPostState::default()
+            .with_accounts([
+                (B256::with_last_byte(1), None),
+                (B256::with_last_byte(30), Some(Account { nonce: 1, ..Default::default() })),
+            ])
+            .with_storages([
+                (B256::with_last_byte(2), HashedStorage::from_iter(true, [])),
+                (
+                    B256::with_last_byte(3),
+                    HashedStorage::from_iter(
+                        false,
+                        [(B256::from(U256::from(1)), U256::ZERO)].into_iter().chain(
+                            (20..25u64).map(|slot| (B256::from(U256::from(slot)), U256::from(1))),
+                        ),
+                    ),
+                ),
+            ]);
+        let sorted_changes = changes.clone().into_sorted();
+        let (expected_root, staged_updates) = StateRoot::from_tx(provider.tx_ref())
+            .with_hashed_cursor_factory(HashedPostStateCursorFactory::new(
+                provider.tx_ref(),
+                &sorted_changes,
+            ))
+            .with_prefix_sets(changes.construct_prefix_sets())
+            .root_with_updates()
+            .unwrap();
+
+        // Write the hashed state, but keep the trie updates staged.
+        HashedStateChanges(changes).write_to_db(provider.tx_ref()).unwrap();
+        assert_eq!(staged_updates.resulting_root(provider.tx_ref()).unwrap(), expected_root);
+
+        // The resulting root matches the root of the trie after the updates are written.
+        staged_updates.flush(provider.tx_ref()).unwrap();
+        assert_eq!(StateRoot::from_tx(provider.tx_ref()).root().unwrap(), expected_root);
+        assert_eq!(
+            TrieUpdates::default().resulting_root(provider.tx_ref()).unwrap(),
+            expected_root
+        );
+    }
+
+    #[test]
+    fn flush_stores_root_nodes() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+        let contract = keccak256(B256::ZERO);
+        let stored_roots = || {
+            let account_root = tx
+                .get::<tables::AccountsTrie>(StoredNibbles::default())
+                .unwrap()
+                .and_then(|node| node.0.root_hash);
+            let storage_root = tx
+                .cursor_dup_read::<tables::StoragesTrie>()
+                .unwrap()
+                .seek_by_key_subkey(contract, StoredNibblesSubKey(Nibbles::default()))
+                .unwrap()
+                .filter(|entry| entry.nibbles.is_empty())
+                .and_then(|entry| entry.node.root_hash);
+            (account_root, storage_root)
+        };
+
+        let state = HashedPostState::default()
+            .with_accounts(
+                (0..1_000u64)
+                    .map(|i| (keccak256(B256::from(U256::from(i))), Some(Account::default()))),
+            )
+            .with_storages([(
+                contract,
+                HashedStorage::from_iter(
+                    false,
+                    (1..1_000u64)
+                        .map(|slot| (keccak256(B256::from(U256::from(slot))), U256::from(slot))),
+                ),
+            )]);
+        HashedStateChanges(state).write_to_db(tx).unwrap();
+        let (root, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        let storage_root = StorageRoot::from_tx_hashed(tx, contract)
+            .with_trie_cursor_factory(NoopTrieCursorFactory)
+            .root()
+            .unwrap();
+        updates.flush(tx).unwrap();
+        assert_eq!(stored_roots(), (Some(root), Some(storage_root)));
+
+        // The stale root nodes are replaced when the tries change.
+        let changes = HashedPostState::default()
+            .with_accounts([(
+                keccak256(B256::ZERO),
+                Some(Account { nonce: 1, ..Default::default() }),
+            )])
+            .with_storages([(
+                contract,
+                HashedStorage::from_iter(false, [(B256::with_last_byte(1), U256::from(1))]),
+            )]);
+        let prefix_sets = changes.construct_prefix_sets();
+        HashedStateChanges(changes).write_to_db(tx).unwrap();
+        let (root, updates) =
+            StateRoot::from_tx(tx).with_prefix_sets(prefix_sets).root_with_updates().unwrap();
+        let storage_root = StorageRoot::from_tx_hashed(tx, contract)
+            .with_trie_cursor_factory(NoopTrieCursorFactory)
+            .root()
+            .unwrap();
+        updates.flush(tx).unwrap();
+        assert_eq!(stored_roots(), (Some(root), Some(storage_root)));
+        assert_eq!(
+            StateRoot::from_tx(tx).with_prefix_sets(TriePrefixSets::all_changed()).root().unwrap(),
+            root
+        );
+    }
+
+    #[test]
+    fn updates_from_nodes_resulting_root() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+
+        let initial_state = HashedPostState::default()
+            .with_accounts((0..50u8).map(|i| {
+                (B256::with_last_byte(i), Some(Account { nonce: i as u64, ..Default::default() }))
+            }))
+            .with_storages((0..3u8).map(|i| {
+                (
+                    B256::with_last_byte(i),
+                    HashedStorage::from_iter(
+                        false,
+                        (1..50u64).map(|slot| (B256::from(U256::from(slot)), U256::from(slot))),
+                    ),
+                )
+            }));
+        HashedStateChanges(initial_state).write_to_db(provider.tx_ref()).unwrap();
+        let (_, updates) = StateRoot::from_tx(provider.tx_ref()).root_with_updates().unwrap();
+        updates.flush(provider.tx_ref()).unwrap();
+
+        let changes = HashedPostState::default()
+            .with_accounts((0..50u8).step_by(7).map(|i| (B256::with_last_byte(i), None)).chain([(
+                B256::with_last_byte(100),
+                Some(Account { nonce: 1, ..Default::default() }),
+            )]))
+            .with_storages([
+                (B256::with_last_byte(1), HashedStorage::from_iter(true, [])),
+                (
+                    B256::with_last_byte(2),
+                    HashedStorage::from_iter(
+                        false,
+                        (50..60u64).map(|slot| (B256::from(U256::from(slot)), U256::from(1))),
+                    ),
+                ),
+            ]);
+        let prefix_sets = changes.construct_prefix_sets();
+        HashedStateChanges(changes).write_to_db(provider.tx_ref()).unwrap();
+        let (expected_root, updates) = StateRoot::from_tx(provider.tx_ref())
+            .with_prefix_sets(prefix_sets)
+            .root_with_updates()
+            .unwrap();
+
+        let nodes = updates.to_nodes();
+        let tombstones = updates.tombstones();
+        assert!(!nodes.is_empty());
+        assert!(tombstones.contains(&TrieKey::StorageTrie(B256::with_last_byte(1))));
+        assert!(nodes.iter().all(|(hash, node)| keccak256(node) == *hash));
+
+        // Applying the nodes yields the same updates and the same root as applying the updates.
+        let reconstructed = TrieUpdates::from_nodes(nodes.clone(), tombstones.clone()).unwrap();
+        assert_eq!(reconstructed, updates);
+        assert_eq!(reconstructed.resulting_root(provider.tx_ref()).unwrap(), expected_root);
+        reconstructed.flush(provider.tx_ref()).unwrap();
+        assert_eq!(StateRoot::from_tx(provider.tx_ref()).root().unwrap(), expected_root);
+
+        // The tampered and the malformed nodes are rejected.
+        let (hash, node) = nodes[0].clone();
+        let mut tampered = node.to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            TrieUpdates::from_nodes([(hash, Bytes::from(tampered.clone()))], []),
+            Err(TrieNodesError::HashMismatch(GotExpected::new(keccak256(&tampered), hash)))
+        );
+        let truncated = Bytes::copy_from_slice(&node[..node.len() - 1]);
+        let truncated_hash = keccak256(&truncated);
+        assert_eq!(
+            TrieUpdates::from_nodes([(truncated_hash, truncated)], []),
+            Err(TrieNodesError::Malformed(truncated_hash))
+        );
+    }
+
+    #[test]
+    fn batches_flush_to_same_tries() {
+        let initial_state = HashedPostState::default()
+            .with_accounts((0..50u8).map(|i| {
+                (B256::with_last_byte(i), Some(Account { nonce: i as u64, ..Default::default() }))
+            }))
+            .with_storages((0..3u8).map(|i| {
+                (
+                    B256::with_last_byte(i),
+                    HashedStorage::from_iter(
+                        false,
+                        (1..50u64).map(|slot| (B256::from(U256::from(slot)), U256::from(slot))),
+                    ),
+                )
+            }));
+        let changes = HashedPostState::default()
+            .with_accounts([
+                (B256::with_last_byte(1), None),
+                (B256::with_last_byte(100), Some(Account { nonce: 1, ..Default::default() })),
+            ])
+            .with_storages([(
+                B256::with_last_byte(2),
+                HashedStorage::from_iter(
+                    false,
+                    (50..60u64).map(|slot| (B256::from(U256::from(slot)), U256::from(1))),
+                ),
+            )]);
+
+        // Writes the initial state and its trie, then the changes along with the given updates,
+        // and returns the resulting trie tables.
+        let flush_batches = |batches: &dyn Fn(TrieUpdates) -> Vec<TrieUpdates>| {
+            let factory = create_test_provider_factory();
+            let provider = factory.provider_rw().unwrap();
+            let tx = provider.tx_ref();
+            HashedStateChanges(initial_state.clone()).write_to_db(tx).unwrap();
+            let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+            updates.flush(tx).unwrap();
+
+            let prefix_sets = changes.construct_prefix_sets();
+            HashedStateChanges(changes.clone()).write_to_db(tx).unwrap();
+            let (root, mut updates) =
+                StateRoot::from_tx(tx).with_prefix_sets(prefix_sets).root_with_updates().unwrap();
+            // The storage nodes of the wiped trie are discarded by the wipe.
+            let node = BranchNodeCompact::new(0b11, 0, 0, vec![], None);
+            updates.extend([(
+                TrieKey::StorageNode(
+                    B256::with_last_byte(1),
+                    StoredNibblesSubKey(Nibbles::from_nibbles([1])),
+                ),
+                TrieOp::Update(node),
+            )]);
+            for batch in batches(updates) {
+                batch.flush(tx).unwrap();
+            }
+
+            let account_nodes = tx
+                .cursor_read::<tables::AccountsTrie>()
+                .unwrap()
+                .walk(None)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let storage_nodes = tx
+                .cursor_dup_read::<tables::StoragesTrie>()
+                .unwrap()
+                .walk(None)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(StateRoot::from_tx(tx).root().unwrap(), root);
+            assert!(storage_nodes.iter().all(|(key, _)| *key != B256::with_last_byte(1)));
+            (account_nodes, storage_nodes)
+        };
+
+        let expected = flush_batches(&|updates| vec![updates]);
+        for max_entries in [1, 2, 3, 10, 1_000] {
+            let tries = flush_batches(&|updates| {
+                let len = updates.len();
+                let batches = updates.into_batches(max_entries);
+                assert_eq!(batches.len(), len.div_ceil(max_entries));
+                assert!(batches.iter().all(|batch| batch.len() <= max_entries));
+                batches
+            });
+            assert_eq!(tries, expected, "batches of {max_entries} entries");
+        }
+        assert!(TrieUpdates::default().into_batches(1).is_empty());
+    }
+}
@@ -12,6 +12,11 @@ use reth_primitives::{
 /// `TrieWalker` is a structure that enables traversal of a Merkle trie.
 /// It allows moving through the trie in a depth-first manner, skipping certain branches
 /// if they have not changed.
+///
+/// The nodes of the skipped subtrees are never read from the cursor, the hash of a skipped child
+/// is taken from the hashes of its parent node. Only the nodes on the paths to the changed keys
+/// are read and decoded, so the decoding work is proportional to the changes rather than to the
+/// size of the trie.
 #[derive(Debug)]
 pub struct TrieWalker<C> {
     /// A mutable reference to a trie cursor instance used for navigating the trie.
@@ -26,13 +31,21 @@ pub struct TrieWalker<C> {
     pub changes: PrefixSet,
     /// The trie updates to be applied to the trie.
     trie_updates: Option<TrieUpdates>,
+    /// The number of stored subtrees descended into rather than reused by their hash.
+    subtrees_descended: u64,
 }
 
 impl<C> TrieWalker<C> {
     /// Constructs a new `TrieWalker` from existing stack and a cursor.
     pub fn from_stack(cursor: C, stack: Vec<CursorSubNode>, changes: PrefixSet) -> Self {
-        let mut this =
-            Self { cursor, changes, stack, can_skip_current_node: false, trie_updates: None };
+        let mut this = Self {
+            cursor,
+            changes,
+            stack,
+            can_skip_current_node: false,
+            trie_updates: None,
+            subtrees_descended: 0,
+        };
         this.update_skip_node();
         this
     }
@@ -56,6 +69,11 @@ impl<C> TrieWalker<C> {
         (self.stack, trie_updates.unwrap_or_default())
     }
 
+    /// Take the trie updates collected so far, leaving the walker collecting the following ones.
+    pub fn take_updates(&mut self) -> TrieUpdates {
+        self.trie_updates.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     /// Prints the current stack of trie nodes.
     pub fn print_stack(&self) {
         println!("====================== STACK ======================");
@@ -65,6 +83,15 @@ impl<C> TrieWalker<C> {
         println!("====================== END STACK ======================\n");
     }
 
+    /// Returns the number of stored subtrees the walker descended into so far, i.e. the subtrees
+    /// with a stored hash that could not be reused because they contain changed keys.
+    ///
+    /// The reused subtrees are the ones emitted as branch nodes by the
+    /// [`TrieNodeIter`](crate::node_iter::TrieNodeIter).
+    pub const fn subtrees_descended(&self) -> u64 {
+        self.subtrees_descended
+    }
+
     /// The current length of the trie updates.
     pub fn updates_len(&self) -> usize {
         self.trie_updates.as_ref().map(|u| u.len()).unwrap_or(0)
@@ -87,18 +114,13 @@ impl<C> TrieWalker<C> {
 
     /// Returns the next unprocessed key in the trie.
     pub fn next_unprocessed_key(&self) -> Option<B256> {
-        self.key()
-            .and_then(|key| {
-                if self.can_skip_current_node {
-                    key.increment().map(|inc| inc.pack())
-                } else {
-                    Some(key.pack())
-                }
-            })
-            .map(|mut key| {
-                key.resize(32, 0);
-                B256::from_slice(key.as_slice())
-            })
+        self.key().and_then(|key| {
+            if self.can_skip_current_node {
+                key.increment().map(|inc| pack_key(&inc))
+            } else {
+                Some(pack_key(key))
+            }
+        })
     }
 
     /// Updates the skip node flag based on the walker's current state.
@@ -120,6 +142,7 @@ impl<C: TrieCursor> TrieWalker<C> {
             stack: vec![CursorSubNode::default()],
             can_skip_current_node: false,
             trie_updates: None,
+            subtrees_descended: 0,
         };
 
         // Set up the root node of the trie in the stack, if it exists.
@@ -139,11 +162,26 @@ impl<C: TrieCursor> TrieWalker<C> {
     /// * `Result<Option<Nibbles>, Error>` - The next key in the trie or an error.
     pub fn advance(&mut self) -> Result<Option<Nibbles>, DatabaseError> {
         if let Some(last) = self.stack.last() {
+            if !self.can_skip_current_node && last.hash_flag() {
+                self.subtrees_descended += 1;
+            }
+
             if !self.can_skip_current_node && self.children_are_in_trie() {
                 // If we can't skip the current node and the children are in the trie,
                 // either consume the next node or move to the next sibling.
                 match last.nibble() {
-                    -1 => self.move_to_next_sibling(true)?,
+                    -1 => {
+                        // The stored root node is stale once its trie has changed, it is replaced
+                        // by the root node emitted by the hash builder, if any.
+                        if last.node.is_some() {
+                            if let Some((updates, key)) =
+                                self.trie_updates.as_mut().zip(self.cursor.current()?)
+                            {
+                                updates.schedule_delete(key);
+                            }
+                        }
+                        self.move_to_next_sibling(true)?
+                    }
                     _ => self.consume_node()?,
                 }
             } else {
@@ -243,17 +281,51 @@ impl<C: TrieCursor> TrieWalker<C> {
     }
 }
 
+/// Packs the nibbles into a hashed key, right-padded with zeroes.
+///
+/// Equivalent to the padded [`Nibbles::pack`], but packs the key in place instead of allocating
+/// the packed bytes on every step of the walk.
+pub(crate) fn pack_key(nibbles: &[u8]) -> B256 {
+    let mut key = B256::ZERO;
+    for (index, nibble) in nibbles.iter().take(64).enumerate() {
+        key[index / 2] |= if index % 2 == 0 { nibble << 4 } else { *nibble };
+    }
+    key
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         prefix_set::PrefixSetMut,
         trie_cursor::{DatabaseAccountTrieCursor, DatabaseStorageTrieCursor},
+        updates::TrieKey,
+        StateRoot,
+    };
+    use reth_db::{
+        cursor::DbCursorRW,
+        tables,
+        transaction::{DbTx, DbTxMut},
+    };
+    use reth_primitives::{
+        keccak256,
+        trie::{StorageTrieEntry, StoredBranchNode},
+        Account, U256,
     };
-    use reth_db::{cursor::DbCursorRW, tables, transaction::DbTxMut};
-    use reth_primitives::trie::{StorageTrieEntry, StoredBranchNode};
     use reth_provider::test_utils::create_test_provider_factory;
 
+    #[test]
+    fn pack_key_matches_padded_pack() {
+        for len in [0, 1, 2, 31, 63, 64] {
+            let nibbles = Nibbles::from_nibbles_unchecked(
+                (0..len).map(|i| (i % 16) as u8).collect::<Vec<_>>(),
+            );
+            let mut packed = nibbles.pack();
+            packed.resize(32, 0);
+            assert_eq!(pack_key(&nibbles), B256::from_slice(&packed));
+        }
+    }
+
     #[test]
     fn walk_nodes_with_common_prefix() {
         let inputs = vec![
@@ -301,6 +373,71 @@ mod tests {
         test_cursor(storage_trie, &expected);
     }
 
+    /// The trie cursor recording the keys of the nodes it returns.
+    #[derive(Debug)]
+    struct RecordingTrieCursor<C> {
+        inner: C,
+        read: Vec<Nibbles>,
+    }
+
+    impl<C: TrieCursor> TrieCursor for RecordingTrieCursor<C> {
+        fn seek_exact(
+            &mut self,
+            key: Nibbles,
+        ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+            let entry = self.inner.seek_exact(key)?;
+            self.read.extend(entry.as_ref().map(|(key, _)| key.clone()));
+            Ok(entry)
+        }
+
+        fn seek(
+            &mut self,
+            key: Nibbles,
+        ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+            let entry = self.inner.seek(key)?;
+            self.read.extend(entry.as_ref().map(|(key, _)| key.clone()));
+            Ok(entry)
+        }
+
+        fn current(&mut self) -> Result<Option<TrieKey>, DatabaseError> {
+            self.inner.current()
+        }
+    }
+
+    #[test]
+    fn skipped_subtrees_are_not_read() {
+        let factory = create_test_provider_factory();
+        let tx = factory.provider_rw().unwrap();
+        let hashed_addresses = (0..5_000u64)
+            .map(|i| {
+                let hashed_address = keccak256(B256::from(U256::from(i)));
+                let account = Account { nonce: i, ..Default::default() };
+                tx.tx_ref().put::<tables::HashedAccounts>(hashed_address, account).unwrap();
+                hashed_address
+            })
+            .collect::<Vec<_>>();
+        let (_, updates) = StateRoot::from_tx(tx.tx_ref()).root_with_updates().unwrap();
+        updates.flush(tx.tx_ref()).unwrap();
+        let stored = tx.tx_ref().entries::<tables::AccountsTrie>().unwrap();
+
+        let changed = Nibbles::unpack(hashed_addresses[0]);
+        let mut prefix_set = PrefixSetMut::default();
+        prefix_set.insert(changed.clone());
+        let cursor = DatabaseAccountTrieCursor::new(
+            tx.tx_ref().cursor_read::<tables::AccountsTrie>().unwrap(),
+        );
+        let mut trie = RecordingTrieCursor { inner: cursor, read: Vec::new() };
+        let mut walker = TrieWalker::new(&mut trie, prefix_set.freeze());
+        while walker.advance().unwrap().is_some() {}
+
+        // Only the nodes on the path to the changed key are read.
+        assert!(trie.read.len() > 1);
+        assert!(trie.read.len() < stored / 10);
+        for key in &trie.read {
+            assert!(changed.starts_with(key), "node {key:?} is off the changed path");
+        }
+    }
+
     fn test_cursor<T>(mut trie: T, expected: &[Vec<u8>])
     where
         T: TrieCursor,
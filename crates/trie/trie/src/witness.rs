@@ -0,0 +1,647 @@
+use crate::{
+    hashed_cursor::HashedCursorFactory,
+    node_iter::{TrieElement, TrieNodeIter},
+    prefix_set::{PrefixSet, TriePrefixSets},
+    proof::{MultiProof, MultiProofError},
+    trie_cursor::TrieCursorFactory,
+    updates::TrieUpdates,
+    walker::{pack_key, TrieWalker},
+    StorageRoot,
+};
+use alloy_rlp::{BufMut, Decodable, Encodable};
+use reth_execution_errors::StateRootError;
+use reth_primitives::{
+    keccak256,
+    trie::{proof::ProofRetainer, HashBuilder, Nibbles, TrieAccount},
+    Bytes, B256,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+#[cfg(feature = "metrics")]
+use crate::metrics::{TrieRootMetrics, TrieType};
+
+/// The nodes of the account and storage tries along the paths of the changed keys, as of the state
+/// root computed by [`root_updates_and_witness`].
+///
+/// The witness proves the values of all changed accounts and storage slots, including the absence
+/// of the removed ones, against the new state root, see [`MultiProof::verify_complete`].
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct StateWitness {
+    /// The nodes of the account trie along the paths of the changed accounts.
+    pub accounts: MultiProof,
+    /// The nodes of the storage tries along the paths of the changed slots, keyed by the hashed
+    /// address of the account. The accounts that do not exist anymore have no storage witness.
+    pub storages: HashMap<B256, MultiProof>,
+}
+
+/// The version of the binary encoding of the [`StateWitness`], see [`StateWitness::encode`].
+pub const WITNESS_ENCODING_VERSION: u8 = 1;
+
+/// The maximum length of an encoded trie node, the length of a branch node with 16 hashes.
+const MAX_NODE_LENGTH: usize = 532;
+
+impl StateWitness {
+    /// Encodes the witness into the compact binary format exchanged with the stateless clients.
+    ///
+    /// The encoding starts with the [`WITNESS_ENCODING_VERSION`] byte, followed by the account
+    /// proof and the number of the storage proofs as a big-endian `u32`, each preceded by the
+    /// hashed address of its account, in ascending order. A proof is encoded as the number of the
+    /// targets as a big-endian `u32` followed by the targets, and the number of the nodes as a
+    /// big-endian `u32` followed by the nodes in ascending order of their paths. A node is encoded
+    /// as the number of the nibbles of its path as a byte, the packed path and the length of the
+    /// node as a big-endian `u16` followed by the node.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![WITNESS_ENCODING_VERSION];
+        encode_proof(&self.accounts, &mut out);
+
+        let mut storages = self.storages.iter().collect::<Vec<_>>();
+        storages.sort_unstable_by_key(|(hashed_address, _)| **hashed_address);
+        out.extend_from_slice(&(storages.len() as u32).to_be_bytes());
+        for (hashed_address, storage) in storages {
+            out.extend_from_slice(hashed_address.as_slice());
+            encode_proof(storage, &mut out);
+        }
+        out
+    }
+
+    /// Decodes the witness from its binary encoding, see [`Self::encode`].
+    ///
+    /// The whole input has to be consumed, the nodes longer than a branch node with all children
+    /// referenced by their hashes are rejected.
+    pub fn decode(buf: &[u8]) -> Result<Self, WitnessDecodeError> {
+        let mut reader = WitnessReader(buf);
+        let version = reader.take(1)?[0];
+        if version != WITNESS_ENCODING_VERSION {
+            return Err(WitnessDecodeError::UnsupportedVersion(version))
+        }
+
+        let accounts = reader.proof()?;
+        let mut storages = HashMap::new();
+        for _ in 0..reader.u32()? {
+            let hashed_address = B256::from_slice(reader.take(32)?);
+            if storages.insert(hashed_address, reader.proof()?).is_some() {
+                return Err(WitnessDecodeError::DuplicateStorage(hashed_address))
+            }
+        }
+
+        if !reader.0.is_empty() {
+            return Err(WitnessDecodeError::TrailingBytes(reader.0.len()))
+        }
+        Ok(Self { accounts, storages })
+    }
+
+    /// Verifies that the witness proves the changed keys of the prefix sets against the state
+    /// root, as returned by [`root_updates_and_witness`] with the same prefix sets.
+    ///
+    /// The account proof has to prove exactly the changed accounts, see
+    /// [`MultiProof::verify_complete`]. Every proven account has to come with the storage proof of
+    /// exactly its changed slots against its storage root, the absent accounts and the accounts
+    /// without changed slots with none.
+    pub fn verify_against(
+        &self,
+        root: B256,
+        prefix_sets: &TriePrefixSets,
+    ) -> Result<(), WitnessVerificationError> {
+        if self.accounts.targets != witness_targets(&prefix_sets.account_prefix_set) {
+            return Err(WitnessVerificationError::AccountTargets)
+        }
+
+        let mut unverified = self.storages.keys().copied().collect::<HashSet<_>>();
+        for (hashed_address, value) in self.accounts.leaf_values(root)? {
+            let storage_targets = witness_targets(&prefix_sets.storage_prefix_set(&hashed_address));
+            let storage = self.storages.get(&hashed_address);
+            unverified.remove(&hashed_address);
+            let value = match value {
+                Some(value) if !storage_targets.is_empty() => value,
+                _ if storage.is_some() => {
+                    return Err(WitnessVerificationError::UnexpectedStorage(hashed_address))
+                }
+                _ => continue,
+            };
+
+            let storage =
+                storage.ok_or(WitnessVerificationError::MissingStorage(hashed_address))?;
+            if storage.targets != storage_targets {
+                return Err(WitnessVerificationError::StorageTargets(hashed_address))
+            }
+            let account = TrieAccount::decode(&mut &value[..])?;
+            storage
+                .verify_complete(account.storage_root)
+                .map_err(|error| WitnessVerificationError::Storage { hashed_address, error })?;
+        }
+
+        match unverified.into_iter().next() {
+            Some(hashed_address) => {
+                Err(WitnessVerificationError::UnexpectedStorage(hashed_address))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the hashes of the nodes of the witness that are not needed to prove the changed
+    /// keys of the prefix sets against the state root, e.g. to catch a witness generator including
+    /// superfluous nodes. The witness returned by [`root_updates_and_witness`] has none.
+    ///
+    /// The paths of the changed accounts are walked through the account proof and the paths of
+    /// the changed slots of every proven account through its storage proof, like in
+    /// [`Self::verify_against`]. The nodes off these paths are reported, including all nodes of
+    /// the storage proofs of the accounts that are absent or have no changed slots, the account
+    /// nodes first and then the storage nodes by the hashed address of the account. The targets
+    /// of the proofs are not compared with the prefix sets, so the nodes proving the keys that are
+    /// not changed are reported as unused as well.
+    pub fn find_unused(
+        &self,
+        root: B256,
+        prefix_sets: &TriePrefixSets,
+    ) -> Result<Vec<B256>, WitnessVerificationError> {
+        let account_targets = witness_targets(&prefix_sets.account_prefix_set);
+        let (values, used) = self.accounts.walk_targets(root, &account_targets)?;
+        let mut unused = unused_node_hashes(&self.accounts, &used).collect::<Vec<_>>();
+
+        let mut storages = self.storages.iter().collect::<Vec<_>>();
+        storages.sort_unstable_by_key(|(hashed_address, _)| **hashed_address);
+        for (hashed_address, storage) in storages {
+            let storage_targets = witness_targets(&prefix_sets.storage_prefix_set(hashed_address));
+            let value = match values.get(hashed_address) {
+                Some(Some(value)) if !storage_targets.is_empty() => value,
+                _ => {
+                    unused.extend(storage.nodes.values().map(keccak256));
+                    continue
+                }
+            };
+
+            let account = TrieAccount::decode(&mut &value[..])?;
+            let (_, used) =
+                storage.walk_targets(account.storage_root, &storage_targets).map_err(|error| {
+                    WitnessVerificationError::Storage { hashed_address: *hashed_address, error }
+                })?;
+            unused.extend(unused_node_hashes(storage, &used));
+        }
+        Ok(unused)
+    }
+}
+
+/// The error returned when the binary encoding of a [`StateWitness`] fails to decode.
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum WitnessDecodeError {
+    /// The encoding version is not supported.
+    #[error("unsupported witness encoding version {0}")]
+    UnsupportedVersion(u8),
+    /// The input ends before the end of the witness.
+    #[error("witness encoding is truncated")]
+    Truncated,
+    /// The input continues after the end of the witness.
+    #[error("{0} trailing bytes after the witness encoding")]
+    TrailingBytes(usize),
+    /// The node is longer than any valid trie node.
+    #[error("witness node of {0} bytes is too long")]
+    OversizedNode(usize),
+    /// The path of the node is longer than the key or not padded with a zero nibble.
+    #[error("invalid witness node path")]
+    InvalidPath,
+    /// The proof has multiple nodes at the same path.
+    #[error("duplicate witness node at {0:?}")]
+    DuplicateNode(Nibbles),
+    /// The witness has multiple storage proofs of the same account.
+    #[error("duplicate storage witness of account {0}")]
+    DuplicateStorage(B256),
+}
+
+/// The error returned when a [`StateWitness`] does not prove the changed keys of the prefix sets.
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum WitnessVerificationError {
+    /// The account proof does not prove exactly the changed accounts.
+    #[error("witness accounts differ from the changed accounts")]
+    AccountTargets,
+    /// The account proof is not complete.
+    #[error("account witness: {0}")]
+    Accounts(#[from] MultiProofError),
+    /// The proven account leaf failed to decode.
+    #[error("failed to decode witness account: {0}")]
+    Account(#[from] alloy_rlp::Error),
+    /// The storage proof of the account with changed slots is missing.
+    #[error("storage witness of account {0} is missing")]
+    MissingStorage(B256),
+    /// The storage proof is present for an absent account or an account without changed slots.
+    #[error("unexpected storage witness of account {0}")]
+    UnexpectedStorage(B256),
+    /// The storage proof does not prove exactly the changed slots of the account.
+    #[error("storage witness of account {0} differs from the changed slots")]
+    StorageTargets(B256),
+    /// The storage proof is not complete.
+    #[error("storage witness of account {hashed_address}: {error}")]
+    Storage {
+        /// The hashed address of the account.
+        hashed_address: B256,
+        /// The error of the storage proof.
+        error: MultiProofError,
+    },
+}
+
+/// Appends the binary encoding of the proof, see [`StateWitness::encode`].
+fn encode_proof(proof: &MultiProof, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(proof.targets.len() as u32).to_be_bytes());
+    for target in &proof.targets {
+        out.extend_from_slice(target.as_slice());
+    }
+    out.extend_from_slice(&(proof.nodes.len() as u32).to_be_bytes());
+    for (path, node) in &proof.nodes {
+        out.push(path.len() as u8);
+        out.extend_from_slice(&path.pack());
+        out.extend_from_slice(&(node.len() as u16).to_be_bytes());
+        out.extend_from_slice(node);
+    }
+}
+
+/// The reader of the binary encoding of the witness.
+struct WitnessReader<'a>(&'a [u8]);
+
+impl<'a> WitnessReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], WitnessDecodeError> {
+        if self.0.len() < len {
+            return Err(WitnessDecodeError::Truncated)
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, WitnessDecodeError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn proof(&mut self) -> Result<MultiProof, WitnessDecodeError> {
+        let mut targets = Vec::new();
+        for _ in 0..self.u32()? {
+            targets.push(B256::from_slice(self.take(32)?));
+        }
+
+        let mut nodes = BTreeMap::new();
+        for _ in 0..self.u32()? {
+            let path_len = self.take(1)?[0] as usize;
+            if path_len > 64 {
+                return Err(WitnessDecodeError::InvalidPath)
+            }
+            let path = Nibbles::unpack(self.take(path_len.div_ceil(2))?);
+            if path[path_len..].iter().any(|nibble| *nibble != 0) {
+                return Err(WitnessDecodeError::InvalidPath)
+            }
+            let path = Nibbles::from_nibbles_unchecked(&path[..path_len]);
+
+            let node_len = u16::from_be_bytes(self.take(2)?.try_into().expect("2 bytes")) as usize;
+            if node_len > MAX_NODE_LENGTH {
+                return Err(WitnessDecodeError::OversizedNode(node_len))
+            }
+            let node = Bytes::copy_from_slice(self.take(node_len)?);
+            if nodes.insert(path.clone(), node).is_some() {
+                return Err(WitnessDecodeError::DuplicateNode(path))
+            }
+        }
+
+        Ok(MultiProof { targets, nodes })
+    }
+}
+
+/// Computes the state root, the trie updates and the witness of the changed keys in a single walk.
+///
+/// The walk is the same as the one of [`StateRoot`](crate::StateRoot) with the given prefix sets,
+/// with the hash builders of the account trie and of the changed storage tries retaining the nodes
+/// along the paths of the changed keys. The walk descends along these paths anyway, so the witness
+/// comes at the cost of keeping the nodes rather than of additional reads.
+///
+/// The targets of the witness are the full keys of the prefix sets, e.g. as loaded from the
+/// changesets or built from a [`HashedPostState`](crate::HashedPostState). The prefix sets marking
+/// all keys as changed, see [`TriePrefixSets::all_changed`], have no targets.
+pub fn root_updates_and_witness<F>(
+    factory: F,
+    prefix_sets: TriePrefixSets,
+) -> Result<(B256, TrieUpdates, StateWitness), StateRootError>
+where
+    F: TrieCursorFactory + HashedCursorFactory + Clone,
+{
+    let account_targets = witness_targets(&prefix_sets.account_prefix_set);
+    let walker =
+        TrieWalker::new(factory.account_trie_cursor()?, prefix_sets.account_prefix_set.clone())
+            .with_updates(true);
+    let retainer = ProofRetainer::from_iter(account_targets.iter().map(Nibbles::unpack));
+    let mut hash_builder = HashBuilder::default().with_updates(true).with_proof_retainer(retainer);
+
+    let mut trie_updates = TrieUpdates::default();
+    let mut storages = HashMap::new();
+    let mut account_rlp = Vec::with_capacity(128);
+    let mut account_node_iter = TrieNodeIter::new(walker, factory.hashed_account_cursor()?);
+    while let Some(node) = account_node_iter.try_next()? {
+        match node {
+            TrieElement::Branch(node) => {
+                hash_builder.add_branch(node.key, node.value, node.children_are_in_trie);
+            }
+            TrieElement::Leaf(hashed_address, account) => {
+                let storage_prefix_set = prefix_sets.storage_prefix_set(&hashed_address);
+                let storage_targets = witness_targets(&storage_prefix_set);
+                let (storage_root, updates, nodes) = StorageRoot::new_hashed(
+                    factory.clone(),
+                    factory.clone(),
+                    hashed_address,
+                    #[cfg(feature = "metrics")]
+                    TrieRootMetrics::new(TrieType::Storage),
+                )
+                .with_prefix_set(storage_prefix_set)
+                .root_with_updates_and_proofs(storage_targets.iter().map(Nibbles::unpack))?;
+                trie_updates.extend(updates);
+                if !storage_targets.is_empty() {
+                    storages.insert(hashed_address, MultiProof { targets: storage_targets, nodes });
+                }
+
+                account_rlp.clear();
+                let account = TrieAccount::from((account, storage_root));
+                account.encode(&mut account_rlp as &mut dyn BufMut);
+                hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp);
+            }
+        }
+    }
+
+    let root = hash_builder.root();
+    let accounts = MultiProof { targets: account_targets, nodes: hash_builder.take_proofs() };
+    trie_updates.finalize_state_updates(
+        account_node_iter.walker,
+        hash_builder,
+        prefix_sets.destroyed_accounts,
+    );
+
+    Ok((root, trie_updates, StateWitness { accounts, storages }))
+}
+
+/// Returns the hashes of the nodes of the proof that are not at the given paths.
+fn unused_node_hashes<'a>(
+    proof: &'a MultiProof,
+    used: &'a BTreeSet<Nibbles>,
+) -> impl Iterator<Item = B256> + 'a {
+    proof.nodes.iter().filter(|(path, _)| !used.contains(*path)).map(|(_, node)| keccak256(node))
+}
+
+/// Returns the full keys of the prefix set.
+fn witness_targets(prefix_set: &PrefixSet) -> Vec<B256> {
+    prefix_set.iter().filter(|key| key.len() == 64).map(|key| pack_key(key)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prefix_set::PrefixSetMut,
+        test_utils::{state_root_prehashed, storage_root_prehashed},
+        StateRoot,
+    };
+    use reth_db::{tables, transaction::DbTxMut};
+    use reth_primitives::{keccak256, Account, StorageEntry, U256};
+    use reth_provider::test_utils::create_test_provider_factory;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn witness_proves_changed_keys() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let mut state = (0..200u64)
+            .map(|i| {
+                let storage = (0..i % 10)
+                    .map(|slot| (keccak256(B256::from(U256::from(slot))), U256::from(i + 1)))
+                    .collect::<BTreeMap<_, _>>();
+                let account = Account { nonce: i, ..Default::default() };
+                (keccak256(B256::from(U256::from(i))), (account, storage))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (hashed_address, (account, storage)) in &state {
+            tx.put::<tables::HashedAccounts>(*hashed_address, *account).unwrap();
+            for (hashed_slot, value) in storage {
+                let entry = StorageEntry { key: *hashed_slot, value: *value };
+                tx.put::<tables::HashedStorages>(*hashed_address, entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // Update an account, change and remove slots of another one and add a missing account.
+        let mut account_prefix_set = PrefixSetMut::default();
+        let mut storage_prefix_sets = HashMap::new();
+        let updated = keccak256(B256::from(U256::from(7)));
+        state.get_mut(&updated).unwrap().0.nonce += 1;
+        tx.put::<tables::HashedAccounts>(updated, state[&updated].0).unwrap();
+        account_prefix_set.insert(Nibbles::unpack(updated));
+
+        let with_storage = keccak256(B256::from(U256::from(9)));
+        let mut storage_prefix_set = PrefixSetMut::default();
+        for slot in [0u64, 5, 20] {
+            let hashed_slot = keccak256(B256::from(U256::from(slot)));
+            let storage = &mut state.get_mut(&with_storage).unwrap().1;
+            if let Some(value) = storage.remove(&hashed_slot) {
+                let entry = StorageEntry { key: hashed_slot, value };
+                tx.delete::<tables::HashedStorages>(with_storage, Some(entry)).unwrap();
+            }
+            if slot != 5 {
+                storage.insert(hashed_slot, U256::from(slot + 100));
+                let entry = StorageEntry { key: hashed_slot, value: U256::from(slot + 100) };
+                tx.put::<tables::HashedStorages>(with_storage, entry).unwrap();
+            }
+            storage_prefix_set.insert(Nibbles::unpack(hashed_slot));
+        }
+        account_prefix_set.insert(Nibbles::unpack(with_storage));
+        storage_prefix_sets.insert(with_storage, storage_prefix_set.freeze());
+
+        let missing = keccak256(B256::from(U256::from(1_000)));
+        account_prefix_set.insert(Nibbles::unpack(missing));
+
+        let prefix_sets = || TriePrefixSets {
+            account_prefix_set: account_prefix_set.clone().freeze(),
+            storage_prefix_sets: storage_prefix_sets.clone(),
+            destroyed_accounts: Default::default(),
+        };
+        let (root, updates, witness) = root_updates_and_witness(tx, prefix_sets()).unwrap();
+        let (expected_root, expected_updates) =
+            StateRoot::from_tx(tx).with_prefix_sets(prefix_sets()).root_with_updates().unwrap();
+        assert_eq!(root, expected_root);
+        assert_eq!(updates, expected_updates);
+        assert_eq!(
+            root,
+            state_root_prehashed(
+                state.iter().map(|(key, (account, storage))| (*key, (*account, storage.clone())))
+            )
+        );
+
+        // The witness proves every changed account and slot against the new roots.
+        assert_eq!(witness.accounts.targets.len(), 3);
+        assert_eq!(witness.accounts.verify_complete(root), Ok(()));
+        assert_eq!(witness.storages.len(), 1);
+        let storage_root = storage_root_prehashed(state[&with_storage].1.clone());
+        assert_eq!(witness.storages[&with_storage].verify_complete(storage_root), Ok(()));
+    }
+
+    #[test]
+    fn witness_encoding_round_trip() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed = |i: u64| keccak256(B256::from(U256::from(i)));
+        for i in 0..100u64 {
+            tx.put::<tables::HashedAccounts>(hashed(i), Account { nonce: i, ..Default::default() })
+                .unwrap();
+            for slot in 0..i % 5 {
+                let entry = StorageEntry { key: hashed(slot), value: U256::from(i + 1) };
+                tx.put::<tables::HashedStorages>(hashed(i), entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        // Update an account, change a slot of another one and add a missing account.
+        let (updated, with_storage, missing) = (hashed(7), hashed(9), hashed(1_000));
+        tx.put::<tables::HashedAccounts>(updated, Account { nonce: 100, ..Default::default() })
+            .unwrap();
+        tx.delete::<tables::HashedStorages>(
+            with_storage,
+            Some(StorageEntry { key: hashed(1), value: U256::from(10) }),
+        )
+        .unwrap();
+        tx.put::<tables::HashedStorages>(
+            with_storage,
+            StorageEntry { key: hashed(1), value: U256::from(11) },
+        )
+        .unwrap();
+        tx.put::<tables::HashedAccounts>(missing, Account::default()).unwrap();
+
+        let account_prefix_set =
+            PrefixSetMut::from([updated, with_storage, missing].map(Nibbles::unpack)).freeze();
+        let storage_prefix_set = PrefixSetMut::from([Nibbles::unpack(hashed(1))]).freeze();
+        let prefix_sets = TriePrefixSets {
+            account_prefix_set,
+            storage_prefix_sets: HashMap::from([(with_storage, storage_prefix_set)]),
+            destroyed_accounts: Default::default(),
+        };
+        let (root, _, witness) = root_updates_and_witness(tx, prefix_sets.clone()).unwrap();
+        assert_eq!(
+            root,
+            StateRoot::from_tx(tx).with_prefix_sets(prefix_sets.clone()).root().unwrap()
+        );
+        assert_eq!(witness.storages.len(), 1);
+
+        let encoded = witness.encode();
+        assert_eq!(encoded[0], WITNESS_ENCODING_VERSION);
+        let decoded = StateWitness::decode(&encoded).unwrap();
+        assert_eq!(decoded, witness);
+        assert_eq!(decoded.verify_against(root, &prefix_sets), Ok(()));
+
+        // The witness does not prove the keys that are not changed.
+        let mut other_prefix_sets = prefix_sets.clone();
+        other_prefix_sets.storage_prefix_sets.clear();
+        assert_eq!(
+            decoded.verify_against(root, &other_prefix_sets),
+            Err(WitnessVerificationError::UnexpectedStorage(with_storage))
+        );
+
+        // The truncated, extended and unknown encodings are rejected.
+        assert_eq!(
+            StateWitness::decode(&encoded[..encoded.len() - 1]),
+            Err(WitnessDecodeError::Truncated)
+        );
+        assert_eq!(StateWitness::decode(&[]), Err(WitnessDecodeError::Truncated));
+        let extended = [&encoded[..], &[0]].concat();
+        assert_eq!(StateWitness::decode(&extended), Err(WitnessDecodeError::TrailingBytes(1)));
+        let unknown = [&[WITNESS_ENCODING_VERSION + 1][..], &encoded[1..]].concat();
+        assert_eq!(
+            StateWitness::decode(&unknown),
+            Err(WitnessDecodeError::UnsupportedVersion(WITNESS_ENCODING_VERSION + 1))
+        );
+
+        // The node longer than a branch node with all children hashed is rejected.
+        let mut oversized = StateWitness::default();
+        oversized.accounts.nodes.insert(Nibbles::default(), Bytes::from(vec![0; 533]));
+        assert_eq!(
+            StateWitness::decode(&oversized.encode()),
+            Err(WitnessDecodeError::OversizedNode(533))
+        );
+
+        // The tampered node fails the verification.
+        let mut tampered = decoded;
+        let (path, node) = tampered.accounts.nodes.pop_last().unwrap();
+        let mut node = node.to_vec();
+        *node.last_mut().unwrap() ^= 1;
+        tampered.accounts.nodes.insert(path, node.into());
+        let tampered = StateWitness::decode(&tampered.encode()).unwrap();
+        assert!(matches!(
+            tampered.verify_against(root, &prefix_sets),
+            Err(WitnessVerificationError::Accounts(MultiProofError::HashMismatch { .. }))
+        ));
+    }
+
+    #[test]
+    fn witness_find_unused() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let hashed = |i: u64| keccak256(B256::from(U256::from(i)));
+        for i in 0..100u64 {
+            tx.put::<tables::HashedAccounts>(hashed(i), Account { nonce: i, ..Default::default() })
+                .unwrap();
+            for slot in 0..i % 5 {
+                let entry = StorageEntry { key: hashed(slot), value: U256::from(i + 1) };
+                tx.put::<tables::HashedStorages>(hashed(i), entry).unwrap();
+            }
+        }
+        let (_, updates) = StateRoot::from_tx(tx).root_with_updates().unwrap();
+        updates.flush(tx).unwrap();
+
+        let prefix_sets = |accounts: &[B256], slots: &[(B256, B256)]| {
+            let mut storage_prefix_sets = HashMap::<B256, PrefixSetMut>::new();
+            for (hashed_address, hashed_slot) in slots {
+                storage_prefix_sets
+                    .entry(*hashed_address)
+                    .or_default()
+                    .insert(Nibbles::unpack(hashed_slot));
+            }
+            TriePrefixSets {
+                account_prefix_set: PrefixSetMut::from(accounts.iter().map(Nibbles::unpack))
+                    .freeze(),
+                storage_prefix_sets: storage_prefix_sets
+                    .into_iter()
+                    .map(|(hashed_address, prefix_set)| (hashed_address, prefix_set.freeze()))
+                    .collect(),
+                destroyed_accounts: Default::default(),
+            }
+        };
+
+        // The witness of the walk is minimal.
+        let changed = prefix_sets(&[hashed(7), hashed(9)], &[(hashed(9), hashed(1))]);
+        let (root, _, witness) = root_updates_and_witness(tx, changed.clone()).unwrap();
+        assert_eq!(witness.storages.len(), 1);
+        assert_eq!(witness.find_unused(root, &changed), Ok(Vec::new()));
+
+        // The nodes proving the other keys are reported.
+        let other = prefix_sets(&[hashed(54)], &[(hashed(54), hashed(3))]);
+        let (other_root, _, other_witness) = root_updates_and_witness(tx, other).unwrap();
+        assert_eq!(other_root, root);
+        let (path, node) = other_witness
+            .accounts
+            .nodes
+            .iter()
+            .find(|(path, _)| !witness.accounts.nodes.contains_key(*path))
+            .unwrap();
+        let mut bloated = witness.clone();
+        bloated.accounts.nodes.insert(path.clone(), node.clone());
+        assert_eq!(bloated.find_unused(root, &changed), Ok(vec![keccak256(node)]));
+        assert_eq!(
+            bloated.verify_against(root, &changed),
+            Err(WitnessVerificationError::Accounts(MultiProofError::UnusedNode(path.clone())))
+        );
+
+        // So are the nodes of the storage proof of an account without changed slots.
+        let mut bloated = witness.clone();
+        bloated.storages.extend(other_witness.storages.clone());
+        let storage_nodes =
+            other_witness.storages[&hashed(54)].nodes.values().map(keccak256).collect::<Vec<_>>();
+        assert!(!storage_nodes.is_empty());
+        assert_eq!(bloated.find_unused(root, &changed), Ok(storage_nodes));
+    }
+}